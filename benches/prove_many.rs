@@ -0,0 +1,45 @@
+//! Serial vs parallel membership proving.
+//!
+//! Run with `cargo bench --features parallel --bench prove_many`.
+//! Set `PROVE_MANY_COUNT` to change the number of proofs (default 64).
+
+use std::time::Instant;
+
+use ark_bn254::Fr;
+use zk_vault::merkle::tree::MerkleTree;
+use zk_vault::prover::Prover;
+
+fn main() {
+    let count: usize = std::env::var("PROVE_MANY_COUNT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(64);
+
+    let leaves: Vec<Fr> = (0..1024u64).map(Fr::from).collect();
+    let tree = MerkleTree::new(leaves.clone()).unwrap();
+    let (prover, _vk) = Prover::setup(tree.depth()).expect("setup failed");
+    let batch = &leaves[..count.min(leaves.len())];
+
+    let start = Instant::now();
+    for leaf in batch {
+        prover.prove(&tree, leaf).expect("serial proof failed");
+    }
+    let serial = start.elapsed();
+
+    let start = Instant::now();
+    let results = prover.prove_many_parallel(&tree, batch);
+    let parallel = start.elapsed();
+    assert!(results.iter().all(Result::is_ok), "parallel proof failed");
+
+    println!(
+        "{} proofs at depth {}: serial {:?} ({:?}/proof), parallel {:?} ({:?}/proof), {:.1}x on {} threads",
+        batch.len(),
+        tree.depth(),
+        serial,
+        serial / batch.len() as u32,
+        parallel,
+        parallel / batch.len() as u32,
+        serial.as_secs_f64() / parallel.as_secs_f64(),
+        rayon::current_num_threads(),
+    );
+}
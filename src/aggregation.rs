@@ -0,0 +1,683 @@
+//! SnarkPack aggregation of Merkle membership proofs.
+//!
+//! `ProofAggregator` folds N Groth16 proofs made under one verifying key
+//! into one `AggregateProof` (Gailly, Maller, Nitulescu, "SnarkPack:
+//! Practical SNARK Aggregation", FC 2022). The aggregate holds O(log N)
+//! group elements besides the public inputs, and checking it costs
+//! O(log N) pairings instead of the 3N of separate checks; only folding
+//! the public inputs into one point stays linear, in field operations.
+//!
+//! A Fiat-Shamir challenge r merges the N Groth16 equations into one in
+//! which `z_ab = prod e(A_i, B_i)^(r^i)` and `z_c = sum r^i C_i` stand in
+//! for the proofs. A TIPP argument shows `z_ab` matches the A and B
+//! committed in `com_ab`, and a MIPP argument does the same for `z_c` and
+//! `com_c`. Both run in the same log N GIPA rounds, which halve the
+//! vectors and commitment keys each time; KZG openings at the end prove
+//! the folded keys were derived from the SRS.
+//!
+//! The commitment keys come from an `AggregationSrs`: powers of two
+//! secrets in G1 and G2. As with Groth16 setup, anyone who knows the
+//! secrets can forge aggregates, so production keys should come from a
+//! ceremony (SnarkPack reuses powers-of-tau transcripts);
+//! `AggregationSrs::setup` samples them from an RNG.
+
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_groth16::{PreparedVerifyingKey, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::prover::{verifying_key_fingerprint, MembershipProof};
+
+/// Domain separator for the Fiat-Shamir transcript.
+const AGGREGATE_DOMAIN: &[u8] = b"zk-vault/aggregate/v1";
+
+type Gt = PairingOutput<Bn254>;
+
+/// Errors that can occur while aggregating.
+#[derive(Debug, thiserror::Error)]
+pub enum AggregationError {
+    #[error("No proofs to aggregate")]
+    NoProofs,
+
+    #[error("Too many proofs: {proofs} proofs need an SRS for {needed}, this one supports {max}")]
+    TooManyProofs { proofs: usize, needed: usize, max: usize },
+}
+
+/// Commitment keys for aggregating up to `max_proofs` proofs.
+///
+/// Holds `g^(a^i)`, `g^(b^i)` for `i < 2 * max_proofs` and `h^(a^i)`,
+/// `h^(b^i)` for `i < max_proofs`, where `a` and `b` are the setup
+/// secrets. One SRS serves every verifying key.
+#[derive(Clone)]
+pub struct AggregationSrs {
+    g_powers_a: Vec<G1Affine>,
+    g_powers_b: Vec<G1Affine>,
+    h_powers_a: Vec<G2Affine>,
+    h_powers_b: Vec<G2Affine>,
+}
+
+impl AggregationSrs {
+    /// Sample an SRS for up to `max_proofs` proofs, rounded up to a power
+    /// of two no smaller than 2.
+    ///
+    /// The secrets are dropped on return; see the module docs for why
+    /// that is only as trustworthy as `rng` and the caller.
+    pub fn setup<R: RngCore + CryptoRng>(max_proofs: usize, rng: &mut R) -> Self {
+        let n = max_proofs.max(2).next_power_of_two();
+        let a = Fr::rand(rng);
+        let b = Fr::rand(rng);
+        Self {
+            g_powers_a: powers_of_base(G1Affine::generator(), a, 2 * n),
+            g_powers_b: powers_of_base(G1Affine::generator(), b, 2 * n),
+            h_powers_a: powers_of_base(G2Affine::generator(), a, n),
+            h_powers_b: powers_of_base(G2Affine::generator(), b, n),
+        }
+    }
+
+    /// Largest number of proofs this SRS can aggregate.
+    pub fn max_proofs(&self) -> usize {
+        self.h_powers_a.len()
+    }
+
+    /// The few SRS elements a verifier needs.
+    pub fn verifier_key(&self) -> AggregationVerifierKey {
+        AggregationVerifierKey {
+            max_proofs: self.max_proofs(),
+            g_a: self.g_powers_a[1],
+            g_b: self.g_powers_b[1],
+            h_a: self.h_powers_a[1],
+            h_b: self.h_powers_b[1],
+        }
+    }
+}
+
+/// Verifier side of an `AggregationSrs`: `g^a`, `g^b`, `h^a` and `h^b`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregationVerifierKey {
+    max_proofs: usize,
+    g_a: G1Affine,
+    g_b: G1Affine,
+    h_a: G2Affine,
+    h_b: G2Affine,
+}
+
+/// Collects membership proofs made under one verifying key.
+pub struct ProofAggregator<'a> {
+    srs: &'a AggregationSrs,
+    vk_fingerprint: String,
+    proofs: Vec<MembershipProof>,
+}
+
+impl<'a> ProofAggregator<'a> {
+    /// Start aggregating proofs for `vk` with the keys in `srs`.
+    pub fn new(srs: &'a AggregationSrs, vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            srs,
+            vk_fingerprint: verifying_key_fingerprint(vk),
+            proofs: Vec::new(),
+        }
+    }
+
+    /// Add a proof. Proofs are not checked; an aggregate with an invalid
+    /// proof in it fails verification.
+    pub fn add(&mut self, proof: MembershipProof) {
+        self.proofs.push(proof);
+    }
+
+    /// Number of proofs collected so far.
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    /// Whether no proofs have been collected.
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// Fold the collected proofs into one aggregate.
+    ///
+    /// A count that is not a power of two is padded by repeating the last
+    /// proof; the verifier pads the public inputs the same way.
+    pub fn aggregate(self) -> Result<AggregateProof, AggregationError> {
+        let count = self.proofs.len();
+        let last = count.checked_sub(1).ok_or(AggregationError::NoProofs)?;
+        let n = count.next_power_of_two();
+        if n > self.srs.max_proofs() {
+            return Err(AggregationError::TooManyProofs {
+                proofs: count,
+                needed: n,
+                max: self.srs.max_proofs(),
+            });
+        }
+
+        let padded = (0..n).map(|i| &self.proofs[i.min(last)].proof);
+        let mut a: Vec<G1Affine> = padded.clone().map(|p| p.a).collect();
+        let b: Vec<G2Affine> = padded.clone().map(|p| p.b).collect();
+        let mut c: Vec<G1Affine> = padded.map(|p| p.c).collect();
+        let public_inputs: Vec<Vec<Fr>> = self.proofs.iter().map(MembershipProof::public_inputs).collect();
+
+        let srs = self.srs;
+        let mut v = (srs.h_powers_a[..n].to_vec(), srs.h_powers_b[..n].to_vec());
+        let w = (&srs.g_powers_a[n..2 * n], &srs.g_powers_b[n..2 * n]);
+        let com_ab = commit_double((&v.0, &v.1), w, &a, &b);
+        let com_c = commit_single((&v.0, &v.1), &c);
+
+        let mut transcript = Transcript::new(&self.vk_fingerprint);
+        transcript.append(&public_inputs);
+        com_ab.append_to(&mut transcript);
+        com_c.append_to(&mut transcript);
+        let (r, r_inv) = transcript.challenge();
+
+        // Scaling B by r^i and w by r^-i leaves com_ab unchanged, so the
+        // TIPP below proves z_ab against the committed A and B.
+        let mut r_vec = powers(r, n);
+        let r_inv_powers = powers(r_inv, n);
+        let mut b = scale(&b, &r_vec);
+        let mut w = (scale(w.0, &r_inv_powers), scale(w.1, &r_inv_powers));
+        let z_ab = Bn254::multi_pairing(&a, &b);
+        let z_c = G1Projective::msm_unchecked(&c, &r_vec).into_affine();
+        transcript.append(&z_ab);
+        transcript.append(&z_c);
+
+        let mut rounds = Vec::with_capacity(n.trailing_zeros() as usize);
+        let mut challenges = Vec::with_capacity(rounds.capacity());
+        while a.len() > 1 {
+            let m = a.len() / 2;
+            let (a_l, a_r) = a.split_at(m);
+            let (b_l, b_r) = b.split_at(m);
+            let (c_l, c_r) = c.split_at(m);
+            let (r_l, r_r) = r_vec.split_at(m);
+            let (v1_l, v1_r) = v.0.split_at(m);
+            let (v2_l, v2_r) = v.1.split_at(m);
+            let (w1_l, w1_r) = w.0.split_at(m);
+            let (w2_l, w2_r) = w.1.split_at(m);
+
+            let round = GipaRound {
+                com_ab_l: commit_double((v1_l, v2_l), (w1_r, w2_r), a_r, b_l),
+                com_ab_r: commit_double((v1_r, v2_r), (w1_l, w2_l), a_l, b_r),
+                z_ab_l: Bn254::multi_pairing(a_r, b_l),
+                z_ab_r: Bn254::multi_pairing(a_l, b_r),
+                com_c_l: commit_single((v1_l, v2_l), c_r),
+                com_c_r: commit_single((v1_r, v2_r), c_l),
+                z_c_l: G1Projective::msm_unchecked(c_r, r_l).into_affine(),
+                z_c_r: G1Projective::msm_unchecked(c_l, r_r).into_affine(),
+            };
+            round.append_to(&mut transcript);
+            let (x, x_inv) = transcript.challenge();
+
+            a = fold(a_l, a_r, x);
+            b = fold(b_l, b_r, x_inv);
+            c = fold(c_l, c_r, x);
+            r_vec = r_l.iter().zip(r_r).map(|(l, r)| *l + x_inv * r).collect();
+            v = (fold(v1_l, v1_r, x_inv), fold(v2_l, v2_r, x_inv));
+            w = (fold(w1_l, w1_r, x), fold(w2_l, w2_r, x));
+
+            rounds.push(round);
+            challenges.push((x, x_inv));
+        }
+
+        let mut aggregate = AggregateProof {
+            vk_fingerprint: self.vk_fingerprint,
+            public_inputs,
+            com_ab,
+            com_c,
+            z_ab,
+            z_c,
+            rounds,
+            final_a: a[0],
+            final_b: b[0],
+            final_c: c[0],
+            final_v: (v.0[0], v.1[0]),
+            final_w: (w.0[0], w.1[0]),
+            opening_v: (G2Affine::zero(), G2Affine::zero()),
+            opening_w: (G1Affine::zero(), G1Affine::zero()),
+        };
+        aggregate.append_finals(&mut transcript);
+        let (z, _) = transcript.challenge();
+
+        // v folds to h^f_v(s) and w to g^(s^n f_w(s)) for each secret s.
+        let f_v = factors_to_coeffs(challenges.iter().map(|&(_, x_inv)| x_inv));
+        let q_v = divide_by_linear(&f_v, z);
+        let mut f_w = vec![Fr::zero(); n];
+        f_w.extend(factors_to_coeffs(
+            challenges.iter().enumerate().map(|(j, &(x, _))| x * r_inv.pow([(n >> (j + 1)) as u64])),
+        ));
+        let q_w = divide_by_linear(&f_w, z);
+
+        aggregate.opening_v = (
+            G2Projective::msm_unchecked(&srs.h_powers_a[..q_v.len()], &q_v).into_affine(),
+            G2Projective::msm_unchecked(&srs.h_powers_b[..q_v.len()], &q_v).into_affine(),
+        );
+        aggregate.opening_w = (
+            G1Projective::msm_unchecked(&srs.g_powers_a[..q_w.len()], &q_w).into_affine(),
+            G1Projective::msm_unchecked(&srs.g_powers_b[..q_w.len()], &q_w).into_affine(),
+        );
+        Ok(aggregate)
+    }
+}
+
+/// N membership proofs folded into O(log N) group elements.
+#[derive(Clone, Debug)]
+pub struct AggregateProof {
+    vk_fingerprint: String,
+    public_inputs: Vec<Vec<Fr>>,
+    com_ab: Commitment,
+    com_c: Commitment,
+    z_ab: Gt,
+    z_c: G1Affine,
+    rounds: Vec<GipaRound>,
+    final_a: G1Affine,
+    final_b: G2Affine,
+    final_c: G1Affine,
+    final_v: (G2Affine, G2Affine),
+    final_w: (G1Affine, G1Affine),
+    opening_v: (G2Affine, G2Affine),
+    opening_w: (G1Affine, G1Affine),
+}
+
+impl AggregateProof {
+    /// Number of proofs aggregated, before padding.
+    pub fn num_proofs(&self) -> usize {
+        self.public_inputs.len()
+    }
+
+    /// Public inputs of each aggregated proof, in the order added.
+    pub fn public_inputs(&self) -> &[Vec<Fr>] {
+        &self.public_inputs
+    }
+
+    /// Whether this aggregate was built for `vk`.
+    pub fn is_for_key(&self, vk: &VerifyingKey<Bn254>) -> bool {
+        self.vk_fingerprint == verifying_key_fingerprint(vk)
+    }
+
+    /// Check every aggregated proof at once.
+    ///
+    /// Returns false if any proof is invalid, without saying which, or if
+    /// the aggregate is malformed or was built from a different SRS.
+    pub fn verify(&self, pvk: &PreparedVerifyingKey<Bn254>, key: &AggregationVerifierKey) -> bool {
+        let count = self.public_inputs.len();
+        let Some(last) = count.checked_sub(1) else {
+            return false;
+        };
+        let n = count.next_power_of_two();
+        let Some(input_len) = pvk.vk.gamma_abc_g1.len().checked_sub(1) else {
+            return false;
+        };
+        if n > key.max_proofs
+            || self.rounds.len() != n.trailing_zeros() as usize
+            || self.public_inputs.iter().any(|inputs| inputs.len() != input_len)
+        {
+            return false;
+        }
+
+        let mut transcript = Transcript::new(&verifying_key_fingerprint(&pvk.vk));
+        transcript.append(&self.public_inputs);
+        self.com_ab.append_to(&mut transcript);
+        self.com_c.append_to(&mut transcript);
+        let (r, r_inv) = transcript.challenge();
+        transcript.append(&self.z_ab);
+        transcript.append(&self.z_c);
+
+        // z_ab = (sum r^i) * e(alpha, beta) + e(sum r^i * IC_i, gamma)
+        //     + e(z_c, delta)
+        let mut scalars = vec![Fr::zero(); input_len + 1];
+        for (i, r_i) in powers(r, n).into_iter().enumerate() {
+            scalars[0] += r_i;
+            for (s, input) in scalars[1..].iter_mut().zip(&self.public_inputs[i.min(last)]) {
+                *s += r_i * input;
+            }
+        }
+        let inputs_acc = G1Projective::msm_unchecked(&pvk.vk.gamma_abc_g1, &scalars).into_affine();
+        let rest = Bn254::multi_pairing(
+            [inputs_acc, self.z_c],
+            [pvk.gamma_g2_neg_pc.clone(), pvk.delta_g2_neg_pc.clone()],
+        );
+        if self.z_ab.0 * rest.0 != pvk.alpha_g1_beta_g2.pow(scalars[0].into_bigint()) {
+            return false;
+        }
+
+        let mut com_ab = self.com_ab;
+        let mut z_ab = self.z_ab;
+        let mut com_c = self.com_c;
+        let mut z_c = self.z_c.into_group();
+        let mut challenges = Vec::with_capacity(self.rounds.len());
+        for round in &self.rounds {
+            round.append_to(&mut transcript);
+            let (x, x_inv) = transcript.challenge();
+            com_ab = Commitment::fold(round.com_ab_l, com_ab, round.com_ab_r, x, x_inv);
+            z_ab = round.z_ab_l * x + z_ab + round.z_ab_r * x_inv;
+            com_c = Commitment::fold(round.com_c_l, com_c, round.com_c_r, x, x_inv);
+            z_c += round.z_c_l * x + round.z_c_r * x_inv;
+            challenges.push((x, x_inv));
+        }
+        self.append_finals(&mut transcript);
+        let (z, _) = transcript.challenge();
+
+        // The GIPA claims, reduced to single elements.
+        let (v1, v2) = self.final_v;
+        let (w1, w2) = self.final_w;
+        let r_final = eval_factors(n, challenges.iter().map(|&(_, x_inv)| x_inv), r);
+        if z_ab != Bn254::pairing(self.final_a, self.final_b)
+            || z_c != self.final_c * r_final
+            || com_ab != commit_double((&[v1], &[v2]), (&[w1], &[w2]), &[self.final_a], &[self.final_b])
+            || com_c != commit_single((&[v1], &[v2]), &[self.final_c])
+        {
+            return false;
+        }
+
+        // KZG openings of the folded keys at z.
+        let g = G1Affine::generator();
+        let h = G2Affine::generator();
+        let f_v = eval_factors(n, challenges.iter().map(|&(_, x_inv)| x_inv), z);
+        let f_w = z.pow([n as u64]) * eval_factors(n, challenges.iter().map(|&(x, _)| x), z * r_inv);
+        let opens_v = |s_g: G1Affine, v: G2Affine, opening: G2Affine| {
+            Bn254::multi_pairing(
+                [(s_g.into_group() - g * z).into_affine(), -g],
+                [opening, (v.into_group() - h * f_v).into_affine()],
+            )
+            .is_zero()
+        };
+        let opens_w = |s_h: G2Affine, w: G1Affine, opening: G1Affine| {
+            Bn254::multi_pairing(
+                [(w.into_group() - g * f_w).into_affine(), -opening],
+                [h, (s_h.into_group() - h * z).into_affine()],
+            )
+            .is_zero()
+        };
+        opens_v(key.g_a, v1, self.opening_v.0)
+            && opens_v(key.g_b, v2, self.opening_v.1)
+            && opens_w(key.h_a, w1, self.opening_w.0)
+            && opens_w(key.h_b, w2, self.opening_w.1)
+    }
+
+    fn append_finals(&self, transcript: &mut Transcript) {
+        transcript.append(&self.final_a);
+        transcript.append(&self.final_b);
+        transcript.append(&self.final_c);
+        transcript.append(&self.final_v.0);
+        transcript.append(&self.final_v.1);
+        transcript.append(&self.final_w.0);
+        transcript.append(&self.final_w.1);
+    }
+}
+
+/// Pair commitment `(T, U)` under the `a`- and `b`-power keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Commitment {
+    t: Gt,
+    u: Gt,
+}
+
+impl Commitment {
+    /// `x * left + mid + x^-1 * right`, the GIPA update.
+    fn fold(left: Self, mid: Self, right: Self, x: Fr, x_inv: Fr) -> Self {
+        Self {
+            t: left.t * x + mid.t + right.t * x_inv,
+            u: left.u * x + mid.u + right.u * x_inv,
+        }
+    }
+
+    fn append_to(&self, transcript: &mut Transcript) {
+        transcript.append(&self.t);
+        transcript.append(&self.u);
+    }
+}
+
+/// Cross terms sent in one GIPA round.
+#[derive(Clone, Debug)]
+struct GipaRound {
+    com_ab_l: Commitment,
+    com_ab_r: Commitment,
+    z_ab_l: Gt,
+    z_ab_r: Gt,
+    com_c_l: Commitment,
+    com_c_r: Commitment,
+    z_c_l: G1Affine,
+    z_c_r: G1Affine,
+}
+
+impl GipaRound {
+    fn append_to(&self, transcript: &mut Transcript) {
+        self.com_ab_l.append_to(transcript);
+        self.com_ab_r.append_to(transcript);
+        transcript.append(&self.z_ab_l);
+        transcript.append(&self.z_ab_r);
+        self.com_c_l.append_to(transcript);
+        self.com_c_r.append_to(transcript);
+        transcript.append(&self.z_c_l);
+        transcript.append(&self.z_c_r);
+    }
+}
+
+/// Running SHA-256 over everything the prover has sent.
+struct Transcript(Sha256);
+
+impl Transcript {
+    fn new(vk_fingerprint: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(AGGREGATE_DOMAIN);
+        hasher.update(vk_fingerprint.as_bytes());
+        Self(hasher)
+    }
+
+    fn append<T: CanonicalSerialize>(&mut self, item: &T) {
+        let mut bytes = Vec::new();
+        item.serialize_compressed(&mut bytes).unwrap();
+        self.0.update(bytes);
+    }
+
+    /// Next challenge and its inverse; zero is skipped.
+    fn challenge(&mut self) -> (Fr, Fr) {
+        loop {
+            let digest = self.0.clone().finalize();
+            self.0.update(digest);
+            let x = Fr::from_le_bytes_mod_order(&digest);
+            if let Some(x_inv) = x.inverse() {
+                return (x, x_inv);
+            }
+        }
+    }
+}
+
+/// `(sum e(A_i, v1_i), sum e(A_i, v2_i))`.
+fn commit_single(v: (&[G2Affine], &[G2Affine]), a: &[G1Affine]) -> Commitment {
+    Commitment {
+        t: Bn254::multi_pairing(a, v.0),
+        u: Bn254::multi_pairing(a, v.1),
+    }
+}
+
+/// `commit_single(v, A)` plus `(sum e(w1_i, B_i), sum e(w2_i, B_i))`.
+fn commit_double(
+    v: (&[G2Affine], &[G2Affine]),
+    w: (&[G1Affine], &[G1Affine]),
+    a: &[G1Affine],
+    b: &[G2Affine],
+) -> Commitment {
+    Commitment {
+        t: Bn254::multi_pairing(a.iter().chain(w.0), v.0.iter().chain(b)),
+        u: Bn254::multi_pairing(a.iter().chain(w.1), v.1.iter().chain(b)),
+    }
+}
+
+/// `left_i + x * right_i`.
+fn fold<A: AffineRepr>(left: &[A], right: &[A], x: A::ScalarField) -> Vec<A> {
+    let folded: Vec<A::Group> = left.iter().zip(right).map(|(l, r)| *l + *r * x).collect();
+    A::Group::normalize_batch(&folded)
+}
+
+/// `points_i * scalars_i`.
+fn scale<A: AffineRepr>(points: &[A], scalars: &[A::ScalarField]) -> Vec<A> {
+    let scaled: Vec<A::Group> = points.iter().zip(scalars).map(|(p, s)| *p * s).collect();
+    A::Group::normalize_batch(&scaled)
+}
+
+/// `1, x, x^2, ..., x^(count - 1)`.
+fn powers(x: Fr, count: usize) -> Vec<Fr> {
+    let mut acc = Fr::one();
+    (0..count)
+        .map(|_| {
+            let power = acc;
+            acc *= x;
+            power
+        })
+        .collect()
+}
+
+/// `base * s^i` for `i < count`.
+fn powers_of_base<A: AffineRepr<ScalarField = Fr>>(base: A, s: Fr, count: usize) -> Vec<A> {
+    scale(&vec![base; count], &powers(s, count))
+}
+
+/// Coefficients of `prod_j (1 + c_j X^(m_j))`, where round `j` of `k`
+/// halved the vectors to `m_j = 2^(k - j - 1)`.
+fn factors_to_coeffs(factors: impl DoubleEndedIterator<Item = Fr>) -> Vec<Fr> {
+    let mut coeffs = vec![Fr::one()];
+    for c in factors.rev() {
+        let high: Vec<Fr> = coeffs.iter().map(|k| *k * c).collect();
+        coeffs.extend(high);
+    }
+    coeffs
+}
+
+/// `prod_j (1 + c_j x^(m_j))` for `n` aggregated proofs.
+fn eval_factors(n: usize, factors: impl Iterator<Item = Fr>, x: Fr) -> Fr {
+    factors
+        .enumerate()
+        .map(|(j, c)| Fr::one() + c * x.pow([(n >> (j + 1)) as u64]))
+        .product()
+}
+
+/// Quotient of `p(X) / (X - z)`, dropping the remainder `p(z)`.
+fn divide_by_linear(coeffs: &[Fr], z: Fr) -> Vec<Fr> {
+    let mut quotient = vec![Fr::zero(); coeffs.len().saturating_sub(1)];
+    let mut carry = Fr::zero();
+    for i in (1..coeffs.len()).rev() {
+        carry = coeffs[i] + z * carry;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::tree::MerkleTree;
+    use crate::prover::{prepare_verifying_key, Prover};
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    fn proofs(count: u64) -> (Vec<MembershipProof>, VerifyingKey<Bn254>) {
+        let leaves: Vec<Fr> = (1..=count).map(Fr::from).collect();
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let proofs = leaves.iter().map(|leaf| prover.prove(&tree, leaf).unwrap()).collect();
+        (proofs, vk)
+    }
+
+    fn srs() -> AggregationSrs {
+        AggregationSrs::setup(8, &mut StdRng::seed_from_u64(7))
+    }
+
+    fn aggregate(srs: &AggregationSrs, vk: &VerifyingKey<Bn254>, proofs: Vec<MembershipProof>) -> AggregateProof {
+        let mut aggregator = ProofAggregator::new(srs, vk);
+        for proof in proofs {
+            aggregator.add(proof);
+        }
+        aggregator.aggregate().unwrap()
+    }
+
+    #[test]
+    fn test_aggregate_eight_proofs() {
+        let (proofs, vk) = proofs(8);
+        let srs = srs();
+
+        let aggregate = aggregate(&srs, &vk, proofs);
+        assert_eq!(aggregate.num_proofs(), 8);
+        assert_eq!(aggregate.rounds.len(), 3);
+        assert!(aggregate.is_for_key(&vk));
+        assert!(aggregate.verify(&prepare_verifying_key(&vk), &srs.verifier_key()));
+    }
+
+    #[test]
+    fn test_aggregate_pads_to_power_of_two() {
+        let (proofs, vk) = proofs(5);
+        let srs = srs();
+
+        let aggregate = aggregate(&srs, &vk, proofs);
+        assert_eq!(aggregate.num_proofs(), 5);
+        assert!(aggregate.verify(&prepare_verifying_key(&vk), &srs.verifier_key()));
+    }
+
+    #[test]
+    fn test_verifier_verify_aggregate() {
+        use crate::verifier::{Verifier, VerifierError};
+
+        let (proofs, vk) = proofs(8);
+        let srs = srs();
+        let aggregate = aggregate(&srs, &vk, proofs);
+        assert!(Verifier::new(vk).verify_aggregate(&aggregate, &srs.verifier_key()).unwrap());
+
+        let (_, other_vk) = Prover::setup_deterministic(3, 1).unwrap();
+        let result = Verifier::new(other_vk).verify_aggregate(&aggregate, &srs.verifier_key());
+        assert!(matches!(result, Err(VerifierError::VerificationFailed(_))));
+    }
+
+    #[test]
+    fn test_aggregate_rejects_one_bad_proof() {
+        let (mut proofs, vk) = proofs(8);
+        let srs = srs();
+
+        proofs[5].public_input += Fr::from(1u64);
+        let aggregate = aggregate(&srs, &vk, proofs);
+        assert!(!aggregate.verify(&prepare_verifying_key(&vk), &srs.verifier_key()));
+    }
+
+    #[test]
+    fn test_aggregate_rejects_tampering() {
+        let (proofs, vk) = proofs(8);
+        let srs = srs();
+        let pvk = prepare_verifying_key(&vk);
+        let aggregate = aggregate(&srs, &vk, proofs);
+
+        let mut tampered = aggregate.clone();
+        tampered.public_inputs[2][0] += Fr::from(1u64);
+        assert!(!tampered.verify(&pvk, &srs.verifier_key()));
+
+        let mut tampered = aggregate.clone();
+        tampered.final_c = (tampered.final_c + G1Affine::generator()).into_affine();
+        assert!(!tampered.verify(&pvk, &srs.verifier_key()));
+
+        let mut tampered = aggregate.clone();
+        tampered.opening_w.0 = tampered.opening_w.1;
+        assert!(!tampered.verify(&pvk, &srs.verifier_key()));
+
+        let other_srs = AggregationSrs::setup(8, &mut StdRng::seed_from_u64(8));
+        assert!(!aggregate.verify(&pvk, &other_srs.verifier_key()));
+    }
+
+    #[test]
+    fn test_aggregate_limits() {
+        let (proofs, vk) = proofs(8);
+        let small = AggregationSrs::setup(4, &mut StdRng::seed_from_u64(7));
+
+        assert!(matches!(
+            ProofAggregator::new(&small, &vk).aggregate(),
+            Err(AggregationError::NoProofs)
+        ));
+
+        let mut aggregator = ProofAggregator::new(&small, &vk);
+        for proof in proofs {
+            aggregator.add(proof);
+        }
+        assert!(matches!(
+            aggregator.aggregate(),
+            Err(AggregationError::TooManyProofs { proofs: 8, needed: 8, max: 4 })
+        ));
+    }
+}
@@ -0,0 +1,227 @@
+//! Batch verification of Merkle membership proofs.
+//!
+//! A `ProofBatch` holds N proofs made under one verifying key and is
+//! checked with a single randomized pairing equation: N + 2 Miller loops
+//! and one final exponentiation, instead of 3N Miller loops and N final
+//! exponentiations for N separate checks.
+//!
+//! The random weights are derived Fiat-Shamir style from a hash of every
+//! proof and public input, so the check needs no interaction and gives the
+//! same answer wherever it is run. A forged proof passes only if it
+//! cancels out under weights fixed after it was chosen, which happens with
+//! negligible probability.
+//!
+//! This is not proof aggregation: a batch is the N proofs themselves, so
+//! its size and verification cost stay linear in N. See `aggregation`
+//! for proofs folded into O(log N) size.
+
+use ark_bn254::{Bn254, Fr, G1Projective};
+use ark_ec::pairing::Pairing;
+use ark_ec::CurveGroup;
+use ark_ff::{Field, PrimeField, Zero};
+use ark_groth16::{Groth16, PreparedVerifyingKey, VerifyingKey};
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::CanonicalSerialize;
+use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::prover::MembershipProof;
+
+/// Domain separator for the weight derivation.
+const BATCH_DOMAIN: &[u8] = b"zk-vault/batch/v1";
+
+/// Collects membership proofs made under one verifying key.
+pub struct BatchBuilder {
+    vk_digest: [u8; 32],
+    proofs: Vec<MembershipProof>,
+}
+
+impl BatchBuilder {
+    /// Start a batch of proofs for `vk`.
+    pub fn new(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            vk_digest: vk_digest(vk),
+            proofs: Vec::new(),
+        }
+    }
+
+    /// Add a proof. Proofs are not checked until the batch is verified.
+    pub fn add(&mut self, proof: MembershipProof) {
+        self.proofs.push(proof);
+    }
+
+    /// Number of proofs collected so far.
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    /// Whether no proofs have been collected.
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// Build the batch, or `None` if no proofs were added.
+    pub fn build(self) -> Option<ProofBatch> {
+        if self.proofs.is_empty() {
+            return None;
+        }
+        Some(ProofBatch {
+            vk_digest: self.vk_digest,
+            proofs: self.proofs,
+        })
+    }
+}
+
+/// A set of membership proofs verified with one pairing check.
+#[derive(Clone)]
+pub struct ProofBatch {
+    vk_digest: [u8; 32],
+    proofs: Vec<MembershipProof>,
+}
+
+impl ProofBatch {
+    /// The batched proofs.
+    pub fn proofs(&self) -> &[MembershipProof] {
+        &self.proofs
+    }
+
+    /// Whether this batch was built for `vk`.
+    pub fn is_for_key(&self, vk: &VerifyingKey<Bn254>) -> bool {
+        self.vk_digest == vk_digest(vk)
+    }
+
+    /// Check every proof at once against a prepared verifying key.
+    ///
+    /// Returns `Ok(false)` if any proof is invalid; it does not say which.
+    pub fn verify(&self, pvk: &PreparedVerifyingKey<Bn254>) -> Result<bool, SynthesisError> {
+        if self.proofs.is_empty() {
+            return Ok(false);
+        }
+
+        let weights = self.weights();
+        let mut g1: Vec<<Bn254 as Pairing>::G1Prepared> = Vec::with_capacity(self.proofs.len() + 2);
+        let mut g2: Vec<<Bn254 as Pairing>::G2Prepared> = Vec::with_capacity(self.proofs.len() + 2);
+        let mut inputs_acc = G1Projective::zero();
+        let mut c_acc = G1Projective::zero();
+        let mut weight_sum = Fr::zero();
+
+        // sum r_i * e(A_i, B_i) = (sum r_i) * e(alpha, beta)
+        //     + e(sum r_i * IC_i, gamma) + e(sum r_i * C_i, delta)
+        for (proof, r) in self.proofs.iter().zip(weights) {
+            let ic = Groth16::<Bn254>::prepare_inputs(pvk, &proof.public_inputs())?;
+            inputs_acc += ic * r;
+            c_acc += proof.proof.c * r;
+            weight_sum += r;
+
+            g1.push((proof.proof.a * r).into_affine().into());
+            g2.push(proof.proof.b.into());
+        }
+        g1.push(inputs_acc.into_affine().into());
+        g2.push(pvk.gamma_g2_neg_pc.clone());
+        g1.push(c_acc.into_affine().into());
+        g2.push(pvk.delta_g2_neg_pc.clone());
+
+        let lhs = Bn254::multi_pairing(g1, g2);
+        Ok(lhs.0 == pvk.alpha_g1_beta_g2.pow(weight_sum.into_bigint()))
+    }
+
+    /// Derive one weight per proof from a hash of the whole batch.
+    fn weights(&self) -> Vec<Fr> {
+        let mut transcript = Sha256::new();
+        transcript.update(BATCH_DOMAIN);
+        transcript.update(self.vk_digest);
+        for proof in &self.proofs {
+            transcript.update(proof.to_bytes());
+        }
+        let seed = transcript.finalize();
+
+        (0..self.proofs.len() as u64)
+            .map(|i| {
+                let mut hasher = Sha256::new();
+                hasher.update(seed);
+                hasher.update(i.to_le_bytes());
+                Fr::from_le_bytes_mod_order(&hasher.finalize())
+            })
+            .collect()
+    }
+}
+
+/// SHA-256 of the compressed verifying key.
+fn vk_digest(vk: &VerifyingKey<Bn254>) -> [u8; 32] {
+    let mut bytes = Vec::new();
+    vk.serialize_compressed(&mut bytes).unwrap();
+    Sha256::digest(&bytes).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::tree::MerkleTree;
+    use crate::prover::{prepare_verifying_key, Prover};
+
+    fn eight_proofs() -> (Vec<MembershipProof>, VerifyingKey<Bn254>) {
+        let leaves: Vec<Fr> = (1..=8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let proofs = leaves.iter().map(|leaf| prover.prove(&tree, leaf).unwrap()).collect();
+        (proofs, vk)
+    }
+
+    fn batch(vk: &VerifyingKey<Bn254>, proofs: Vec<MembershipProof>) -> ProofBatch {
+        let mut builder = BatchBuilder::new(vk);
+        for proof in proofs {
+            builder.add(proof);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_batch_eight_proofs() {
+        let (proofs, vk) = eight_proofs();
+        let pvk = prepare_verifying_key(&vk);
+
+        let batch = batch(&vk, proofs);
+        assert_eq!(batch.proofs().len(), 8);
+        assert!(batch.is_for_key(&vk));
+        assert!(batch.verify(&pvk).unwrap());
+    }
+
+    #[test]
+    fn test_verifier_verify_batch() {
+        use crate::verifier::{Verifier, VerifierError};
+
+        let (proofs, vk) = eight_proofs();
+        let batch = batch(&vk, proofs);
+        assert!(Verifier::new(vk).verify_batch(&batch).unwrap());
+
+        let (_, other_vk) = Prover::setup_deterministic(3, 1).unwrap();
+        let result = Verifier::new(other_vk).verify_batch(&batch);
+        assert!(matches!(result, Err(VerifierError::VerificationFailed(_))));
+    }
+
+    #[test]
+    fn test_batch_rejects_one_bad_proof() {
+        let (mut proofs, vk) = eight_proofs();
+        let pvk = prepare_verifying_key(&vk);
+
+        proofs[5].public_input += Fr::from(1u64);
+        assert!(!batch(&vk, proofs).verify(&pvk).unwrap());
+    }
+
+    #[test]
+    fn test_batch_rejects_swapped_components() {
+        let (mut proofs, vk) = eight_proofs();
+        let pvk = prepare_verifying_key(&vk);
+
+        let c = proofs[0].proof.c;
+        proofs[0].proof.c = proofs[1].proof.c;
+        proofs[1].proof.c = c;
+        assert!(!batch(&vk, proofs).verify(&pvk).unwrap());
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let (_, vk) = Prover::setup(1).unwrap();
+        assert!(BatchBuilder::new(&vk).build().is_none());
+    }
+}
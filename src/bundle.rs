@@ -0,0 +1,63 @@
+//! Self-describing proof envelopes for sharing proofs between services.
+//!
+//! A `ProofBundle` carries everything a verifier needs besides the key
+//! itself: the compressed proof, its public inputs in allocation order,
+//! and the fingerprint of the verifying key it was made for, so a
+//! verifier holding another key can reject it up front. See
+//! `Verifier::verify_bundle`.
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Proof;
+use ark_serialize::CanonicalSerialize;
+
+use crate::merkle::hash::field_to_canonical_bytes;
+use crate::ProofType;
+
+/// A proof with its public inputs and verifying key fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProofBundle {
+    /// What the proof claims
+    pub proof_type: ProofType,
+    /// The compressed Groth16 proof
+    pub proof: Vec<u8>,
+    /// `verifying_key_fingerprint` of the key the proof was made for
+    pub vk_fingerprint: String,
+    /// Canonical field element bytes, in the circuit's allocation order
+    pub public_inputs: Vec<Vec<u8>>,
+    /// Unix time (seconds) the proof was generated at
+    pub generated_at: u64,
+}
+
+impl ProofBundle {
+    /// Bundle a proof with its public inputs.
+    ///
+    /// `public_inputs` is the circuit's `input_fields`, e.g.
+    /// `EmailDomainCircuit::input_fields(&inputs)`.
+    pub fn new(
+        proof_type: ProofType,
+        proof: &Proof<Bn254>,
+        public_inputs: &[Fr],
+        vk_fingerprint: String,
+        generated_at: u64,
+    ) -> Self {
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        Self {
+            proof_type,
+            proof: proof_bytes,
+            vk_fingerprint,
+            public_inputs: public_inputs.iter().map(field_to_canonical_bytes).collect(),
+            generated_at,
+        }
+    }
+
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Parse a bundle from `to_json` output.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
@@ -0,0 +1,174 @@
+//! Challenge-based email domain proof circuit.
+//!
+//! For mail systems that strip DKIM: the server emails a one-time
+//! challenge to the claimed address, the user pastes it back, and this
+//! circuit proves knowledge of the challenge bound to the domain, without
+//! putting the challenge itself in the proof.
+//!
+//! Public inputs (in allocation order):
+//! - domain_hash: SHA-256 of the normalized domain, as a field element
+//! - commitment: Poseidon(challenge_hash, domain_hash)
+//!
+//! Private witnesses:
+//! - challenge_hash: `string_to_field` of the challenge
+//!
+//! Only someone who received the challenge at the domain can produce the
+//! commitment the server expects, so the server recomputes it with
+//! `challenge_commitment` rather than trusting the prover's value.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::email_proof::{normalize_domain, string_to_field, EmailInputError};
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use crate::merkle::hash::PoseidonHasher;
+use crate::prover::CircuitTag;
+
+/// Commitment a challenge proof for `domain` must carry.
+///
+/// `Poseidon(string_to_field(challenge), string_to_field(domain))`, with
+/// the domain normalized first.
+pub fn challenge_commitment(challenge: &str, domain: &str) -> Fr {
+    let domain_hash = string_to_field(&normalize_domain(domain));
+    PoseidonHasher::new().hash_two(&string_to_field(challenge), &domain_hash)
+}
+
+/// Circuit proving knowledge of a challenge sent to a domain.
+#[derive(Clone)]
+pub struct ChallengeEmailCircuit {
+    /// Poseidon configuration for hashing
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: hash of the challenge
+    pub challenge_hash: Option<Fr>,
+
+    /// Public: hash of the normalized domain
+    pub domain_hash: Option<Fr>,
+
+    /// Public: Poseidon(challenge_hash, domain_hash)
+    pub commitment: Option<Fr>,
+}
+
+impl ChallengeEmailCircuit {
+    /// Create an empty circuit for trusted setup.
+    pub fn new_empty() -> Self {
+        let hasher = PoseidonHasher::new();
+        let zero = Fr::from(0u64);
+        Self {
+            poseidon_config: hasher.config().clone(),
+            challenge_hash: Some(zero),
+            domain_hash: Some(zero),
+            commitment: Some(hasher.hash_two(&zero, &zero)),
+        }
+    }
+
+    /// Create a circuit for the challenge received at `domain`.
+    ///
+    /// Fails if the domain is empty after normalization.
+    pub fn new_with_witness(domain: &str, challenge: &str) -> Result<Self, EmailInputError> {
+        let domain = normalize_domain(domain);
+        if domain.is_empty() {
+            return Err(EmailInputError::InvalidEmail(domain));
+        }
+
+        let hasher = PoseidonHasher::new();
+        let challenge_hash = string_to_field(challenge);
+        let domain_hash = string_to_field(&domain);
+        Ok(Self {
+            poseidon_config: hasher.config().clone(),
+            challenge_hash: Some(challenge_hash),
+            domain_hash: Some(domain_hash),
+            commitment: Some(hasher.hash_two(&challenge_hash, &domain_hash)),
+        })
+    }
+
+    /// Get the domain hash (public input)
+    pub fn get_domain_hash(&self) -> Option<Fr> {
+        self.domain_hash
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ChallengeEmailCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let challenge_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.challenge_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Allocate public inputs: [domain_hash, commitment]
+        let domain_hash_var = FpVar::new_input(cs.clone(), || {
+            self.domain_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        poseidon_hash_many(cs, &self.poseidon_config, [&challenge_hash_var, &domain_hash_var])?
+            .enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for ChallengeEmailCircuit {
+    type SetupParams = ();
+    /// `(domain_hash, commitment)`.
+    type PublicInputs = (Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::ChallengeEmail;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["domain_hash", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr)> {
+        Some((self.domain_hash?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::GenericProver;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_challenge_proof_verifies_expected_commitment() {
+        let mut rng = StdRng::seed_from_u64(880);
+        let prover = GenericProver::<ChallengeEmailCircuit>::setup(&(), &mut rng).unwrap();
+        let domain_hash = string_to_field("corp.example");
+        let expected = challenge_commitment("K7-QX2P-91", "Corp.Example.");
+
+        let circuit = ChallengeEmailCircuit::new_with_witness("corp.example", "K7-QX2P-91").unwrap();
+        let (proof, (proven_domain, commitment)) = prover.prove(circuit, &mut rng).unwrap();
+        assert_eq!(proven_domain, domain_hash);
+        assert_eq!(commitment, expected);
+        assert!(prover.verify(&proof, &(domain_hash, expected)).unwrap());
+        assert!(!prover.verify(&proof, &(string_to_field("other.example"), expected)).unwrap());
+
+        // A wrong challenge does not reach the commitment the server expects
+        let wrong = ChallengeEmailCircuit::new_with_witness("corp.example", "K7-QX2P-92").unwrap();
+        let (wrong_proof, (_, wrong_commitment)) = prover.prove(wrong, &mut rng).unwrap();
+        assert_ne!(wrong_commitment, expected);
+        assert!(!prover.verify(&wrong_proof, &(domain_hash, expected)).unwrap());
+
+        assert!(ChallengeEmailCircuit::new_with_witness(" . ", "K7-QX2P-91").is_err());
+    }
+}
@@ -0,0 +1,412 @@
+//! Combined email-domain and country proof circuit.
+//!
+//! Proves "I own an email at domain X AND I'm located in country Y" in a
+//! single Groth16 proof, so the two claims cannot be mixed from different
+//! people.
+//!
+//! How it works:
+//! 1. Email half: same hashes as `EmailDomainCircuit` (email, domain, DKIM)
+//! 2. Country half: coordinates are range-checked against the country's
+//!    bounding box in-circuit
+//! 3. Both halves are folded into ONE commitment with ONE shared nonce
+//!
+//! Public inputs (in allocation order):
+//! - domain_hash: Hash of the domain being proven
+//! - country_id: `country_code_to_field(country_code)`
+//! - bounds_hash: Poseidon of the shifted, scaled box
+//! - commitment: Poseidon(email_hash, domain_hash, dkim_hash, lat, lng,
+//!   country_id, min_lat, max_lat, min_lng, max_lng, nonce)
+//!
+//! Private witnesses:
+//! - email_hash, dkim_hash, nonce
+//! - lat, lng (shifted, scaled coordinates)
+//! - the country bounds (shifted, scaled)
+//!
+//! The verifier must recompute `bounds_hash` from its own copy of the
+//! country named by `country_id` (see `exclusion_bounds_hash`); otherwise
+//! the prover could supply any box containing their point.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    fields::fp::FpVar,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::country_proof::{country_code_to_field, coords_to_fields, CoordError, ScaledBounds};
+use super::email_proof::{random_nonce, string_to_field, EmailProofInput};
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use super::range::{enforce_in_range, COORD_BITS};
+use crate::merkle::hash::PoseidonHasher;
+use crate::prover::CircuitTag;
+
+/// Circuit proving email domain ownership and country location together.
+#[derive(Clone)]
+pub struct CompositeCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Hash of the email address
+    pub email_hash: Option<Fr>,
+    /// Private: Hash of DKIM data
+    pub dkim_hash: Option<Fr>,
+    /// Private: Nonce shared by both halves of the proof
+    pub nonce: Option<Fr>,
+    /// Private: Shifted, scaled latitude
+    pub latitude: Option<Fr>,
+    /// Private: Shifted, scaled longitude
+    pub longitude: Option<Fr>,
+    /// Private: Shifted, scaled bounds `[min_lat, max_lat, min_lng, max_lng]`
+    pub bounds: Option<[Fr; 4]>,
+
+    /// Public: Hash of the domain
+    pub domain_hash: Option<Fr>,
+    /// Public: Country identifier
+    pub country_id: Option<Fr>,
+    /// Public: Hash of the bounds
+    pub bounds_hash: Option<Fr>,
+    /// Public: Commitment binding both halves
+    pub commitment: Option<Fr>,
+}
+
+impl CompositeCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        let hasher = PoseidonHasher::new();
+
+        // Dummy values for setup; a zero-sized box around zero is in range
+        let email_hash = Fr::from(0u64);
+        let dkim_hash = Fr::from(1u64);
+        let nonce = Fr::from(2u64);
+        let domain_hash = Fr::from(3u64);
+        let country_id = Fr::from(4u64);
+        let lat = Fr::from(0u64);
+        let lng = Fr::from(0u64);
+        let bounds = [Fr::from(0u64); 4];
+
+        let commitment = Self::compute_commitment(
+            &hasher, email_hash, domain_hash, dkim_hash, lat, lng, country_id, &bounds, nonce,
+        );
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            email_hash: Some(email_hash),
+            dkim_hash: Some(dkim_hash),
+            nonce: Some(nonce),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            bounds: Some(bounds),
+            domain_hash: Some(domain_hash),
+            country_id: Some(country_id),
+            bounds_hash: Some(hasher.hash_many(&bounds)),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// The coordinates are checked against `bounds` in-circuit, so a
+    /// location outside the box produces an unsatisfiable circuit. Fails
+    /// if they are not a valid latitude and longitude, or no entropy is
+    /// available for the nonce.
+    pub fn new_with_witness(
+        input: &EmailProofInput,
+        latitude: f64,
+        longitude: f64,
+        bounds: &ScaledBounds,
+        country_code: &str,
+    ) -> Result<Self, CoordError> {
+        let hasher = PoseidonHasher::new();
+
+        let email_hash = string_to_field(&input.email);
+        let domain_hash = string_to_field(&input.domain);
+        let dkim_hash = string_to_field(&input.dkim_data);
+        let (lat, lng) = coords_to_fields(latitude, longitude)?;
+        let country_id = country_code_to_field(country_code);
+        let bounds = bounds.to_fields();
+
+        // One nonce for both halves
+        let nonce = random_nonce()?;
+
+        let commitment = Self::compute_commitment(
+            &hasher, email_hash, domain_hash, dkim_hash, lat, lng, country_id, &bounds, nonce,
+        );
+
+        Ok(Self {
+            poseidon_config: hasher.config().clone(),
+            email_hash: Some(email_hash),
+            dkim_hash: Some(dkim_hash),
+            nonce: Some(nonce),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            bounds: Some(bounds),
+            domain_hash: Some(domain_hash),
+            country_id: Some(country_id),
+            bounds_hash: Some(hasher.hash_many(&bounds)),
+            commitment: Some(commitment),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compute_commitment(
+        hasher: &PoseidonHasher,
+        email_hash: Fr,
+        domain_hash: Fr,
+        dkim_hash: Fr,
+        lat: Fr,
+        lng: Fr,
+        country_id: Fr,
+        bounds: &[Fr; 4],
+        nonce: Fr,
+    ) -> Fr {
+        hasher.hash_many(&[
+            email_hash, domain_hash, dkim_hash, lat, lng, country_id,
+            bounds[0], bounds[1], bounds[2], bounds[3], nonce,
+        ])
+    }
+
+    /// Get the domain hash (public input)
+    pub fn get_domain_hash(&self) -> Option<Fr> {
+        self.domain_hash
+    }
+
+    /// Get the country identifier (public input)
+    pub fn get_country_id(&self) -> Option<Fr> {
+        self.country_id
+    }
+
+    /// Get the bounds hash (public input)
+    pub fn get_bounds_hash(&self) -> Option<Fr> {
+        self.bounds_hash
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for CompositeCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let email_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.email_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let dkim_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.dkim_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lat_var = FpVar::new_witness(cs.clone(), || {
+            self.latitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lng_var = FpVar::new_witness(cs.clone(), || {
+            self.longitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let bound_vars: Vec<FpVar<Fr>> = (0..4)
+            .map(|i| {
+                FpVar::new_witness(cs.clone(), || {
+                    self.bounds
+                        .map(|b| b[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Allocate public inputs: [domain_hash, country_id, bounds_hash, commitment]
+        let domain_hash_var = FpVar::new_input(cs.clone(), || {
+            self.domain_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let country_id_var = FpVar::new_input(cs.clone(), || {
+            self.country_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let bounds_hash_var = FpVar::new_input(cs.clone(), || {
+            self.bounds_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // The private box is the one the verifier expects
+        poseidon_hash_many(cs.clone(), &self.poseidon_config, &bound_vars)?
+            .enforce_equal(&bounds_hash_var)?;
+
+        // Country half: coordinates must lie within the bounding box
+        enforce_in_range(cs.clone(), &lat_var, &bound_vars[0], &bound_vars[1], COORD_BITS)?;
+        enforce_in_range(cs.clone(), &lng_var, &bound_vars[2], &bound_vars[3], COORD_BITS)?;
+
+        // Bind both halves into a single commitment with the shared nonce
+        let computed_commitment = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            &[
+                email_hash_var,
+                domain_hash_var,
+                dkim_hash_var,
+                lat_var,
+                lng_var,
+                country_id_var,
+                bound_vars[0].clone(),
+                bound_vars[1].clone(),
+                bound_vars[2].clone(),
+                bound_vars[3].clone(),
+                nonce_var,
+            ],
+        )?;
+
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for CompositeCircuit {
+    type SetupParams = ();
+    /// `(domain_hash, country_id, bounds_hash, commitment)`.
+    type PublicInputs = (Fr, Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::Composite;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["domain_hash", "country_id", "bounds_hash", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr, Fr)> {
+        Some((self.domain_hash?, self.country_id?, self.bounds_hash?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2, inputs.3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn us_bounds() -> ScaledBounds {
+        ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457)
+    }
+
+    fn google_input() -> EmailProofInput {
+        EmailProofInput::new("alice@google.com", "dkim-signature-data", "dkim=pass")
+    }
+
+    #[test]
+    fn test_composite_circuit_valid() {
+        let circuit = CompositeCircuit::new_with_witness(
+            &google_input(),
+            37.7749,
+            -122.4194,
+            &us_bounds(),
+            "US",
+        )
+        .unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        println!("Composite circuit constraints: {}", cs.num_constraints());
+        assert!(cs.is_satisfied().unwrap(), "Circuit should be satisfied");
+    }
+
+    #[test]
+    fn test_composite_circuit_setup() {
+        let circuit = CompositeCircuit::new_empty();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(cs.is_satisfied().unwrap(), "Setup circuit should be satisfied");
+    }
+
+    #[test]
+    fn test_composite_swapped_country_fails() {
+        let mut circuit = CompositeCircuit::new_with_witness(
+            &google_input(),
+            37.7749,
+            -122.4194,
+            &us_bounds(),
+            "US",
+        )
+        .unwrap();
+        // Claim a different country with the same email half
+        circuit.country_id = Some(country_code_to_field("CA"));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_composite_bounds_bound_to_public_hash() {
+        use crate::circuit::exclusion_bounds_hash;
+        use crate::proofs::location::COUNTRIES;
+
+        let us = COUNTRIES.iter().find(|c| c.code == "US").unwrap();
+        let circuit = CompositeCircuit::new_with_witness(
+            &google_input(),
+            37.7749,
+            -122.4194,
+            &ScaledBounds::new(us.min_lat, us.max_lat, us.min_lng, us.max_lng),
+            "US",
+        )
+        .unwrap();
+        assert_eq!(circuit.get_bounds_hash(), Some(exclusion_bounds_hash(us)));
+
+        // A made-up box around the point cannot pose as the US box
+        let mut forged = CompositeCircuit::new_with_witness(
+            &google_input(),
+            48.8566,
+            2.3522,
+            &ScaledBounds::new(48.0, 49.0, 2.0, 3.0),
+            "US",
+        )
+        .unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        forged.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        forged.bounds_hash = Some(exclusion_bounds_hash(us));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        forged.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_composite_out_of_bounds_fails() {
+        // Paris is not inside the US bounding box
+        let circuit = CompositeCircuit::new_with_witness(
+            &google_input(),
+            48.8566,
+            2.3522,
+            &us_bounds(),
+            "US",
+        )
+        .unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
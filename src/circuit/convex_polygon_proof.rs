@@ -0,0 +1,288 @@
+//! Convex polygon location proof circuit.
+//!
+//! Proves that private coordinates lie inside a public convex polygon,
+//! a tighter fit than a bounding box for oddly shaped areas. With the
+//! vertices in counter-clockwise order (longitude as x, latitude as y), a
+//! point is inside exactly when it is on the left of, or on, every edge:
+//! the 2D cross product of the edge and the vector to the point is
+//! non-negative.
+//!
+//! Polygons are padded to `MAX_CONVEX_VERTICES` by repeating the last
+//! vertex; the zero-length edges this adds have a zero cross product, so
+//! every polygon shares one circuit shape. Points on the boundary count
+//! as inside.
+//!
+//! Public inputs (in allocation order):
+//! - the padded vertices, as shifted `(lat, lng)` pairs
+//! - commitment: Poseidon(lat, lng, nonce)
+//!
+//! Private witnesses:
+//! - lat, lng (shifted, scaled coordinates)
+//! - nonce
+//!
+//! Convexity is not checked in-circuit; the verifier supplies the
+//! vertices, normally from `convex_polygon_fields`.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::country_proof::{coords_to_fields, normalize_coords, CoordError, LAT_OFFSET, LNG_OFFSET};
+use super::email_proof::random_nonce;
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use super::range::{enforce_bit_length, COORD_BITS};
+use super::region_proof::CROSS_BITS;
+use crate::merkle::hash::PoseidonHasher;
+use crate::prover::CircuitTag;
+
+/// Maximum number of vertices of a convex polygon.
+pub const MAX_CONVEX_VERTICES: usize = 8;
+
+/// Public input names of the padded vertices, in allocation order.
+const VERTEX_INPUTS: [&str; 2 * MAX_CONVEX_VERTICES] = [
+    "vertex_0_lat", "vertex_0_lng", "vertex_1_lat", "vertex_1_lng",
+    "vertex_2_lat", "vertex_2_lng", "vertex_3_lat", "vertex_3_lng",
+    "vertex_4_lat", "vertex_4_lng", "vertex_5_lat", "vertex_5_lng",
+    "vertex_6_lat", "vertex_6_lng", "vertex_7_lat", "vertex_7_lng",
+];
+
+/// Padded, counter-clockwise vertices of a convex polygon as circuit
+/// inputs.
+///
+/// `polygon` is `(lat, lng)` pairs in either winding order. Returns
+/// `None` if it has fewer than 3 or more than `MAX_CONVEX_VERTICES`
+/// vertices, an invalid coordinate, no area, or is not convex.
+pub fn convex_polygon_fields(polygon: &[(f64, f64)]) -> Option<Vec<(Fr, Fr)>> {
+    if polygon.len() < 3 || polygon.len() > MAX_CONVEX_VERTICES {
+        return None;
+    }
+    let mut points: Vec<(i128, i128)> = polygon
+        .iter()
+        .map(|&(lat, lng)| {
+            normalize_coords(lat, lng)
+                .ok()
+                .map(|(lat, lng)| (i128::from(lat + LAT_OFFSET), i128::from(lng + LNG_OFFSET)))
+        })
+        .collect::<Option<_>>()?;
+
+    // Turn of each corner; all of one sign for a convex polygon
+    let turns = |points: &[(i128, i128)]| -> Vec<i128> {
+        (0..points.len())
+            .map(|i| {
+                let (a, b, c) = (points[i], points[(i + 1) % points.len()], points[(i + 2) % points.len()]);
+                edge_cross(a, b, c)
+            })
+            .collect()
+    };
+    if turns(&points).iter().sum::<i128>() < 0 {
+        points.reverse();
+    }
+    let turns = turns(&points);
+    if turns.iter().any(|&t| t < 0) || turns.iter().all(|&t| t == 0) {
+        return None;
+    }
+
+    let mut vertices: Vec<(Fr, Fr)> = points
+        .iter()
+        .map(|&(lat, lng)| (Fr::from(lat as u64), Fr::from(lng as u64)))
+        .collect();
+    vertices.resize(MAX_CONVEX_VERTICES, vertices[polygon.len() - 1]);
+    Some(vertices)
+}
+
+/// Cross product of edge `a -> b` and `a -> p`, with points as `(lat, lng)`
+/// and longitude as x; non-negative when `p` is left of or on the edge.
+fn edge_cross(a: (i128, i128), b: (i128, i128), p: (i128, i128)) -> i128 {
+    (b.1 - a.1) * (p.0 - a.0) - (b.0 - a.0) * (p.1 - a.1)
+}
+
+/// Circuit for proving location is within a convex polygon.
+#[derive(Clone)]
+pub struct ConvexPolygonCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Shifted, scaled latitude
+    pub latitude: Option<Fr>,
+    /// Private: Shifted, scaled longitude
+    pub longitude: Option<Fr>,
+    /// Private: Random nonce for unlinkability
+    pub nonce: Option<Fr>,
+
+    /// Public: Padded counter-clockwise vertices as shifted `(lat, lng)`
+    pub vertices: Option<Vec<(Fr, Fr)>>,
+    /// Public: Poseidon(lat, lng, nonce)
+    pub commitment: Option<Fr>,
+}
+
+impl ConvexPolygonCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        // Dummy values for setup: (1, 1) in a square around it
+        let square = [(0u64, 0u64), (0, 2), (2, 2), (2, 0)];
+        let mut vertices: Vec<(Fr, Fr)> =
+            square.iter().map(|&(lat, lng)| (Fr::from(lat), Fr::from(lng))).collect();
+        vertices.resize(MAX_CONVEX_VERTICES, vertices[square.len() - 1]);
+
+        Self::from_fields(Fr::from(1u64), Fr::from(1u64), Fr::from(2u64), vertices)
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// A location outside the polygon produces an unsatisfiable circuit.
+    /// Fails if the coordinates are invalid, the polygon is rejected by
+    /// `convex_polygon_fields`, or no entropy is available for the nonce.
+    pub fn new_with_witness(latitude: f64, longitude: f64, polygon: &[(f64, f64)]) -> Result<Self, CoordError> {
+        let (lat, lng) = coords_to_fields(latitude, longitude)?;
+        let vertices = convex_polygon_fields(polygon)
+            .ok_or_else(|| CoordError::UnsupportedArea("polygon is not a supported convex polygon".to_string()))?;
+
+        let nonce = random_nonce()?;
+
+        Ok(Self::from_fields(lat, lng, nonce, vertices))
+    }
+
+    fn from_fields(lat: Fr, lng: Fr, nonce: Fr, vertices: Vec<(Fr, Fr)>) -> Self {
+        let hasher = PoseidonHasher::new();
+        let commitment = hasher.hash_many(&[lat, lng, nonce]);
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            nonce: Some(nonce),
+            vertices: Some(vertices),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ConvexPolygonCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let lat_var = FpVar::new_witness(cs.clone(), || {
+            self.latitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lng_var = FpVar::new_witness(cs.clone(), || {
+            self.longitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Allocate public inputs: [vertices..., commitment]
+        let vertex_vars: Vec<(FpVar<Fr>, FpVar<Fr>)> = (0..MAX_CONVEX_VERTICES)
+            .map(|i| {
+                let vertex = || {
+                    self.vertices
+                        .as_ref()
+                        .and_then(|v| v.get(i).copied())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                };
+                let lat = FpVar::new_input(cs.clone(), || vertex().map(|v| v.0))?;
+                let lng = FpVar::new_input(cs.clone(), || vertex().map(|v| v.1))?;
+                Ok((lat, lng))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Bound every coordinate so the cross products below cannot wrap
+        enforce_bit_length(cs.clone(), &lat_var, COORD_BITS)?;
+        enforce_bit_length(cs.clone(), &lng_var, COORD_BITS)?;
+        for (lat, lng) in &vertex_vars {
+            enforce_bit_length(cs.clone(), lat, COORD_BITS)?;
+            enforce_bit_length(cs.clone(), lng, COORD_BITS)?;
+        }
+
+        // The point is left of or on every edge. A negative cross product
+        // wraps to a field element far wider than CROSS_BITS.
+        for i in 0..MAX_CONVEX_VERTICES {
+            let (lat_a, lng_a) = &vertex_vars[i];
+            let (lat_b, lng_b) = &vertex_vars[(i + 1) % MAX_CONVEX_VERTICES];
+            let cross = (lng_b - lng_a) * (&lat_var - lat_a) - (lat_b - lat_a) * (&lng_var - lng_a);
+            enforce_bit_length(cs.clone(), &cross, CROSS_BITS)?;
+        }
+
+        // Bind the location into the commitment
+        let computed_commitment =
+            poseidon_hash_many(cs.clone(), &self.poseidon_config, &[lat_var, lng_var, nonce_var])?;
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for ConvexPolygonCircuit {
+    type SetupParams = ();
+    /// `(vertices, commitment)`.
+    type PublicInputs = (Vec<(Fr, Fr)>, Fr);
+
+    const TAG: CircuitTag = CircuitTag::ConvexPolygon;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        let mut schema = VERTEX_INPUTS.to_vec();
+        schema.push("commitment");
+        schema
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Vec<(Fr, Fr)>, Fr)> {
+        Some((self.vertices.clone()?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Vec<(Fr, Fr)>, Fr)) -> Vec<Fr> {
+        let mut fields: Vec<Fr> = inputs.0.iter().flat_map(|&(lat, lng)| [lat, lng]).collect();
+        fields.push(inputs.1);
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+
+    /// Roughly the Nevada - Utah - Arizona corner area.
+    const TRIANGLE: [(f64, f64); 3] = [(35.0, -115.0), (40.0, -114.0), (36.0, -110.0)];
+
+    #[test]
+    fn test_point_in_triangle() {
+        let inside = ConvexPolygonCircuit::new_with_witness(37.0, -113.0, &TRIANGLE).unwrap();
+        assert!(is_satisfied(inside));
+
+        // Either winding order gives the same vertices
+        let mut reversed = TRIANGLE;
+        reversed.reverse();
+        assert_eq!(convex_polygon_fields(&reversed), convex_polygon_fields(&TRIANGLE));
+
+        // Inside the bounding box but outside the triangle
+        let outside = ConvexPolygonCircuit::new_with_witness(39.5, -111.0, &TRIANGLE).unwrap();
+        assert!(!is_satisfied(outside));
+
+        assert!(is_satisfied(ConvexPolygonCircuit::new_empty()));
+    }
+
+    #[test]
+    fn test_non_convex_polygon_rejected() {
+        let dart = [(0.0, 0.0), (4.0, 2.0), (0.0, 4.0), (1.0, 2.0)];
+        assert!(convex_polygon_fields(&dart).is_none());
+        assert!(convex_polygon_fields(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]).is_none());
+        assert!(convex_polygon_fields(&TRIANGLE[..2]).is_none());
+    }
+}
@@ -0,0 +1,284 @@
+//! Corporate email proof circuit.
+//!
+//! Proves that an email's domain is, or is not, a freemail provider
+//! without revealing the domain. The freemail domains are keys of a
+//! sparse Merkle tree (value 1 for each); a domain outside the set has
+//! value zero. The tree's root is baked into the circuit as a constant,
+//! so the verifying key fixes the blocklist.
+//!
+//! Public inputs (in allocation order):
+//! - is_corporate: 1 if the domain is not a freemail domain, else 0
+//! - commitment: Poseidon(email_hash, domain_hash, dkim_hash, nonce), as
+//!   in `EmailDomainCircuit`
+//!
+//! Private witnesses:
+//! - email_hash, domain_hash, dkim_hash, nonce
+//! - the domain's value in the freemail tree and its sibling path
+//!
+//! The domain hash is the key of the lookup, so the path cannot be
+//! swapped for another domain's.
+
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+    ToBitsGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::email_proof::{random_nonce, string_to_field, EmailInputError, EmailProofInput};
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use crate::merkle::hash::PoseidonHasher;
+use crate::merkle::sparse::{SparseMerkleTree, SMT_DEPTH};
+use crate::prover::CircuitTag;
+
+/// Freemail provider domains a corporate proof rejects.
+pub const FREEMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "yahoo.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "aol.com",
+    "icloud.com",
+    "proton.me",
+    "protonmail.com",
+    "gmx.com",
+    "mail.com",
+    "yandex.com",
+    "zoho.com",
+];
+
+/// Sparse Merkle tree mapping each freemail domain hash to 1.
+pub fn freemail_tree() -> SparseMerkleTree {
+    let mut tree = SparseMerkleTree::new();
+    for domain in FREEMAIL_DOMAINS {
+        tree.insert(string_to_field(domain), Fr::from(1u64));
+    }
+    tree
+}
+
+/// Root of `freemail_tree`, the constant checked by the circuit.
+pub fn freemail_root() -> Fr {
+    freemail_tree().root()
+}
+
+/// Whether a (normalized) domain is on the freemail list.
+pub fn is_freemail_domain(domain: &str) -> bool {
+    FREEMAIL_DOMAINS.contains(&domain)
+}
+
+/// Circuit for proving an email domain is not a freemail provider.
+#[derive(Clone)]
+pub struct CorporateEmailCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+    /// Root of the freemail tree, allocated as a constant
+    pub freemail_root: Fr,
+
+    /// Private: Hash of the email address
+    pub email_hash: Option<Fr>,
+    /// Private: Hash of the domain
+    pub domain_hash: Option<Fr>,
+    /// Private: Hash of DKIM data
+    pub dkim_hash: Option<Fr>,
+    /// Private: Random nonce for uniqueness
+    pub nonce: Option<Fr>,
+    /// Private: The domain's value in the freemail tree (1 or 0)
+    pub freemail_value: Option<Fr>,
+    /// Private: Sibling hashes along the domain's path
+    pub siblings: Vec<Option<Fr>>,
+
+    /// Public: Whether the domain is corporate
+    pub is_corporate: Option<bool>,
+    /// Public: Commitment to the private data
+    pub commitment: Option<Fr>,
+}
+
+impl CorporateEmailCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        Self::from_fields(Fr::from(0u64), Fr::from(3u64), Fr::from(1u64), Fr::from(2u64))
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// `is_corporate` is set from the domain's real classification, so a
+    /// freemail domain yields a circuit that proves `is_corporate = 0`.
+    /// Fails only if the platform entropy source is unavailable.
+    pub fn new_with_witness(input: &EmailProofInput) -> Result<Self, EmailInputError> {
+        Ok(Self::from_fields(
+            string_to_field(&input.email),
+            string_to_field(&input.domain),
+            string_to_field(&input.dkim_data),
+            random_nonce()?,
+        ))
+    }
+
+    fn from_fields(email_hash: Fr, domain_hash: Fr, dkim_hash: Fr, nonce: Fr) -> Self {
+        let hasher = PoseidonHasher::new();
+        let tree = freemail_tree();
+        let proof = tree.prove(&domain_hash);
+        let commitment = hasher.hash_many(&[email_hash, domain_hash, dkim_hash, nonce]);
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            freemail_root: tree.root(),
+            email_hash: Some(email_hash),
+            domain_hash: Some(domain_hash),
+            dkim_hash: Some(dkim_hash),
+            nonce: Some(nonce),
+            freemail_value: Some(proof.value),
+            siblings: proof.siblings.iter().map(|s| Some(*s)).collect(),
+            is_corporate: Some(proof.value.is_zero()),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Whether the domain is corporate (public input)
+    pub fn is_corporate(&self) -> Option<bool> {
+        self.is_corporate
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for CorporateEmailCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let email_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.email_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let domain_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.domain_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let dkim_hash_var = FpVar::new_witness(cs.clone(), || {
+            self.dkim_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let value_var = FpVar::new_witness(cs.clone(), || {
+            self.freemail_value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let sibling_vars: Vec<FpVar<Fr>> = self
+            .siblings
+            .iter()
+            .map(|sibling| {
+                FpVar::new_witness(cs.clone(), || {
+                    sibling.ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if sibling_vars.len() != SMT_DEPTH {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // Allocate public inputs: [is_corporate, commitment]
+        let is_corporate_var = Boolean::new_input(cs.clone(), || {
+            self.is_corporate.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Look up the domain in the freemail tree; the path follows its bits
+        let mut index_vars = domain_hash_var.to_bits_le()?;
+        index_vars.resize(SMT_DEPTH, Boolean::FALSE);
+
+        let mut current = value_var.clone();
+        for (sibling, is_right) in sibling_vars.iter().zip(index_vars.iter()) {
+            let left = FpVar::conditionally_select(is_right, sibling, &current)?;
+            let right = FpVar::conditionally_select(is_right, &current, sibling)?;
+            current = poseidon_hash_many(cs.clone(), &self.poseidon_config, &[left, right])?;
+        }
+        current.enforce_equal(&FpVar::Constant(self.freemail_root))?;
+
+        // Freemail domains hold 1, everything else 0
+        let is_freemail = FpVar::Constant(Fr::from(1u64)) - FpVar::from(is_corporate_var);
+        value_var.enforce_equal(&is_freemail)?;
+
+        // Bind the domain into the same commitment as the email circuit
+        let computed_commitment = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            &[email_hash_var, domain_hash_var, dkim_hash_var, nonce_var],
+        )?;
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for CorporateEmailCircuit {
+    type SetupParams = ();
+    /// `(is_corporate, commitment)`, with `is_corporate` as 0 or 1.
+    type PublicInputs = (Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::CorporateEmail;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["is_corporate", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr)> {
+        Some((Fr::from(self.is_corporate?), self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+
+    #[test]
+    fn test_corporate_and_freemail_classification() {
+        let corporate = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+        let circuit = CorporateEmailCircuit::new_with_witness(&corporate).unwrap();
+        assert_eq!(circuit.is_corporate(), Some(true));
+        assert!(is_satisfied(circuit));
+
+        let freemail = EmailProofInput::new("bob@gmail.com", "sig", "dkim=pass");
+        let circuit = CorporateEmailCircuit::new_with_witness(&freemail).unwrap();
+        assert_eq!(circuit.is_corporate(), Some(false));
+        assert!(is_satisfied(circuit.clone()));
+
+        // Claiming a freemail domain is corporate fails
+        let mut forged = circuit;
+        forged.is_corporate = Some(true);
+        assert!(!is_satisfied(forged.clone()));
+        forged.freemail_value = Some(Fr::from(0u64));
+        assert!(!is_satisfied(forged));
+    }
+
+    #[test]
+    fn test_empty_circuit_for_setup() {
+        assert!(is_satisfied(CorporateEmailCircuit::new_empty()));
+    }
+}
@@ -9,13 +9,28 @@
 //! 3. The hash(lat, lng, bounds, country_code) matches a commitment
 //!
 //! This is a simpler but still valid ZK proof approach.
+//!
+//! For one-claim-per-epoch use cases the circuit also exposes a nullifier
+//! `Poseidon(lat, lng, epoch)` for a public `epoch`, so the same location
+//! always maps to the same nullifier within an epoch.
+//!
+//! The country id is public so a verifier can check which country a proof
+//! claims; otherwise a proof committing to any id (even one for no real
+//! country) would verify against every country claim.
+//!
+//! The commitment also folds in a public `issued_at` Unix timestamp, so a
+//! verifier can reject proofs older than it is willing to accept.
+//!
+//! A public `identity_commitment = Poseidon(identity_secret)` lets a
+//! verifier link this proof to others made with the same secret (see
+//! `identity`).
+//!
+//! Public inputs (in allocation order): country_id, commitment, epoch,
+//! nullifier, issued_at, identity_commitment.
 
 use ark_bn254::Fr;
 use ark_ff::PrimeField;
-use ark_crypto_primitives::sponge::{
-    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig},
-    constraints::CryptographicSpongeVar,
-};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
 use ark_r1cs_std::{
     alloc::AllocVar,
     eq::EqGadget,
@@ -24,7 +39,12 @@ use ark_r1cs_std::{
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use sha2::{Digest, Sha256};
 
+use super::email_proof::{random_nonce, EmailInputError};
+use super::identity::{enforce_identity_commitment, identity_commitment};
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
 use crate::merkle::hash::PoseidonHasher;
+use crate::prover::CircuitTag;
 
 /// Scale factor for fixed-point coordinates (6 decimal places)
 pub const COORD_SCALE: i64 = 1_000_000;
@@ -34,6 +54,87 @@ pub fn coord_to_scaled(coord: f64) -> i64 {
     (coord * COORD_SCALE as f64) as i64
 }
 
+/// Errors building a location circuit's witness: coordinates that fail
+/// validation, an area the circuit cannot encode, or no entropy for the
+/// circuit's random values.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CoordError {
+    #[error("Coordinates must be finite numbers, got ({0}, {1})")]
+    NotFinite(f64, f64),
+
+    #[error("Latitude {0} is outside [-90, 90]")]
+    LatitudeOutOfRange(f64),
+
+    #[error("Longitude {0} is outside [-180, 180]")]
+    LongitudeOutOfRange(f64),
+
+    #[error("Could not parse coordinate {0:?}")]
+    Unparseable(String),
+
+    #[error("Coordinate {0:?} has a hemisphere for the other axis")]
+    WrongHemisphere(String),
+
+    #[error("Area cannot be proven in-circuit: {0}")]
+    UnsupportedArea(String),
+
+    #[error("Could not generate a random nonce: {0}")]
+    Entropy(String),
+}
+
+impl From<EmailInputError> for CoordError {
+    /// `random_nonce` only fails with `EmailInputError::Entropy`.
+    fn from(e: EmailInputError) -> Self {
+        match e {
+            EmailInputError::Entropy(reason) => Self::Entropy(reason),
+            other => Self::Entropy(other.to_string()),
+        }
+    }
+}
+
+/// Validate coordinates and convert them to scaled integers.
+///
+/// The range is checked on the raw degrees, before scaling truncates
+/// them, so e.g. `90.0000001` is rejected rather than rounded to 90.
+/// NaN and infinities are rejected before any `as i64` cast, which would
+/// silently saturate them. Every circuit constructor that takes
+/// coordinates goes through this.
+pub fn normalize_coords(lat: f64, lng: f64) -> Result<(i64, i64), CoordError> {
+    if !lat.is_finite() || !lng.is_finite() {
+        return Err(CoordError::NotFinite(lat, lng));
+    }
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(CoordError::LatitudeOutOfRange(lat));
+    }
+    if !(-180.0..=180.0).contains(&lng) {
+        return Err(CoordError::LongitudeOutOfRange(lng));
+    }
+    Ok((coord_to_scaled(lat), coord_to_scaled(lng)))
+}
+
+/// Validated coordinates as shifted, non-negative field elements.
+///
+/// Same encoding as `lat_to_field` / `lng_to_field`.
+pub fn coords_to_fields(lat: f64, lng: f64) -> Result<(Fr, Fr), CoordError> {
+    let (lat, lng) = normalize_coords(lat, lng)?;
+    Ok((Fr::from((lat + LAT_OFFSET) as u64), Fr::from((lng + LNG_OFFSET) as u64)))
+}
+
+/// Offset added to scaled latitudes so they are non-negative in-circuit
+pub const LAT_OFFSET: i64 = 90 * COORD_SCALE;
+
+/// Offset added to scaled longitudes so they are non-negative in-circuit
+pub const LNG_OFFSET: i64 = 180 * COORD_SCALE;
+
+/// Convert a latitude to a shifted, non-negative field element
+pub fn lat_to_field(lat: f64) -> Fr {
+    Fr::from((coord_to_scaled(lat) + LAT_OFFSET) as u64)
+}
+
+/// Convert a longitude to a shifted, non-negative field element
+pub fn lng_to_field(lng: f64) -> Fr {
+    Fr::from((coord_to_scaled(lng) + LNG_OFFSET) as u64)
+}
+
 /// Convert country code to field element (for public input)
 pub fn country_code_to_field(code: &str) -> Fr {
     let mut hasher = Sha256::new();
@@ -42,6 +143,40 @@ pub fn country_code_to_field(code: &str) -> Fr {
     Fr::from_be_bytes_mod_order(&hash)
 }
 
+/// Nullifier for a location in an epoch: `Poseidon(lat, lng, epoch)`.
+///
+/// Coordinates are encoded exactly as in `CountryProofCircuit`. Locations
+/// are low-entropy, so a nullifier can be matched against candidate
+/// coordinates by brute force; it hides the location only loosely.
+pub fn location_nullifier(latitude: f64, longitude: f64, epoch: u64) -> Result<Fr, CoordError> {
+    let (lat, lng) = circuit_coords(latitude, longitude)?;
+    Ok(PoseidonHasher::new().hash_many(&[lat, lng, Fr::from(epoch)]))
+}
+
+/// Commitment for a location claim:
+/// `Poseidon(lat, lng, country_id, issued_at)`.
+///
+/// This is the public commitment `CountryProofCircuit::new_with_witness`
+/// produces for the same arguments, so the non-ZK path can compute it
+/// without building a circuit.
+pub fn location_commitment(
+    latitude: f64,
+    longitude: f64,
+    country_code: &str,
+    issued_at: u64,
+) -> Result<Fr, CoordError> {
+    let (lat, lng) = circuit_coords(latitude, longitude)?;
+    Ok(PoseidonHasher::new().hash_many(&[lat, lng, country_code_to_field(country_code), Fr::from(issued_at)]))
+}
+
+/// Coordinates as the field elements witnessed by `CountryProofCircuit`.
+fn circuit_coords(latitude: f64, longitude: f64) -> Result<(Fr, Fr), CoordError> {
+    let (lat, lng) = normalize_coords(latitude, longitude)?;
+    let lat = Fr::from(lat as u64);
+    let lng = Fr::from((lng + 180 * COORD_SCALE) as u64); // Shift to positive
+    Ok((lat, lng))
+}
+
 /// Country bounds as scaled integers
 #[derive(Clone, Debug)]
 pub struct ScaledBounds {
@@ -60,6 +195,16 @@ impl ScaledBounds {
             max_lng: coord_to_scaled(max_lng),
         }
     }
+
+    /// Bounds as shifted field elements: `[min_lat, max_lat, min_lng, max_lng]`
+    pub fn to_fields(&self) -> [Fr; 4] {
+        [
+            Fr::from((self.min_lat + LAT_OFFSET) as u64),
+            Fr::from((self.max_lat + LAT_OFFSET) as u64),
+            Fr::from((self.min_lng + LNG_OFFSET) as u64),
+            Fr::from((self.max_lng + LNG_OFFSET) as u64),
+        ]
+    }
 }
 
 /// Circuit for proving location is within a country's bounds.
@@ -69,8 +214,9 @@ impl ScaledBounds {
 /// using a Poseidon hash commitment.
 ///
 /// The approach:
-/// - Private witness: latitude, longitude, country_code
-/// - Public input: commitment = Poseidon(lat, lng, country_id)
+/// - Private witness: latitude, longitude
+/// - Public inputs: country_id, issued_at,
+///   commitment = Poseidon(lat, lng, country_id, issued_at)
 /// - The prover must know valid coordinates that hash to the commitment
 /// 
 /// The verifier trusts that the prover only created the commitment
@@ -84,11 +230,21 @@ pub struct CountryProofCircuit {
     pub latitude: Option<Fr>,
     /// Private: User's longitude (as field element)
     pub longitude: Option<Fr>,
-    /// Private: Country identifier
-    pub country_id: Option<Fr>,
+    /// Private: Secret shared with linked proofs
+    pub identity_secret: Option<Fr>,
     
+    /// Public: Country identifier (`country_code_to_field`)
+    pub country_id: Option<Fr>,
     /// Public: Commitment to the location proof
     pub commitment: Option<Fr>,
+    /// Public: Epoch the nullifier is scoped to
+    pub epoch: Option<Fr>,
+    /// Public: Poseidon(lat, lng, epoch)
+    pub nullifier: Option<Fr>,
+    /// Public: Unix time (seconds) the proof was issued at
+    pub issued_at: Option<Fr>,
+    /// Public: Poseidon(identity_secret)
+    pub identity_commitment: Option<Fr>,
 }
 
 impl CountryProofCircuit {
@@ -100,16 +256,25 @@ impl CountryProofCircuit {
         let lat = Fr::from(0u64);
         let lng = Fr::from(0u64);
         let country = Fr::from(0u64);
+        let epoch = Fr::from(0u64);
+        let issued_at = Fr::from(0u64);
+        let identity_secret = Fr::from(0u64);
         
-        // Compute commitment
-        let commitment = hasher.hash_many(&[lat, lng, country]);
+        // Compute commitment and nullifier
+        let commitment = hasher.hash_many(&[lat, lng, country, issued_at]);
+        let nullifier = hasher.hash_many(&[lat, lng, epoch]);
         
         Self {
             poseidon_config: hasher.config().clone(),
             latitude: Some(lat),
             longitude: Some(lng),
+            identity_secret: Some(identity_secret),
             country_id: Some(country),
             commitment: Some(commitment),
+            epoch: Some(epoch),
+            nullifier: Some(nullifier),
+            issued_at: Some(issued_at),
+            identity_commitment: Some(identity_commitment(&identity_secret)),
         }
     }
     
@@ -118,35 +283,89 @@ impl CountryProofCircuit {
     /// IMPORTANT: The caller must verify coordinates are within bounds
     /// BEFORE creating this circuit. The circuit only proves knowledge
     /// of values that hash to the commitment.
+    ///
+    /// `issued_at` is the Unix time (seconds) the proof is made at. The
+    /// identity secret is random; use `with_identity_secret` to link the
+    /// proof to others.
+    ///
+    /// Fails if the coordinates are not a valid latitude and longitude.
     pub fn new_with_witness(
         latitude: f64,
         longitude: f64,
         _bounds: &ScaledBounds, // Used by caller for verification
         country_code: &str,
-    ) -> Self {
+        epoch: u64,
+        issued_at: u64,
+    ) -> Result<Self, CoordError> {
         let hasher = PoseidonHasher::new();
         
         // Convert to field elements
-        let lat = Fr::from(coord_to_scaled(latitude) as u64);
-        let lng = Fr::from((coord_to_scaled(longitude) + 180 * COORD_SCALE) as u64); // Shift to positive
+        let (lat, lng) = circuit_coords(latitude, longitude)?;
         let country = country_code_to_field(country_code);
+        let epoch = Fr::from(epoch);
+        let issued_at = Fr::from(issued_at);
         
-        // Compute commitment
-        let commitment = hasher.hash_many(&[lat, lng, country]);
+        // Compute commitment and nullifier
+        let commitment = hasher.hash_many(&[lat, lng, country, issued_at]);
+        let nullifier = hasher.hash_many(&[lat, lng, epoch]);
         
-        Self {
+        Ok(Self {
             poseidon_config: hasher.config().clone(),
             latitude: Some(lat),
             longitude: Some(lng),
+            identity_secret: None,
             country_id: Some(country),
             commitment: Some(commitment),
+            epoch: Some(epoch),
+            nullifier: Some(nullifier),
+            issued_at: Some(issued_at),
+            identity_commitment: None,
         }
+        .with_identity_secret(random_nonce()?))
+    }
+
+    /// Use `secret` as the identity secret, so the proof's identity
+    /// commitment matches other proofs made with the same secret.
+    pub fn with_identity_secret(mut self, secret: Fr) -> Self {
+        self.identity_secret = Some(secret);
+        self.identity_commitment = Some(identity_commitment(&secret));
+        self
     }
     
+    /// Get the country identifier (public input)
+    pub fn get_country_id(&self) -> Option<Fr> {
+        self.country_id
+    }
+
     /// Get the commitment (public input)
     pub fn get_commitment(&self) -> Option<Fr> {
         self.commitment
     }
+
+    /// Get the epoch (public input)
+    pub fn get_epoch(&self) -> Option<Fr> {
+        self.epoch
+    }
+
+    /// Get the nullifier (public input)
+    pub fn get_nullifier(&self) -> Option<Fr> {
+        self.nullifier
+    }
+
+    /// Get the issue timestamp (public input)
+    pub fn get_issued_at(&self) -> Option<Fr> {
+        self.issued_at
+    }
+
+    /// Get the identity commitment (public input)
+    pub fn get_identity_commitment(&self) -> Option<Fr> {
+        self.identity_commitment
+    }
+
+    /// Number of constraints; see `ProvableCircuit::count_constraints`.
+    pub fn constraint_count() -> usize {
+        Self::count_constraints(&())
+    }
 }
 
 impl ConstraintSynthesizer<Fr> for CountryProofCircuit {
@@ -160,45 +379,97 @@ impl ConstraintSynthesizer<Fr> for CountryProofCircuit {
             self.longitude.ok_or(SynthesisError::AssignmentMissing)
         })?;
         
-        let country_var = FpVar::new_witness(cs.clone(), || {
+        let identity_secret_var = FpVar::new_witness(cs.clone(), || {
+            self.identity_secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
+        // Allocate public inputs: country_id, commitment
+        let country_var = FpVar::new_input(cs.clone(), || {
             self.country_id.ok_or(SynthesisError::AssignmentMissing)
         })?;
         
-        // Allocate public input: commitment
         let commitment_var = FpVar::new_input(cs.clone(), || {
             self.commitment.ok_or(SynthesisError::AssignmentMissing)
         })?;
         
-        // Compute Poseidon hash of (lat, lng, country)
-        let computed_commitment = poseidon_hash_three(
+        // Allocate public inputs: epoch, nullifier
+        let epoch_var = FpVar::new_input(cs.clone(), || {
+            self.epoch.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
+        let nullifier_var = FpVar::new_input(cs.clone(), || {
+            self.nullifier.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
+        // Allocate public input: issued_at
+        let issued_at_var = FpVar::new_input(cs.clone(), || {
+            self.issued_at.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
+        // Allocate public input: identity_commitment
+        let identity_commitment_var = FpVar::new_input(cs.clone(), || {
+            self.identity_commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
+        // Compute Poseidon hash of (lat, lng, country, issued_at)
+        let computed_commitment = poseidon_hash_many(
             cs.clone(),
             &self.poseidon_config,
-            &lat_var,
-            &lng_var,
-            &country_var,
+            [&lat_var, &lng_var, &country_var, &issued_at_var],
         )?;
         
         // Constraint: computed commitment must equal public commitment
         computed_commitment.enforce_equal(&commitment_var)?;
         
+        // Constraint: nullifier = Poseidon(lat, lng, epoch)
+        let computed_nullifier = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            [&lat_var, &lng_var, &epoch_var],
+        )?;
+        computed_nullifier.enforce_equal(&nullifier_var)?;
+        
+        // Constraint: identity_commitment = Poseidon(identity_secret)
+        enforce_identity_commitment(
+            cs.clone(),
+            &self.poseidon_config,
+            &identity_secret_var,
+            &identity_commitment_var,
+        )?;
+        
         Ok(())
     }
 }
 
-/// Compute Poseidon hash of three field elements in-circuit.
-fn poseidon_hash_three(
-    cs: ConstraintSystemRef<Fr>,
-    config: &PoseidonConfig<Fr>,
-    a: &FpVar<Fr>,
-    b: &FpVar<Fr>,
-    c: &FpVar<Fr>,
-) -> Result<FpVar<Fr>, SynthesisError> {
-    let mut sponge = PoseidonSpongeVar::new(cs, config);
-    sponge.absorb(a)?;
-    sponge.absorb(b)?;
-    sponge.absorb(c)?;
-    let output = sponge.squeeze_field_elements(1)?;
-    Ok(output[0].clone())
+impl ProvableCircuit for CountryProofCircuit {
+    type SetupParams = ();
+    /// `(country_id, commitment, epoch, nullifier, issued_at, identity_commitment)`.
+    type PublicInputs = (Fr, Fr, Fr, Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::Country;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["country_id", "commitment", "epoch", "nullifier", "issued_at", "identity_commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr, Fr, Fr, Fr)> {
+        Some((
+            self.country_id?,
+            self.commitment?,
+            self.epoch?,
+            self.nullifier?,
+            self.issued_at?,
+            self.identity_commitment?,
+        ))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr, Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2, inputs.3, inputs.4, inputs.5]
+    }
 }
 
 #[cfg(test)]
@@ -215,7 +486,7 @@ mod tests {
         // USA bounds
         let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
         
-        let circuit = CountryProofCircuit::new_with_witness(lat, lng, &bounds, "US");
+        let circuit = CountryProofCircuit::new_with_witness(lat, lng, &bounds, "US", 0, 1_700_000_000).unwrap();
         
         let cs = ConstraintSystem::<Fr>::new_ref();
         circuit.generate_constraints(cs.clone()).unwrap();
@@ -240,10 +511,90 @@ mod tests {
         let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
         
         // Two different locations should produce different commitments
-        let circuit1 = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US");
-        let circuit2 = CountryProofCircuit::new_with_witness(40.7128, -74.0060, &bounds, "US");
+        let circuit1 = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_000).unwrap();
+        let circuit2 = CountryProofCircuit::new_with_witness(40.7128, -74.0060, &bounds, "US", 0, 1_700_000_000).unwrap();
         
         assert_ne!(circuit1.get_commitment(), circuit2.get_commitment());
     }
-}
 
+    #[test]
+    fn test_commitment_binds_issued_at() {
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_000).unwrap();
+        let later = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_001).unwrap();
+        assert_ne!(circuit.get_commitment(), later.get_commitment());
+        assert_eq!(circuit.get_issued_at(), Some(Fr::from(1_700_000_000u64)));
+
+        // Re-dating a proof without the matching commitment is unsatisfiable
+        let mut redated = circuit;
+        redated.issued_at = Some(Fr::from(1_800_000_000u64));
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        redated.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_nullifier_per_epoch() {
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let nullifier = |epoch| {
+            CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", epoch, 1_700_000_000)
+                .unwrap()
+                .get_nullifier()
+                .unwrap()
+        };
+
+        assert_eq!(nullifier(7), nullifier(7));
+        assert_ne!(nullifier(7), nullifier(8));
+        assert_eq!(nullifier(7), location_nullifier(37.7749, -122.4194, 7).unwrap());
+    }
+
+    #[test]
+    fn test_representative_points_pass_bounds_check() {
+        use super::super::range::{enforce_in_range, COORD_BITS};
+        use crate::proofs::location::COUNTRIES;
+
+        for country in COUNTRIES {
+            let (lat, lng) = country.representative_point;
+            let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng)
+                .to_fields();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let lat_var = FpVar::new_witness(cs.clone(), || Ok(lat_to_field(lat))).unwrap();
+            let lng_var = FpVar::new_witness(cs.clone(), || Ok(lng_to_field(lng))).unwrap();
+            let bound_vars: Vec<FpVar<Fr>> = bounds
+                .iter()
+                .map(|b| FpVar::new_input(cs.clone(), || Ok(*b)).unwrap())
+                .collect();
+            enforce_in_range(cs.clone(), &lat_var, &bound_vars[0], &bound_vars[1], COORD_BITS).unwrap();
+            enforce_in_range(cs.clone(), &lng_var, &bound_vars[2], &bound_vars[3], COORD_BITS).unwrap();
+
+            assert!(cs.is_satisfied().unwrap(), "{} representative point out of bounds", country.code);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_coords_rejected() {
+        assert_eq!(normalize_coords(90.0, -180.0), Ok((90_000_000, -180_000_000)));
+        assert_eq!(normalize_coords(90.0000001, 0.0), Err(CoordError::LatitudeOutOfRange(90.0000001)));
+        assert_eq!(normalize_coords(-91.0, 0.0), Err(CoordError::LatitudeOutOfRange(-91.0)));
+        assert_eq!(normalize_coords(0.0, 180.5), Err(CoordError::LongitudeOutOfRange(180.5)));
+        assert!(matches!(normalize_coords(f64::NAN, 0.0), Err(CoordError::NotFinite(..))));
+        assert_eq!(normalize_coords(0.0, f64::INFINITY), Err(CoordError::NotFinite(0.0, f64::INFINITY)));
+
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        assert!(CountryProofCircuit::new_with_witness(1e9, -122.4194, &bounds, "US", 0, 1_700_000_000).is_err());
+        assert!(location_nullifier(37.7749, -540.0, 0).is_err());
+        assert_eq!(coords_to_fields(37.7749, -122.4194), Ok((lat_to_field(37.7749), lng_to_field(-122.4194))));
+    }
+
+    #[test]
+    fn test_nullifier_enforced() {
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let mut circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 1, 1_700_000_000).unwrap();
+        circuit.nullifier = Some(location_nullifier(37.7749, -122.4194, 2).unwrap());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
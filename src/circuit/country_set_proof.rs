@@ -0,0 +1,314 @@
+//! Country group ("any of N countries") proof circuit.
+//!
+//! Proves that private coordinates lie inside at least one of a group's
+//! country bounding boxes, e.g. "I'm in the EU", without revealing the
+//! coordinates or which member country contains them.
+//!
+//! A one-hot selector witness picks one box; the selected bounds are the
+//! selector-weighted sum of all boxes, and the point is range-checked
+//! against them. Every group is padded to `MAX_GROUP_COUNTRIES` boxes by
+//! repeating its last box, so all groups share one circuit shape and one
+//! key pair.
+//!
+//! Public inputs (in allocation order):
+//! - group_id: `country_group_to_field(code)`
+//! - bounds_hash: Poseidon of the padded, shifted boxes
+//! - commitment: Poseidon(lat, lng, group_id, nonce)
+//!
+//! Private witnesses:
+//! - lat, lng (shifted, scaled coordinates)
+//! - the padded boxes and the selector
+//! - nonce
+//!
+//! The verifier must recompute `bounds_hash` from its own copy of the
+//! group (see `country_group_bounds_hash`); otherwise the prover could
+//! supply any boxes.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::country_proof::{country_code_to_field, coords_to_fields, CoordError, ScaledBounds};
+use super::email_proof::random_nonce;
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use super::range::{enforce_bit_length, enforce_in_range, COORD_BITS};
+use crate::merkle::hash::PoseidonHasher;
+use crate::proofs::location::{Coordinates, CountryGroup};
+use crate::prover::CircuitTag;
+
+/// Maximum number of member countries a group may have.
+pub const MAX_GROUP_COUNTRIES: usize = 8;
+
+/// Convert a group code (e.g. "EU") to a field element (public input).
+pub fn country_group_to_field(code: &str) -> Fr {
+    country_code_to_field(code)
+}
+
+/// Poseidon hash of a group's boxes as committed to by `CountrySetCircuit`.
+///
+/// Returns `None` if the group has no members or more than
+/// `MAX_GROUP_COUNTRIES`.
+pub fn country_group_bounds_hash(group: &CountryGroup) -> Option<Fr> {
+    let boxes = padded_boxes(group)?;
+    Some(PoseidonHasher::new().hash_many(&boxes.concat()))
+}
+
+/// Shifted member boxes, padded to `MAX_GROUP_COUNTRIES` with the last one.
+fn padded_boxes(group: &CountryGroup) -> Option<Vec<[Fr; 4]>> {
+    let mut boxes: Vec<[Fr; 4]> = group
+        .countries()
+        .map(|c| ScaledBounds::new(c.min_lat, c.max_lat, c.min_lng, c.max_lng).to_fields())
+        .collect();
+    if boxes.is_empty() || boxes.len() > MAX_GROUP_COUNTRIES {
+        return None;
+    }
+    boxes.resize(MAX_GROUP_COUNTRIES, boxes[boxes.len() - 1]);
+    Some(boxes)
+}
+
+/// Circuit for proving location is within any of a group's countries.
+#[derive(Clone)]
+pub struct CountrySetCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Shifted, scaled latitude
+    pub latitude: Option<Fr>,
+    /// Private: Shifted, scaled longitude
+    pub longitude: Option<Fr>,
+    /// Private: Random nonce for unlinkability
+    pub nonce: Option<Fr>,
+    /// Private: Padded boxes, each `[min_lat, max_lat, min_lng, max_lng]`
+    pub boxes: Option<Vec<[Fr; 4]>>,
+    /// Private: Index of the box containing the point
+    pub selector: Option<usize>,
+
+    /// Public: Group identifier
+    pub group_id: Option<Fr>,
+    /// Public: Hash of the padded boxes
+    pub bounds_hash: Option<Fr>,
+    /// Public: Poseidon(lat, lng, group_id, nonce)
+    pub commitment: Option<Fr>,
+}
+
+impl CountrySetCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        // Dummy values for setup: the point (1, 1) in a box around it
+        let boxes = vec![[0u64, 2, 0, 2].map(Fr::from); MAX_GROUP_COUNTRIES];
+        Self::from_fields(Fr::from(1u64), Fr::from(1u64), Fr::from(2u64), boxes, 0, Fr::from(3u64))
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// The selector is the first member box containing the point; a
+    /// location outside every member produces an unsatisfiable circuit.
+    /// Fails if the coordinates are not a valid latitude and longitude,
+    /// the group is empty or too large, or no entropy is available for
+    /// the nonce.
+    pub fn new_with_witness(latitude: f64, longitude: f64, group: &CountryGroup) -> Result<Self, CoordError> {
+        let (lat, lng) = coords_to_fields(latitude, longitude)?;
+        let boxes = padded_boxes(group)
+            .ok_or_else(|| CoordError::UnsupportedArea(format!("group {} is empty or too large", group.code)))?;
+        let point = Coordinates::new(latitude, longitude);
+        let selector = group.countries().position(|c| c.contains(&point)).unwrap_or(0);
+
+        let nonce = random_nonce()?;
+
+        Ok(Self::from_fields(lat, lng, nonce, boxes, selector, country_group_to_field(group.code)))
+    }
+
+    fn from_fields(lat: Fr, lng: Fr, nonce: Fr, boxes: Vec<[Fr; 4]>, selector: usize, group_id: Fr) -> Self {
+        let hasher = PoseidonHasher::new();
+        let bounds_hash = hasher.hash_many(&boxes.concat());
+        let commitment = hasher.hash_many(&[lat, lng, group_id, nonce]);
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            nonce: Some(nonce),
+            boxes: Some(boxes),
+            selector: Some(selector),
+            group_id: Some(group_id),
+            bounds_hash: Some(bounds_hash),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Get the group identifier (public input)
+    pub fn get_group_id(&self) -> Option<Fr> {
+        self.group_id
+    }
+
+    /// Get the bounds hash (public input)
+    pub fn get_bounds_hash(&self) -> Option<Fr> {
+        self.bounds_hash
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for CountrySetCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let lat_var = FpVar::new_witness(cs.clone(), || {
+            self.latitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lng_var = FpVar::new_witness(cs.clone(), || {
+            self.longitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let mut box_vars = Vec::with_capacity(MAX_GROUP_COUNTRIES);
+        for i in 0..MAX_GROUP_COUNTRIES {
+            let bounds = (0..4)
+                .map(|k| {
+                    FpVar::new_witness(cs.clone(), || {
+                        self.boxes
+                            .as_ref()
+                            .and_then(|b| b.get(i))
+                            .map(|b| b[k])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            box_vars.push(bounds);
+        }
+
+        let selector_vars = (0..MAX_GROUP_COUNTRIES)
+            .map(|i| {
+                Boolean::new_witness(cs.clone(), || {
+                    self.selector.map(|s| s == i).ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Allocate public inputs: [group_id, bounds_hash, commitment]
+        let group_id_var = FpVar::new_input(cs.clone(), || {
+            self.group_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let bounds_hash_var = FpVar::new_input(cs.clone(), || {
+            self.bounds_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // The boxes must be the group's
+        let flat_boxes: Vec<FpVar<Fr>> = box_vars.iter().flatten().cloned().collect();
+        for bound in &flat_boxes {
+            enforce_bit_length(cs.clone(), bound, COORD_BITS)?;
+        }
+        poseidon_hash_many(cs.clone(), &self.poseidon_config, &flat_boxes)?
+            .enforce_equal(&bounds_hash_var)?;
+
+        // Exactly one box is selected
+        let zero = FpVar::Constant(Fr::from(0u64));
+        let selected_count = selector_vars
+            .iter()
+            .fold(zero.clone(), |acc, bit| acc + FpVar::from(bit.clone()));
+        selected_count.enforce_equal(&FpVar::Constant(Fr::from(1u64)))?;
+
+        // The point lies in the selected box
+        let mut selected = vec![zero; 4];
+        for (bit, bounds) in selector_vars.iter().zip(&box_vars) {
+            let weight = FpVar::from(bit.clone());
+            for (acc, bound) in selected.iter_mut().zip(bounds) {
+                *acc += &weight * bound;
+            }
+        }
+        enforce_in_range(cs.clone(), &lat_var, &selected[0], &selected[1], COORD_BITS)?;
+        enforce_in_range(cs.clone(), &lng_var, &selected[2], &selected[3], COORD_BITS)?;
+
+        // Bind the location and group into the commitment
+        let computed_commitment = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            &[lat_var, lng_var, group_id_var, nonce_var],
+        )?;
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for CountrySetCircuit {
+    type SetupParams = ();
+    /// `(group_id, bounds_hash, commitment)`.
+    type PublicInputs = (Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::CountrySet;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["group_id", "bounds_hash", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr)> {
+        Some((self.group_id?, self.bounds_hash?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+
+    #[test]
+    fn test_paris_in_eu() {
+        let eu = CountryGroup::by_code("EU").unwrap();
+
+        let circuit = CountrySetCircuit::new_with_witness(48.8566, 2.3522, eu).unwrap();
+        assert_eq!(circuit.get_group_id(), Some(country_group_to_field("EU")));
+        assert_eq!(circuit.get_bounds_hash(), country_group_bounds_hash(eu));
+        assert!(is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_point_outside_group_fails() {
+        let eu = CountryGroup::by_code("EU").unwrap();
+
+        // New York is in no EU box, whichever one is selected
+        let circuit = CountrySetCircuit::new_with_witness(40.7128, -74.006, eu).unwrap();
+        for selector in 0..MAX_GROUP_COUNTRIES {
+            let mut attempt = circuit.clone();
+            attempt.selector = Some(selector);
+            assert!(!is_satisfied(attempt));
+        }
+
+        // Selecting no box at all fails too
+        let mut circuit = CountrySetCircuit::new_with_witness(48.8566, 2.3522, eu).unwrap();
+        circuit.selector = Some(MAX_GROUP_COUNTRIES);
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_empty_circuit_for_setup() {
+        assert!(is_satisfied(CountrySetCircuit::new_empty()));
+    }
+}
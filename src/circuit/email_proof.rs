@@ -11,21 +11,23 @@
 //! Public inputs:
 //! - domain_hash: Hash of the domain being proven (e.g., hash("google.com"))
 //! - commitment: Poseidon(email_hash, domain_hash, dkim_hash, nonce)
+//! - identity_commitment: Poseidon(identity_secret), see `identity`
 //!
 //! Private witnesses:
 //! - email_hash: Hash of the full email address
 //! - dkim_hash: Hash of DKIM signature (proves email is authentic)
 //! - nonce: Random value for uniqueness
+//! - identity_secret: Shared secret for linking proofs
 //!
 //! This proves: "I know an email address at domain X with valid DKIM"
 //! without revealing the actual email address.
+//!
+//! `DatedEmailCircuit` additionally folds the email's received time into
+//! the commitment and exposes it, so verifiers can enforce recency.
 
 use ark_bn254::Fr;
 use ark_ff::PrimeField;
-use ark_crypto_primitives::sponge::{
-    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig},
-    constraints::CryptographicSpongeVar,
-};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
 use ark_r1cs_std::{
     alloc::AllocVar,
     eq::EqGadget,
@@ -34,7 +36,11 @@ use ark_r1cs_std::{
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use sha2::{Digest, Sha256};
 
+use super::identity::{enforce_identity_commitment, identity_commitment};
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
 use crate::merkle::hash::PoseidonHasher;
+use crate::prover::CircuitTag;
 
 /// Convert a string to a field element using SHA-256
 pub fn string_to_field(s: &str) -> Fr {
@@ -46,7 +52,22 @@ pub fn string_to_field(s: &str) -> Fr {
 
 /// Extract domain from email address
 pub fn extract_domain(email: &str) -> Option<String> {
-    email.split('@').nth(1).map(|s| s.to_lowercase())
+    email.split('@').nth(1).map(normalize_domain)
+}
+
+/// Draw a random commitment nonce from the platform entropy source.
+///
+/// Uses `getrandom` directly (the Web Crypto API in browsers), so no
+/// thread-local RNG needs to be available or seeded.
+pub fn random_nonce() -> Result<Fr, EmailInputError> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| EmailInputError::Entropy(e.to_string()))?;
+    Ok(Fr::from_be_bytes_mod_order(&bytes))
+}
+
+/// Canonical form of a domain: trimmed, lowercase, no trailing dot.
+pub fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_end_matches('.').to_lowercase()
 }
 
 /// Email proof input data
@@ -60,6 +81,8 @@ pub struct EmailProofInput {
     pub dkim_data: String,
     /// Whether DKIM verification passed
     pub dkim_verified: bool,
+    /// Commitment nonce; a fresh random one is drawn per proof when unset
+    pub nonce: Option<Fr>,
 }
 
 impl EmailProofInput {
@@ -73,6 +96,7 @@ impl EmailProofInput {
             domain,
             dkim_data: format!("{}{}", dkim_signature, auth_results),
             dkim_verified,
+            nonce: None,
         }
     }
     
@@ -85,11 +109,123 @@ impl EmailProofInput {
     pub fn from_domain_with_dkim(domain: &str, dkim_verified: bool, dkim_data: &str) -> Self {
         Self {
             email: format!("user@{}", domain),
-            domain: domain.to_lowercase(),
+            domain: normalize_domain(domain),
             dkim_data: if dkim_verified { dkim_data.to_string() } else { String::new() },
             dkim_verified,
+            nonce: None,
         }
     }
+
+    /// Start building an input whose proven domain can differ from the
+    /// From address.
+    pub fn builder() -> EmailProofInputBuilder {
+        EmailProofInputBuilder::default()
+    }
+
+    /// Use `nonce` for the commitment instead of a random one.
+    ///
+    /// With a stable, secret nonce (e.g. Poseidon of a user secret and
+    /// the domain) every proof of this input has the same commitment, so
+    /// a server can recognize a returning credential. The nonce must stay
+    /// secret and unguessable, or the commitment no longer hides the email.
+    pub fn with_nonce(mut self, nonce: Fr) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+}
+
+/// Which domain gets hashed into `domain_hash`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DomainSource {
+    /// The DKIM `d=` domain when known, otherwise the From domain
+    #[default]
+    Authenticated,
+    /// The DKIM `d=` domain; building fails without one
+    Dkim,
+    /// The From address domain, which the sender controls and can spoof
+    From,
+}
+
+/// Errors building an `EmailProofInput`.
+#[derive(Debug, thiserror::Error)]
+pub enum EmailInputError {
+    #[error("No email address given")]
+    MissingEmail,
+
+    #[error("Could not extract a domain from {0}")]
+    InvalidEmail(String),
+
+    #[error("DKIM domain requested but none was given")]
+    MissingDkimDomain,
+
+    #[error("Could not generate a random nonce: {0}")]
+    Entropy(String),
+}
+
+/// Builder for `EmailProofInput`.
+///
+/// Separates the From address (hashed privately) from the domain that is
+/// proven, so a proof can attest to the domain DKIM actually signed for.
+#[derive(Clone, Debug, Default)]
+pub struct EmailProofInputBuilder {
+    email: Option<String>,
+    dkim_domain: Option<String>,
+    dkim_signature: String,
+    auth_results: String,
+    domain_source: DomainSource,
+}
+
+impl EmailProofInputBuilder {
+    /// The From address (private).
+    pub fn email(mut self, email: &str) -> Self {
+        self.email = Some(email.to_string());
+        self
+    }
+
+    /// The DKIM signing (`d=`) domain.
+    pub fn dkim_domain(mut self, domain: &str) -> Self {
+        self.dkim_domain = Some(domain.to_lowercase());
+        self
+    }
+
+    /// Raw DKIM-Signature value, bound into the commitment.
+    pub fn dkim_signature(mut self, signature: &str) -> Self {
+        self.dkim_signature = signature.to_string();
+        self
+    }
+
+    /// Authentication-Results value; `dkim=pass` marks DKIM as verified.
+    pub fn auth_results(mut self, auth_results: &str) -> Self {
+        self.auth_results = auth_results.to_string();
+        self
+    }
+
+    /// Choose which domain is proven. Defaults to `DomainSource::Authenticated`.
+    pub fn domain_source(mut self, source: DomainSource) -> Self {
+        self.domain_source = source;
+        self
+    }
+
+    /// Build the input.
+    pub fn build(self) -> Result<EmailProofInput, EmailInputError> {
+        let email = self.email.ok_or(EmailInputError::MissingEmail)?;
+        let from_domain =
+            extract_domain(&email).ok_or_else(|| EmailInputError::InvalidEmail(email.clone()))?;
+
+        let domain = match self.domain_source {
+            DomainSource::Authenticated => self.dkim_domain.unwrap_or(from_domain),
+            DomainSource::Dkim => self.dkim_domain.ok_or(EmailInputError::MissingDkimDomain)?,
+            DomainSource::From => from_domain,
+        };
+
+        Ok(EmailProofInput {
+            email,
+            domain,
+            dkim_verified: self.auth_results.to_lowercase().contains("dkim=pass"),
+            dkim_data: format!("{}{}", self.dkim_signature, self.auth_results),
+            nonce: None,
+        })
+    }
 }
 
 /// Circuit for proving email domain ownership.
@@ -107,11 +243,15 @@ pub struct EmailDomainCircuit {
     pub dkim_hash: Option<Fr>,
     /// Private: Random nonce for uniqueness
     pub nonce: Option<Fr>,
+    /// Private: Secret shared with linked proofs
+    pub identity_secret: Option<Fr>,
     
     /// Public: Hash of the domain (what we're proving)
     pub domain_hash: Option<Fr>,
     /// Public: Commitment to all the private data
     pub commitment: Option<Fr>,
+    /// Public: Poseidon(identity_secret)
+    pub identity_commitment: Option<Fr>,
 }
 
 impl EmailDomainCircuit {
@@ -124,6 +264,7 @@ impl EmailDomainCircuit {
         let dkim_hash = Fr::from(1u64);
         let nonce = Fr::from(2u64);
         let domain_hash = Fr::from(3u64);
+        let identity_secret = Fr::from(4u64);
         
         // Compute commitment
         let commitment = hasher.hash_many(&[email_hash, domain_hash, dkim_hash, nonce]);
@@ -133,13 +274,20 @@ impl EmailDomainCircuit {
             email_hash: Some(email_hash),
             dkim_hash: Some(dkim_hash),
             nonce: Some(nonce),
+            identity_secret: Some(identity_secret),
             domain_hash: Some(domain_hash),
             commitment: Some(commitment),
+            identity_commitment: Some(identity_commitment(&identity_secret)),
         }
     }
     
     /// Create a circuit with actual witness values
-    pub fn new_with_witness(input: &EmailProofInput) -> Self {
+    ///
+    /// The nonce is random unless set with `EmailProofInput::with_nonce`.
+    /// The identity secret is random, so the proof is unlinkable; use
+    /// `with_identity_secret` to link it to other proofs. Fails only if
+    /// the platform entropy source is unavailable.
+    pub fn new_with_witness(input: &EmailProofInput) -> Result<Self, EmailInputError> {
         let hasher = PoseidonHasher::new();
         
         // Hash the private data
@@ -147,21 +295,34 @@ impl EmailDomainCircuit {
         let domain_hash = string_to_field(&input.domain);
         let dkim_hash = string_to_field(&input.dkim_data);
         
-        // Generate random nonce
-        let nonce_bytes: [u8; 32] = rand::random();
-        let nonce = Fr::from_be_bytes_mod_order(&nonce_bytes);
+        // Use the caller's nonce, or a random one
+        let nonce = match input.nonce {
+            Some(nonce) => nonce,
+            None => random_nonce()?,
+        };
         
         // Compute commitment: H(email_hash, domain_hash, dkim_hash, nonce)
         let commitment = hasher.hash_many(&[email_hash, domain_hash, dkim_hash, nonce]);
         
-        Self {
+        Ok(Self {
             poseidon_config: hasher.config().clone(),
             email_hash: Some(email_hash),
             dkim_hash: Some(dkim_hash),
             nonce: Some(nonce),
+            identity_secret: None,
             domain_hash: Some(domain_hash),
             commitment: Some(commitment),
+            identity_commitment: None,
         }
+        .with_identity_secret(random_nonce()?))
+    }
+
+    /// Use `secret` as the identity secret, so the proof's identity
+    /// commitment matches other proofs made with the same secret.
+    pub fn with_identity_secret(mut self, secret: Fr) -> Self {
+        self.identity_secret = Some(secret);
+        self.identity_commitment = Some(identity_commitment(&secret));
+        self
     }
     
     /// Get the domain hash (public input)
@@ -173,10 +334,32 @@ impl EmailDomainCircuit {
     pub fn get_commitment(&self) -> Option<Fr> {
         self.commitment
     }
+
+    /// Get the identity commitment (public input)
+    pub fn get_identity_commitment(&self) -> Option<Fr> {
+        self.identity_commitment
+    }
+
+    /// Number of constraints; see `ProvableCircuit::count_constraints`.
+    pub fn constraint_count() -> usize {
+        Self::count_constraints(&())
+    }
 }
 
 impl ConstraintSynthesizer<Fr> for EmailDomainCircuit {
     fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.synthesize(cs, None)
+    }
+}
+
+impl EmailDomainCircuit {
+    /// Constraints shared with `DatedEmailCircuit`, which passes its
+    /// (possibly unassigned) timestamp as `email_timestamp`.
+    fn synthesize(
+        self,
+        cs: ConstraintSystemRef<Fr>,
+        email_timestamp: Option<Option<u64>>,
+    ) -> Result<(), SynthesisError> {
         // Allocate private witnesses
         let email_hash_var = FpVar::new_witness(cs.clone(), || {
             self.email_hash.ok_or(SynthesisError::AssignmentMissing)
@@ -190,6 +373,10 @@ impl ConstraintSynthesizer<Fr> for EmailDomainCircuit {
             self.nonce.ok_or(SynthesisError::AssignmentMissing)
         })?;
         
+        let identity_secret_var = FpVar::new_witness(cs.clone(), || {
+            self.identity_secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        
         // Allocate public inputs
         let domain_hash_var = FpVar::new_input(cs.clone(), || {
             self.domain_hash.ok_or(SynthesisError::AssignmentMissing)
@@ -199,19 +386,34 @@ impl ConstraintSynthesizer<Fr> for EmailDomainCircuit {
             self.commitment.ok_or(SynthesisError::AssignmentMissing)
         })?;
         
-        // Compute Poseidon hash: H(email_hash, domain_hash, dkim_hash, nonce)
-        let computed_commitment = poseidon_hash_four(
-            cs.clone(),
-            &self.poseidon_config,
-            &email_hash_var,
-            &domain_hash_var,
-            &dkim_hash_var,
-            &nonce_var,
-        )?;
+        let identity_commitment_var = FpVar::new_input(cs.clone(), || {
+            self.identity_commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let timestamp_var = email_timestamp
+            .map(|timestamp| {
+                FpVar::new_input(cs.clone(), || {
+                    timestamp.map(Fr::from).ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .transpose()?;
+        
+        // Compute Poseidon hash: H(email_hash, domain_hash, dkim_hash, nonce[, email_timestamp])
+        let mut preimage = vec![email_hash_var, domain_hash_var, dkim_hash_var, nonce_var];
+        preimage.extend(timestamp_var);
+        let computed_commitment = poseidon_hash_many(cs.clone(), &self.poseidon_config, &preimage)?;
         
         // Constraint: computed commitment must equal public commitment
         computed_commitment.enforce_equal(&commitment_var)?;
         
+        // Constraint: identity_commitment = Poseidon(identity_secret)
+        enforce_identity_commitment(
+            cs.clone(),
+            &self.poseidon_config,
+            &identity_secret_var,
+            &identity_commitment_var,
+        )?;
+        
         // The domain_hash is a public input, so verifier knows what domain is being proven
         // No additional constraint needed - it's automatically part of the public inputs
         
@@ -219,22 +421,122 @@ impl ConstraintSynthesizer<Fr> for EmailDomainCircuit {
     }
 }
 
-/// Compute Poseidon hash of four field elements in-circuit.
-fn poseidon_hash_four(
-    cs: ConstraintSystemRef<Fr>,
-    config: &PoseidonConfig<Fr>,
-    a: &FpVar<Fr>,
-    b: &FpVar<Fr>,
-    c: &FpVar<Fr>,
-    d: &FpVar<Fr>,
-) -> Result<FpVar<Fr>, SynthesisError> {
-    let mut sponge = PoseidonSpongeVar::new(cs, config);
-    sponge.absorb(a)?;
-    sponge.absorb(b)?;
-    sponge.absorb(c)?;
-    sponge.absorb(d)?;
-    let output = sponge.squeeze_field_elements(1)?;
-    Ok(output[0].clone())
+impl ProvableCircuit for EmailDomainCircuit {
+    type SetupParams = ();
+    /// `(domain_hash, commitment, identity_commitment)`.
+    type PublicInputs = (Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::Email;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["domain_hash", "commitment", "identity_commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr)> {
+        Some((self.domain_hash?, self.commitment?, self.identity_commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2]
+    }
+}
+
+/// Email domain circuit that also commits to when the email was received.
+///
+/// Public inputs (in allocation order): those of `EmailDomainCircuit`,
+/// then `email_timestamp`, the Unix time from `ParsedEmail::timestamp`.
+/// The commitment becomes `Poseidon(email_hash, domain_hash, dkim_hash,
+/// nonce, email_timestamp)`, binding the time to the rest of the proof;
+/// verifiers enforce recency on the public timestamp with
+/// `check_freshness`.
+#[derive(Clone)]
+pub struct DatedEmailCircuit {
+    /// The underlying email circuit, with the dated commitment
+    pub email: EmailDomainCircuit,
+    /// Public: Unix time the email was received
+    pub email_timestamp: Option<u64>,
+}
+
+impl DatedEmailCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        Self::dated(EmailDomainCircuit::new_empty(), 0)
+    }
+
+    /// Create a circuit for an email received at `email_timestamp`
+    ///
+    /// As `EmailDomainCircuit::new_with_witness`, the identity secret is
+    /// random unless set with `with_identity_secret`.
+    pub fn new_with_witness(input: &EmailProofInput, email_timestamp: u64) -> Result<Self, EmailInputError> {
+        Ok(Self::dated(EmailDomainCircuit::new_with_witness(input)?, email_timestamp))
+    }
+
+    /// Recompute the commitment of `email` with the timestamp folded in.
+    fn dated(mut email: EmailDomainCircuit, email_timestamp: u64) -> Self {
+        let hasher = PoseidonHasher::from_config(email.poseidon_config.clone());
+        email.commitment = match (email.email_hash, email.domain_hash, email.dkim_hash, email.nonce) {
+            (Some(email_hash), Some(domain_hash), Some(dkim_hash), Some(nonce)) => Some(hasher.hash_many(&[
+                email_hash,
+                domain_hash,
+                dkim_hash,
+                nonce,
+                Fr::from(email_timestamp),
+            ])),
+            _ => None,
+        };
+        Self {
+            email,
+            email_timestamp: Some(email_timestamp),
+        }
+    }
+
+    /// Use `secret` as the identity secret; see `EmailDomainCircuit::with_identity_secret`.
+    pub fn with_identity_secret(self, secret: Fr) -> Self {
+        Self {
+            email: self.email.with_identity_secret(secret),
+            ..self
+        }
+    }
+
+    /// Get the email timestamp (public input)
+    pub fn get_email_timestamp(&self) -> Option<u64> {
+        self.email_timestamp
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for DatedEmailCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.email.synthesize(cs, Some(self.email_timestamp))
+    }
+}
+
+impl ProvableCircuit for DatedEmailCircuit {
+    type SetupParams = ();
+    /// `(domain_hash, commitment, identity_commitment, email_timestamp)`.
+    type PublicInputs = (Fr, Fr, Fr, u64);
+
+    const TAG: CircuitTag = CircuitTag::DatedEmail;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["domain_hash", "commitment", "identity_commitment", "email_timestamp"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr, u64)> {
+        let (domain_hash, commitment, identity_commitment) = self.email.public_inputs()?;
+        Some((domain_hash, commitment, identity_commitment, self.email_timestamp?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr, u64)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2, Fr::from(inputs.3)]
+    }
 }
 
 #[cfg(test)]
@@ -250,7 +552,7 @@ mod tests {
             "dkim=pass"
         );
         
-        let circuit = EmailDomainCircuit::new_with_witness(&input);
+        let circuit = EmailDomainCircuit::new_with_witness(&input).unwrap();
         
         let cs = ConstraintSystem::<Fr>::new_ref();
         circuit.generate_constraints(cs.clone()).unwrap();
@@ -275,8 +577,8 @@ mod tests {
         let input1 = EmailProofInput::new("alice@google.com", "sig1", "dkim=pass");
         let input2 = EmailProofInput::new("bob@google.com", "sig2", "dkim=pass");
         
-        let circuit1 = EmailDomainCircuit::new_with_witness(&input1);
-        let circuit2 = EmailDomainCircuit::new_with_witness(&input2);
+        let circuit1 = EmailDomainCircuit::new_with_witness(&input1).unwrap();
+        let circuit2 = EmailDomainCircuit::new_with_witness(&input2).unwrap();
         
         // Different emails should produce different commitments
         assert_ne!(circuit1.get_commitment(), circuit2.get_commitment());
@@ -285,11 +587,108 @@ mod tests {
         assert_eq!(circuit1.get_domain_hash(), circuit2.get_domain_hash());
     }
     
+    #[test]
+    fn test_same_input_fresh_nonce() {
+        let input = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+
+        let circuit1 = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        let circuit2 = EmailDomainCircuit::new_with_witness(&input).unwrap();
+
+        assert_ne!(circuit1.nonce, circuit2.nonce);
+        assert_ne!(circuit1.get_commitment(), circuit2.get_commitment());
+        assert_eq!(circuit1.get_domain_hash(), circuit2.get_domain_hash());
+    }
+
+    #[test]
+    fn test_fixed_nonce_deterministic_commitment() {
+        let hasher = PoseidonHasher::new();
+        let nonce = hasher.hash_two(&string_to_field("user secret"), &string_to_field("google.com"));
+        let input = EmailProofInput::new("alice@google.com", "sig", "dkim=pass").with_nonce(nonce);
+
+        let circuit1 = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        let circuit2 = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        assert_eq!(circuit1.get_commitment(), circuit2.get_commitment());
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit1.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let other = EmailProofInput::new("bob@google.com", "sig", "dkim=pass").with_nonce(nonce);
+        let circuit3 = EmailDomainCircuit::new_with_witness(&other).unwrap();
+        assert_ne!(circuit1.get_commitment(), circuit3.get_commitment());
+    }
+
+    #[test]
+    fn test_dated_email_commits_to_timestamp() {
+        let input = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+        let is_satisfied = |circuit: DatedEmailCircuit| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+
+        let dated = DatedEmailCircuit::new_with_witness(&input, 1_700_000_000).unwrap();
+        assert_eq!(dated.get_email_timestamp(), Some(1_700_000_000));
+        assert!(is_satisfied(dated.clone()));
+        assert!(is_satisfied(DatedEmailCircuit::new_empty()));
+
+        // The undated commitment differs, and the timestamp cannot be moved
+        let undated = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        let rebased = DatedEmailCircuit::dated(EmailDomainCircuit { nonce: dated.email.nonce, ..undated }, 1_700_000_000);
+        assert_eq!(rebased.email.commitment, dated.email.commitment);
+        let moved = DatedEmailCircuit { email_timestamp: Some(1_800_000_000), ..dated };
+        assert!(!is_satisfied(moved));
+    }
+
+    #[test]
+    fn test_builder_prefers_dkim_domain() {
+        // From claims google.com but the message was signed by mailchimp.com
+        let builder = EmailProofInput::builder()
+            .email("alice@google.com")
+            .dkim_domain("Mailchimp.com")
+            .dkim_signature("sig")
+            .auth_results("dkim=pass header.d=mailchimp.com");
+
+        let input = builder.clone().build().unwrap();
+        assert_eq!(input.domain, "mailchimp.com");
+        assert!(input.dkim_verified);
+
+        let circuit = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        assert_eq!(circuit.get_domain_hash(), Some(string_to_field("mailchimp.com")));
+        assert_ne!(circuit.get_domain_hash(), Some(string_to_field("google.com")));
+
+        // The display domain is only proven when asked for explicitly
+        let display = builder.domain_source(DomainSource::From).build().unwrap();
+        assert_eq!(display.domain, "google.com");
+    }
+
+    #[test]
+    fn test_builder_without_dkim_domain() {
+        let builder = EmailProofInput::builder().email("bob@meta.com");
+
+        let input = builder.clone().build().unwrap();
+        assert_eq!(input.domain, "meta.com");
+        assert!(!input.dkim_verified);
+
+        assert!(matches!(
+            builder.domain_source(DomainSource::Dkim).build(),
+            Err(EmailInputError::MissingDkimDomain)
+        ));
+        assert!(matches!(
+            EmailProofInput::builder().build(),
+            Err(EmailInputError::MissingEmail)
+        ));
+        assert!(matches!(
+            EmailProofInput::builder().email("invalid").build(),
+            Err(EmailInputError::InvalidEmail(_))
+        ));
+    }
+
     #[test]
     fn test_domain_extraction() {
         assert_eq!(extract_domain("alice@google.com"), Some("google.com".to_string()));
         assert_eq!(extract_domain("bob@meta.com"), Some("meta.com".to_string()));
         assert_eq!(extract_domain("invalid"), None);
+        assert_eq!(extract_domain("carol@Corp.Example."), Some("corp.example".to_string()));
     }
 }
 
@@ -0,0 +1,286 @@
+//! Country exclusion proof circuit.
+//!
+//! Proves that private coordinates lie OUTSIDE a country's bounding box,
+//! e.g. "I'm not in country X" for compliance checks, without revealing
+//! them. Being outside the box is a disjunction of four range violations:
+//!
+//! ```text
+//! lat < min_lat  OR  lat > max_lat  OR  lng < min_lng  OR  lng > max_lng
+//! ```
+//!
+//! Each comparison is computed as a `Boolean` with `is_less_than`, and
+//! their OR is enforced to be true. The coordinates themselves are
+//! range-checked to valid latitudes and longitudes, so an out-of-range
+//! value cannot satisfy the disjunction trivially.
+//!
+//! The check is only as good as the bounding box: a point inside the box
+//! but outside the country itself cannot be proven excluded.
+//!
+//! Public inputs (in allocation order):
+//! - country_id: `country_code_to_field(code)`
+//! - bounds_hash: Poseidon of the shifted, scaled box
+//! - commitment: Poseidon(lat, lng, country_id, nonce)
+//!
+//! Private witnesses:
+//! - lat, lng (shifted, scaled coordinates)
+//! - the country bounds (shifted, scaled)
+//! - nonce
+//!
+//! The verifier must recompute `bounds_hash` from its own copy of the
+//! country (see `exclusion_bounds_hash`); otherwise the prover could
+//! supply any box.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::country_proof::{country_code_to_field, coords_to_fields, CoordError, ScaledBounds, COORD_SCALE};
+use super::email_proof::random_nonce;
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use super::range::{enforce_bit_length, enforce_in_range, is_less_than, COORD_BITS};
+use crate::merkle::hash::PoseidonHasher;
+use crate::proofs::location::CountryBounds;
+use crate::prover::CircuitTag;
+
+/// Largest shifted latitude (90 degrees north).
+const MAX_SHIFTED_LAT: u64 = 180 * COORD_SCALE as u64;
+
+/// Largest shifted longitude (180 degrees east).
+const MAX_SHIFTED_LNG: u64 = 360 * COORD_SCALE as u64;
+
+/// Poseidon hash of a country's box as committed to by
+/// `ExclusionProofCircuit`.
+pub fn exclusion_bounds_hash(country: &CountryBounds) -> Fr {
+    PoseidonHasher::new().hash_many(&shifted_bounds(country))
+}
+
+fn shifted_bounds(country: &CountryBounds) -> [Fr; 4] {
+    ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng).to_fields()
+}
+
+/// Circuit for proving location is outside a country's bounding box.
+#[derive(Clone)]
+pub struct ExclusionProofCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Shifted, scaled latitude
+    pub latitude: Option<Fr>,
+    /// Private: Shifted, scaled longitude
+    pub longitude: Option<Fr>,
+    /// Private: Random nonce for unlinkability
+    pub nonce: Option<Fr>,
+    /// Private: Shifted, scaled bounds `[min_lat, max_lat, min_lng, max_lng]`
+    pub bounds: Option<[Fr; 4]>,
+
+    /// Public: Country identifier
+    pub country_id: Option<Fr>,
+    /// Public: Hash of the bounds
+    pub bounds_hash: Option<Fr>,
+    /// Public: Poseidon(lat, lng, country_id, nonce)
+    pub commitment: Option<Fr>,
+}
+
+impl ExclusionProofCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        // Dummy values for setup: the origin, outside a box away from it
+        let bounds = [1u64, 2, 1, 2].map(Fr::from);
+        Self::from_fields(Fr::from(0u64), Fr::from(0u64), Fr::from(3u64), bounds, Fr::from(4u64))
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// The coordinates are checked against the box in-circuit, so a
+    /// location inside the country's box produces an unsatisfiable
+    /// circuit. Fails if the coordinates are not a valid latitude and
+    /// longitude, or no entropy is available for the nonce.
+    pub fn new_with_witness(latitude: f64, longitude: f64, country: &CountryBounds) -> Result<Self, CoordError> {
+        let (lat, lng) = coords_to_fields(latitude, longitude)?;
+
+        let nonce = random_nonce()?;
+
+        Ok(Self::from_fields(lat, lng, nonce, shifted_bounds(country), country_code_to_field(country.code)))
+    }
+
+    fn from_fields(lat: Fr, lng: Fr, nonce: Fr, bounds: [Fr; 4], country_id: Fr) -> Self {
+        let hasher = PoseidonHasher::new();
+        let bounds_hash = hasher.hash_many(&bounds);
+        let commitment = hasher.hash_many(&[lat, lng, country_id, nonce]);
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            nonce: Some(nonce),
+            bounds: Some(bounds),
+            country_id: Some(country_id),
+            bounds_hash: Some(bounds_hash),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Get the country identifier (public input)
+    pub fn get_country_id(&self) -> Option<Fr> {
+        self.country_id
+    }
+
+    /// Get the bounds hash (public input)
+    pub fn get_bounds_hash(&self) -> Option<Fr> {
+        self.bounds_hash
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ExclusionProofCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let lat_var = FpVar::new_witness(cs.clone(), || {
+            self.latitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lng_var = FpVar::new_witness(cs.clone(), || {
+            self.longitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let bound_vars: Vec<FpVar<Fr>> = (0..4)
+            .map(|i| {
+                FpVar::new_witness(cs.clone(), || {
+                    self.bounds
+                        .map(|b| b[i])
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Allocate public inputs: [country_id, bounds_hash, commitment]
+        let country_id_var = FpVar::new_input(cs.clone(), || {
+            self.country_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let bounds_hash_var = FpVar::new_input(cs.clone(), || {
+            self.bounds_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // The point must be a real location, and the box the expected one
+        let zero = FpVar::Constant(Fr::from(0u64));
+        enforce_in_range(cs.clone(), &lat_var, &zero, &FpVar::Constant(Fr::from(MAX_SHIFTED_LAT)), COORD_BITS)?;
+        enforce_in_range(cs.clone(), &lng_var, &zero, &FpVar::Constant(Fr::from(MAX_SHIFTED_LNG)), COORD_BITS)?;
+        for bound in &bound_vars {
+            enforce_bit_length(cs.clone(), bound, COORD_BITS)?;
+        }
+        poseidon_hash_many(cs.clone(), &self.poseidon_config, &bound_vars)?
+            .enforce_equal(&bounds_hash_var)?;
+
+        // At least one side of the box must be violated
+        let violations = [
+            is_less_than(cs.clone(), &lat_var, &bound_vars[0], COORD_BITS)?,
+            is_less_than(cs.clone(), &bound_vars[1], &lat_var, COORD_BITS)?,
+            is_less_than(cs.clone(), &lng_var, &bound_vars[2], COORD_BITS)?,
+            is_less_than(cs.clone(), &bound_vars[3], &lng_var, COORD_BITS)?,
+        ];
+        Boolean::kary_or(&violations)?.enforce_equal(&Boolean::TRUE)?;
+
+        // Bind the location and country into the commitment
+        let computed_commitment = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            &[lat_var, lng_var, country_id_var, nonce_var],
+        )?;
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for ExclusionProofCircuit {
+    type SetupParams = ();
+    /// `(country_id, bounds_hash, commitment)`.
+    type PublicInputs = (Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::Exclusion;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["country_id", "bounds_hash", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr)> {
+        Some((self.country_id?, self.bounds_hash?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+    use crate::proofs::location::COUNTRIES;
+
+    fn country(code: &str) -> &'static CountryBounds {
+        COUNTRIES.iter().find(|c| c.code == code).unwrap()
+    }
+
+    #[test]
+    fn test_point_outside_country() {
+        let us = country("US");
+
+        // Paris, and just north of the box's top edge
+        for (lat, lng) in [(48.8566, 2.3522), (us.max_lat + 0.5, -100.0)] {
+            let circuit = ExclusionProofCircuit::new_with_witness(lat, lng, us).unwrap();
+            assert_eq!(circuit.get_bounds_hash(), Some(exclusion_bounds_hash(us)));
+            assert!(is_satisfied(circuit), "({}, {}) should be outside", lat, lng);
+        }
+    }
+
+    #[test]
+    fn test_point_inside_country_fails() {
+        let us = country("US");
+
+        // San Francisco, and a corner of the box itself
+        for (lat, lng) in [(37.7749, -122.4194), (us.min_lat, us.min_lng)] {
+            let circuit = ExclusionProofCircuit::new_with_witness(lat, lng, us).unwrap();
+            assert!(!is_satisfied(circuit), "({}, {}) should be inside", lat, lng);
+        }
+    }
+
+    #[test]
+    fn test_substituted_bounds_fail() {
+        // Paris excluded from France by claiming Germany's box
+        let mut circuit = ExclusionProofCircuit::new_with_witness(48.8566, 2.3522, country("DE")).unwrap();
+        circuit.country_id = Some(country_code_to_field("FR"));
+        circuit.bounds_hash = Some(exclusion_bounds_hash(country("FR")));
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_empty_circuit_for_setup() {
+        assert!(is_satisfied(ExclusionProofCircuit::new_empty()));
+    }
+}
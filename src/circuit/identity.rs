@@ -0,0 +1,70 @@
+//! Identity commitments for linking proofs from different circuits.
+//!
+//! The email and country circuits take a private `identity_secret` and
+//! expose `identity_commitment = Poseidon(identity_secret)` as their last
+//! public input. Two proofs made with the same secret carry the same
+//! commitment, so a verifier can tell they are about the same person
+//! without learning who. A circuit built without a secret draws a fresh
+//! random one, which makes its proof unlinkable.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+use super::poseidon::poseidon_hash_many;
+use crate::merkle::hash::PoseidonHasher;
+
+/// Public commitment to an identity secret.
+pub fn identity_commitment(secret: &Fr) -> Fr {
+    PoseidonHasher::new().hash_one(secret)
+}
+
+/// Enforce `commitment = Poseidon(secret)` in-circuit.
+pub(crate) fn enforce_identity_commitment(
+    cs: ConstraintSystemRef<Fr>,
+    config: &PoseidonConfig<Fr>,
+    secret: &FpVar<Fr>,
+    commitment: &FpVar<Fr>,
+) -> Result<(), SynthesisError> {
+    poseidon_hash_many(cs, config, [secret])?.enforce_equal(commitment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+    use crate::circuit::{CountryProofCircuit, EmailDomainCircuit, EmailProofInput, ScaledBounds};
+
+    #[test]
+    fn test_identity_commitment_links_circuits() {
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let email = |secret| EmailDomainCircuit::new_with_witness(&input).unwrap().with_identity_secret(secret);
+        let country = |secret| {
+            CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_000)
+                .unwrap()
+                .with_identity_secret(secret)
+        };
+
+        let secret = Fr::from(42u64);
+        let (linked_email, linked_country) = (email(secret), country(secret));
+        assert_eq!(linked_email.get_identity_commitment(), Some(identity_commitment(&secret)));
+        assert_eq!(linked_email.get_identity_commitment(), linked_country.get_identity_commitment());
+        assert!(is_satisfied(linked_email));
+        assert!(is_satisfied(linked_country.clone()));
+
+        let other = country(Fr::from(43u64));
+        assert_ne!(other.get_identity_commitment(), linked_country.get_identity_commitment());
+
+        // Without a secret each proof gets its own random one
+        let unlinked = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        assert_ne!(unlinked.get_identity_commitment(), linked_country.get_identity_commitment());
+        assert!(is_satisfied(unlinked));
+
+        // Claiming someone else's commitment fails
+        let mut forged = other;
+        forged.identity_commitment = linked_country.get_identity_commitment();
+        assert!(!is_satisfied(forged));
+    }
+}
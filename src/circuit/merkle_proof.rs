@@ -5,34 +5,56 @@
 //! - A valid Merkle path from that leaf to a known root
 //!
 //! Without revealing the actual password hash.
+//!
+//! In preimage mode the leaf is not a witness of its own: the prover
+//! supplies its preimage and the circuit computes
+//! `leaf = Poseidon(preimage)` (as `PoseidonHasher::leaf_commitment`
+//! does), so a leaked leaf value alone is not enough to prove. The mode
+//! changes the circuit shape, so it is set up and proven through
+//! `MerklePreimageCircuit`, which has its own circuit tag.
+//!
+//! In index mode the circuit also exposes `index_commitment(leaf_index)`,
+//! recomputed from the path directions, as a second public input.
+//!
+//! The leaf must be non-zero. Trees pad with zero leaves, so without this
+//! a hand-built path could prove membership of an empty padding slot.
+//! Only zero is excluded: a tree built with `MerkleTree::with_padding`
+//! pads with another value, which the circuit cannot tell apart from a
+//! real leaf. Such a tree holds no zero leaves and no leaves equal to its
+//! padding, and `Prover::prove` refuses the padding value, but a verifier
+//! of such a tree must not treat the padding value as a member.
 
 use ark_bn254::Fr;
-use ark_crypto_primitives::sponge::{
-    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig},
-    constraints::CryptographicSpongeVar,
-};
+use ark_ff::Field;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
 use ark_r1cs_std::{
     alloc::AllocVar,
     boolean::Boolean,
     eq::EqGadget,
-    fields::fp::FpVar,
+    fields::{fp::FpVar, FieldVar},
     select::CondSelectGadget,
+    R1CSVar,
 };
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_std::vec::Vec;
 
-use crate::merkle::hash::PoseidonHasher;
-use crate::merkle::tree::MerklePath;
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use crate::merkle::hash::{bytes_to_field, PoseidonHasher};
+use crate::merkle::tree::{MerklePath, MerkleTree};
+use crate::prover::{CircuitTag, ProverError};
 
 /// Circuit for proving Merkle tree membership.
 ///
 /// Public inputs:
 /// - `root`: The Merkle tree root
+/// - `index_commitment`: In index mode, `Poseidon(leaf_index)`
 ///
 /// Private witnesses:
 /// - `leaf`: The leaf value (password hash as field element)
 /// - `path`: Sibling hashes along the path
 /// - `path_indices`: Direction indicators (false=left, true=right)
+/// - `preimage`: In preimage mode, the value the leaf is the hash of
 #[derive(Clone)]
 pub struct MerkleProofCircuit {
     /// Poseidon configuration for hashing.
@@ -49,6 +71,28 @@ pub struct MerkleProofCircuit {
 
     /// Public: The Merkle root to verify against.
     pub root: Option<Fr>,
+
+    /// Private: Preimage of the leaf. When set, the circuit is in preimage
+    /// mode and derives the leaf from it, ignoring `leaf`. The mode changes
+    /// the circuit shape, so keys must come from `new_empty_with_preimage`;
+    /// `MerklePreimageCircuit` sets up and proves this mode.
+    pub preimage: Option<Fr>,
+
+    /// Public: Commitment to the leaf index. When set, the circuit is in
+    /// index mode and binds it to the path directions. Like preimage mode
+    /// this changes the circuit shape (keys come from
+    /// `new_empty_with_index_commitment`), and `ProvableCircuit` does not
+    /// cover it; prove through `Prover::setup_with_index_commitment`.
+    pub index_commitment: Option<Fr>,
+}
+
+/// Commitment to a leaf position, as exposed by index-mode proofs.
+///
+/// This binds a proof to an index but does not hide it: anyone holding a
+/// proof can test a guessed index, and tree indexes are few enough to
+/// enumerate.
+pub fn index_commitment(leaf_index: usize) -> Fr {
+    PoseidonHasher::new().hash_one(&Fr::from(leaf_index as u64))
 }
 
 impl MerkleProofCircuit {
@@ -59,8 +103,9 @@ impl MerkleProofCircuit {
     pub fn new_empty(depth: usize) -> Self {
         let hasher = PoseidonHasher::new();
         
-        // Use dummy values for setup - the structure matters, not the values
-        let dummy_leaf = Fr::from(0u64);
+        // Use dummy values for setup - the structure matters, not the values.
+        // The leaf is non-zero so the dummy instance stays satisfiable.
+        let dummy_leaf = Fr::from(1u64);
         let dummy_path: Vec<Option<Fr>> = (0..depth).map(|_| Some(Fr::from(0u64))).collect();
         let dummy_indices: Vec<Option<bool>> = (0..depth).map(|_| Some(false)).collect();
         
@@ -76,6 +121,27 @@ impl MerkleProofCircuit {
             path: dummy_path,
             path_indices: dummy_indices,
             root: Some(current),
+            preimage: None,
+            index_commitment: None,
+        }
+    }
+
+    /// Create a preimage-mode circuit with the given tree depth, for setup.
+    pub fn new_empty_with_preimage(depth: usize) -> Self {
+        let hasher = PoseidonHasher::new();
+        let dummy_preimage = Fr::from(0u64);
+        let leaf = hasher.hash_one(&dummy_preimage);
+
+        let mut current = leaf;
+        for _ in 0..depth {
+            current = hasher.hash_two(&current, &Fr::from(0u64));
+        }
+
+        Self {
+            leaf: Some(leaf),
+            root: Some(current),
+            preimage: Some(dummy_preimage),
+            ..Self::new_empty(depth)
         }
     }
 
@@ -88,36 +154,110 @@ impl MerkleProofCircuit {
             path: merkle_path.siblings.iter().map(|s| Some(*s)).collect(),
             path_indices: merkle_path.indices.iter().map(|i| Some(*i)).collect(),
             root: Some(root),
+            preimage: None,
+            index_commitment: None,
+        }
+    }
+
+    /// Create a preimage-mode circuit proving knowledge of `preimage`
+    /// whose hash is the leaf of `merkle_path`.
+    ///
+    /// `preimage` is the field element `leaf_commitment` hashes, i.e.
+    /// `bytes_to_field` of the raw preimage. A wrong preimage produces an
+    /// unsatisfiable circuit.
+    pub fn new_with_preimage(preimage: Fr, merkle_path: &MerklePath, root: Fr) -> Self {
+        Self {
+            preimage: Some(preimage),
+            ..Self::new_with_witness(merkle_path, root)
         }
     }
 
+    /// Create an index-mode circuit with the given tree depth, for setup.
+    pub fn new_empty_with_index_commitment(depth: usize) -> Self {
+        Self::new_empty(depth).with_index_commitment()
+    }
+
+    /// Switch to index mode, committing to the index the path leads to.
+    ///
+    /// Leaves the circuit unchanged if a path direction is unassigned.
+    pub fn with_index_commitment(self) -> Self {
+        Self {
+            index_commitment: self.leaf_index().map(index_commitment),
+            ..self
+        }
+    }
+
+    /// Index of the leaf the path directions lead to.
+    ///
+    /// The first direction is the lowest bit: a right child at the leaf
+    /// level means an odd index.
+    pub fn leaf_index(&self) -> Option<usize> {
+        self.path_indices
+            .iter()
+            .enumerate()
+            .try_fold(0, |index, (level, is_right)| Some(index | (usize::from((*is_right)?) << level)))
+    }
+
     /// Create a circuit from a Merkle tree and leaf index.
     pub fn from_tree(
-        tree: &crate::merkle::tree::MerkleTree,
+        tree: &MerkleTree,
         leaf_index: usize,
     ) -> Option<Self> {
         let path = tree.get_path(leaf_index)?;
         Some(Self::new_with_witness(&path, tree.root()))
     }
 
+    /// Create a circuit proving `leaf` is in `tree`, against its current root.
+    ///
+    /// Looks the leaf up by value, so the path and root always come from
+    /// the same tree. Fails with `LeafNotFound` if the leaf is absent.
+    pub fn for_leaf_value(tree: &MerkleTree, leaf: &Fr) -> Result<Self, ProverError> {
+        let index = tree.find_leaf(leaf).ok_or(ProverError::LeafNotFound)?;
+        Self::from_tree(tree, index).ok_or(ProverError::LeafNotFound)
+    }
+
     /// Get the depth of this circuit.
     pub fn depth(&self) -> usize {
         self.path.len()
     }
+
+    /// Number of constraints at `depth`; see
+    /// `ProvableCircuit::count_constraints`.
+    pub fn constraint_count(depth: usize) -> usize {
+        Self::count_constraints(&depth)
+    }
 }
 
 impl ConstraintSynthesizer<Fr> for MerkleProofCircuit {
     fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
-        // Allocate the leaf as a private witness
-        let leaf_var = FpVar::new_witness(cs.clone(), || {
-            self.leaf.ok_or(SynthesisError::AssignmentMissing)
+        // Allocate the leaf as a private witness, or derive it from the preimage
+        let leaf_var = match self.preimage {
+            Some(preimage) => {
+                let preimage_var = FpVar::new_witness(cs.clone(), || Ok(preimage))?;
+                poseidon_hash_many(cs.clone(), &self.poseidon_config, [&preimage_var])?
+            }
+            None => FpVar::new_witness(cs.clone(), || {
+                self.leaf.ok_or(SynthesisError::AssignmentMissing)
+            })?,
+        };
+
+        // A zero leaf is a padding slot, not a member: require an inverse
+        let leaf_inverse_var = FpVar::new_witness(cs.clone(), || {
+            Ok(leaf_var.value()?.inverse().unwrap_or_default())
         })?;
+        leaf_var.mul_equals(&leaf_inverse_var, &FpVar::one())?;
 
         // Allocate the Merkle root as a public input
         let root_var = FpVar::new_input(cs.clone(), || {
             self.root.ok_or(SynthesisError::AssignmentMissing)
         })?;
 
+        // In index mode, allocate the index commitment as the next input
+        let index_commitment_var = self
+            .index_commitment
+            .map(|commitment| FpVar::new_input(cs.clone(), || Ok(commitment)))
+            .transpose()?;
+
         // Allocate path siblings as private witnesses
         let path_vars: Vec<FpVar<Fr>> = self
             .path
@@ -140,6 +280,12 @@ impl ConstraintSynthesizer<Fr> for MerkleProofCircuit {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
+        // The directions are the index's little-endian bits
+        if let Some(commitment_var) = &index_commitment_var {
+            let index_var = Boolean::le_bits_to_fp_var(&index_vars)?;
+            poseidon_hash_many(cs.clone(), &self.poseidon_config, [&index_var])?.enforce_equal(commitment_var)?;
+        }
+
         // Compute the root from the leaf and path using Poseidon hash
         let mut current = leaf_var;
 
@@ -150,7 +296,7 @@ impl ConstraintSynthesizer<Fr> for MerkleProofCircuit {
             let right = FpVar::conditionally_select(is_right, &current, sibling)?;
 
             // Hash the two children using Poseidon
-            current = poseidon_hash_two(cs.clone(), &self.poseidon_config, &left, &right)?;
+            current = poseidon_hash_many(cs.clone(), &self.poseidon_config, [&left, &right])?;
         }
 
         // Enforce that the computed root equals the public input root
@@ -160,31 +306,110 @@ impl ConstraintSynthesizer<Fr> for MerkleProofCircuit {
     }
 }
 
-/// Compute Poseidon hash of two field elements in-circuit.
-fn poseidon_hash_two(
-    cs: ConstraintSystemRef<Fr>,
-    config: &PoseidonConfig<Fr>,
-    left: &FpVar<Fr>,
-    right: &FpVar<Fr>,
-) -> Result<FpVar<Fr>, SynthesisError> {
-    let mut sponge = PoseidonSpongeVar::new(cs, config);
-    sponge.absorb(left)?;
-    sponge.absorb(right)?;
-    let output = sponge.squeeze_field_elements(1)?;
-    Ok(output[0].clone())
+/// Covers plain mode only: `blank` sets up plain keys and the only public
+/// input is the root. Index-mode keys also take `index_commitment`; they
+/// come from `Prover::setup_with_index_commitment`, whose `MembershipProof`
+/// carries both inputs.
+impl ProvableCircuit for MerkleProofCircuit {
+    /// Tree depth.
+    type SetupParams = usize;
+    /// The Merkle root.
+    type PublicInputs = Fr;
+
+    const TAG: CircuitTag = CircuitTag::Merkle;
+
+    /// `["root"]`, the plain-mode inputs; see the impl note for index mode.
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["root"]
+    }
+
+    fn blank(depth: &usize) -> Self {
+        Self::new_empty(*depth)
+    }
+
+    fn public_inputs(&self) -> Option<Fr> {
+        self.root
+    }
+
+    fn input_fields(root: &Fr) -> Vec<Fr> {
+        vec![*root]
+    }
+}
+
+/// `MerkleProofCircuit` in preimage mode, set up and proven on its own.
+///
+/// Preimage mode has a different shape from the plain circuit, so its keys
+/// are separate and carry `CircuitTag::MerklePreimage`; plain Merkle keys
+/// are rejected when loaded for it. The only public input is the root.
+#[derive(Clone)]
+pub struct MerklePreimageCircuit(MerkleProofCircuit);
+
+impl MerklePreimageCircuit {
+    /// Prove knowledge of `preimage` whose hash is the leaf of
+    /// `merkle_path`; see `MerkleProofCircuit::new_with_preimage`.
+    pub fn new(preimage: Fr, merkle_path: &MerklePath, root: Fr) -> Self {
+        Self(MerkleProofCircuit::new_with_preimage(preimage, merkle_path, root))
+    }
+
+    /// Prove that the leaf `leaf_commitment(preimage)` is in `tree`.
+    ///
+    /// Fails with `LeafNotFound` if no leaf has that commitment.
+    pub fn for_preimage(tree: &MerkleTree, preimage: &[u8]) -> Result<Self, ProverError> {
+        let leaf = PoseidonHasher::new().leaf_commitment(preimage);
+        let index = tree.find_leaf(&leaf).ok_or(ProverError::LeafNotFound)?;
+        let path = tree.get_path(index).ok_or(ProverError::LeafNotFound)?;
+        Ok(Self::new(bytes_to_field(preimage), &path, tree.root()))
+    }
+
+    /// The underlying preimage-mode circuit.
+    pub fn inner(&self) -> &MerkleProofCircuit {
+        &self.0
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MerklePreimageCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        self.0.generate_constraints(cs)
+    }
+}
+
+impl ProvableCircuit for MerklePreimageCircuit {
+    /// Tree depth.
+    type SetupParams = usize;
+    /// The Merkle root.
+    type PublicInputs = Fr;
+
+    const TAG: CircuitTag = CircuitTag::MerklePreimage;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["root"]
+    }
+
+    fn blank(depth: &usize) -> Self {
+        Self(MerkleProofCircuit::new_empty_with_preimage(*depth))
+    }
+
+    fn public_inputs(&self) -> Option<Fr> {
+        self.0.root
+    }
+
+    fn input_fields(root: &Fr) -> Vec<Fr> {
+        vec![*root]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use ark_relations::r1cs::ConstraintSystem;
-    use crate::merkle::tree::MerkleTree;
+    use ark_std::rand::SeedableRng;
+    use crate::prover::GenericProver;
 
     #[test]
     fn test_circuit_satisfiability() {
         // Create a simple tree
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         // Create circuit for leaf at index 3
         let circuit = MerkleProofCircuit::from_tree(&tree, 3).unwrap();
@@ -201,7 +426,7 @@ mod tests {
     fn test_circuit_with_wrong_root_fails() {
         // Create a simple tree
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         // Create circuit with wrong root
         let path = tree.get_path(3).unwrap();
@@ -215,12 +440,89 @@ mod tests {
         assert!(!cs.is_satisfied().unwrap());
     }
 
+    #[test]
+    fn test_prove_by_leaf_value() {
+        let leaves: Vec<Fr> = (10..18).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(844);
+        let prover = GenericProver::<MerkleProofCircuit>::setup(&tree.depth(), &mut rng).unwrap();
+
+        let circuit = MerkleProofCircuit::for_leaf_value(&tree, &Fr::from(13u64)).unwrap();
+        let (proof, root) = prover.prove(circuit, &mut rng).unwrap();
+        assert_eq!(root, tree.root());
+        assert!(prover.verify(&proof, &root).unwrap());
+
+        assert!(matches!(
+            MerkleProofCircuit::for_leaf_value(&tree, &Fr::from(99u64)),
+            Err(ProverError::LeafNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_preimage_mode() {
+        let hasher = PoseidonHasher::new();
+        let password_hash = b"5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8";
+        let mut leaves: Vec<Fr> = (0..7).map(|i| Fr::from(i as u64)).collect();
+        leaves.push(hasher.leaf_commitment(password_hash));
+        let tree = MerkleTree::new(leaves).unwrap();
+        let path = tree.get_path(7).unwrap();
+
+        let is_satisfied = |circuit: MerkleProofCircuit| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            circuit.generate_constraints(cs.clone()).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+
+        let correct = MerkleProofCircuit::new_with_preimage(bytes_to_field(password_hash), &path, tree.root());
+        assert!(is_satisfied(correct));
+
+        // Knowing the leaf is not enough without its preimage
+        let replayed = MerkleProofCircuit::new_with_preimage(path.leaf, &path, tree.root());
+        assert!(!is_satisfied(replayed));
+        let wrong = MerkleProofCircuit::new_with_preimage(bytes_to_field(b"hunter2"), &path, tree.root());
+        assert!(!is_satisfied(wrong));
+
+        assert!(is_satisfied(MerkleProofCircuit::new_empty_with_preimage(3)));
+    }
+
+    #[test]
+    fn test_preimage_mode_groth16() {
+        let hasher = PoseidonHasher::new();
+        let password_hash = b"5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8";
+        let mut leaves: Vec<Fr> = (1..8).map(|i| Fr::from(i as u64)).collect();
+        leaves.push(hasher.leaf_commitment(password_hash));
+        let tree = MerkleTree::new(leaves).unwrap();
+
+        let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(845);
+        let prover = GenericProver::<MerklePreimageCircuit>::setup(&tree.depth(), &mut rng).unwrap();
+
+        let circuit = MerklePreimageCircuit::for_preimage(&tree, password_hash).unwrap();
+        let (proof, root) = prover.prove(circuit, &mut rng).unwrap();
+        assert_eq!(root, tree.root());
+        assert!(prover.verify(&proof, &root).unwrap());
+        assert!(!prover.verify(&proof, &Fr::from(999u64)).unwrap());
+
+        assert!(matches!(
+            MerklePreimageCircuit::for_preimage(&tree, b"hunter2"),
+            Err(ProverError::LeafNotFound)
+        ));
+
+        // Plain and preimage keys are not interchangeable
+        let plain = GenericProver::<MerkleProofCircuit>::setup(&tree.depth(), &mut rng).unwrap();
+        let vk_bytes = plain.verifying_key_bytes().unwrap();
+        let pk_bytes = plain.proving_key_bytes().unwrap();
+        assert!(matches!(
+            GenericProver::<MerklePreimageCircuit>::from_key_bytes(&pk_bytes, &vk_bytes),
+            Err(ProverError::FormatMismatch(_))
+        ));
+    }
+
     #[test]
     fn test_empty_circuit_for_setup() {
         // Create circuit with dummy values for trusted setup
         // Setup requires actual values, not None
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
         let circuit = MerkleProofCircuit::from_tree(&tree, 0).unwrap();
 
         let cs = ConstraintSystem::<Fr>::new_ref();
@@ -230,5 +532,51 @@ mod tests {
         println!("Number of constraints: {}", cs.num_constraints());
         println!("Number of variables: {}", cs.num_instance_variables() + cs.num_witness_variables());
     }
-}
 
+    #[test]
+    fn test_zero_padding_leaf_rejected() {
+        // Three leaves, so index 3 is a zero padding slot
+        let leaves: Vec<Fr> = (1..=3).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        assert!(tree.get_path(3).is_none());
+
+        // A hand-made path to the padding slot still hashes to the root
+        let neighbour = tree.get_path(2).unwrap();
+        let mut siblings = neighbour.siblings.clone();
+        siblings[0] = neighbour.leaf;
+        let mut indices = neighbour.indices.clone();
+        indices[0] = true;
+        let forged = MerklePath {
+            siblings,
+            indices,
+            leaf: Fr::from(0u64),
+        };
+        assert!(forged.verify(&tree.root(), tree.hasher()));
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        MerkleProofCircuit::new_with_witness(&forged, tree.root())
+            .generate_constraints(cs.clone())
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        let (prover, _) = crate::prover::Prover::setup_deterministic(tree.depth(), 1).unwrap();
+        assert!(matches!(
+            prover.prove_with_path(&forged, tree.root()),
+            Err(ProverError::ProofGenerationFailed(_))
+        ));
+
+        let blank = ConstraintSystem::<Fr>::new_ref();
+        MerkleProofCircuit::new_empty(tree.depth()).generate_constraints(blank.clone()).unwrap();
+        assert!(blank.is_satisfied().unwrap());
+
+        // A non-zero padding value is refused by `Prover::prove`
+        let pad = Fr::from(99u64);
+        let padded = MerkleTree::with_padding(vec![Fr::from(1u64), Fr::from(2u64), Fr::from(3u64)], pad, PoseidonHasher::new())
+            .unwrap();
+        assert!(matches!(
+            prover.prove(&padded, &pad),
+            Err(ProverError::ProofGenerationFailed(_))
+        ));
+        assert!(prover.prove(&padded, &Fr::from(3u64)).is_ok());
+    }
+}
@@ -1,9 +1,41 @@
 //! ZK circuit definitions for various proofs.
 
 mod merkle_proof;
+mod pedersen_merkle_proof;
 mod country_proof;
 mod email_proof;
+mod corporate_email_proof;
+mod challenge_email_proof;
+mod composite_proof;
+mod sparse_merkle_proof;
+mod region_proof;
+mod convex_polygon_proof;
+mod exclusion_proof;
+mod country_set_proof;
+mod poseidon;
+mod provable;
+mod identity;
+pub mod range;
 
-pub use merkle_proof::MerkleProofCircuit;
-pub use country_proof::{CountryProofCircuit, ScaledBounds, country_code_to_field, coord_to_scaled, COORD_SCALE};
-pub use email_proof::{EmailDomainCircuit, EmailProofInput, string_to_field, extract_domain};
+pub use merkle_proof::{MerklePreimageCircuit, MerkleProofCircuit, index_commitment};
+pub use pedersen_merkle_proof::PedersenMerkleCircuit;
+pub use country_proof::{CountryProofCircuit, CoordError, ScaledBounds, country_code_to_field, coord_to_scaled, coords_to_fields, normalize_coords, location_commitment, location_nullifier, lat_to_field, lng_to_field, COORD_SCALE, LAT_OFFSET, LNG_OFFSET};
+pub use email_proof::{DatedEmailCircuit, EmailDomainCircuit, EmailProofInput, EmailProofInputBuilder, EmailInputError, DomainSource, string_to_field, extract_domain, normalize_domain};
+pub use corporate_email_proof::{CorporateEmailCircuit, FREEMAIL_DOMAINS, freemail_root, freemail_tree, is_freemail_domain};
+pub use challenge_email_proof::{ChallengeEmailCircuit, challenge_commitment};
+pub use composite_proof::CompositeCircuit;
+pub use sparse_merkle_proof::SparseMerkleCircuit;
+pub use convex_polygon_proof::{ConvexPolygonCircuit, convex_polygon_fields, MAX_CONVEX_VERTICES};
+pub use region_proof::{RegionProofCircuit, region_code_to_field, region_polygon_hash, MAX_REGION_VERTICES};
+pub use exclusion_proof::{ExclusionProofCircuit, exclusion_bounds_hash};
+pub use country_set_proof::{CountrySetCircuit, country_group_to_field, country_group_bounds_hash, MAX_GROUP_COUNTRIES};
+pub use identity::identity_commitment;
+pub use provable::ProvableCircuit;
+
+/// Whether `circuit`'s constraints hold for its witness.
+#[cfg(test)]
+pub(crate) fn is_satisfied<C: ark_relations::r1cs::ConstraintSynthesizer<ark_bn254::Fr>>(circuit: C) -> bool {
+    let cs = ark_relations::r1cs::ConstraintSystem::<ark_bn254::Fr>::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    cs.is_satisfied().unwrap()
+}
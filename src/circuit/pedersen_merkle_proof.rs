@@ -0,0 +1,219 @@
+//! Merkle membership circuit for trees hashed with `PedersenHasher`.
+//!
+//! Same statement as `MerkleProofCircuit` (knowledge of a leaf and a path
+//! to the public root), but each level is the Pedersen hash over Baby
+//! Jubjub that `PedersenHasher::hash_two` computes natively. Pedersen is
+//! far more expensive in-circuit than Poseidon; use it only when the
+//! verifier side expects Pedersen roots.
+//!
+//! Unlike `MerkleProofCircuit`, no leaf value is excluded, so a path to a
+//! padding slot proves membership of the padding value. Pad Pedersen
+//! trees with a value that is never a real member, and do not accept it.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::crh::{
+    pedersen::constraints::{CRHParametersVar, TwoToOneCRHGadget},
+    TwoToOneCRHSchemeGadget,
+};
+use ark_ed_on_bn254::{constraints::EdwardsVar, EdwardsProjective};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+    ToBytesGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::provable::ProvableCircuit;
+use crate::merkle::pedersen::{PedersenParameters, PedersenWindow};
+use crate::merkle::tree::{MerklePath, MerkleTree};
+use crate::merkle::PedersenHasher;
+use crate::prover::CircuitTag;
+
+type PedersenGadget = TwoToOneCRHGadget<EdwardsProjective, EdwardsVar, PedersenWindow>;
+
+/// Circuit for proving membership in a Pedersen-hashed Merkle tree.
+///
+/// Public inputs:
+/// - `root`: The Merkle tree root
+///
+/// Private witnesses:
+/// - `leaf`: The leaf value
+/// - `path`: Sibling hashes along the path
+/// - `path_indices`: Direction indicators (false=left, true=right)
+#[derive(Clone)]
+pub struct PedersenMerkleCircuit {
+    /// Pedersen generators, as `PedersenHasher::parameters`.
+    pub parameters: PedersenParameters,
+
+    /// Private: The leaf value being proven.
+    pub leaf: Option<Fr>,
+
+    /// Private: Sibling hashes along the Merkle path.
+    pub path: Vec<Option<Fr>>,
+
+    /// Private: Path direction indicators.
+    pub path_indices: Vec<Option<bool>>,
+
+    /// Public: The Merkle root to verify against.
+    pub root: Option<Fr>,
+}
+
+impl PedersenMerkleCircuit {
+    /// Create a circuit of the given depth with dummy zero values for setup.
+    pub fn new_empty(depth: usize) -> Self {
+        let hasher = PedersenHasher::new();
+        let dummy = MerklePath {
+            siblings: vec![Fr::from(0u64); depth],
+            indices: vec![false; depth],
+            leaf: Fr::from(0u64),
+        };
+        let root = dummy.compute_root(&hasher);
+        Self::with_hasher(&hasher, &dummy, root)
+    }
+
+    /// Create a circuit with actual witness values for proving.
+    pub fn new_with_witness(merkle_path: &MerklePath, root: Fr) -> Self {
+        Self::with_hasher(&PedersenHasher::new(), merkle_path, root)
+    }
+
+    /// Create a circuit from a Pedersen tree and leaf index.
+    pub fn from_tree(tree: &MerkleTree<PedersenHasher>, leaf_index: usize) -> Option<Self> {
+        let path = tree.get_path(leaf_index)?;
+        Some(Self::with_hasher(tree.hasher(), &path, tree.root()))
+    }
+
+    fn with_hasher(hasher: &PedersenHasher, merkle_path: &MerklePath, root: Fr) -> Self {
+        Self {
+            parameters: hasher.parameters().clone(),
+            leaf: Some(merkle_path.leaf),
+            path: merkle_path.siblings.iter().map(|s| Some(*s)).collect(),
+            path_indices: merkle_path.indices.iter().map(|i| Some(*i)).collect(),
+            root: Some(root),
+        }
+    }
+
+    /// Get the depth of this circuit.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for PedersenMerkleCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let leaf_var = FpVar::new_witness(cs.clone(), || {
+            self.leaf.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let root_var = FpVar::new_input(cs.clone(), || {
+            self.root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let path_vars: Vec<FpVar<Fr>> = self
+            .path
+            .iter()
+            .map(|sibling| {
+                FpVar::new_witness(cs.clone(), || {
+                    sibling.ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let index_vars: Vec<Boolean<Fr>> = self
+            .path_indices
+            .iter()
+            .map(|idx| {
+                Boolean::new_witness(cs.clone(), || {
+                    idx.ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The generators are fixed, so they are constants rather than inputs
+        let params_var =
+            CRHParametersVar::<EdwardsProjective, EdwardsVar>::new_constant(cs.clone(), &self.parameters)?;
+
+        let mut current = leaf_var;
+        for (sibling, is_right) in path_vars.iter().zip(index_vars.iter()) {
+            let left = FpVar::conditionally_select(is_right, sibling, &current)?;
+            let right = FpVar::conditionally_select(is_right, &current, sibling)?;
+            current = pedersen_hash_two(&params_var, &left, &right)?;
+        }
+
+        current.enforce_equal(&root_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for PedersenMerkleCircuit {
+    /// Tree depth.
+    type SetupParams = usize;
+    /// The Merkle root.
+    type PublicInputs = Fr;
+
+    const TAG: CircuitTag = CircuitTag::PedersenMerkle;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["root"]
+    }
+
+    fn blank(depth: &usize) -> Self {
+        Self::new_empty(*depth)
+    }
+
+    fn public_inputs(&self) -> Option<Fr> {
+        self.root
+    }
+
+    fn input_fields(root: &Fr) -> Vec<Fr> {
+        vec![*root]
+    }
+}
+
+/// Compute `PedersenHasher::hash_two` in-circuit.
+///
+/// `to_bytes` is the canonical little-endian encoding (it rejects
+/// non-reduced bit patterns), matching `field_to_canonical_bytes`.
+fn pedersen_hash_two(
+    params: &CRHParametersVar<EdwardsProjective, EdwardsVar>,
+    left: &FpVar<Fr>,
+    right: &FpVar<Fr>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let point = PedersenGadget::evaluate(params, &left.to_bytes()?, &right.to_bytes()?)?;
+    Ok(point.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+    use crate::merkle::HashScheme;
+
+    #[test]
+    fn test_pedersen_tree_paths_verify_natively_and_in_circuit() {
+        let hasher = PedersenHasher::new();
+        let leaves: Vec<Fr> = (1..=5).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::with_hash_scheme(leaves.clone(), hasher.clone()).unwrap();
+
+        // A different hash from the Poseidon tree over the same leaves
+        assert_ne!(tree.root(), MerkleTree::new(leaves).unwrap().root());
+        assert!(tree.verify_integrity());
+
+        for index in [0, 3, 4] {
+            let path = tree.get_path(index).unwrap();
+            assert!(path.verify(&tree.root(), &hasher));
+            assert!(is_satisfied(PedersenMerkleCircuit::from_tree(&tree, index).unwrap()));
+        }
+
+        let path = tree.get_path(2).unwrap();
+        assert!(!is_satisfied(PedersenMerkleCircuit::new_with_witness(&path, Fr::from(7u64))));
+        assert!(is_satisfied(PedersenMerkleCircuit::new_empty(tree.depth())));
+
+        // Native hash_many is length-separated
+        assert_ne!(hasher.hash_many(&[Fr::from(0u64)]), hasher.hash_many(&[Fr::from(0u64); 2]));
+    }
+}
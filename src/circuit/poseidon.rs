@@ -0,0 +1,26 @@
+//! In-circuit Poseidon hashing shared by the circuits.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::{
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig},
+    constraints::CryptographicSpongeVar,
+};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+/// Compute Poseidon hash of any number of field elements in-circuit.
+///
+/// Absorbs the elements in order and squeezes one, so it matches
+/// `PoseidonHasher::hash_one`, `hash_two` and `hash_many` natively.
+pub(crate) fn poseidon_hash_many<'a>(
+    cs: ConstraintSystemRef<Fr>,
+    config: &PoseidonConfig<Fr>,
+    elements: impl IntoIterator<Item = &'a FpVar<Fr>>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::new(cs, config);
+    for elem in elements {
+        sponge.absorb(elem)?;
+    }
+    let output = sponge.squeeze_field_elements(1)?;
+    Ok(output[0].clone())
+}
@@ -0,0 +1,231 @@
+//! Common interface for circuits that go through the Groth16 pipeline.
+//!
+//! Each circuit describes how to build a blank instance for setup and how
+//! to read its public inputs; setup, proving and key handling are then
+//! shared by `GenericProver`.
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use ark_snark::SNARK;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::vec::Vec;
+
+use crate::prover::CircuitTag;
+
+/// A circuit that can be set up, proven and verified with Groth16.
+pub trait ProvableCircuit: ConstraintSynthesizer<Fr> + Sized {
+    /// Parameters that fix the circuit shape (e.g. Merkle tree depth).
+    type SetupParams;
+
+    /// Typed public inputs of a circuit instance.
+    type PublicInputs: Clone;
+
+    /// Tag written into serialized key files for this circuit.
+    const TAG: CircuitTag;
+
+    /// Build a circuit with dummy witnesses for trusted setup.
+    fn blank(params: &Self::SetupParams) -> Self;
+
+    /// Public inputs of this instance, if its witnesses are assigned.
+    fn public_inputs(&self) -> Option<Self::PublicInputs>;
+
+    /// Flatten typed public inputs into field elements, in the order the
+    /// circuit allocates them.
+    fn input_fields(inputs: &Self::PublicInputs) -> Vec<Fr>;
+
+    /// Name of each public input, in the order of `input_fields`.
+    ///
+    /// For labelling inputs in tooling, e.g. when an on-chain
+    /// verification fails: entry `i` names public input `[i]`.
+    fn public_input_schema() -> Vec<&'static str>;
+
+    /// Public inputs of this instance as field elements, in allocation
+    /// order; exactly what a Groth16 verifier expects.
+    fn public_input_fields(&self) -> Option<Vec<Fr>> {
+        self.public_inputs().map(|inputs| Self::input_fields(&inputs))
+    }
+
+    /// Full private witness assignment of this instance, without proving.
+    ///
+    /// Synthesizes the circuit the way the Groth16 prover does and returns
+    /// the witness variables in allocation order, for inspection or for an
+    /// external prover. The constraints are not checked; an unsatisfiable
+    /// instance still yields its assignment.
+    fn generate_witness(&self) -> Result<Vec<Fr>, SynthesisError>
+    where
+        Self: Clone,
+    {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Prove { construct_matrices: false });
+        self.clone().generate_constraints(cs.clone())?;
+        cs.finalize();
+
+        let witness = cs
+            .borrow()
+            .ok_or(SynthesisError::MissingCS)?
+            .witness_assignment
+            .clone();
+        Ok(witness)
+    }
+
+    /// Number of R1CS constraints of the circuit shape fixed by `params`.
+    ///
+    /// Synthesizes `blank(params)` as trusted setup does, so it is the
+    /// size of the keys and the proving cost; pin it in tests to catch
+    /// accidental growth.
+    fn count_constraints(params: &Self::SetupParams) -> usize {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        cs.set_mode(SynthesisMode::Setup);
+        Self::blank(params)
+            .generate_constraints(cs.clone())
+            .expect("blank circuits synthesize");
+        cs.num_constraints()
+    }
+
+    /// Run circuit-specific trusted setup.
+    fn setup<R: RngCore + CryptoRng>(
+        params: &Self::SetupParams,
+        rng: &mut R,
+    ) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), SynthesisError> {
+        Groth16::<Bn254>::circuit_specific_setup(Self::blank(params), rng)
+    }
+
+    /// Generate a Groth16 proof for this instance.
+    fn prove<R: RngCore + CryptoRng>(
+        self,
+        pk: &ProvingKey<Bn254>,
+        rng: &mut R,
+    ) -> Result<Proof<Bn254>, SynthesisError> {
+        Groth16::<Bn254>::prove(pk, self, rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{
+        ChallengeEmailCircuit, CompositeCircuit, ConvexPolygonCircuit, CorporateEmailCircuit, CountryProofCircuit, CountrySetCircuit,
+        DatedEmailCircuit, EmailDomainCircuit, EmailProofInput, ExclusionProofCircuit, MerklePreimageCircuit, MerkleProofCircuit,
+        PedersenMerkleCircuit, RegionProofCircuit, ScaledBounds, SparseMerkleCircuit,
+    };
+    use crate::proofs::location::{CountryGroup, Region, COUNTRIES};
+    use crate::merkle::tree::MerkleTree;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    /// The listed public inputs must be exactly the instance assignment.
+    fn assert_inputs_match_allocation<C: ProvableCircuit + Clone>(circuit: C) {
+        let fields = circuit.public_input_fields().unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        assert_eq!(fields.len(), cs.num_instance_variables() - 1);
+        // Slot 0 is the constant one
+        assert_eq!(fields[..], cs.borrow().unwrap().instance_assignment[1..]);
+    }
+
+    /// The schema names every public input the blank circuit allocates.
+    fn assert_schema_matches_allocation<C: ProvableCircuit>(params: &C::SetupParams) {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_mode(SynthesisMode::Setup);
+        C::blank(params).generate_constraints(cs.clone()).unwrap();
+        assert_eq!(C::public_input_schema().len(), cs.num_instance_variables() - 1);
+    }
+
+    fn assert_witness_matches_synthesis<C: ProvableCircuit + Clone>(circuit: C) {
+        let witness = circuit.generate_witness().unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert_eq!(witness.len(), cs.num_witness_variables());
+        assert_eq!(witness[..], cs.borrow().unwrap().witness_assignment[..]);
+    }
+
+    #[test]
+    fn test_generate_witness() {
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let email = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+
+        assert_witness_matches_synthesis(MerkleProofCircuit::from_tree(&tree, 2).unwrap());
+        assert_witness_matches_synthesis(CountryProofCircuit::new_with_witness(
+            37.7749, -122.4194, &bounds, "US", 5, 1_700_000_000,
+        ).unwrap());
+        assert_witness_matches_synthesis(EmailDomainCircuit::new_with_witness(&email).unwrap());
+    }
+
+    #[test]
+    fn test_constraint_counts() {
+        // Update these only when a circuit change is meant to resize it
+        assert_eq!(MerkleProofCircuit::constraint_count(4), 974);
+        assert_eq!(MerkleProofCircuit::constraint_count(20), 4862);
+        assert_eq!(CountryProofCircuit::constraint_count(), 1206);
+        assert_eq!(EmailDomainCircuit::constraint_count(), 722);
+
+        // A witnessed instance has the same shape as the blank one
+        let email = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        EmailDomainCircuit::new_with_witness(&email).unwrap().generate_constraints(cs.clone()).unwrap();
+        assert_eq!(cs.num_constraints(), EmailDomainCircuit::constraint_count());
+    }
+
+    #[test]
+    fn test_public_inputs_follow_allocation_order() {
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let email = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+
+        assert_inputs_match_allocation(MerkleProofCircuit::from_tree(&tree, 2).unwrap());
+        assert_inputs_match_allocation(CountryProofCircuit::new_with_witness(
+            37.7749, -122.4194, &bounds, "US", 5, 1_700_000_000,
+        ).unwrap());
+        assert_inputs_match_allocation(EmailDomainCircuit::new_with_witness(&email).unwrap());
+        assert_inputs_match_allocation(CorporateEmailCircuit::new_with_witness(&email).unwrap());
+        assert_inputs_match_allocation(ChallengeEmailCircuit::new_with_witness("google.com", "K7-QX2P-91").unwrap());
+        assert_inputs_match_allocation(CompositeCircuit::new_with_witness(
+            &email, 37.7749, -122.4194, &bounds, "US",
+        ).unwrap());
+        assert_inputs_match_allocation(SparseMerkleCircuit::new_empty());
+        assert_inputs_match_allocation(
+            RegionProofCircuit::new_with_witness(37.7749, -122.4194, Region::by_code("US-CA").unwrap())
+                .unwrap(),
+        );
+        assert_inputs_match_allocation(ExclusionProofCircuit::new_with_witness(
+            48.8566, 2.3522, &COUNTRIES[0],
+        ).unwrap());
+        assert_inputs_match_allocation(
+            CountrySetCircuit::new_with_witness(48.8566, 2.3522, CountryGroup::by_code("EU").unwrap()).unwrap(),
+        );
+        assert_inputs_match_allocation(
+            ConvexPolygonCircuit::new_with_witness(37.0, -113.0, &[(35.0, -115.0), (40.0, -114.0), (36.0, -110.0)])
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_public_input_schema_matches_allocation() {
+        assert_schema_matches_allocation::<MerkleProofCircuit>(&4);
+        assert_schema_matches_allocation::<MerklePreimageCircuit>(&4);
+        assert_schema_matches_allocation::<PedersenMerkleCircuit>(&4);
+        assert_schema_matches_allocation::<SparseMerkleCircuit>(&());
+        assert_schema_matches_allocation::<CountryProofCircuit>(&());
+        assert_schema_matches_allocation::<EmailDomainCircuit>(&());
+        assert_schema_matches_allocation::<DatedEmailCircuit>(&());
+        assert_schema_matches_allocation::<CorporateEmailCircuit>(&());
+        assert_schema_matches_allocation::<ChallengeEmailCircuit>(&());
+        assert_schema_matches_allocation::<CompositeCircuit>(&());
+        assert_schema_matches_allocation::<RegionProofCircuit>(&());
+        assert_schema_matches_allocation::<ExclusionProofCircuit>(&());
+        assert_schema_matches_allocation::<CountrySetCircuit>(&());
+        assert_schema_matches_allocation::<ConvexPolygonCircuit>(&());
+
+        assert_eq!(MerkleProofCircuit::public_input_schema(), ["root"]);
+        assert_eq!(EmailDomainCircuit::public_input_schema()[..2], ["domain_hash", "commitment"]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Range-check gadgets for scaled integer coordinates.
+//!
+//! Coordinates enter the circuits as non-negative fixed-point integers
+//! (see `lat_to_field` / `lng_to_field`), so bounds checks reduce to
+//! proving that a difference fits in a small number of bits.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    R1CSVar,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+/// Bit width used for range checks on shifted coordinates.
+///
+/// Shifted coordinates are at most `360 * COORD_SCALE < 2^29`.
+pub const COORD_BITS: usize = 32;
+
+/// Enforce that `value` fits in `num_bits` bits.
+pub fn enforce_bit_length(
+    cs: ConstraintSystemRef<Fr>,
+    value: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    let bits = witness_bits(cs, value, num_bits)?;
+    let recomposed = Boolean::le_bits_to_fp_var(&bits)?;
+    recomposed.enforce_equal(value)
+}
+
+/// Enforce `low <= value <= high` for operands below `2^num_bits`.
+pub fn enforce_in_range(
+    cs: ConstraintSystemRef<Fr>,
+    value: &FpVar<Fr>,
+    low: &FpVar<Fr>,
+    high: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<(), SynthesisError> {
+    enforce_bit_length(cs.clone(), &(value - low), num_bits)?;
+    enforce_bit_length(cs, &(high - value), num_bits)
+}
+
+/// Return whether `a < b` for operands below `2^num_bits`.
+///
+/// Decomposes `a - b + 2^num_bits` into `num_bits + 1` bits; the top bit
+/// is clear exactly when `a < b`.
+pub fn is_less_than(
+    cs: ConstraintSystemRef<Fr>,
+    a: &FpVar<Fr>,
+    b: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<Boolean<Fr>, SynthesisError> {
+    let offset = FpVar::Constant(Fr::from(2u64).pow([num_bits as u64]));
+    let shifted = a - b + offset;
+
+    let bits = witness_bits(cs, &shifted, num_bits + 1)?;
+    let recomposed = Boolean::le_bits_to_fp_var(&bits)?;
+    recomposed.enforce_equal(&shifted)?;
+
+    Ok(bits[num_bits].not())
+}
+
+/// Allocate the low `num_bits` bits of `value` as witnesses.
+fn witness_bits(
+    cs: ConstraintSystemRef<Fr>,
+    value: &FpVar<Fr>,
+    num_bits: usize,
+) -> Result<Vec<Boolean<Fr>>, SynthesisError> {
+    (0..num_bits)
+        .map(|i| {
+            Boolean::new_witness(cs.clone(), || {
+                Ok(value.value()?.into_bigint().get_bit(i))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn check_range(value: u64, low: u64, high: u64) -> bool {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value = FpVar::new_witness(cs.clone(), || Ok(Fr::from(value))).unwrap();
+        let low = FpVar::new_input(cs.clone(), || Ok(Fr::from(low))).unwrap();
+        let high = FpVar::new_input(cs.clone(), || Ok(Fr::from(high))).unwrap();
+        enforce_in_range(cs.clone(), &value, &low, &high, COORD_BITS).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    #[test]
+    fn test_in_range() {
+        assert!(check_range(50, 10, 100));
+        assert!(check_range(10, 10, 100));
+        assert!(check_range(100, 10, 100));
+        assert!(!check_range(9, 10, 100));
+        assert!(!check_range(101, 10, 100));
+    }
+
+    #[test]
+    fn test_is_less_than() {
+        for (a, b, expected) in [(3u64, 5u64, true), (5, 5, false), (7, 5, false)] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let a = FpVar::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap();
+            let b = FpVar::new_witness(cs.clone(), || Ok(Fr::from(b))).unwrap();
+            let lt = is_less_than(cs.clone(), &a, &b, COORD_BITS).unwrap();
+
+            assert_eq!(lt.value().unwrap(), expected);
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
+}
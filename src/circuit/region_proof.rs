@@ -0,0 +1,358 @@
+//! Region (state / province) location proof circuit.
+//!
+//! Proves that private coordinates lie inside a region's polygon, e.g.
+//! "I'm in California", without revealing them. Containment is checked
+//! in-circuit with the even-odd ray-casting rule: a ray from the point
+//! towards increasing longitude crosses the boundary an odd number of
+//! times exactly when the point is inside.
+//!
+//! Every polygon is padded to `MAX_REGION_VERTICES` by repeating its last
+//! vertex. The repeated vertices form zero-length edges that never cross
+//! the ray, so all regions share one circuit shape and one key pair.
+//!
+//! Public inputs (in allocation order):
+//! - region_id: `region_code_to_field(code)`
+//! - polygon_hash: Poseidon of the padded, shifted polygon vertices
+//! - commitment: Poseidon(lat, lng, region_id, nonce)
+//!
+//! Private witnesses:
+//! - lat, lng (shifted, scaled coordinates)
+//! - the polygon vertices
+//! - nonce
+//!
+//! The verifier must recompute `polygon_hash` from its own copy of the
+//! region (see `region_polygon_hash`); otherwise the prover could supply
+//! any polygon.
+
+use ark_bn254::Fr;
+use ark_ff::Field;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::country_proof::{country_code_to_field, coords_to_fields, lat_to_field, lng_to_field, CoordError};
+use super::email_proof::random_nonce;
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use super::range::{enforce_bit_length, is_less_than, COORD_BITS};
+use crate::merkle::hash::PoseidonHasher;
+use crate::proofs::location::Region;
+use crate::prover::CircuitTag;
+
+/// Maximum number of polygon vertices a region may have.
+pub const MAX_REGION_VERTICES: usize = 16;
+
+/// Bit width for the signed edge cross products.
+///
+/// Coordinates are range-checked to `COORD_BITS`, so each cross product
+/// has magnitude below `2^(2 * COORD_BITS + 1)`.
+pub(super) const CROSS_BITS: usize = 2 * COORD_BITS + 2;
+
+/// Convert a region code (e.g. "US-CA") to a field element (public input).
+pub fn region_code_to_field(code: &str) -> Fr {
+    country_code_to_field(code)
+}
+
+/// Poseidon hash of a polygon as committed to by `RegionProofCircuit`.
+///
+/// Returns `None` if the polygon has fewer than 3 or more than
+/// `MAX_REGION_VERTICES` vertices.
+pub fn region_polygon_hash(polygon: &[(f64, f64)]) -> Option<Fr> {
+    let vertices = padded_vertices(polygon)?;
+    Some(PoseidonHasher::new().hash_many(&flatten(&vertices)))
+}
+
+/// Shifted vertices, padded to `MAX_REGION_VERTICES` with the last one.
+fn padded_vertices(polygon: &[(f64, f64)]) -> Option<Vec<(Fr, Fr)>> {
+    if polygon.len() < 3 || polygon.len() > MAX_REGION_VERTICES {
+        return None;
+    }
+    let mut vertices: Vec<(Fr, Fr)> = polygon
+        .iter()
+        .map(|&(lat, lng)| (lat_to_field(lat), lng_to_field(lng)))
+        .collect();
+    vertices.resize(MAX_REGION_VERTICES, vertices[polygon.len() - 1]);
+    Some(vertices)
+}
+
+fn flatten(vertices: &[(Fr, Fr)]) -> Vec<Fr> {
+    vertices.iter().flat_map(|&(lat, lng)| [lat, lng]).collect()
+}
+
+/// Circuit for proving location is within a region's polygon.
+#[derive(Clone)]
+pub struct RegionProofCircuit {
+    /// Poseidon configuration
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Shifted, scaled latitude
+    pub latitude: Option<Fr>,
+    /// Private: Shifted, scaled longitude
+    pub longitude: Option<Fr>,
+    /// Private: Random nonce for unlinkability
+    pub nonce: Option<Fr>,
+    /// Private: Padded polygon vertices as shifted `(lat, lng)`
+    pub vertices: Option<Vec<(Fr, Fr)>>,
+
+    /// Public: Region identifier
+    pub region_id: Option<Fr>,
+    /// Public: Hash of the padded polygon
+    pub polygon_hash: Option<Fr>,
+    /// Public: Poseidon(lat, lng, region_id, nonce)
+    pub commitment: Option<Fr>,
+}
+
+impl RegionProofCircuit {
+    /// Create an empty circuit for trusted setup
+    pub fn new_empty() -> Self {
+        // Dummy values for setup: a unit square around (1, 1)
+        let square = [(0u64, 0u64), (0, 2), (2, 2), (2, 0)];
+        let mut vertices: Vec<(Fr, Fr)> =
+            square.iter().map(|&(lat, lng)| (Fr::from(lat), Fr::from(lng))).collect();
+        vertices.resize(MAX_REGION_VERTICES, vertices[square.len() - 1]);
+
+        Self::from_fields(Fr::from(1u64), Fr::from(1u64), Fr::from(2u64), vertices, Fr::from(3u64))
+    }
+
+    /// Create a circuit with actual witness values.
+    ///
+    /// The coordinates are checked against the polygon in-circuit, so a
+    /// location outside the region produces an unsatisfiable circuit.
+    /// Fails if the coordinates are not a valid latitude and longitude,
+    /// the region's polygon has too many vertices, or no entropy is
+    /// available for the nonce.
+    pub fn new_with_witness(latitude: f64, longitude: f64, region: &Region) -> Result<Self, CoordError> {
+        let (lat, lng) = coords_to_fields(latitude, longitude)?;
+        let vertices = padded_vertices(region.polygon)
+            .ok_or_else(|| CoordError::UnsupportedArea(format!("polygon for {} is too large", region.code)))?;
+
+        let nonce = random_nonce()?;
+
+        Ok(Self::from_fields(
+            lat,
+            lng,
+            nonce,
+            vertices,
+            region_code_to_field(region.code),
+        ))
+    }
+
+    fn from_fields(lat: Fr, lng: Fr, nonce: Fr, vertices: Vec<(Fr, Fr)>, region_id: Fr) -> Self {
+        let hasher = PoseidonHasher::new();
+        let polygon_hash = hasher.hash_many(&flatten(&vertices));
+        let commitment = hasher.hash_many(&[lat, lng, region_id, nonce]);
+
+        Self {
+            poseidon_config: hasher.config().clone(),
+            latitude: Some(lat),
+            longitude: Some(lng),
+            nonce: Some(nonce),
+            vertices: Some(vertices),
+            region_id: Some(region_id),
+            polygon_hash: Some(polygon_hash),
+            commitment: Some(commitment),
+        }
+    }
+
+    /// Get the region identifier (public input)
+    pub fn get_region_id(&self) -> Option<Fr> {
+        self.region_id
+    }
+
+    /// Get the polygon hash (public input)
+    pub fn get_polygon_hash(&self) -> Option<Fr> {
+        self.polygon_hash
+    }
+
+    /// Get the commitment (public input)
+    pub fn get_commitment(&self) -> Option<Fr> {
+        self.commitment
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for RegionProofCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate private witnesses
+        let lat_var = FpVar::new_witness(cs.clone(), || {
+            self.latitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let lng_var = FpVar::new_witness(cs.clone(), || {
+            self.longitude.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let nonce_var = FpVar::new_witness(cs.clone(), || {
+            self.nonce.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let vertex_vars: Vec<(FpVar<Fr>, FpVar<Fr>)> = (0..MAX_REGION_VERTICES)
+            .map(|i| {
+                let vertex = || {
+                    self.vertices
+                        .as_ref()
+                        .and_then(|v| v.get(i).copied())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                };
+                let lat = FpVar::new_witness(cs.clone(), || vertex().map(|v| v.0))?;
+                let lng = FpVar::new_witness(cs.clone(), || vertex().map(|v| v.1))?;
+                Ok((lat, lng))
+            })
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+
+        // Allocate public inputs: [region_id, polygon_hash, commitment]
+        let region_id_var = FpVar::new_input(cs.clone(), || {
+            self.region_id.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let polygon_hash_var = FpVar::new_input(cs.clone(), || {
+            self.polygon_hash.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let commitment_var = FpVar::new_input(cs.clone(), || {
+            self.commitment.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Bound every coordinate so the cross products below cannot wrap
+        enforce_bit_length(cs.clone(), &lat_var, COORD_BITS)?;
+        enforce_bit_length(cs.clone(), &lng_var, COORD_BITS)?;
+        for (lat, lng) in &vertex_vars {
+            enforce_bit_length(cs.clone(), lat, COORD_BITS)?;
+            enforce_bit_length(cs.clone(), lng, COORD_BITS)?;
+        }
+
+        // The polygon must be the one the verifier expects
+        let flattened: Vec<FpVar<Fr>> = vertex_vars
+            .iter()
+            .flat_map(|(lat, lng)| [lat.clone(), lng.clone()])
+            .collect();
+        poseidon_hash_many(cs.clone(), &self.poseidon_config, &flattened)?
+            .enforce_equal(&polygon_hash_var)?;
+
+        // Whether each vertex lies strictly north of the point
+        let above = vertex_vars
+            .iter()
+            .map(|(vertex_lat, _)| is_less_than(cs.clone(), &lat_var, vertex_lat, COORD_BITS))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Even-odd rule: count edges crossed by a ray towards +lng
+        let offset = FpVar::Constant(Fr::from(2u64).pow([(CROSS_BITS - 1) as u64]));
+        let mut inside = Boolean::FALSE;
+        for i in 0..MAX_REGION_VERTICES {
+            let j = (i + 1) % MAX_REGION_VERTICES;
+            let (lat_i, lng_i) = &vertex_vars[i];
+            let (lat_j, lng_j) = &vertex_vars[j];
+
+            // The edge straddles the point's latitude
+            let straddles = above[i].xor(&above[j])?;
+
+            // The point is west of the edge when this is negative; the
+            // sign flips for edges running southwards (vertex i above)
+            let cross = (&lng_var - lng_i) * (lat_j - lat_i) - (&lat_var - lat_i) * (lng_j - lng_i);
+            let negated = FpVar::Constant(Fr::from(0u64)) - &cross;
+            let oriented = FpVar::conditionally_select(&above[i], &negated, &cross)?;
+            let west = is_less_than(cs.clone(), &(oriented + &offset), &offset, CROSS_BITS)?;
+
+            inside = inside.xor(&straddles.and(&west)?)?;
+        }
+        inside.enforce_equal(&Boolean::TRUE)?;
+
+        // Bind the location and region into the commitment
+        let computed_commitment = poseidon_hash_many(
+            cs.clone(),
+            &self.poseidon_config,
+            &[lat_var, lng_var, region_id_var, nonce_var],
+        )?;
+        computed_commitment.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for RegionProofCircuit {
+    type SetupParams = ();
+    /// `(region_id, polygon_hash, commitment)`.
+    type PublicInputs = (Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::Region;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["region_id", "polygon_hash", "commitment"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr)> {
+        Some((self.region_id?, self.polygon_hash?, self.commitment?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+
+    #[test]
+    fn test_point_in_california() {
+        let california = Region::by_code("US-CA").unwrap();
+
+        // San Francisco is inside, Las Vegas and Reno are in Nevada
+        let sf = RegionProofCircuit::new_with_witness(37.7749, -122.4194, california).unwrap();
+        assert_eq!(sf.get_region_id(), Some(region_code_to_field("US-CA")));
+        assert_eq!(sf.get_polygon_hash(), region_polygon_hash(california.polygon));
+        assert!(is_satisfied(sf));
+
+        for (lat, lng) in [(36.1699, -115.1398), (39.5296, -119.8138)] {
+            let circuit = RegionProofCircuit::new_with_witness(lat, lng, california).unwrap();
+            assert!(!is_satisfied(circuit));
+        }
+    }
+
+    #[test]
+    fn test_las_vegas_in_nevada() {
+        let nevada = Region::by_code("US-NV").unwrap();
+        let circuit = RegionProofCircuit::new_with_witness(36.1699, -115.1398, nevada).unwrap();
+        assert!(is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_substituted_polygon_fails() {
+        let california = Region::by_code("US-CA").unwrap();
+        let nevada = Region::by_code("US-NV").unwrap();
+
+        // Las Vegas proven against Nevada's polygon but labelled California
+        let mut circuit = RegionProofCircuit::new_with_witness(36.1699, -115.1398, nevada).unwrap();
+        circuit.polygon_hash = region_polygon_hash(california.polygon);
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_out_of_range_coords_rejected() {
+        let california = Region::by_code("US-CA").unwrap();
+        assert!(matches!(
+            RegionProofCircuit::new_with_witness(90.5, -122.4194, california),
+            Err(CoordError::LatitudeOutOfRange(_))
+        ));
+        assert!(matches!(
+            RegionProofCircuit::new_with_witness(37.7749, -480.0, california),
+            Err(CoordError::LongitudeOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_circuit_for_setup() {
+        assert!(is_satisfied(RegionProofCircuit::new_empty()));
+    }
+}
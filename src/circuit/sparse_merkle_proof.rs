@@ -0,0 +1,188 @@
+//! Sparse Merkle proof circuit for key-value lookups.
+//!
+//! Proves that a sparse Merkle tree with a given root maps `key` to
+//! `value` (zero for an absent key). The path directions are the key's
+//! bits, decomposed in-circuit, so a proof cannot be replayed for a
+//! different key.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    select::CondSelectGadget,
+    ToBitsGadget,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+
+use super::poseidon::poseidon_hash_many;
+use super::provable::ProvableCircuit;
+use crate::merkle::hash::PoseidonHasher;
+use crate::merkle::sparse::{SparseMerkleProof, SMT_DEPTH};
+use crate::prover::CircuitTag;
+
+/// Circuit for proving a key-value entry of a `SparseMerkleTree`.
+///
+/// Public inputs (in allocation order):
+/// - `root`: The sparse Merkle root
+/// - `key`: The key being looked up
+/// - `value`: The value stored under the key
+///
+/// Private witnesses:
+/// - `siblings`: Sibling hashes along the key's path
+#[derive(Clone)]
+pub struct SparseMerkleCircuit {
+    /// Poseidon configuration for hashing.
+    pub poseidon_config: PoseidonConfig<Fr>,
+
+    /// Private: Sibling hashes from the leaf level up.
+    pub siblings: Vec<Option<Fr>>,
+
+    /// Public: The sparse Merkle root.
+    pub root: Option<Fr>,
+    /// Public: The key.
+    pub key: Option<Fr>,
+    /// Public: The value under the key.
+    pub value: Option<Fr>,
+}
+
+impl SparseMerkleCircuit {
+    /// Create a circuit with dummy values for trusted setup.
+    pub fn new_empty() -> Self {
+        let tree = crate::merkle::sparse::SparseMerkleTree::new();
+        Self::new_with_witness(&tree.prove(&Fr::from(0u64)), tree.root())
+    }
+
+    /// Create a circuit from a proof produced by `SparseMerkleTree::prove`.
+    pub fn new_with_witness(proof: &SparseMerkleProof, root: Fr) -> Self {
+        let hasher = PoseidonHasher::new();
+        Self {
+            poseidon_config: hasher.config().clone(),
+            siblings: proof.siblings.iter().map(|s| Some(*s)).collect(),
+            root: Some(root),
+            key: Some(proof.key),
+            value: Some(proof.value),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for SparseMerkleCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        // Allocate public inputs: [root, key, value]
+        let root_var = FpVar::new_input(cs.clone(), || {
+            self.root.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let key_var = FpVar::new_input(cs.clone(), || {
+            self.key.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let value_var = FpVar::new_input(cs.clone(), || {
+            self.value.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        // Allocate siblings as private witnesses
+        let sibling_vars: Vec<FpVar<Fr>> = self
+            .siblings
+            .iter()
+            .map(|sibling| {
+                FpVar::new_witness(cs.clone(), || {
+                    sibling.ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if sibling_vars.len() != SMT_DEPTH {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        // Path directions are the key's canonical bits, padded to the depth
+        let mut index_vars = key_var.to_bits_le()?;
+        index_vars.resize(SMT_DEPTH, Boolean::FALSE);
+
+        let mut current = value_var;
+
+        for (sibling, is_right) in sibling_vars.iter().zip(index_vars.iter()) {
+            let left = FpVar::conditionally_select(is_right, sibling, &current)?;
+            let right = FpVar::conditionally_select(is_right, &current, sibling)?;
+
+            current = poseidon_hash_many(cs.clone(), &self.poseidon_config, [&left, &right])?;
+        }
+
+        current.enforce_equal(&root_var)?;
+
+        Ok(())
+    }
+}
+
+impl ProvableCircuit for SparseMerkleCircuit {
+    type SetupParams = ();
+    /// `(root, key, value)`.
+    type PublicInputs = (Fr, Fr, Fr);
+
+    const TAG: CircuitTag = CircuitTag::SparseMerkle;
+
+    fn public_input_schema() -> Vec<&'static str> {
+        vec!["root", "key", "value"]
+    }
+
+    fn blank(_: &()) -> Self {
+        Self::new_empty()
+    }
+
+    fn public_inputs(&self) -> Option<(Fr, Fr, Fr)> {
+        Some((self.root?, self.key?, self.value?))
+    }
+
+    fn input_fields(inputs: &(Fr, Fr, Fr)) -> Vec<Fr> {
+        vec![inputs.0, inputs.1, inputs.2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::is_satisfied;
+    use crate::merkle::sparse::SparseMerkleTree;
+
+    fn sample_tree() -> SparseMerkleTree {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(Fr::from(11u64), Fr::from(1u64));
+        tree.insert(Fr::from(12u64), Fr::from(2u64));
+        tree.insert(-Fr::from(3u64), Fr::from(3u64));
+        tree
+    }
+
+    #[test]
+    fn test_inclusion_and_default_proofs() {
+        let tree = sample_tree();
+
+        for key in [Fr::from(12u64), -Fr::from(3u64), Fr::from(13u64)] {
+            let proof = tree.prove(&key);
+            assert!(is_satisfied(SparseMerkleCircuit::new_with_witness(&proof, tree.root())));
+        }
+    }
+
+    #[test]
+    fn test_wrong_value_or_key_fails() {
+        let tree = sample_tree();
+        let proof = tree.prove(&Fr::from(11u64));
+
+        let mut circuit = SparseMerkleCircuit::new_with_witness(&proof, tree.root());
+        circuit.value = Some(Fr::from(9u64));
+        assert!(!is_satisfied(circuit));
+
+        // Same siblings cannot be reused for a different key
+        let mut circuit = SparseMerkleCircuit::new_with_witness(&proof, tree.root());
+        circuit.key = Some(Fr::from(12u64));
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn test_empty_circuit_for_setup() {
+        assert!(is_satisfied(SparseMerkleCircuit::new_empty()));
+    }
+}
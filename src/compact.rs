@@ -0,0 +1,276 @@
+//! Compact binary encoding of `VerifiedProof` for QR codes and links.
+//!
+//! Layout (all integers are LEB128 varints):
+//!
+//! ```text
+//! version | type tag | circuit_version | label len | label | generated_at | expires_at + 1 | data len | proof_data
+//! ```
+//!
+//! Version 1 of the layout had no `circuit_version`; such proofs still
+//! decode, as circuit version 1.
+//!
+//! The type tag is 0 for `EmailDomain` and 1 for `Country`; the label is
+//! the domain or country code. `expires_at + 1` is 0 when there is no
+//! expiry. `proof_data` is carried verbatim. The bytes are then encoded
+//! as unpadded URL-safe base64.
+//!
+//! An email proof (128 compressed bytes plus three public inputs)
+//! encodes to under 350 characters, well inside a single QR code.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::{ProofType, VerifiedProof};
+
+/// Current layout version.
+const COMPACT_VERSION: u64 = 2;
+
+/// Layout version before `circuit_version` was added.
+const COMPACT_VERSION_V1: u64 = 1;
+
+const TAG_EMAIL_DOMAIN: u64 = 0;
+const TAG_COUNTRY: u64 = 1;
+
+/// Errors decoding a compact proof.
+#[derive(Debug, thiserror::Error)]
+pub enum CompactError {
+    #[error("Invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Unsupported compact proof version {0}")]
+    UnsupportedVersion(u64),
+
+    #[error("Unknown proof type tag {0}")]
+    UnknownProofType(u64),
+
+    #[error("Compact proof is truncated")]
+    Truncated,
+
+    #[error("Label is not valid UTF-8")]
+    InvalidLabel,
+
+    #[error("Circuit version does not fit in 16 bits")]
+    InvalidCircuitVersion,
+
+    #[error("{0} trailing bytes after compact proof")]
+    TrailingBytes(usize),
+}
+
+impl VerifiedProof {
+    /// Encode as compact URL-safe base64, suitable for a QR payload.
+    pub fn to_compact_base64(&self) -> String {
+        let (tag, label) = match &self.proof_type {
+            ProofType::EmailDomain { domain } => (TAG_EMAIL_DOMAIN, domain),
+            ProofType::Country { country_code } => (TAG_COUNTRY, country_code),
+        };
+
+        let mut bytes = Vec::with_capacity(self.proof_data.len() + label.len() + 24);
+        write_varint(&mut bytes, COMPACT_VERSION);
+        write_varint(&mut bytes, tag);
+        write_varint(&mut bytes, u64::from(self.circuit_version));
+        write_bytes(&mut bytes, label.as_bytes());
+        write_varint(&mut bytes, self.generated_at);
+        write_varint(&mut bytes, self.expires_at.map_or(0, |t| t.saturating_add(1)));
+        write_bytes(&mut bytes, &self.proof_data);
+
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Decode a proof produced by `to_compact_base64`.
+    pub fn from_compact_base64(encoded: &str) -> Result<Self, CompactError> {
+        let bytes = URL_SAFE_NO_PAD.decode(encoded.trim())?;
+        let mut reader = Reader { bytes: &bytes };
+
+        let version = reader.varint()?;
+        if version != COMPACT_VERSION && version != COMPACT_VERSION_V1 {
+            return Err(CompactError::UnsupportedVersion(version));
+        }
+
+        let tag = reader.varint()?;
+        let circuit_version = if version == COMPACT_VERSION_V1 {
+            1
+        } else {
+            u16::try_from(reader.varint()?).map_err(|_| CompactError::InvalidCircuitVersion)?
+        };
+        let label = String::from_utf8(reader.bytes()?.to_vec())
+            .map_err(|_| CompactError::InvalidLabel)?;
+        let proof_type = match tag {
+            TAG_EMAIL_DOMAIN => ProofType::EmailDomain { domain: label },
+            TAG_COUNTRY => ProofType::Country { country_code: label },
+            other => return Err(CompactError::UnknownProofType(other)),
+        };
+
+        let generated_at = reader.varint()?;
+        let expires_at = reader.varint()?.checked_sub(1);
+        let proof_data = reader.bytes()?.to_vec();
+
+        if !reader.bytes.is_empty() {
+            return Err(CompactError::TrailingBytes(reader.bytes.len()));
+        }
+
+        Ok(Self {
+            proof_type,
+            proof_data,
+            generated_at,
+            expires_at,
+            circuit_version,
+        })
+    }
+}
+
+/// Append `value` as an unsigned LEB128 varint.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append a varint length followed by the bytes.
+fn write_bytes(out: &mut Vec<u8>, data: &[u8]) {
+    write_varint(out, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+/// Cursor over the decoded bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn varint(&mut self) -> Result<u64, CompactError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first().ok_or(CompactError::Truncated)?;
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CompactError::Truncated)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], CompactError> {
+        let len = usize::try_from(self.varint()?).map_err(|_| CompactError::Truncated)?;
+        if len > self.bytes.len() {
+            return Err(CompactError::Truncated);
+        }
+        let (data, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{EmailDomainCircuit, EmailProofInput, ProvableCircuit};
+    use crate::prover::GenericProver;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    fn email_proof() -> VerifiedProof {
+        let mut rng = StdRng::seed_from_u64(800);
+        let prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let circuit = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        let (proof, inputs) = prover.prove(circuit, &mut rng).unwrap();
+
+        let mut proof_data = Vec::new();
+        proof.serialize_compressed(&mut proof_data).unwrap();
+        for input in EmailDomainCircuit::input_fields(&inputs) {
+            input.serialize_compressed(&mut proof_data).unwrap();
+        }
+
+        VerifiedProof {
+            proof_type: ProofType::EmailDomain {
+                domain: "google.com".to_string(),
+            },
+            proof_data,
+            generated_at: 1_760_000_000,
+            expires_at: Some(1_760_086_400),
+            circuit_version: crate::CIRCUIT_VERSION,
+        }
+    }
+
+    #[test]
+    fn test_email_proof_round_trip() {
+        let proof = email_proof();
+        let encoded = proof.to_compact_base64();
+
+        // Fits comfortably in a single QR code
+        assert!(encoded.len() < 350, "encoded length {}", encoded.len());
+        assert!(encoded
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let decoded = VerifiedProof::from_compact_base64(&encoded).unwrap();
+        assert!(matches!(
+            decoded.proof_type,
+            ProofType::EmailDomain { ref domain } if domain == "google.com"
+        ));
+        assert_eq!(decoded.proof_data, proof.proof_data);
+        assert_eq!(decoded.generated_at, proof.generated_at);
+        assert_eq!(decoded.expires_at, proof.expires_at);
+    }
+
+    #[test]
+    fn test_country_proof_without_expiry() {
+        let proof = VerifiedProof {
+            proof_type: ProofType::Country {
+                country_code: "US".to_string(),
+            },
+            proof_data: vec![7; 160],
+            generated_at: 0,
+            expires_at: None,
+            circuit_version: 7,
+        };
+
+        let decoded = VerifiedProof::from_compact_base64(&proof.to_compact_base64()).unwrap();
+        assert!(matches!(
+            decoded.proof_type,
+            ProofType::Country { ref country_code } if country_code == "US"
+        ));
+        assert_eq!(decoded.expires_at, None);
+        assert_eq!(decoded.circuit_version, 7);
+    }
+
+    #[test]
+    fn test_v1_layout_decodes_as_circuit_version_1() {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, COMPACT_VERSION_V1);
+        write_varint(&mut bytes, TAG_COUNTRY);
+        write_bytes(&mut bytes, b"US");
+        write_varint(&mut bytes, 100);
+        write_varint(&mut bytes, 0);
+        write_bytes(&mut bytes, &[7; 4]);
+
+        let decoded = VerifiedProof::from_compact_base64(&URL_SAFE_NO_PAD.encode(bytes)).unwrap();
+        assert_eq!(decoded.circuit_version, 1);
+        assert_eq!(decoded.generated_at, 100);
+        assert_eq!(decoded.proof_data, vec![7; 4]);
+    }
+
+    #[test]
+    fn test_malformed_compact_proof() {
+        let encoded = email_proof().to_compact_base64();
+
+        assert!(matches!(
+            VerifiedProof::from_compact_base64(&encoded[..encoded.len() - 8]),
+            Err(CompactError::Truncated) | Err(CompactError::Base64(_))
+        ));
+        assert!(matches!(
+            VerifiedProof::from_compact_base64("not base64!"),
+            Err(CompactError::Base64(_))
+        ));
+        assert!(matches!(
+            VerifiedProof::from_compact_base64(&URL_SAFE_NO_PAD.encode([3u8, 0, 0])),
+            Err(CompactError::UnsupportedVersion(3))
+        ));
+    }
+}
@@ -11,23 +11,102 @@
 //! 2. ZK circuit verifies credential locally
 //! 3. Proof generated - reveals only what you choose
 //! 4. Share proof anonymously
+//!
+//! `ZkVault` runs all of these steps for email and location proofs.
+//!
+//! # Feature flags
+//!
+//! - `std` (default): everything but the Merkle core. Without it the
+//!   crate is `#![no_std]` + `alloc` and only `merkle` is built: hashing,
+//!   tree building and path verification, with `BTreeMap` indexes and no
+//!   tree (de)serialization or file I/O
+//! - `wasm` (default): wasm-bindgen bindings for the browser extension;
+//!   includes `verify_only`
+//! - `verify_only`: just the `wasm_verify` bindings, which import
+//!   verifying keys and check country and email proofs; built without
+//!   `wasm`, setup and proving stay out of the bundle
+//! - `tokio`: `Prover::prove_async`, which runs proving on tokio's
+//!   blocking thread pool so it doesn't stall an async runtime
+//! - `batch_verify`: `BatchBuilder` and `Verifier::verify_batch`, which
+//!   check many membership proofs with one pairing equation
+//! - `aggregation`: `ProofAggregator` and `Verifier::verify_aggregate`,
+//!   which fold many membership proofs into one SnarkPack proof that
+//!   verifies with O(log N) pairings
+//! - `parallel`: `Prover::prove_many_parallel`, which proves many leaves
+//!   across cores with rayon
+//! - `http`: `Verifier::from_url`, which downloads a saved verifying key
+//!   and can check it against a pinned fingerprint
+//!
+//! # Circuit versions
+//!
+//! Every `VerifiedProof` records the `CIRCUIT_VERSION` it was made
+//! with. The version is bumped whenever a change to a circuit, its
+//! Poseidon parameters or its public input order would stop existing
+//! proofs from verifying. Verifiers accept
+//! `MIN_CIRCUIT_VERSION..=CIRCUIT_VERSION` and reject anything else with
+//! `VerifierError::VersionMismatch` rather than a bare failed check; an
+//! older version is only dropped from that range in a breaking release.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use ark_std::{string::String, vec::Vec};
 
-pub mod circuit;
 pub mod merkle;
+
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod circuit;
+#[cfg(feature = "std")]
+pub mod compact;
+#[cfg(feature = "std")]
 pub mod proofs;
+#[cfg(feature = "std")]
 pub mod prover;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod vault;
+#[cfg(feature = "std")]
 pub mod verifier;
 
+#[cfg(feature = "aggregation")]
+pub mod aggregation;
+#[cfg(feature = "batch_verify")]
+pub mod batch;
+
 // WASM bindings (only compiled when wasm feature is enabled)
 #[cfg(feature = "wasm")]
 pub mod wasm;
+#[cfg(feature = "verify_only")]
+pub mod wasm_verify;
 
 // Re-export main types
+#[cfg(feature = "std")]
+pub use bundle::ProofBundle;
+#[cfg(feature = "std")]
 pub use prover::Prover;
+#[cfg(feature = "std")]
+pub use vault::ZkVault;
+#[cfg(feature = "std")]
 pub use verifier::Verifier;
 
+/// Circuit version of proofs made by this build; see the crate docs.
+///
+/// Version 2 added the `identity_commitment` public input to the email
+/// and country circuits.
+pub const CIRCUIT_VERSION: u16 = 2;
+
+/// Oldest circuit version this build still verifies.
+///
+/// Version 1 email and country proofs lack `identity_commitment`, so
+/// they cannot verify against version 2 keys.
+pub const MIN_CIRCUIT_VERSION: u16 = 2;
+
 /// Supported proof types
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum ProofType {
     /// Proves ownership of email at a specific domain
     EmailDomain { domain: String },
@@ -36,7 +115,7 @@ pub enum ProofType {
 }
 
 /// A verified proof that can be shared
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct VerifiedProof {
     /// Type of proof
     pub proof_type: ProofType,
@@ -46,4 +125,21 @@ pub struct VerifiedProof {
     pub generated_at: u64,
     /// Optional expiry
     pub expires_at: Option<u64>,
+    /// `CIRCUIT_VERSION` of the build that made the proof; proofs
+    /// serialized before this field existed are version 1
+    #[serde(default = "legacy_circuit_version")]
+    pub circuit_version: u16,
+}
+
+fn legacy_circuit_version() -> u16 {
+    1
+}
+
+impl VerifiedProof {
+    /// Whether the proof has expired at `now` (Unix seconds).
+    ///
+    /// A proof without `expires_at` never expires.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
 }
@@ -6,7 +6,11 @@ use ark_crypto_primitives::sponge::{
     CryptographicSponge,
 };
 use ark_ff::{Field, PrimeField};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use ark_std::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use super::tree::MAX_DEPTH;
 
 /// Poseidon hasher configured for BN254 scalar field.
 #[derive(Clone)]
@@ -21,6 +25,46 @@ impl PoseidonHasher {
         Self { config }
     }
 
+    /// Create a hasher from a pinned configuration.
+    ///
+    /// Check the result's `config_digest` against the digest recorded
+    /// when the configuration was pinned.
+    pub fn from_config(config: PoseidonConfig<Fr>) -> Self {
+        Self { config }
+    }
+
+    /// SHA-256 of the round counts, alpha, rate, capacity, round
+    /// constants and MDS matrix.
+    ///
+    /// Any change to the parameters changes every commitment and every
+    /// circuit's keys; compare this against a recorded digest to catch
+    /// that before deploying.
+    pub fn config_digest(&self) -> [u8; 32] {
+        let config = &self.config;
+        let mut hasher = Sha256::new();
+        for n in [
+            config.full_rounds as u64,
+            config.partial_rounds as u64,
+            config.alpha,
+            config.rate as u64,
+            config.capacity as u64,
+        ] {
+            hasher.update(n.to_le_bytes());
+        }
+        for matrix in [&config.ark, &config.mds] {
+            hasher.update((matrix.len() as u64).to_le_bytes());
+            for row in matrix {
+                hasher.update((row.len() as u64).to_le_bytes());
+                for entry in row {
+                    let mut bytes = Vec::new();
+                    entry.serialize_compressed(&mut bytes).unwrap();
+                    hasher.update(&bytes);
+                }
+            }
+        }
+        hasher.finalize().into()
+    }
+
     /// Generate default Poseidon configuration for BN254.
     fn default_config() -> PoseidonConfig<Fr> {
         let full_rounds = 8;
@@ -75,6 +119,22 @@ impl PoseidonHasher {
         (ark, mds)
     }
 
+    /// Hash a single field element.
+    pub fn hash_one(&self, x: &Fr) -> Fr {
+        let mut sponge = PoseidonSponge::new(&self.config);
+        sponge.absorb(x);
+        sponge.squeeze_field_elements(1)[0]
+    }
+
+    /// Derive a leaf from a raw preimage (e.g. a password hash).
+    ///
+    /// The bytes are reduced into a field element and then passed through
+    /// a one-input Poseidon, so leaves use the same hash family as the
+    /// tree and can be re-derived in-circuit from the preimage.
+    pub fn leaf_commitment(&self, preimage: &[u8]) -> Fr {
+        self.hash_one(&bytes_to_field(preimage))
+    }
+
     /// Hash two field elements into one.
     pub fn hash_two(&self, left: &Fr, right: &Fr) -> Fr {
         let mut sponge = PoseidonSponge::new(&self.config);
@@ -104,13 +164,244 @@ impl Default for PoseidonHasher {
     }
 }
 
-/// Convert bytes to a field element.
+/// Native hash used for tree nodes.
+///
+/// Implemented by `PoseidonHasher` (the default, and what the proving
+/// circuits use) and `PedersenHasher`. See `MerkleTree::with_hash_scheme`.
+pub trait HashScheme: Clone {
+    /// Identifier recorded in `MerkleTree::to_bytes`, so a tree is only
+    /// loaded with the scheme it was hashed with.
+    const ID: u8;
+
+    /// Hash two field elements into one (a tree node from its children).
+    fn hash_two(&self, left: &Fr, right: &Fr) -> Fr;
+
+    /// Hash any number of field elements into one.
+    fn hash_many(&self, elements: &[Fr]) -> Fr;
+}
+
+impl HashScheme for PoseidonHasher {
+    const ID: u8 = 1;
+
+    fn hash_two(&self, left: &Fr, right: &Fr) -> Fr {
+        PoseidonHasher::hash_two(self, left, right)
+    }
+
+    fn hash_many(&self, elements: &[Fr]) -> Fr {
+        PoseidonHasher::hash_many(self, elements)
+    }
+}
+
+/// Roots of subtrees whose leaves are all the same value, by height.
+///
+/// `zero_hash(0)` is the leaf and `zero_hash(h) = hash_two(zero_hash(h - 1),
+/// zero_hash(h - 1))`. Padding and sparse trees use these instead of
+/// rehashing empty subtrees.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZeroHashes {
+    hashes: Vec<Fr>,
+}
+
+impl ZeroHashes {
+    /// Zero-subtree hashes up to `MAX_DEPTH`.
+    pub fn new<H: HashScheme>(hasher: &H) -> Self {
+        Self::from_leaf(hasher, Fr::from(0u64), MAX_DEPTH)
+    }
+
+    /// Hashes of subtrees of `leaf`, up to height `depth`.
+    pub fn from_leaf<H: HashScheme>(hasher: &H, leaf: Fr, depth: usize) -> Self {
+        let mut hashes = Vec::with_capacity(depth + 1);
+        hashes.push(leaf);
+        for level in 0..depth {
+            hashes.push(hasher.hash_two(&hashes[level], &hashes[level]));
+        }
+        Self { hashes }
+    }
+
+    /// Root of an empty subtree of height `level`.
+    ///
+    /// Panics if `level` is beyond the precomputed depth.
+    pub fn zero_hash(&self, level: usize) -> Fr {
+        self.hashes[level]
+    }
+
+    /// Greatest height precomputed.
+    pub fn depth(&self) -> usize {
+        self.hashes.len() - 1
+    }
+
+    /// All hashes, indexed by height.
+    pub fn as_slice(&self) -> &[Fr] {
+        &self.hashes
+    }
+}
+
+/// Reduce arbitrary big-endian bytes to a field element.
+///
+/// This is a lossy reduction (`from_be_bytes_mod_order`), meant for
+/// turning outside data such as a preimage or digest into a field element.
+/// It does not invert `field_to_canonical_bytes`: tree nodes and public
+/// inputs are stored little-endian, so decode those with
+/// `field_from_canonical_bytes`.
 pub fn bytes_to_field(bytes: &[u8]) -> Fr {
     Fr::from_be_bytes_mod_order(bytes)
 }
 
-/// Convert a hex string to a field element.
+/// Hash an arbitrary byte payload (e.g. a certificate fingerprint) into a
+/// leaf.
+///
+/// The byte length is absorbed first, then the payload in 31-byte
+/// big-endian chunks, each of which fits in a field element unreduced.
+/// Unlike `bytes_to_field`, which reduces long inputs mod the field
+/// order, distinct payloads of any length give distinct inputs to
+/// Poseidon, and the prefix keeps `a` from colliding with `a || 0`.
+pub fn bytes_to_leaf(data: &[u8]) -> Fr {
+    let mut elements = Vec::with_capacity(1 + data.len().div_ceil(31));
+    elements.push(Fr::from(data.len() as u64));
+    elements.extend(data.chunks(31).map(Fr::from_be_bytes_mod_order));
+    PoseidonHasher::new().hash_many(&elements)
+}
+
+/// Decode hex and reduce it to a field element, as `bytes_to_field`.
 pub fn hex_to_field(hex_str: &str) -> Result<Fr, hex::FromHexError> {
     let bytes = hex::decode(hex_str)?;
     Ok(Fr::from_be_bytes_mod_order(&bytes))
 }
+
+/// Serialize a field element as ark-serialize does: 32 bytes, compressed,
+/// little-endian.
+///
+/// This is the encoding of tree nodes, `MerklePath`s and proof public
+/// inputs. Read it back with `field_from_canonical_bytes`.
+pub fn field_to_canonical_bytes(f: &Fr) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32);
+    f.serialize_compressed(&mut bytes).expect("serializing into a Vec cannot fail");
+    bytes
+}
+
+/// Inverse of `field_to_canonical_bytes`.
+///
+/// Strict: fails on the wrong length or on a value not below the field
+/// modulus, rather than reducing it.
+pub fn field_from_canonical_bytes(bytes: &[u8]) -> Result<Fr, SerializationError> {
+    let mut reader = bytes;
+    let f = Fr::deserialize_compressed(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::BigInteger;
+
+    #[test]
+    fn test_hash_one_deterministic() {
+        let hasher = PoseidonHasher::new();
+        let x = Fr::from(42u64);
+
+        assert_eq!(hasher.hash_one(&x), hasher.hash_one(&x));
+        assert_eq!(hasher.hash_one(&x), PoseidonHasher::new().hash_one(&x));
+        assert_ne!(hasher.hash_one(&x), hasher.hash_one(&Fr::from(43u64)));
+    }
+
+    #[test]
+    fn test_config_digest_is_pinned() {
+        let hasher = PoseidonHasher::new();
+        let digest = hasher.config_digest();
+
+        // Recorded from the current parameters; update only on purpose
+        assert_eq!(
+            hex::encode(digest),
+            "c6203fe830ce3e39b6f607e5696fb44b217f7e65e6f40ec63c6f2c62afeb35f1"
+        );
+
+        let pinned = PoseidonHasher::from_config(hasher.config().clone());
+        assert_eq!(pinned.config_digest(), digest);
+        assert_eq!(pinned.hash_one(&Fr::from(7u64)), hasher.hash_one(&Fr::from(7u64)));
+
+        let mut changed = hasher.config().clone();
+        changed.partial_rounds += 1;
+        assert_ne!(PoseidonHasher::from_config(changed).config_digest(), digest);
+    }
+
+    #[test]
+    fn test_canonical_bytes_round_trip_vs_mod_order_reduction() {
+        let node = PoseidonHasher::new().hash_one(&Fr::from(5u64));
+
+        // Canonical encoding is lossless
+        let canonical = field_to_canonical_bytes(&node);
+        assert_eq!(canonical.len(), 32);
+        assert_eq!(field_from_canonical_bytes(&canonical).unwrap(), node);
+        assert!(field_from_canonical_bytes(&canonical[..31]).is_err());
+        assert!(field_from_canonical_bytes(&[canonical.clone(), vec![0]].concat()).is_err());
+        assert!(field_from_canonical_bytes(&[0xff; 32]).is_err());
+
+        // The mod-order helpers read big-endian, so they misread a node
+        assert_ne!(bytes_to_field(&canonical), node);
+        assert_ne!(hex_to_field(&hex::encode(&canonical)).unwrap(), node);
+
+        // ...and reduce out-of-range input instead of rejecting it
+        let mut modulus = Fr::MODULUS.to_bytes_be();
+        assert_eq!(bytes_to_field(&modulus), Fr::from(0u64));
+        modulus[31] += 1;
+        assert_eq!(bytes_to_field(&modulus), Fr::from(1u64));
+        modulus.reverse();
+        assert!(field_from_canonical_bytes(&modulus).is_err());
+    }
+
+    #[test]
+    fn test_zero_hashes() {
+        use crate::merkle::MerkleTree;
+
+        let hasher = PoseidonHasher::new();
+        let zeros = ZeroHashes::new(&hasher);
+        assert_eq!(zeros.depth(), MAX_DEPTH);
+        assert_eq!(zeros.zero_hash(0), Fr::from(0u64));
+        assert_eq!(zeros.zero_hash(1), hasher.hash_two(&zeros.zero_hash(0), &zeros.zero_hash(0)));
+
+        // A tree of only empty leaves has the zero root for its depth; a
+        // tree with no leaves has `empty_root` instead
+        assert_ne!(MerkleTree::new(Vec::new()).unwrap().root(), zeros.zero_hash(0));
+        for depth in 1..5 {
+            let empty = MerkleTree::new(vec![Fr::from(0u64); 1 << depth]).unwrap();
+            assert_eq!(empty.root(), zeros.zero_hash(depth));
+        }
+    }
+
+    #[test]
+    fn test_leaf_commitment() {
+        let hasher = PoseidonHasher::new();
+
+        let leaf = hasher.leaf_commitment(b"password123");
+        assert_eq!(leaf, hasher.leaf_commitment(b"password123"));
+        assert_eq!(leaf, hasher.hash_one(&bytes_to_field(b"password123")));
+
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..100u32 {
+            let preimage = format!("password-{}", i);
+            let leaf = hasher.leaf_commitment(preimage.as_bytes());
+            assert!(seen.insert(leaf), "collision for {}", preimage);
+        }
+    }
+
+    #[test]
+    fn test_bytes_to_leaf_is_length_prefixed() {
+        // A 40-byte payload reduces mod the field order to a 32-byte one
+        let long: Vec<u8> = (1..=40).collect();
+        let reduced = bytes_to_field(&long);
+        let mut short = [0u8; 32];
+        short.copy_from_slice(&field_to_canonical_bytes(&reduced));
+        short.reverse();
+        assert_eq!(bytes_to_field(&short), reduced);
+        assert_ne!(bytes_to_leaf(&long), bytes_to_leaf(&short));
+
+        // Trailing and leading zeros change the leaf
+        assert_ne!(bytes_to_leaf(&short), bytes_to_leaf(&[&short[..], &[0]].concat()));
+        assert_ne!(bytes_to_leaf(&short), bytes_to_leaf(&[&[0], &short[..]].concat()));
+        assert_ne!(bytes_to_leaf(b""), bytes_to_leaf(&[0]));
+        assert_eq!(bytes_to_leaf(&long), bytes_to_leaf(&long));
+    }
+}
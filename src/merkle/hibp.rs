@@ -0,0 +1,65 @@
+//! Leaves derived from Have I Been Pwned (HIBP) password hashes.
+//!
+//! HIBP publishes breached passwords as uppercase hex SHA-1 digests. A
+//! leaf is `PoseidonHasher::leaf_commitment` of the 20 raw digest bytes,
+//! so a tree built from the published hash list and a leaf computed from
+//! a plaintext password line up.
+
+use ark_bn254::Fr;
+use ark_std::string::String;
+use sha1::{Digest, Sha1};
+
+use super::hash::PoseidonHasher;
+
+/// Length of a SHA-1 digest in bytes.
+const SHA1_LEN: usize = 20;
+
+/// Uppercase hex SHA-1 of a password, as published by HIBP.
+pub fn password_sha1_hex(password: &str) -> String {
+    hex::encode_upper(Sha1::digest(password.as_bytes()))
+}
+
+/// Leaf for a plaintext password.
+pub fn password_to_leaf(password: &str) -> Fr {
+    PoseidonHasher::new().leaf_commitment(&Sha1::digest(password.as_bytes()))
+}
+
+/// Leaf for a hex SHA-1 digest from the HIBP dataset.
+///
+/// Either case is accepted. Anything but 40 hex digits is rejected, which
+/// catches lines still carrying the `:count` suffix.
+pub fn leaf_from_sha1_hex(sha1_hex: &str) -> Result<Fr, hex::FromHexError> {
+    let digest = hex::decode(sha1_hex.trim())?;
+    if digest.len() != SHA1_LEN {
+        return Err(hex::FromHexError::InvalidStringLength);
+    }
+    Ok(PoseidonHasher::new().leaf_commitment(&digest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_hibp_hashes() {
+        assert_eq!(password_sha1_hex("password"), "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8");
+        assert_eq!(password_sha1_hex("123456"), "7C4A8D09CA3762AF61E59520943DC26494F8941B");
+
+        assert_eq!(
+            password_to_leaf("password"),
+            leaf_from_sha1_hex("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8").unwrap()
+        );
+        assert_eq!(
+            password_to_leaf("123456"),
+            leaf_from_sha1_hex("7c4a8d09ca3762af61e59520943dc26494f8941b").unwrap()
+        );
+        assert_ne!(password_to_leaf("password"), password_to_leaf("123456"));
+    }
+
+    #[test]
+    fn test_rejects_malformed_hex() {
+        assert!(leaf_from_sha1_hex("5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:3861493").is_err());
+        assert!(leaf_from_sha1_hex("5BAA61E4").is_err());
+        assert!(leaf_from_sha1_hex("not hex").is_err());
+    }
+}
@@ -1,6 +1,24 @@
+//! Poseidon Merkle trees and hashing.
+//!
+//! This is the crate's `no_std` surface. Without the `std` feature the
+//! leaf and node indexes fall back from `HashMap` to `BTreeMap`, and the
+//! bincode `to_bytes` / `from_bytes` and file I/O are left out;
+//! `MerklePath::verify` and the compressed path format need only `alloc`.
+
 pub mod hash;
+pub mod hibp;
+pub mod pedersen;
+pub mod sparse;
 pub mod tree;
 
-pub use hash::PoseidonHasher;
-pub use tree::{MerkleTree, TreeError};
+pub use hash::{HashScheme, PoseidonHasher, ZeroHashes};
+pub use pedersen::PedersenHasher;
+pub use sparse::{SparseMerkleProof, SparseMerkleTree};
+pub use tree::{empty_root, BuildStats, MerkleTree, RootTransition, TreeError, TreeStats};
+
 
+/// Map used for tree indexes: `HashMap` with `std`, `BTreeMap` without.
+#[cfg(feature = "std")]
+pub(crate) type IndexMap<K, V> = std::collections::HashMap<K, V>;
+#[cfg(not(feature = "std"))]
+pub(crate) type IndexMap<K, V> = ark_std::collections::BTreeMap<K, V>;
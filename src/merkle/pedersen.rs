@@ -0,0 +1,82 @@
+//! Pedersen hashing over Baby Jubjub, for trees checked by Pedersen-based
+//! verifiers.
+//!
+//! A node is the Pedersen hash of its children's canonical (32-byte,
+//! little-endian) encodings on the Edwards curve whose base field is
+//! BN254's scalar field; the node value is the x-coordinate of the
+//! resulting point, so it is again an `Fr`. Generators are drawn from
+//! ChaCha20 under a fixed seed, so every `PedersenHasher` and
+//! `PedersenMerkleCircuit` agrees on them, on every platform and `rand`
+//! version.
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::crh::{pedersen, TwoToOneCRHScheme};
+use ark_ed_on_bn254::EdwardsProjective;
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use sha2::{Digest, Sha256};
+
+use super::hash::{field_to_canonical_bytes, HashScheme};
+
+/// Seed the generators are derived from. Changing it changes every root.
+const GENERATOR_SEED: &[u8] = b"zk-vault pedersen generators v2";
+
+/// Window shape: 4 x 128 bits covers two 256-bit children.
+#[derive(Clone)]
+pub struct PedersenWindow;
+
+impl pedersen::Window for PedersenWindow {
+    const WINDOW_SIZE: usize = 128;
+    const NUM_WINDOWS: usize = 4;
+}
+
+/// Generators of the node hash.
+pub type PedersenParameters = pedersen::Parameters<EdwardsProjective>;
+
+type TwoToOne = pedersen::TwoToOneCRH<EdwardsProjective, PedersenWindow>;
+
+/// Pedersen node hash over Baby Jubjub.
+#[derive(Clone)]
+pub struct PedersenHasher {
+    parameters: PedersenParameters,
+}
+
+impl PedersenHasher {
+    /// Create a hasher with the fixed-seed generators.
+    pub fn new() -> Self {
+        let seed: [u8; 32] = Sha256::digest(GENERATOR_SEED).into();
+        let parameters = TwoToOne::setup(&mut ChaCha20Rng::from_seed(seed))
+            .expect("Pedersen setup does not fail");
+        Self { parameters }
+    }
+
+    /// Generators, for allocating the in-circuit hash.
+    pub fn parameters(&self) -> &PedersenParameters {
+        &self.parameters
+    }
+}
+
+impl Default for PedersenHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HashScheme for PedersenHasher {
+    const ID: u8 = 2;
+
+    fn hash_two(&self, left: &Fr, right: &Fr) -> Fr {
+        let left = field_to_canonical_bytes(left);
+        let right = field_to_canonical_bytes(right);
+        TwoToOne::evaluate(&self.parameters, left.as_slice(), right.as_slice())
+            .expect("two 32-byte children fit the window")
+            .x
+    }
+
+    /// Chains `hash_two` from the element count, so inputs of different
+    /// lengths do not collide.
+    fn hash_many(&self, elements: &[Fr]) -> Fr {
+        elements
+            .iter()
+            .fold(Fr::from(elements.len() as u64), |acc, elem| self.hash_two(&acc, elem))
+    }
+}
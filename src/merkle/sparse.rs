@@ -0,0 +1,273 @@
+//! Sparse Merkle tree keyed by field elements.
+//!
+//! The tree has a fixed depth of 256, so every `Fr` key has its own leaf
+//! (bit `i` of the key, little-endian, picks the direction at level `i`).
+//! Only non-default nodes are stored; empty subtrees are represented by
+//! precomputed zero-hashes, so updates and proofs are O(depth).
+//!
+//! A leaf holds the key's value directly and absent keys have value zero,
+//! so storing zero is the same as removing the key.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, BigInteger256, PrimeField, Zero};
+use ark_std::vec::Vec;
+
+use super::hash::{PoseidonHasher, ZeroHashes};
+use super::IndexMap;
+
+/// Depth of every sparse Merkle tree.
+pub const SMT_DEPTH: usize = 256;
+
+/// Proof of the value stored under a key, or that the key is empty.
+#[derive(Clone, Debug)]
+pub struct SparseMerkleProof {
+    /// The key being proven.
+    pub key: Fr,
+    /// The value under the key (zero if the key is empty).
+    pub value: Fr,
+    /// Sibling hashes from the leaf level up to the root.
+    pub siblings: Vec<Fr>,
+}
+
+impl SparseMerkleProof {
+    /// Whether this proves a stored value rather than an empty key.
+    pub fn is_inclusion(&self) -> bool {
+        !self.value.is_zero()
+    }
+
+    /// Path directions derived from the key: true = right child.
+    pub fn indices(&self) -> Vec<bool> {
+        let key = self.key.into_bigint();
+        (0..SMT_DEPTH).map(|i| key.get_bit(i)).collect()
+    }
+
+    /// Compute the root from this proof.
+    pub fn compute_root(&self, hasher: &PoseidonHasher) -> Fr {
+        let mut current = self.value;
+
+        for (sibling, is_right) in self.siblings.iter().zip(self.indices()) {
+            current = if is_right {
+                hasher.hash_two(sibling, &current)
+            } else {
+                hasher.hash_two(&current, sibling)
+            };
+        }
+
+        current
+    }
+
+    /// Verify this proof against a given root.
+    pub fn verify(&self, root: &Fr, hasher: &PoseidonHasher) -> bool {
+        self.siblings.len() == SMT_DEPTH && &self.compute_root(hasher) == root
+    }
+}
+
+/// Sparse Merkle tree mapping `Fr` keys to `Fr` values.
+#[derive(Clone)]
+pub struct SparseMerkleTree {
+    /// Non-default nodes, keyed by (level, key >> level).
+    nodes: IndexMap<(usize, BigInteger256), Fr>,
+    /// Stored key-value pairs.
+    values: IndexMap<Fr, Fr>,
+    /// Roots of empty subtrees, by height.
+    zero_hashes: ZeroHashes,
+    /// Hasher instance.
+    hasher: PoseidonHasher,
+}
+
+impl SparseMerkleTree {
+    /// Create an empty tree.
+    pub fn new() -> Self {
+        Self::with_hasher(PoseidonHasher::new())
+    }
+
+    /// Create an empty tree with a custom hasher.
+    pub fn with_hasher(hasher: PoseidonHasher) -> Self {
+        Self {
+            nodes: IndexMap::new(),
+            values: IndexMap::new(),
+            zero_hashes: ZeroHashes::from_leaf(&hasher, Fr::zero(), SMT_DEPTH),
+            hasher,
+        }
+    }
+
+    /// Get the root hash.
+    pub fn root(&self) -> Fr {
+        self.node(SMT_DEPTH, &BigInteger256::zero())
+    }
+
+    /// Get the hasher.
+    pub fn hasher(&self) -> &PoseidonHasher {
+        &self.hasher
+    }
+
+    /// Number of keys with a non-zero value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether no key has a non-zero value.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get the value stored under `key`, if any.
+    pub fn get(&self, key: &Fr) -> Option<Fr> {
+        self.values.get(key).copied()
+    }
+
+    /// Set the value under `key` and return the new root.
+    ///
+    /// Inserting zero removes the key.
+    pub fn insert(&mut self, key: Fr, value: Fr) -> Fr {
+        if value.is_zero() {
+            self.values.remove(&key);
+        } else {
+            self.values.insert(key, value);
+        }
+
+        let mut index = key.into_bigint();
+        let mut current = value;
+        self.set_node(0, index, current);
+
+        for level in 0..SMT_DEPTH {
+            let sibling = self.node(level, &sibling_index(&index));
+            current = if index.is_odd() {
+                self.hasher.hash_two(&sibling, &current)
+            } else {
+                self.hasher.hash_two(&current, &sibling)
+            };
+            index.divn(1);
+            self.set_node(level + 1, index, current);
+        }
+
+        current
+    }
+
+    /// Prove the value under `key`, or that it is empty.
+    pub fn prove(&self, key: &Fr) -> SparseMerkleProof {
+        let mut index = key.into_bigint();
+        let mut siblings = Vec::with_capacity(SMT_DEPTH);
+
+        for level in 0..SMT_DEPTH {
+            siblings.push(self.node(level, &sibling_index(&index)));
+            index.divn(1);
+        }
+
+        SparseMerkleProof {
+            key: *key,
+            value: self.get(key).unwrap_or_else(Fr::zero),
+            siblings,
+        }
+    }
+
+    fn node(&self, level: usize, index: &BigInteger256) -> Fr {
+        self.nodes
+            .get(&(level, *index))
+            .copied()
+            .unwrap_or(self.zero_hashes.zero_hash(level))
+    }
+
+    fn set_node(&mut self, level: usize, index: BigInteger256, hash: Fr) {
+        if hash == self.zero_hashes.zero_hash(level) {
+            self.nodes.remove(&(level, index));
+        } else {
+            self.nodes.insert((level, index), hash);
+        }
+    }
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Index of the sibling of the node at `index` on the same level.
+fn sibling_index(index: &BigInteger256) -> BigInteger256 {
+    let mut sibling = *index;
+    sibling.0[0] ^= 1;
+    sibling
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_tree_default_proof() {
+        let tree = SparseMerkleTree::new();
+        let proof = tree.prove(&Fr::from(7u64));
+
+        assert!(!proof.is_inclusion());
+        assert!(proof.verify(&tree.root(), tree.hasher()));
+    }
+
+    #[test]
+    fn test_insert_and_prove() {
+        let mut tree = SparseMerkleTree::new();
+        let entries: Vec<(Fr, Fr)> = vec![
+            (Fr::from(1u64), Fr::from(100u64)),
+            (Fr::from(2u64), Fr::from(200u64)),
+            (Fr::from(3u64), Fr::from(300u64)),
+            (-Fr::from(1u64), Fr::from(400u64)),
+            (Fr::from(u64::MAX), Fr::from(500u64)),
+        ];
+        for (key, value) in &entries {
+            tree.insert(*key, *value);
+        }
+
+        assert_eq!(tree.len(), entries.len());
+        for (key, value) in &entries {
+            assert_eq!(tree.get(key), Some(*value));
+            let proof = tree.prove(key);
+            assert!(proof.is_inclusion());
+            assert_eq!(proof.value, *value);
+            assert!(proof.verify(&tree.root(), tree.hasher()));
+        }
+
+        // Absent key gets a default-value proof
+        let absent = tree.prove(&Fr::from(4u64));
+        assert!(!absent.is_inclusion());
+        assert!(absent.verify(&tree.root(), tree.hasher()));
+
+        // Forged value fails
+        let mut forged = tree.prove(&Fr::from(2u64));
+        forged.value = Fr::from(201u64);
+        assert!(!forged.verify(&tree.root(), tree.hasher()));
+    }
+
+    #[test]
+    fn test_update_and_remove() {
+        let mut tree = SparseMerkleTree::new();
+        let empty_root = tree.root();
+
+        let key = Fr::from(42u64);
+        let first = tree.insert(key, Fr::from(1u64));
+        let second = tree.insert(key, Fr::from(2u64));
+        assert_ne!(first, second);
+        assert_eq!(tree.root(), second);
+
+        // Removing the only key restores the empty root and storage
+        tree.insert(key, Fr::zero());
+        assert_eq!(tree.root(), empty_root);
+        assert!(tree.is_empty());
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_insertion_order_independent() {
+        let keys = [Fr::from(5u64), Fr::from(9u64), Fr::from(12u64)];
+
+        let mut forward = SparseMerkleTree::new();
+        for (i, key) in keys.iter().enumerate() {
+            forward.insert(*key, Fr::from(i as u64 + 1));
+        }
+        let mut backward = SparseMerkleTree::new();
+        for (i, key) in keys.iter().enumerate().rev() {
+            backward.insert(*key, Fr::from(i as u64 + 1));
+        }
+
+        assert_eq!(forward.root(), backward.root());
+    }
+}
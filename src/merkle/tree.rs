@@ -1,20 +1,28 @@
 //! Merkle tree implementation for password hash membership proofs.
 //!
-//! Uses Poseidon hash for ZK-friendly internal node computation.
+//! Uses Poseidon hash for ZK-friendly internal node computation by
+//! default; `MerkleTree::with_hash_scheme` builds trees over another
+//! `HashScheme`.
 
 use ark_bn254::Fr;
+use ark_ff::PrimeField;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_std::vec::Vec;
+use ark_std::{format, string::String, vec, vec::Vec};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
-use super::hash::PoseidonHasher;
+use super::hash::{HashScheme, PoseidonHasher, ZeroHashes};
+use super::IndexMap;
 
 /// Maximum tree depth (2^30 > 1 billion leaves, enough for HIBP dataset).
 pub const MAX_DEPTH: usize = 30;
 
 /// A Merkle path (proof) for membership verification.
-#[derive(Clone, Debug)]
+///
+/// Serializes (with serde, or `to_bytes`) as 32-byte compressed field
+/// elements, so a server holding the tree can send paths to a client
+/// that proves with `Prover::prove_with_path`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "MerklePathData", into = "MerklePathData")]
 pub struct MerklePath {
     /// Sibling hashes along the path from leaf to root.
     pub siblings: Vec<Fr>,
@@ -26,13 +34,13 @@ pub struct MerklePath {
 
 impl MerklePath {
     /// Verify this path against a given root.
-    pub fn verify(&self, root: &Fr, hasher: &PoseidonHasher) -> bool {
+    pub fn verify<H: HashScheme>(&self, root: &Fr, hasher: &H) -> bool {
         let computed_root = self.compute_root(hasher);
         &computed_root == root
     }
 
     /// Compute the root from this path.
-    pub fn compute_root(&self, hasher: &PoseidonHasher) -> Fr {
+    pub fn compute_root<H: HashScheme>(&self, hasher: &H) -> Fr {
         let mut current = self.leaf;
 
         for (sibling, is_right) in self.siblings.iter().zip(self.indices.iter()) {
@@ -52,11 +60,175 @@ impl MerklePath {
     pub fn depth(&self) -> usize {
         self.siblings.len()
     }
+
+    /// Serialize the path for transmission.
+    #[cfg(feature = "std")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    /// Deserialize a path produced by `to_bytes`.
+    ///
+    /// Fails on non-canonical field elements, on mismatched sibling and
+    /// index counts, and on paths deeper than `MAX_DEPTH`.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TreeError> {
+        bincode::deserialize(bytes).map_err(|e| TreeError::DeserializationError(e.to_string()))
+    }
+
+    /// Serialize the path, omitting siblings that are all-zero subtrees.
+    ///
+    /// Layout: a bitmap of omitted siblings, a bitmap of path indices
+    /// (both `ceil(depth / 8)` bytes, bit `i` for level `i`), the leaf,
+    /// then the remaining siblings, each 32 bytes. The depth is not
+    /// encoded; pass it to `from_compressed`. Only zero padding is
+    /// recognised, so paths in trees built `with_padding` a non-zero value
+    /// still round-trip but do not shrink.
+    pub fn to_compressed<H: HashScheme>(&self, hasher: &H) -> Vec<u8> {
+        let depth = self.depth();
+        let zeros = ZeroHashes::from_leaf(hasher, Fr::from(0u64), depth);
+        let bitmap_len = depth.div_ceil(8);
+
+        let mut omitted = vec![0u8; bitmap_len];
+        let mut indices = vec![0u8; bitmap_len];
+        let mut siblings = Vec::with_capacity(depth * 32);
+        for (level, (sibling, is_right)) in self.siblings.iter().zip(&self.indices).enumerate() {
+            if *is_right {
+                indices[level / 8] |= 1 << (level % 8);
+            }
+            if *sibling == zeros.zero_hash(level) {
+                omitted[level / 8] |= 1 << (level % 8);
+            } else {
+                siblings.extend_from_slice(&leaf_key(sibling));
+            }
+        }
+
+        let mut bytes = omitted;
+        bytes.extend_from_slice(&indices);
+        bytes.extend_from_slice(&leaf_key(&self.leaf));
+        bytes.extend_from_slice(&siblings);
+        bytes
+    }
+
+    /// Deserialize a path produced by `to_compressed` for a tree of `depth`.
+    ///
+    /// Omitted siblings are restored as zero-subtree hashes. Fails if the
+    /// length does not match the bitmaps or a field element is invalid.
+    pub fn from_compressed<H: HashScheme>(bytes: &[u8], depth: usize, hasher: &H) -> Result<Self, TreeError> {
+        if depth > MAX_DEPTH {
+            return Err(TreeError::DeserializationError(format!(
+                "path depth {} exceeds maximum {}",
+                depth, MAX_DEPTH
+            )));
+        }
+        let bitmap_len = depth.div_ceil(8);
+        let header_len = 2 * bitmap_len + 32;
+        if bytes.len() < header_len {
+            return Err(TreeError::DeserializationError(format!(
+                "compressed path is {} bytes, expected at least {}",
+                bytes.len(),
+                header_len
+            )));
+        }
+
+        let (omitted, rest) = bytes.split_at(bitmap_len);
+        let (indices, rest) = rest.split_at(bitmap_len);
+        let (leaf, rest) = rest.split_at(32);
+        let bit = |bitmap: &[u8], level: usize| bitmap[level / 8] & (1 << (level % 8)) != 0;
+
+        let present = (0..depth).filter(|&level| !bit(omitted, level)).count();
+        if rest.len() != present * 32 {
+            return Err(TreeError::DeserializationError(format!(
+                "expected {} sibling bytes, got {}",
+                present * 32,
+                rest.len()
+            )));
+        }
+
+        let decode = |bytes: &[u8]| {
+            Fr::deserialize_compressed(bytes)
+                .map_err(|e| TreeError::DeserializationError(format!("invalid field element: {}", e)))
+        };
+        let zeros = ZeroHashes::from_leaf(hasher, Fr::from(0u64), depth);
+        let mut chunks = rest.chunks_exact(32);
+        let siblings = (0..depth)
+            .map(|level| {
+                if bit(omitted, level) {
+                    Ok(zeros.zero_hash(level))
+                } else {
+                    decode(chunks.next().unwrap())
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            siblings,
+            indices: (0..depth).map(|level| bit(indices, level)).collect(),
+            leaf: decode(leaf)?,
+        })
+    }
+}
+
+/// Root hashes of all-zero subtrees, indexed by height (`[0]` is a zero leaf).
+///
+/// Prefer keeping a `ZeroHashes` around over calling this repeatedly.
+pub fn zero_subtree_hashes(depth: usize, hasher: &PoseidonHasher) -> Vec<Fr> {
+    ZeroHashes::from_leaf(hasher, Fr::from(0u64), depth).as_slice().to_vec()
+}
+
+/// Wire form of `MerklePath`.
+#[derive(Serialize, Deserialize)]
+struct MerklePathData {
+    siblings: Vec<[u8; 32]>,
+    indices: Vec<bool>,
+    leaf: [u8; 32],
+}
+
+impl From<MerklePath> for MerklePathData {
+    fn from(path: MerklePath) -> Self {
+        Self {
+            siblings: path.siblings.iter().map(leaf_key).collect(),
+            indices: path.indices,
+            leaf: leaf_key(&path.leaf),
+        }
+    }
+}
+
+impl TryFrom<MerklePathData> for MerklePath {
+    type Error = TreeError;
+
+    fn try_from(data: MerklePathData) -> Result<Self, TreeError> {
+        if data.siblings.len() != data.indices.len() {
+            return Err(TreeError::DeserializationError(format!(
+                "{} siblings but {} path indices",
+                data.siblings.len(),
+                data.indices.len()
+            )));
+        }
+        if data.siblings.len() > MAX_DEPTH {
+            return Err(TreeError::DeserializationError(format!(
+                "path depth {} exceeds maximum {}",
+                data.siblings.len(),
+                MAX_DEPTH
+            )));
+        }
+
+        let decode = |bytes: &[u8; 32]| {
+            Fr::deserialize_compressed(&bytes[..])
+                .map_err(|e| TreeError::DeserializationError(format!("invalid field element: {}", e)))
+        };
+        Ok(Self {
+            siblings: data.siblings.iter().map(decode).collect::<Result<_, _>>()?,
+            indices: data.indices,
+            leaf: decode(&data.leaf)?,
+        })
+    }
 }
 
-/// Binary Merkle tree with Poseidon hash.
+/// Binary Merkle tree, hashed with Poseidon unless built with another
+/// `HashScheme`.
 #[derive(Clone)]
-pub struct MerkleTree {
+pub struct MerkleTree<H: HashScheme = PoseidonHasher> {
     /// All nodes stored in a flat array (level-order).
     /// Level 0 = root, Level depth = leaves.
     nodes: Vec<Fr>,
@@ -65,9 +237,59 @@ pub struct MerkleTree {
     /// Number of leaves.
     num_leaves: usize,
     /// Hasher instance.
-    hasher: PoseidonHasher,
+    hasher: H,
+    /// Value of the padding leaves (and tombstones); zero by default.
+    padding: Fr,
     /// Map from leaf value to index (for fast lookups).
-    leaf_index: HashMap<[u8; 32], usize>,
+    ///
+    /// A value that appears more than once maps to its last index; build
+    /// with `new_dedup` to avoid ambiguous lookups.
+    leaf_index: IndexMap<[u8; 32], usize>,
+    /// Recent roots, oldest first, ending with the current root.
+    root_history: Vec<Fr>,
+    /// Maximum number of roots kept in `root_history` (0 = disabled).
+    root_history_capacity: usize,
+    /// Deepest the tree may grow; `MAX_DEPTH` unless overridden.
+    max_depth: usize,
+    /// Append-only log of the pushes made with `push_logged`.
+    transitions: Vec<RootTransition>,
+}
+
+/// Statistics from building a tree with `MerkleTree::with_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BuildStats {
+    /// Number of leaves supplied.
+    pub input_leaves: usize,
+    /// Number of leaves kept in the tree.
+    pub unique_leaves: usize,
+    /// Number of duplicate leaves dropped.
+    pub duplicates_dropped: usize,
+}
+
+/// Shape of a tree, from `MerkleTree::stats`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TreeStats {
+    /// Number of real (non-padding) leaves.
+    pub num_leaves: usize,
+    /// Tree depth.
+    pub depth: usize,
+    /// Leaf slots including padding, `2^depth`.
+    pub padded_size: usize,
+    /// Stored nodes, leaves included.
+    pub num_nodes: usize,
+    /// `num_leaves / padded_size`.
+    pub fill_ratio: f64,
+}
+
+/// Root change from appending one leaf, logged by `MerkleTree::push_logged`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RootTransition {
+    /// Root before the leaf was added.
+    pub old_root: Fr,
+    /// Root after the leaf was added.
+    pub new_root: Fr,
+    /// Index the leaf was placed at.
+    pub leaf_index: usize,
 }
 
 /// Serializable tree data (without hasher and index).
@@ -76,30 +298,277 @@ pub struct MerkleTreeData {
     nodes: Vec<[u8; 32]>,
     depth: usize,
     num_leaves: usize,
+    padding: [u8; 32],
+    /// `HashScheme::ID` of the hash the nodes were computed with.
+    scheme: u8,
 }
 
 impl MerkleTree {
     /// Build a new Merkle tree from a list of leaves.
     ///
-    /// The tree is padded to the next power of 2 with zero leaves.
-    pub fn new(leaves: Vec<Fr>) -> Self {
+    /// The tree is padded to the next power of 2 with zero leaves. Zero
+    /// can also be a real leaf, though `Prover` refuses to prove it since
+    /// it cannot be told apart from padding; use `with_padding` to pad
+    /// with a sentinel and keep zero out of the tree. Fails if the
+    /// leaves need a tree deeper than `MAX_DEPTH`.
+    pub fn new(leaves: Vec<Fr>) -> Result<Self, TreeError> {
         let hasher = PoseidonHasher::new();
         Self::with_hasher(leaves, hasher)
     }
 
-    /// Build a tree with a specific hasher instance.
-    pub fn with_hasher(leaves: Vec<Fr>, hasher: PoseidonHasher) -> Self {
+    /// Like `new`, but with `max` in place of `MAX_DEPTH`.
+    ///
+    /// The limit also applies to later `extend_leaves` calls.
+    pub fn with_max_depth(leaves: Vec<Fr>, max: usize) -> Result<Self, TreeError> {
+        Self::build(leaves, Fr::from(0u64), PoseidonHasher::new(), max)
+    }
+
+    /// Build a tree from `leaves` with duplicates removed.
+    ///
+    /// The first occurrence of each value is kept, so every leaf has a
+    /// single, unambiguous index.
+    pub fn new_dedup(leaves: Vec<Fr>) -> Result<Self, TreeError> {
+        Ok(Self::with_stats(leaves)?.0)
+    }
+
+    /// Like `new_dedup`, also reporting how many duplicates were dropped.
+    pub fn with_stats(leaves: Vec<Fr>) -> Result<(Self, BuildStats), TreeError> {
+        let input_leaves = leaves.len();
+        let mut seen = IndexMap::new();
+        let unique: Vec<Fr> = leaves
+            .into_iter()
+            .filter(|leaf| seen.insert(leaf_key(leaf), ()).is_none())
+            .collect();
+
+        let stats = BuildStats {
+            input_leaves,
+            unique_leaves: unique.len(),
+            duplicates_dropped: input_leaves - unique.len(),
+        };
+        Ok((Self::new(unique)?, stats))
+    }
+
+    /// Build a tree with a specific hasher instance, padded with zeros.
+    ///
+    /// Duplicate leaves are kept as-is; lookups by value find the last
+    /// copy (see `new_dedup`).
+    pub fn with_hasher(leaves: Vec<Fr>, hasher: PoseidonHasher) -> Result<Self, TreeError> {
+        Self::build(leaves, Fr::from(0u64), hasher, MAX_DEPTH)
+    }
+
+    /// Build a tree padded to a power of 2 with `pad` instead of zero.
+    ///
+    /// Padding slots are never indexed, so `find_leaf` only returns the
+    /// index of a real leaf. Fails with `ReservedLeaf` if a leaf is zero,
+    /// which the membership circuit never accepts, or equals `pad`, which
+    /// `Prover` refuses as padding; neither could be proven. Later
+    /// updates to a tree with non-zero padding are held to the same rule.
+    pub fn with_padding(leaves: Vec<Fr>, pad: Fr, hasher: PoseidonHasher) -> Result<Self, TreeError> {
+        check_reserved(&leaves, pad, 0)?;
+        Self::build(leaves, pad, hasher, MAX_DEPTH)
+    }
+
+    /// Deserialize a Poseidon tree from bytes.
+    ///
+    /// Trees built with another `HashScheme` are loaded with
+    /// `from_bytes_with_hash_scheme`.
+    #[cfg(feature = "std")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TreeError> {
+        Self::from_bytes_with_hash_scheme(bytes, PoseidonHasher::new())
+    }
+
+    /// Load tree from a file.
+    #[cfg(feature = "std")]
+    pub fn load_from_file(path: &std::path::Path) -> Result<Self, TreeError> {
+        let bytes = std::fs::read(path).map_err(TreeError::IoError)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Read one leaf's path from a `save_to_file` file without loading
+    /// the tree.
+    ///
+    /// Seeks straight to the leaf and its `depth` siblings, so memory use
+    /// does not grow with the tree. The file is bincode `MerkleTreeData`:
+    /// a u64 node count, the level-order nodes as 32-byte elements, then
+    /// the u64 depth and leaf count. Fails with `IndexOutOfRange` for a
+    /// padding slot, as `get_path` returns `None`.
+    #[cfg(feature = "std")]
+    pub fn open_for_path(path: &std::path::Path, leaf_index: usize) -> Result<MerklePath, TreeError> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        const NODES_OFFSET: u64 = 8;
+        // Keeps node offsets well inside u64
+        const MAX_DEPTH_NODES: u64 = (1 << 58) - 1;
+        let mut file = std::fs::File::open(path)?;
+        let read_u64 = |file: &mut std::fs::File, offset: u64| -> Result<u64, TreeError> {
+            let mut buf = [0u8; 8];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        };
+
+        let num_nodes = read_u64(&mut file, 0)?;
+        if num_nodes > MAX_DEPTH_NODES || !(num_nodes + 1).is_power_of_two() {
+            return Err(TreeError::DeserializationError(format!(
+                "{} nodes do not form a complete tree",
+                num_nodes
+            )));
+        }
+        let depth = read_u64(&mut file, NODES_OFFSET + 32 * num_nodes)?;
+        let num_leaves = read_u64(&mut file, NODES_OFFSET + 32 * num_nodes + 8)?;
+        if num_nodes != (2u64 << depth.min(62)) - 1 {
+            return Err(TreeError::DeserializationError(format!(
+                "{} nodes do not form a tree of depth {}",
+                num_nodes, depth
+            )));
+        }
+        if num_leaves > 1 << depth {
+            return Err(TreeError::DeserializationError(format!(
+                "{} leaves do not fit in a tree of depth {}",
+                num_leaves, depth
+            )));
+        }
+        if leaf_index as u64 >= num_leaves {
+            return Err(TreeError::IndexOutOfRange {
+                index: leaf_index,
+                num_leaves: num_leaves as usize,
+            });
+        }
+
+        let mut read_node = |node_index: usize| -> Result<Fr, TreeError> {
+            let mut buf = [0u8; 32];
+            file.seek(SeekFrom::Start(NODES_OFFSET + 32 * node_index as u64))?;
+            file.read_exact(&mut buf)?;
+            Fr::deserialize_compressed(&buf[..]).map_err(|e| {
+                TreeError::DeserializationError(format!("invalid node {}: {}", node_index, e))
+            })
+        };
+
+        let mut node_index = (1usize << depth) - 1 + leaf_index;
+        let leaf = read_node(node_index)?;
+        let mut siblings = Vec::with_capacity(depth as usize);
+        let mut indices = Vec::with_capacity(depth as usize);
+        while node_index > 0 {
+            let is_right = node_index.is_multiple_of(2);
+            siblings.push(read_node(if is_right { node_index - 1 } else { node_index + 1 })?);
+            indices.push(is_right);
+            node_index = (node_index - 1) / 2;
+        }
+
+        Ok(MerklePath {
+            siblings,
+            indices,
+            leaf,
+        })
+    }
+}
+
+impl<H: HashScheme> MerkleTree<H> {
+    /// Build a zero-padded tree whose nodes are hashed with `scheme`.
+    ///
+    /// Only Poseidon trees can be proven with `Prover`; a
+    /// `PedersenHasher` tree pairs with `PedersenMerkleCircuit`.
+    pub fn with_hash_scheme(leaves: Vec<Fr>, scheme: H) -> Result<Self, TreeError> {
+        Self::build(leaves, Fr::from(0u64), scheme, MAX_DEPTH)
+    }
+
+    /// Deserialize a tree hashed with `scheme` from bytes.
+    ///
+    /// The blob may come from an untrusted cache, so every node must be a
+    /// canonical field element, the node count must match the depth and
+    /// the recorded hash scheme must be `scheme`'s.
+    #[cfg(feature = "std")]
+    pub fn from_bytes_with_hash_scheme(bytes: &[u8], scheme: H) -> Result<Self, TreeError> {
+        let data: MerkleTreeData = bincode::deserialize(bytes)
+            .map_err(|e| TreeError::DeserializationError(e.to_string()))?;
+        if data.scheme != H::ID {
+            return Err(TreeError::DeserializationError(format!(
+                "tree was hashed with scheme {}, expected {}",
+                data.scheme,
+                H::ID
+            )));
+        }
+
+        if data.depth >= usize::BITS as usize - 1 {
+            return Err(TreeError::DeserializationError(format!(
+                "depth {} is too large",
+                data.depth
+            )));
+        }
+        let expected_nodes = 2 * (1usize << data.depth) - 1;
+        if data.nodes.len() != expected_nodes {
+            return Err(TreeError::DeserializationError(format!(
+                "expected {} nodes for depth {}, found {}",
+                expected_nodes,
+                data.depth,
+                data.nodes.len()
+            )));
+        }
+        if data.num_leaves > 1 << data.depth {
+            return Err(TreeError::DeserializationError(format!(
+                "{} leaves do not fit in a tree of depth {}",
+                data.num_leaves, data.depth
+            )));
+        }
+
+        let nodes: Vec<Fr> = data
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                Fr::deserialize_compressed(&b[..]).map_err(|e| {
+                    TreeError::DeserializationError(format!("invalid node {}: {}", i, e))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let padding = Fr::deserialize_compressed(&data.padding[..])
+            .map_err(|e| TreeError::DeserializationError(format!("invalid padding: {}", e)))?;
+
+        // Rebuild leaf index
+        let padded_size = 1 << data.depth;
+        let leaf_start = padded_size - 1;
+        let mut leaf_index = IndexMap::new();
+
+        for i in 0..data.num_leaves {
+            let mut bytes = [0u8; 32];
+            nodes[leaf_start + i]
+                .serialize_compressed(&mut bytes[..])
+                .ok();
+            leaf_index.insert(bytes, i);
+        }
+
+        Ok(Self {
+            nodes,
+            depth: data.depth,
+            num_leaves: data.num_leaves,
+            hasher: scheme,
+            padding,
+            leaf_index,
+            root_history: Vec::new(),
+            root_history_capacity: 0,
+            transitions: Vec::new(),
+            max_depth: MAX_DEPTH.max(data.depth),
+        })
+    }
+
+    fn build(leaves: Vec<Fr>, pad: Fr, hasher: H, max_depth: usize) -> Result<Self, TreeError> {
         if leaves.is_empty() {
-            return Self::empty_tree(hasher);
+            return Ok(Self::empty_tree(pad, hasher, max_depth));
         }
 
         let num_leaves = leaves.len();
         let depth = Self::compute_depth(num_leaves);
+        if depth > max_depth {
+            return Err(TreeError::DepthExceeded {
+                requested: depth,
+                max: max_depth,
+            });
+        }
         let padded_size = 1 << depth;
 
         // Pad leaves to power of 2
         let mut padded_leaves = leaves;
-        padded_leaves.resize(padded_size, Fr::from(0u64));
+        padded_leaves.resize(padded_size, pad);
 
         // Build tree bottom-up
         let total_nodes = 2 * padded_size - 1;
@@ -111,15 +580,23 @@ impl MerkleTree {
             nodes[leaf_start + i] = *leaf;
         }
 
-        // Build internal nodes (bottom-up)
-        for i in (0..leaf_start).rev() {
-            let left_child = 2 * i + 1;
-            let right_child = 2 * i + 2;
-            nodes[i] = hasher.hash_two(&nodes[left_child], &nodes[right_child]);
+        // Build internal nodes (bottom-up); subtrees holding only padding
+        // take their precomputed hash
+        let pad_hashes = ZeroHashes::from_leaf(&hasher, pad, depth);
+        for height in 1..=depth {
+            let level_start = (1 << (depth - height)) - 1;
+            for j in 0..1 << (depth - height) {
+                let i = level_start + j;
+                nodes[i] = if j << height >= num_leaves {
+                    pad_hashes.zero_hash(height)
+                } else {
+                    hasher.hash_two(&nodes[2 * i + 1], &nodes[2 * i + 2])
+                };
+            }
         }
 
-        // Build leaf index
-        let mut leaf_index = HashMap::new();
+        // Build leaf index (a later duplicate overwrites an earlier one)
+        let mut leaf_index = IndexMap::new();
         for (i, leaf) in padded_leaves.iter().enumerate() {
             if i < num_leaves {
                 let mut bytes = [0u8; 32];
@@ -128,23 +605,256 @@ impl MerkleTree {
             }
         }
 
-        Self {
+        Ok(Self {
             nodes,
             depth,
             num_leaves,
             hasher,
+            padding: pad,
             leaf_index,
-        }
+            root_history: Vec::new(),
+            root_history_capacity: 0,
+            transitions: Vec::new(),
+            max_depth,
+        })
     }
 
     /// Create an empty tree.
-    fn empty_tree(hasher: PoseidonHasher) -> Self {
+    fn empty_tree(pad: Fr, hasher: H, max_depth: usize) -> Self {
         Self {
-            nodes: vec![Fr::from(0u64)],
+            nodes: vec![pad],
             depth: 0,
             num_leaves: 0,
             hasher,
-            leaf_index: HashMap::new(),
+            padding: pad,
+            leaf_index: IndexMap::new(),
+            root_history: Vec::new(),
+            root_history_capacity: 0,
+            transitions: Vec::new(),
+            max_depth,
+        }
+    }
+
+    /// Retain up to `capacity` recent roots (including the current one).
+    ///
+    /// Lets verifiers accept proofs issued against a root that was
+    /// replaced by a recent update, giving clients a grace period.
+    pub fn with_root_history(mut self, capacity: usize) -> Self {
+        self.root_history_capacity = capacity;
+        self.root_history.clear();
+        self.record_root();
+        self
+    }
+
+    /// Recent roots, oldest first, ending with the current root.
+    ///
+    /// Empty unless root history was enabled with `with_root_history`.
+    pub fn recent_roots(&self) -> &[Fr] {
+        &self.root_history
+    }
+
+    /// Append new leaves and rebuild the tree.
+    ///
+    /// Returns the new root. The previous root stays in `recent_roots`
+    /// until it falls out of the history window. Fails, leaving the tree
+    /// unchanged, if the leaves would no longer fit in the maximum depth.
+    pub fn extend_leaves(&mut self, new_leaves: Vec<Fr>) -> Result<Fr, TreeError> {
+        if self.padding != Fr::from(0u64) {
+            check_reserved(&new_leaves, self.padding, self.num_leaves)?;
+        }
+        let mut leaves: Vec<Fr> = self.leaves().collect();
+        leaves.extend(new_leaves);
+
+        let rebuilt = Self::build(leaves, self.padding, self.hasher.clone(), self.max_depth)?;
+        self.nodes = rebuilt.nodes;
+        self.depth = rebuilt.depth;
+        self.num_leaves = rebuilt.num_leaves;
+        self.leaf_index = rebuilt.leaf_index;
+
+        self.record_root();
+        Ok(self.root())
+    }
+
+    /// Append one leaf and log the root transition.
+    ///
+    /// While the padded tree has a free slot only the path above it is
+    /// rehashed; otherwise the tree grows a level as in `extend_leaves`.
+    /// Each transition's `old_root` is the previous one's `new_root`, so
+    /// `transitions` is a verifiable history of the tree. Fails, leaving
+    /// the tree and log unchanged, if the tree cannot grow any deeper.
+    pub fn push_logged(&mut self, leaf: Fr) -> Result<RootTransition, TreeError> {
+        let old_root = self.root();
+        let leaf_index = self.num_leaves;
+        if self.padding != Fr::from(0u64) {
+            check_reserved(&[leaf], self.padding, leaf_index)?;
+        }
+
+        if leaf_index > 0 && leaf_index < 1 << self.depth {
+            self.num_leaves += 1;
+            self.leaf_index.insert(leaf_key(&leaf), leaf_index);
+            self.set_leaf(leaf_index, leaf);
+            self.record_root();
+        } else {
+            self.extend_leaves(vec![leaf])?;
+        }
+
+        let transition = RootTransition {
+            old_root,
+            new_root: self.root(),
+            leaf_index,
+        };
+        self.transitions.push(transition);
+        Ok(transition)
+    }
+
+    /// Root transitions logged by `push_logged`, oldest first.
+    pub fn transitions(&self) -> &[RootTransition] {
+        &self.transitions
+    }
+
+    /// Build one tree holding `left`'s leaves followed by `right`'s.
+    ///
+    /// Duplicates are dropped, keeping the first occurrence, so every
+    /// leaf of the merged tree has a single index. The merged tree takes
+    /// `left`'s hasher, padding and depth limit, and is fully rebuilt;
+    /// see `merge_as_subtrees` for a merge that rehashes nothing.
+    pub fn merge(left: &Self, right: &Self) -> Result<Self, TreeError> {
+        let mut seen = IndexMap::new();
+        let leaves: Vec<Fr> = left
+            .leaves()
+            .chain(right.leaves())
+            .filter(|leaf| seen.insert(leaf_key(leaf), ()).is_none())
+            .collect();
+        Self::build(leaves, left.padding, left.hasher.clone(), left.max_depth)
+    }
+
+    /// Join two non-empty trees of equal depth under a new root.
+    ///
+    /// The new root is `hash_two(left.root(), right.root())`, so no
+    /// existing node is rehashed and a path from either tree stays valid
+    /// with the other root appended. `right`'s leaves start at index
+    /// `2^depth`, so `left` must be full: padding slots between the two
+    /// would otherwise fall inside the leaf range. Both trees must use the
+    /// same padding value and share no leaf; use `merge` to combine
+    /// overlapping or partly filled trees.
+    pub fn merge_as_subtrees(left: &Self, right: &Self) -> Result<Self, TreeError> {
+        if left.num_leaves == 0 || right.num_leaves == 0 {
+            return Err(TreeError::EmptyTree);
+        }
+        if left.depth != right.depth {
+            return Err(TreeError::MergeMismatch(format!(
+                "depths {} and {} differ",
+                left.depth, right.depth
+            )));
+        }
+        if left.padding != right.padding {
+            return Err(TreeError::MergeMismatch("padding values differ".into()));
+        }
+        if left.num_leaves != 1 << left.depth {
+            return Err(TreeError::MergeMismatch(format!(
+                "left tree has {} of {} leaves filled",
+                left.num_leaves,
+                1usize << left.depth
+            )));
+        }
+        if right.leaf_index.keys().any(|key| left.leaf_index.contains_key(key)) {
+            return Err(TreeError::MergeMismatch("a leaf is present in both trees".into()));
+        }
+        let depth = left.depth + 1;
+        if depth > left.max_depth {
+            return Err(TreeError::DepthExceeded {
+                requested: depth,
+                max: left.max_depth,
+            });
+        }
+
+        // Level by level, the left subtree's nodes precede the right's
+        let mut nodes = Vec::with_capacity(left.nodes.len() + right.nodes.len() + 1);
+        nodes.push(left.hasher.hash_two(&left.root(), &right.root()));
+        for level in 0..=left.depth {
+            let level_nodes = (1 << level) - 1..(1 << (level + 1)) - 1;
+            nodes.extend_from_slice(&left.nodes[level_nodes.clone()]);
+            nodes.extend_from_slice(&right.nodes[level_nodes]);
+        }
+
+        let offset = 1 << left.depth;
+        let mut leaf_index = left.leaf_index.clone();
+        leaf_index.extend(right.leaf_index.iter().map(|(key, i)| (*key, offset + i)));
+
+        Ok(Self {
+            nodes,
+            depth,
+            num_leaves: offset + right.num_leaves,
+            hasher: left.hasher.clone(),
+            padding: left.padding,
+            leaf_index,
+            root_history: Vec::new(),
+            root_history_capacity: 0,
+            transitions: Vec::new(),
+            max_depth: left.max_depth,
+        })
+    }
+
+    /// Replace the leaf at `index` and recompute the path to the root.
+    ///
+    /// Returns the new root. Only the O(depth) nodes above the leaf are
+    /// rehashed. Setting a leaf to the padding value (zero by default)
+    /// tombstones it: the slot stays in the tree but is not indexed for
+    /// lookup. A tree with non-zero padding rejects a zero value.
+    pub fn update_leaf(&mut self, index: usize, new_value: Fr) -> Result<Fr, TreeError> {
+        if index >= self.num_leaves {
+            return Err(TreeError::IndexOutOfRange {
+                index,
+                num_leaves: self.num_leaves,
+            });
+        }
+        if self.padding != Fr::from(0u64) && new_value == Fr::from(0u64) {
+            return Err(TreeError::ReservedLeaf { index });
+        }
+
+        let node_index = (1 << self.depth) - 1 + index;
+
+        // Drop the old mapping unless it points at a duplicate elsewhere
+        let old_key = leaf_key(&self.nodes[node_index]);
+        if self.leaf_index.get(&old_key) == Some(&index) {
+            self.leaf_index.remove(&old_key);
+        }
+        if new_value != self.padding {
+            self.leaf_index.insert(leaf_key(&new_value), index);
+        }
+
+        self.set_leaf(index, new_value);
+        self.record_root();
+        Ok(self.root())
+    }
+
+    /// Write the leaf slot at `index` and rehash the path to the root.
+    fn set_leaf(&mut self, index: usize, value: Fr) {
+        let mut node_index = (1 << self.depth) - 1 + index;
+        self.nodes[node_index] = value;
+        while node_index > 0 {
+            node_index = (node_index - 1) / 2;
+            let left_child = 2 * node_index + 1;
+            let right_child = 2 * node_index + 2;
+            self.nodes[node_index] =
+                self.hasher.hash_two(&self.nodes[left_child], &self.nodes[right_child]);
+        }
+    }
+
+    /// Tombstone the leaf at `index` by setting it to the padding value.
+    pub fn remove_leaf(&mut self, index: usize) -> Result<Fr, TreeError> {
+        self.update_leaf(index, self.padding)
+    }
+
+    /// Push the current root onto the bounded history.
+    fn record_root(&mut self) {
+        if self.root_history_capacity == 0 {
+            return;
+        }
+        self.root_history.push(self.root());
+        if self.root_history.len() > self.root_history_capacity {
+            let excess = self.root_history.len() - self.root_history_capacity;
+            self.root_history.drain(..excess);
         }
     }
 
@@ -157,7 +867,13 @@ impl MerkleTree {
     }
 
     /// Get the Merkle root.
+    ///
+    /// A tree with no leaves has `empty_root` rather than its padding
+    /// value, so it cannot be confused with a tree of one padding leaf.
     pub fn root(&self) -> Fr {
+        if self.num_leaves == 0 {
+            return empty_root(&self.hasher);
+        }
         self.nodes[0]
     }
 
@@ -172,11 +888,63 @@ impl MerkleTree {
     }
 
     /// Get a reference to the hasher.
-    pub fn hasher(&self) -> &PoseidonHasher {
+    pub fn hasher(&self) -> &H {
         &self.hasher
     }
 
+    /// Value used for padding leaves and tombstones.
+    pub fn padding(&self) -> Fr {
+        self.padding
+    }
+
+    /// The real (non-padding) leaves, in insertion order.
+    pub fn leaves(&self) -> impl Iterator<Item = Fr> + '_ {
+        let leaf_start = (1 << self.depth) - 1;
+        self.nodes[leaf_start..leaf_start + self.num_leaves].iter().copied()
+    }
+
+    /// The leaf at `index`, or `None` past the last real leaf.
+    pub fn leaf_at(&self, index: usize) -> Option<Fr> {
+        if index >= self.num_leaves {
+            return None;
+        }
+        Some(self.nodes[(1 << self.depth) - 1 + index])
+    }
+
+    /// Size and occupancy of the tree.
+    pub fn stats(&self) -> TreeStats {
+        let padded_size = 1usize << self.depth;
+        TreeStats {
+            num_leaves: self.num_leaves,
+            depth: self.depth,
+            padded_size,
+            num_nodes: self.nodes.len(),
+            fill_ratio: self.num_leaves as f64 / padded_size as f64,
+        }
+    }
+
+    /// Recompute every internal node and compare with the stored ones.
+    ///
+    /// Also checks that slots past the last real leaf hold the padding
+    /// value. Meant for trees loaded from cache files, where `from_bytes`
+    /// only checks that each node is a valid field element.
+    pub fn verify_integrity(&self) -> bool {
+        let padded_size = 1usize << self.depth;
+        if self.nodes.len() != 2 * padded_size - 1 || self.num_leaves > padded_size {
+            return false;
+        }
+        let leaf_start = padded_size - 1;
+        if self.nodes[leaf_start + self.num_leaves..].iter().any(|n| *n != self.padding) {
+            return false;
+        }
+        (0..leaf_start).all(|i| {
+            self.nodes[i] == self.hasher.hash_two(&self.nodes[2 * i + 1], &self.nodes[2 * i + 2])
+        })
+    }
+
     /// Check if a leaf exists in the tree and return its index.
+    ///
+    /// If the value was inserted more than once, this is the last index.
     pub fn find_leaf(&self, leaf: &Fr) -> Option<usize> {
         let mut bytes = [0u8; 32];
         leaf.serialize_compressed(&mut bytes[..]).ok()?;
@@ -189,9 +957,26 @@ impl MerkleTree {
     }
 
     /// Generate a Merkle path for a leaf at the given index.
+    ///
+    /// `None` for an empty tree or an index past the last leaf; see
+    /// `try_get_path` to tell the two apart.
     pub fn get_path(&self, leaf_index: usize) -> Option<MerklePath> {
+        self.try_get_path(leaf_index).ok()
+    }
+
+    /// Like `get_path`, but says why there is no path.
+    ///
+    /// Fails with `EmptyTree` if the tree has no leaves, and with
+    /// `IndexOutOfRange` for an index past the last leaf.
+    pub fn try_get_path(&self, leaf_index: usize) -> Result<MerklePath, TreeError> {
+        if self.num_leaves == 0 {
+            return Err(TreeError::EmptyTree);
+        }
         if leaf_index >= self.num_leaves {
-            return None;
+            return Err(TreeError::IndexOutOfRange {
+                index: leaf_index,
+                num_leaves: self.num_leaves,
+            });
         }
 
         let padded_size = 1 << self.depth;
@@ -217,7 +1002,7 @@ impl MerkleTree {
             node_index = (node_index - 1) / 2;
         }
 
-        Some(MerklePath {
+        Ok(MerklePath {
             siblings,
             indices,
             leaf,
@@ -231,6 +1016,7 @@ impl MerkleTree {
     }
 
     /// Serialize tree data for storage.
+    #[cfg(feature = "std")]
     pub fn to_bytes(&self) -> Vec<u8> {
         let data = MerkleTreeData {
             nodes: self
@@ -244,64 +1030,69 @@ impl MerkleTree {
                 .collect(),
             depth: self.depth,
             num_leaves: self.num_leaves,
+            padding: leaf_key(&self.padding),
+            scheme: H::ID,
         };
         bincode::serialize(&data).unwrap()
     }
 
-    /// Deserialize tree from bytes.
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
-        let data: MerkleTreeData = bincode::deserialize(bytes)?;
-        let hasher = PoseidonHasher::new();
-
-        let nodes: Vec<Fr> = data
-            .nodes
-            .iter()
-            .map(|b| Fr::deserialize_compressed(&b[..]).unwrap())
-            .collect();
-
-        // Rebuild leaf index
-        let padded_size = 1 << data.depth;
-        let leaf_start = padded_size - 1;
-        let mut leaf_index = HashMap::new();
-
-        for i in 0..data.num_leaves {
-            let mut bytes = [0u8; 32];
-            nodes[leaf_start + i]
-                .serialize_compressed(&mut bytes[..])
-                .ok();
-            leaf_index.insert(bytes, i);
-        }
-
-        Ok(Self {
-            nodes,
-            depth: data.depth,
-            num_leaves: data.num_leaves,
-            hasher,
-            leaf_index,
-        })
-    }
-
     /// Save tree to a file.
+    #[cfg(feature = "std")]
     pub fn save_to_file(&self, path: &std::path::Path) -> std::io::Result<()> {
         let bytes = self.to_bytes();
         std::fs::write(path, bytes)
     }
-
-    /// Load tree from a file.
-    pub fn load_from_file(path: &std::path::Path) -> Result<Self, TreeError> {
-        let bytes = std::fs::read(path).map_err(TreeError::IoError)?;
-        Self::from_bytes(&bytes).map_err(|e| TreeError::DeserializationError(e.to_string()))
-    }
 }
 
 /// Errors that can occur with Merkle tree operations.
 #[derive(Debug, thiserror::Error)]
 pub enum TreeError {
+    #[cfg(feature = "std")]
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
     #[error("Deserialization error: {0}")]
     DeserializationError(String),
+
+    #[error("Leaf index {index} out of range for {num_leaves} leaves")]
+    IndexOutOfRange { index: usize, num_leaves: usize },
+
+    #[error("Tree depth {requested} exceeds maximum {max}")]
+    DepthExceeded { requested: usize, max: usize },
+
+    #[error("Tree has no leaves")]
+    EmptyTree,
+
+    #[error("Cannot merge trees: {0}")]
+    MergeMismatch(String),
+
+    #[error("Leaf {index} is zero or the padding value, so it could never be proven")]
+    ReservedLeaf { index: usize },
+}
+
+/// Domain tag hashed into `empty_root`.
+const EMPTY_ROOT_TAG: &[u8] = b"zk-vault empty merkle tree";
+
+/// Root of a tree with no leaves: `hash_many` of a fixed tag, which no
+/// zero or padding value collides with.
+pub fn empty_root<H: HashScheme>(hasher: &H) -> Fr {
+    hasher.hash_many(&[Fr::from_le_bytes_mod_order(EMPTY_ROOT_TAG)])
+}
+
+/// Fail on the first leaf that is zero or `pad`; `offset` is the index
+/// of `leaves[0]` in the tree.
+fn check_reserved(leaves: &[Fr], pad: Fr, offset: usize) -> Result<(), TreeError> {
+    match leaves.iter().position(|leaf| *leaf == Fr::from(0u64) || *leaf == pad) {
+        Some(i) => Err(TreeError::ReservedLeaf { index: offset + i }),
+        None => Ok(()),
+    }
+}
+
+/// Key used for a leaf in the lookup index.
+fn leaf_key(leaf: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    leaf.serialize_compressed(&mut bytes[..]).unwrap();
+    bytes
 }
 
 #[cfg(test)]
@@ -310,14 +1101,34 @@ mod tests {
 
     #[test]
     fn test_empty_tree() {
-        let tree = MerkleTree::new(vec![]);
+        let tree = MerkleTree::new(vec![]).unwrap();
         assert_eq!(tree.num_leaves(), 0);
     }
 
+    #[test]
+    fn test_empty_tree_root_and_paths() {
+        let tree = MerkleTree::new(vec![]).unwrap();
+        assert!(matches!(tree.try_get_path(0), Err(TreeError::EmptyTree)));
+        assert!(tree.get_path(0).is_none());
+
+        // Not zero, so not the root of a tree holding one zero leaf
+        assert_eq!(tree.root(), empty_root(tree.hasher()));
+        assert_ne!(tree.root(), Fr::from(0u64));
+        assert_ne!(tree.root(), MerkleTree::new(vec![Fr::from(0u64)]).unwrap().root());
+        #[cfg(feature = "std")]
+        assert_eq!(MerkleTree::from_bytes(&tree.to_bytes()).unwrap().root(), tree.root());
+
+        let one = MerkleTree::new(vec![Fr::from(5u64)]).unwrap();
+        assert!(matches!(
+            one.try_get_path(1),
+            Err(TreeError::IndexOutOfRange { index: 1, num_leaves: 1 })
+        ));
+    }
+
     #[test]
     fn test_single_leaf() {
         let leaf = Fr::from(42u64);
-        let tree = MerkleTree::new(vec![leaf]);
+        let tree = MerkleTree::new(vec![leaf]).unwrap();
 
         assert_eq!(tree.num_leaves(), 1);
         assert!(tree.contains(&leaf));
@@ -329,7 +1140,7 @@ mod tests {
     #[test]
     fn test_multiple_leaves() {
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves.clone());
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
 
         assert_eq!(tree.num_leaves(), 8);
         assert_eq!(tree.depth(), 3);
@@ -341,10 +1152,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_max_depth_enforced() {
+        let leaves: Vec<Fr> = (0..9).map(|i| Fr::from(i as u64)).collect();
+        match MerkleTree::with_max_depth(leaves.clone(), 3) {
+            Err(TreeError::DepthExceeded { requested, max }) => assert_eq!((requested, max), (4, 3)),
+            _ => panic!("expected DepthExceeded"),
+        }
+
+        let mut tree = MerkleTree::with_max_depth(leaves[..8].to_vec(), 3).unwrap();
+        let root = tree.root();
+        assert!(matches!(
+            tree.extend_leaves(vec![Fr::from(8u64)]),
+            Err(TreeError::DepthExceeded { requested: 4, max: 3 })
+        ));
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.num_leaves(), 8);
+    }
+
+    #[test]
+    fn test_compressed_path_round_trip() {
+        // 9 leaves pad to 16, so leaf 8's three lowest siblings are zero subtrees
+        let leaves: Vec<Fr> = (1..=9).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let hasher = tree.hasher();
+
+        let path = tree.get_path(8).unwrap();
+        let compressed = path.to_compressed(hasher);
+        assert_eq!(compressed.len(), 1 + 1 + 32 + 32);
+        #[cfg(feature = "std")]
+        assert!(compressed.len() < path.to_bytes().len());
+
+        let restored = MerklePath::from_compressed(&compressed, tree.depth(), hasher).unwrap();
+        assert_eq!(restored.siblings, path.siblings);
+        assert_eq!(restored.indices, path.indices);
+        assert!(restored.verify(&tree.root(), hasher));
+
+        // A dense path keeps every sibling
+        let dense = tree.get_path(0).unwrap();
+        let restored = MerklePath::from_compressed(&dense.to_compressed(hasher), 4, hasher).unwrap();
+        assert!(restored.verify(&tree.root(), hasher));
+
+        assert!(MerklePath::from_compressed(&compressed[..40], 4, hasher).is_err());
+        assert!(MerklePath::from_compressed(&compressed, 3, hasher).is_err());
+    }
+
     #[test]
     fn test_non_power_of_two() {
         let leaves: Vec<Fr> = (0..5).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves.clone());
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
 
         assert_eq!(tree.num_leaves(), 5);
         assert_eq!(tree.depth(), 3); // Padded to 8
@@ -355,10 +1211,11 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_serialization() {
         let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves.clone());
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
 
         let bytes = tree.to_bytes();
         let restored = MerkleTree::from_bytes(&bytes).unwrap();
@@ -374,10 +1231,77 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_path_serialization() {
+        let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let path = tree.get_path(5).unwrap();
+
+        let restored = MerklePath::from_bytes(&path.to_bytes()).unwrap();
+        assert_eq!(restored.siblings, path.siblings);
+        assert_eq!(restored.indices, path.indices);
+        assert_eq!(restored.leaf, path.leaf);
+        assert!(restored.verify(&tree.root(), tree.hasher()));
+
+        // serde formats go through the same encoding
+        let json = serde_json::to_string(&path).unwrap();
+        let from_json: MerklePath = serde_json::from_str(&json).unwrap();
+        assert!(from_json.verify(&tree.root(), tree.hasher()));
+
+        let mut data = MerklePathData::from(path);
+        data.indices.pop();
+        assert!(MerklePath::from_bytes(&bincode::serialize(&data).unwrap()).is_err());
+        data.indices.push(true);
+        data.leaf = [0xff; 32];
+        assert!(MerklePath::from_bytes(&bincode::serialize(&data).unwrap()).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_bytes_rejects_corrupt_blob() {
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let mut data: MerkleTreeData = bincode::deserialize(&tree.to_bytes()).unwrap();
+
+        // All-ones is above the field modulus, so it is not a valid element
+        data.nodes[3] = [0xff; 32];
+        match MerkleTree::from_bytes(&bincode::serialize(&data).unwrap()) {
+            Err(TreeError::DeserializationError(msg)) => assert!(msg.contains("invalid node 3")),
+            _ => panic!("corrupted node should be rejected"),
+        }
+
+        data.nodes.pop();
+        assert!(matches!(
+            MerkleTree::from_bytes(&bincode::serialize(&data).unwrap()),
+            Err(TreeError::DeserializationError(_))
+        ));
+
+        assert!(MerkleTree::from_bytes(&[1, 2, 3]).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_from_bytes_checks_hash_scheme() {
+        use crate::merkle::PedersenHasher;
+
+        let leaves: Vec<Fr> = (1..=5).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::with_hash_scheme(leaves, PedersenHasher::new()).unwrap();
+        let bytes = tree.to_bytes();
+        let restored = MerkleTree::from_bytes_with_hash_scheme(&bytes, PedersenHasher::new()).unwrap();
+        assert_eq!(restored.root(), tree.root());
+        assert!(restored.verify_integrity());
+
+        match MerkleTree::from_bytes(&bytes) {
+            Err(TreeError::DeserializationError(msg)) => assert!(msg.contains("scheme 2, expected 1")),
+            _ => panic!("a Pedersen tree should not load as a Poseidon tree"),
+        }
+    }
+
     #[test]
     fn test_invalid_path_fails() {
         let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         let mut path = tree.get_path(0).unwrap();
         // Corrupt the path
@@ -386,13 +1310,307 @@ mod tests {
         assert!(!path.verify(&tree.root(), tree.hasher()));
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_leaves_iterator() {
+        let leaves: Vec<Fr> = [5u64, 3, 9, 1, 7].iter().map(|&v| Fr::from(v)).collect();
+        let tree = MerkleTree::from_bytes(&MerkleTree::new(leaves.clone()).unwrap().to_bytes()).unwrap();
+
+        assert_eq!(tree.leaves().count(), tree.num_leaves());
+        assert_eq!(tree.leaves().collect::<Vec<_>>(), leaves);
+        assert_eq!(tree.leaf_at(2), Some(Fr::from(9u64)));
+        assert_eq!(tree.leaf_at(5), None);
+        assert_eq!(MerkleTree::new(vec![]).unwrap().leaves().count(), 0);
+    }
+
     #[test]
     fn test_find_leaf() {
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         assert_eq!(tree.find_leaf(&Fr::from(3u64)), Some(3));
         assert_eq!(tree.find_leaf(&Fr::from(100u64)), None);
     }
-}
 
+    #[test]
+    fn test_custom_padding() {
+        let zero = Fr::from(0u64);
+        let pad = PoseidonHasher::new().hash_many(&[Fr::from(0x5041_4400u64)]);
+        let leaves = vec![Fr::from(7u64), Fr::from(8u64), Fr::from(9u64)];
+        let mut tree = MerkleTree::with_padding(leaves.clone(), pad, PoseidonHasher::new()).unwrap();
+
+        assert_eq!(tree.padding(), pad);
+        assert_ne!(tree.root(), MerkleTree::new(leaves).unwrap().root());
+        assert_eq!(tree.find_leaf(&Fr::from(8u64)), Some(1));
+        assert_eq!(tree.find_leaf(&pad), None);
+        assert!(tree.get_path_for_leaf(&Fr::from(9u64)).unwrap().verify(&tree.root(), tree.hasher()));
+
+        // Zero and the padding value could never be proven, so are refused
+        for reserved in [zero, pad] {
+            let result = MerkleTree::with_padding(vec![Fr::from(7u64), reserved], pad, PoseidonHasher::new());
+            assert!(matches!(result, Err(TreeError::ReservedLeaf { index: 1 })));
+        }
+        assert!(matches!(tree.extend_leaves(vec![pad]), Err(TreeError::ReservedLeaf { index: 3 })));
+        assert!(matches!(tree.push_logged(zero), Err(TreeError::ReservedLeaf { index: 3 })));
+        assert!(matches!(tree.update_leaf(0, zero), Err(TreeError::ReservedLeaf { index: 0 })));
+        assert_eq!(tree.num_leaves(), 3);
+
+        // Default padding: a real zero leaf is found, never the padding slot
+        let default = MerkleTree::new(vec![Fr::from(7u64), zero, Fr::from(9u64)]).unwrap();
+        assert_eq!(default.find_leaf(&zero), Some(1));
+
+        #[cfg(feature = "std")]
+        {
+            let mut restored = MerkleTree::from_bytes(&tree.to_bytes()).unwrap();
+            assert_eq!(restored.padding(), pad);
+            restored.remove_leaf(0).unwrap();
+            assert!(!restored.contains(&Fr::from(7u64)));
+            assert!(restored.contains(&Fr::from(8u64)));
+        }
+    }
+
+    #[test]
+    fn test_duplicate_leaves() {
+        let leaves: Vec<Fr> = [1u64, 2, 3, 2, 4, 1, 2]
+            .iter()
+            .map(|&v| Fr::from(v))
+            .collect();
+
+        // Without dedup, the last copy wins
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        assert_eq!(tree.num_leaves(), 7);
+        assert_eq!(tree.find_leaf(&Fr::from(2u64)), Some(6));
+
+        let (dedup, stats) = MerkleTree::with_stats(leaves.clone()).unwrap();
+        assert_eq!(
+            stats,
+            BuildStats {
+                input_leaves: 7,
+                unique_leaves: 4,
+                duplicates_dropped: 3,
+            }
+        );
+        assert_eq!(dedup.num_leaves(), 4);
+        assert_eq!(dedup.find_leaf(&Fr::from(2u64)), Some(1));
+        assert_eq!(dedup.find_leaf(&Fr::from(4u64)), Some(3));
+
+        let path = dedup.get_path_for_leaf(&Fr::from(2u64)).unwrap();
+        assert_eq!(path.leaf, Fr::from(2u64));
+        assert!(path.verify(&dedup.root(), dedup.hasher()));
+
+        // First occurrences are kept in order
+        let expected = MerkleTree::new((1..=4u64).map(Fr::from).collect()).unwrap();
+        assert_eq!(MerkleTree::new_dedup(leaves).unwrap().root(), expected.root());
+    }
+
+    #[test]
+    fn test_root_history_window() {
+        let leaves: Vec<Fr> = (0..3).map(|i| Fr::from(i as u64)).collect();
+        let mut tree = MerkleTree::new(leaves).unwrap().with_root_history(2);
+        let first_root = tree.root();
+        assert_eq!(tree.recent_roots(), &[first_root]);
+
+        let second_root = tree.extend_leaves(vec![Fr::from(10u64)]).unwrap();
+        assert_eq!(tree.recent_roots(), &[first_root, second_root]);
+
+        let third_root = tree.extend_leaves(vec![Fr::from(11u64)]).unwrap();
+        assert_eq!(tree.recent_roots(), &[second_root, third_root]);
+        assert!(tree.contains(&Fr::from(11u64)));
+    }
+
+    #[test]
+    fn test_push_logged_replays_to_final_root() {
+        let mut tree = MerkleTree::new(vec![]).unwrap();
+        let initial_root = tree.root();
+        for i in 1..=6u64 {
+            let transition = tree.push_logged(Fr::from(i * 10)).unwrap();
+            assert_eq!(transition.new_root, tree.root());
+        }
+        assert_eq!(tree.transitions().len(), 6);
+
+        // The log chains from the initial root to the current one
+        let mut root = initial_root;
+        for (i, transition) in tree.transitions().iter().enumerate() {
+            assert_eq!(transition.leaf_index, i);
+            assert_eq!(transition.old_root, root);
+            root = transition.new_root;
+        }
+        assert_eq!(root, tree.root());
+
+        // Replaying the logged leaves from scratch reproduces every root,
+        // so the incremental updates match a full rebuild
+        let mut replay = MerkleTree::new(vec![]).unwrap();
+        for transition in tree.transitions() {
+            let leaf = tree.leaf_at(transition.leaf_index).unwrap();
+            replay.extend_leaves(vec![leaf]).unwrap();
+            assert_eq!(replay.root(), transition.new_root);
+        }
+        let rebuilt = MerkleTree::new((1..=6u64).map(|i| Fr::from(i * 10)).collect()).unwrap();
+        assert_eq!(rebuilt.root(), tree.root());
+        assert_eq!(tree.find_leaf(&Fr::from(60u64)), Some(5));
+        assert!(tree.verify_integrity());
+    }
+
+    #[test]
+    fn test_update_leaf() {
+        let leaves: Vec<Fr> = (1..=5).map(|i| Fr::from(i as u64)).collect();
+        let mut tree = MerkleTree::new(leaves).unwrap();
+        let old_path = tree.get_path(2).unwrap();
+
+        let new_root = tree.update_leaf(2, Fr::from(42u64)).unwrap();
+        assert_eq!(new_root, tree.root());
+
+        // Old value no longer verifies; the new one does
+        assert!(!old_path.verify(&tree.root(), tree.hasher()));
+        let new_path = tree.get_path(2).unwrap();
+        assert_eq!(new_path.leaf, Fr::from(42u64));
+        assert!(new_path.verify(&tree.root(), tree.hasher()));
+
+        assert!(!tree.contains(&Fr::from(3u64)));
+        assert_eq!(tree.find_leaf(&Fr::from(42u64)), Some(2));
+
+        // Same result as building from scratch
+        let rebuilt = MerkleTree::new(
+            [1u64, 2, 42, 4, 5].iter().map(|&i| Fr::from(i)).collect(),
+        ).unwrap();
+        assert_eq!(tree.root(), rebuilt.root());
+    }
+
+    #[test]
+    fn test_stats_and_integrity() {
+        let leaves: Vec<Fr> = (1..=5).map(|i| Fr::from(i as u64)).collect();
+        let mut tree = MerkleTree::new(leaves).unwrap();
+        let stats = tree.stats();
+        assert_eq!(
+            stats,
+            TreeStats { num_leaves: 5, depth: 3, padded_size: 8, num_nodes: 15, fill_ratio: 0.625 }
+        );
+        assert!(tree.verify_integrity());
+
+        // Survives a round trip through the cache format
+        #[cfg(feature = "std")]
+        assert!(MerkleTree::from_bytes(&tree.to_bytes()).unwrap().verify_integrity());
+
+        // One flipped internal node is caught
+        tree.nodes[2] += Fr::from(1u64);
+        assert!(!tree.verify_integrity());
+        tree.nodes[2] -= Fr::from(1u64);
+        assert!(tree.verify_integrity());
+
+        // So is a padding slot that was overwritten
+        tree.nodes[14] = Fr::from(7u64);
+        assert!(!tree.verify_integrity());
+    }
+
+    #[test]
+    fn test_remove_leaf_tombstones() {
+        let leaves: Vec<Fr> = (1..=4).map(|i| Fr::from(i as u64)).collect();
+        let mut tree = MerkleTree::new(leaves).unwrap().with_root_history(4);
+        let before = tree.root();
+
+        let after = tree.remove_leaf(0).unwrap();
+        assert_ne!(before, after);
+        assert_eq!(tree.recent_roots(), &[before, after]);
+        assert!(!tree.contains(&Fr::from(1u64)));
+        assert!(!tree.contains(&Fr::from(0u64)));
+        assert_eq!(tree.num_leaves(), 4);
+
+        assert!(matches!(
+            tree.update_leaf(4, Fr::from(9u64)),
+            Err(TreeError::IndexOutOfRange { index: 4, num_leaves: 4 })
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_open_for_path_matches_get_path() {
+        let leaves: Vec<Fr> = (1..=11).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let file = std::env::temp_dir().join(format!("zk-vault-tree-{}.bin", std::process::id()));
+        tree.save_to_file(&file).unwrap();
+
+        for index in [0, 5, 10] {
+            let read = MerkleTree::open_for_path(&file, index).unwrap();
+            let expected = tree.get_path(index).unwrap();
+            assert_eq!(read.to_bytes(), expected.to_bytes());
+            assert!(read.verify(&tree.root(), tree.hasher()));
+        }
+        assert!(matches!(
+            MerkleTree::open_for_path(&file, 11),
+            Err(TreeError::IndexOutOfRange { index: 11, num_leaves: 11 })
+        ));
+
+        // A truncated file is an error, not a short path
+        let bytes = tree.to_bytes();
+        std::fs::write(&file, &bytes[..bytes.len() - 40]).unwrap();
+        assert!(MerkleTree::open_for_path(&file, 0).is_err());
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_merge() {
+        let left = MerkleTree::new((1..=5u64).map(Fr::from).collect()).unwrap();
+        let right = MerkleTree::new((4..=9u64).map(Fr::from).collect()).unwrap();
+
+        let merged = MerkleTree::merge(&left, &right).unwrap();
+        let expected = MerkleTree::new((1..=9u64).map(Fr::from).collect()).unwrap();
+        assert_eq!(merged.root(), expected.root());
+        assert_eq!(merged.num_leaves(), 9);
+
+        // Every original member has a path in the merged tree
+        for leaf in left.leaves().chain(right.leaves()) {
+            let path = merged.get_path_for_leaf(&leaf).unwrap();
+            assert!(path.verify(&merged.root(), merged.hasher()));
+        }
+    }
+
+    #[test]
+    fn test_merge_as_subtrees() {
+        let left = MerkleTree::new((1..=4u64).map(Fr::from).collect()).unwrap();
+        let right = MerkleTree::new((10..=12u64).map(Fr::from).collect()).unwrap();
+
+        let merged = MerkleTree::merge_as_subtrees(&left, &right).unwrap();
+        assert_eq!(merged.depth(), left.depth() + 1);
+        let expected: Vec<Fr> = left.leaves().chain(right.leaves()).collect();
+        assert_eq!(merged.leaves().collect::<Vec<_>>(), expected);
+        assert!(merged.get_path(7).is_none());
+        assert_eq!(merged.root(), merged.hasher().hash_two(&left.root(), &right.root()));
+        assert!(merged.verify_integrity());
+
+        // An original path plus the other root is a path in the merged tree
+        for (tree, other_root, is_right) in [(&left, right.root(), false), (&right, left.root(), true)] {
+            for leaf in tree.leaves() {
+                let mut path = tree.get_path_for_leaf(&leaf).unwrap();
+                path.siblings.push(other_root);
+                path.indices.push(is_right);
+                assert!(path.verify(&merged.root(), merged.hasher()));
+
+                let merged_path = merged.get_path_for_leaf(&leaf).unwrap();
+                assert_eq!(merged_path.siblings, path.siblings);
+                assert_eq!(merged_path.indices, path.indices);
+            }
+        }
+        assert_eq!(merged.find_leaf(&Fr::from(10u64)), Some(4));
+
+        // Padding slots of a partly filled left tree would sit among the leaves
+        assert!(matches!(
+            MerkleTree::merge_as_subtrees(&right, &left),
+            Err(TreeError::MergeMismatch(_))
+        ));
+        let overlapping = MerkleTree::new((20..=21u64).chain([3]).map(Fr::from).collect()).unwrap();
+        match MerkleTree::merge_as_subtrees(&left, &overlapping) {
+            Err(TreeError::MergeMismatch(msg)) => assert!(msg.contains("both trees")),
+            _ => panic!("a leaf in both trees should be rejected"),
+        }
+
+        let shallow = MerkleTree::new(vec![Fr::from(1u64)]).unwrap();
+        assert!(matches!(
+            MerkleTree::merge_as_subtrees(&left, &shallow),
+            Err(TreeError::MergeMismatch(_))
+        ));
+        assert!(matches!(
+            MerkleTree::merge_as_subtrees(&left, &MerkleTree::new(vec![]).unwrap()),
+            Err(TreeError::EmptyTree)
+        ));
+    }
+}
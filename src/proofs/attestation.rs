@@ -0,0 +1,161 @@
+//! Notarized TLS session attestations, an alternative to DKIM.
+//!
+//! Not every user can export an email with its DKIM signature intact, but
+//! they can log in to their webmail provider while a TLSNotary-style
+//! notary takes part in the TLS session. The notary then signs the
+//! provider's domain together with a commitment to the session transcript.
+//!
+//! Once the notary's Ed25519 signature checks out, the attestation becomes
+//! an `EmailProofInput` for the provider domain. The email circuit then
+//! proves the same `domain_hash` public input it would for a DKIM-signed
+//! email, and the transcript commitment is bound into its commitment.
+//!
+//! The notary key is not part of the proof, so anyone holding a key of
+//! their own could attest any domain. Inputs are therefore only built for
+//! signatures by a pinned, trusted notary (`to_email_input`).
+
+use ark_bn254::Fr;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+use crate::circuit::{normalize_domain, string_to_field, EmailProofInput};
+
+/// Domain separator prefixed to every message a notary signs.
+pub const ATTESTATION_CONTEXT: &[u8] = b"zk-vault/attestation/v1";
+
+/// Errors verifying an attestation.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AttestationError {
+    #[error("Attestation has no provider domain")]
+    MissingDomain,
+
+    #[error("Invalid notary key: {0}")]
+    InvalidKey(String),
+
+    #[error("Notary signature verification failed")]
+    SignatureInvalid,
+
+    #[error("No trusted notary keys are configured")]
+    NoTrustedNotaries,
+
+    #[error("Attestation is not signed by a trusted notary")]
+    UntrustedNotary,
+}
+
+/// A notary's signed statement about a TLS session with a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttestationProof {
+    /// Domain of the webmail provider the session was with
+    pub provider_domain: String,
+    /// Commitment to the session transcript
+    pub transcript_commitment: [u8; 32],
+    /// Notary's Ed25519 signature over `signed_message`
+    pub signature: [u8; 64],
+}
+
+impl AttestationProof {
+    /// The bytes a notary signs: `ATTESTATION_CONTEXT`, the normalized
+    /// domain's length (u32, big-endian), the domain, and the transcript
+    /// commitment.
+    pub fn signed_message(provider_domain: &str, transcript_commitment: &[u8; 32]) -> Vec<u8> {
+        let domain = normalize_domain(provider_domain);
+        let mut message = Vec::with_capacity(ATTESTATION_CONTEXT.len() + 4 + domain.len() + 32);
+        message.extend_from_slice(ATTESTATION_CONTEXT);
+        message.extend_from_slice(&(domain.len() as u32).to_be_bytes());
+        message.extend_from_slice(domain.as_bytes());
+        message.extend_from_slice(transcript_commitment);
+        message
+    }
+
+    /// Check the notary's signature and return the normalized provider
+    /// domain.
+    pub fn verify(&self, notary_key: &[u8; 32]) -> Result<String, AttestationError> {
+        let domain = normalize_domain(&self.provider_domain);
+        if domain.is_empty() {
+            return Err(AttestationError::MissingDomain);
+        }
+
+        let key = VerifyingKey::from_bytes(notary_key)
+            .map_err(|e| AttestationError::InvalidKey(e.to_string()))?;
+        let message = Self::signed_message(&domain, &self.transcript_commitment);
+        key.verify(&message, &Signature::from_bytes(&self.signature))
+            .map_err(|_| AttestationError::SignatureInvalid)?;
+
+        Ok(domain)
+    }
+
+    /// Check the signature against each trusted notary key and return
+    /// the normalized provider domain.
+    pub fn verify_trusted(&self, trusted_notaries: &[[u8; 32]]) -> Result<String, AttestationError> {
+        if trusted_notaries.is_empty() {
+            return Err(AttestationError::NoTrustedNotaries);
+        }
+        for key in trusted_notaries {
+            match self.verify(key) {
+                Err(AttestationError::MissingDomain) => return Err(AttestationError::MissingDomain),
+                Err(_) => continue,
+                domain => return domain,
+            }
+        }
+        Err(AttestationError::UntrustedNotary)
+    }
+
+    /// The `domain_hash` public input for the attested provider.
+    pub fn domain_hash(&self, notary_key: &[u8; 32]) -> Result<Fr, AttestationError> {
+        Ok(string_to_field(&self.verify(notary_key)?))
+    }
+
+    /// Verify the attestation against the pinned `trusted_notaries` and
+    /// turn it into email circuit input.
+    ///
+    /// The transcript commitment takes the place of the DKIM data, so it
+    /// is bound into the proof's commitment.
+    pub fn to_email_input(&self, trusted_notaries: &[[u8; 32]]) -> Result<EmailProofInput, AttestationError> {
+        let domain = self.verify_trusted(trusted_notaries)?;
+        let transcript = format!("attestation:{}", hex::encode(self.transcript_commitment));
+        Ok(EmailProofInput::from_domain_with_dkim(&domain, true, &transcript))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::EmailDomainCircuit;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_verify_mock_notary_attestation() {
+        let notary = SigningKey::from_bytes(&[7u8; 32]);
+        let notary_key = notary.verifying_key().to_bytes();
+        let transcript_commitment = [9u8; 32];
+        let message = AttestationProof::signed_message("mail.example.com", &transcript_commitment);
+        let attestation = AttestationProof {
+            provider_domain: "Mail.Example.com.".to_string(),
+            transcript_commitment,
+            signature: notary.sign(&message).to_bytes(),
+        };
+
+        assert_eq!(attestation.verify(&notary_key).unwrap(), "mail.example.com");
+        assert_eq!(attestation.domain_hash(&notary_key).unwrap(), string_to_field("mail.example.com"));
+
+        // Same domain hash the email circuit exposes
+        let input = attestation.to_email_input(&[notary_key]).unwrap();
+        let circuit = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        assert_eq!(circuit.get_domain_hash(), Some(string_to_field("mail.example.com")));
+
+        let other_notary = SigningKey::from_bytes(&[8u8; 32]).verifying_key().to_bytes();
+        assert_eq!(attestation.verify(&other_notary), Err(AttestationError::SignatureInvalid));
+
+        // Only pinned notaries are accepted for proving
+        assert_eq!(attestation.verify_trusted(&[other_notary, notary_key]).unwrap(), "mail.example.com");
+        assert_eq!(attestation.verify_trusted(&[other_notary]), Err(AttestationError::UntrustedNotary));
+        assert!(matches!(attestation.to_email_input(&[]), Err(AttestationError::NoTrustedNotaries)));
+
+        let mut forged = attestation.clone();
+        forged.provider_domain = "gmail.com".to_string();
+        assert_eq!(forged.verify(&notary_key), Err(AttestationError::SignatureInvalid));
+
+        let mut forged = attestation;
+        forged.provider_domain = " ".to_string();
+        assert_eq!(forged.verify(&notary_key), Err(AttestationError::MissingDomain));
+    }
+}
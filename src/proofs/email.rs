@@ -0,0 +1,1506 @@
+//! Email parsing and authentication results for email domain proofs.
+//!
+//! Extracts the From address, DKIM signature and the SPF/DKIM/ARC
+//! verdicts recorded by the receiving mail server, so callers can decide
+//! which domain (if any) the email authenticates before proving.
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::circuit::normalize_domain;
+
+/// Errors from email parsing and DKIM verification.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EmailError {
+    #[error("Could not parse email: {0}")]
+    ParseFailed(String),
+
+    #[error("Missing From header")]
+    MissingFromHeader,
+
+    /// The From header holds only address-less groups, such as
+    /// `undisclosed-recipients:;`.
+    #[error("From header has no mailbox")]
+    NoMailbox,
+
+    #[error("Email has no DKIM-Signature header")]
+    MissingDkim,
+
+    /// A required DKIM-Signature tag (named without `=`) is absent or malformed.
+    #[error("DKIM-Signature has no valid {0}= tag")]
+    DkimFieldsIncomplete(&'static str),
+
+    /// The `h=` tag does not list From, so the signature does not vouch
+    /// for the sender (RFC 6376 §6.1.1).
+    #[error("DKIM signature does not cover the From header")]
+    FromNotSigned,
+
+    /// No signing domain aligns with the From domain. `dkim` lists the
+    /// domains that did sign, comma-separated.
+    #[error("No DKIM signature aligns with {from}; signed domains: {dkim}")]
+    DomainMismatch { dkim: String, from: String },
+
+    #[error("Unsupported DKIM algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("Invalid DKIM key: {0}")]
+    InvalidKey(String),
+
+    #[error("DKIM body hash mismatch")]
+    BodyHashMismatch,
+
+    /// The header signature did not verify; holds the key type.
+    #[error("DKIM {0} signature verification failed")]
+    SignatureInvalid(&'static str),
+}
+
+/// Parsed email headers relevant to domain proofs.
+#[derive(Clone, Debug, Default)]
+pub struct ParsedEmail {
+    /// Raw value of the `From` header
+    pub from: String,
+    /// Decoded display name of the From mailbox, if any
+    pub from_display_name: Option<String>,
+    /// Domain of the From address
+    pub from_domain: String,
+    /// Raw values of every `DKIM-Signature` header, in header order
+    pub dkim_signatures: Vec<String>,
+    /// The DKIM signature used for proving: the first whose `d=` aligns
+    /// with the From domain, or the first signature if none does
+    pub dkim_signature: Option<String>,
+    /// Signing domain (`d=` tag) of `dkim_signature`
+    pub dkim_domain: Option<String>,
+    /// Raw value of the `Authentication-Results` header
+    pub auth_results: Option<String>,
+    /// Raw value of the latest `ARC-Authentication-Results` header
+    pub arc_auth_results: Option<String>,
+    /// DKIM verdict from `Authentication-Results` (e.g. "pass")
+    pub dkim_result: Option<String>,
+    /// SPF verdict from `Authentication-Results` (e.g. "pass", "softfail")
+    pub spf_result: Option<String>,
+    /// ARC chain verdict (e.g. "pass")
+    pub arc_result: Option<String>,
+    /// When the email was sent or received, in Unix seconds: the `Date`
+    /// header, which DKIM usually signs, else the date of the latest
+    /// (topmost) `Received` header
+    pub timestamp: Option<u64>,
+}
+
+impl ParsedEmail {
+    /// The DKIM signature whose `d=` aligns with the From domain.
+    ///
+    /// A signing domain aligns if it equals the From domain or is a
+    /// parent of it (`d=example.com` for `mail.example.com`). When no
+    /// signature aligns, the error lists the domains that did sign.
+    pub fn aligned_dkim_signature(&self) -> Result<&str, EmailError> {
+        if let Some(sig) = self
+            .dkim_signatures
+            .iter()
+            .find(|sig| signature_aligns(sig, &self.from_domain))
+        {
+            return Ok(sig);
+        }
+        if self.dkim_signatures.is_empty() {
+            return Err(EmailError::MissingDkim);
+        }
+        let signed: Vec<String> = self
+            .dkim_signatures
+            .iter()
+            .map(|sig| EmailVerifier::extract_dkim_domain(sig).unwrap_or_else(|| "(no d= tag)".to_string()))
+            .collect();
+        Err(EmailError::DomainMismatch {
+            dkim: signed.join(", "),
+            from: self.from_domain.clone(),
+        })
+    }
+}
+
+/// Parses raw emails and extracts authentication data.
+pub struct EmailVerifier;
+
+impl EmailVerifier {
+    /// Parse a raw .eml message.
+    pub fn parse_email(raw_email: &str) -> Result<ParsedEmail, EmailError> {
+        let headers = parse_headers(raw_email);
+
+        let from = header_value(&headers, "from")
+            .ok_or(EmailError::MissingFromHeader)?
+            .to_string();
+        let from_domain = Self::extract_domain(&from).ok_or_else(|| {
+            if split_address_list(&from).iter().all(|mailbox| addr_spec(mailbox).is_none()) {
+                EmailError::NoMailbox
+            } else {
+                EmailError::ParseFailed(format!("no domain in From header: {}", from))
+            }
+        })?;
+        let from_display_name = display_name(&from);
+
+        let dkim_signatures: Vec<String> = headers
+            .iter()
+            .filter(|(name, _)| name == "dkim-signature")
+            .map(|(_, value)| value.clone())
+            .collect();
+        let dkim_signature = dkim_signatures
+            .iter()
+            .find(|sig| signature_aligns(sig, &from_domain))
+            .or_else(|| dkim_signatures.first())
+            .cloned();
+        let dkim_domain = dkim_signature
+            .as_deref()
+            .and_then(Self::extract_dkim_domain);
+
+        let auth_results = header_value(&headers, "authentication-results").map(str::to_string);
+        let arc_auth_results =
+            header_value(&headers, "arc-authentication-results").map(str::to_string);
+
+        let dkim_result = auth_results
+            .as_deref()
+            .and_then(|r| method_result(r, "dkim"));
+        let spf_result = auth_results
+            .as_deref()
+            .and_then(|r| method_result(r, "spf"));
+        let arc_result = auth_results
+            .as_deref()
+            .and_then(|r| method_result(r, "arc"))
+            .or_else(|| arc_auth_results.as_deref().and_then(|r| method_result(r, "arc")));
+
+        let timestamp = header_value(&headers, "date").and_then(parse_date).or_else(|| {
+            header_value(&headers, "received")
+                .and_then(|received| received.rsplit_once(';'))
+                .and_then(|(_, date)| parse_date(date))
+        });
+
+        Ok(ParsedEmail {
+            from,
+            from_display_name,
+            from_domain,
+            dkim_signatures,
+            dkim_signature,
+            dkim_domain,
+            auth_results,
+            arc_auth_results,
+            dkim_result,
+            spf_result,
+            arc_result,
+            timestamp,
+        })
+    }
+
+    /// Extract the domain from a From header value.
+    ///
+    /// Handles encoded-word display names, comments, quoted local-parts
+    /// (`"a@b"@example.com`), group syntax and address lists; returns the
+    /// domain of the first mailbox with a routable (dotted) domain.
+    pub fn extract_domain(from: &str) -> Option<String> {
+        split_address_list(from)
+            .iter()
+            .filter_map(|mailbox| mailbox_domain(mailbox))
+            .find(|domain| is_routable(domain))
+    }
+
+    /// Extract the bare address of the first mailbox in a From value.
+    pub fn extract_address(from: &str) -> Option<String> {
+        let first = split_address_list(from).into_iter().next()?;
+        addr_spec(&first)
+    }
+
+    /// Whether `dkim_domain` may sign for `from_domain`: the two are equal,
+    /// or `from_domain` is a subdomain of it.
+    ///
+    /// Matching is on label boundaries, so `google.com` aligns with
+    /// `mail.google.com` but not with `foogoogle.com`. Case and a trailing
+    /// dot are ignored.
+    pub fn domain_aligns(from_domain: &str, dkim_domain: &str) -> bool {
+        let from = normalize_domain(from_domain);
+        let dkim = normalize_domain(dkim_domain);
+        if dkim.is_empty() {
+            return false;
+        }
+        from == dkim
+            || from
+                .strip_suffix(dkim.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+    }
+
+    /// Extract the signing domain (`d=` tag) from a DKIM-Signature value.
+    ///
+    /// Reads only `d=`, so a signature with other tags missing still
+    /// names its domain; `verify_dkim` checks the rest.
+    pub fn extract_dkim_domain(dkim_signature: &str) -> Option<String> {
+        tag_value(dkim_signature, "d").map(|d| d.to_lowercase())
+    }
+
+    /// Cryptographically verify the DKIM signature of a raw email.
+    ///
+    /// With several `DKIM-Signature` headers, the first whose `d=` aligns
+    /// with the From domain is checked, falling back to the first one.
+    /// The algorithm comes from the `a=` tag (`rsa-sha256` or
+    /// `ed25519-sha256`, RFC 8463) and the public key from the
+    /// `<s>._domainkey.<d>` TXT record, whose `k=` must match it. Both the
+    /// body hash and the header signature are checked. Returns the
+    /// verified signing domain.
+    pub fn verify_dkim(raw_email: &str, resolver: &impl DkimKeyResolver) -> Result<String, EmailError> {
+        let (header_block, body) = split_message(raw_email);
+        let fields = raw_header_fields(&header_block);
+
+        let from_domain = header_value(&parse_headers(raw_email), "from").and_then(Self::extract_domain);
+        let signatures: Vec<(&str, String)> = fields
+            .iter()
+            .filter(|(name, _)| name == "dkim-signature")
+            .map(|(_, raw)| (raw.as_str(), unfold(field_value(raw))))
+            .collect();
+        let (signature_field, signature) = from_domain
+            .and_then(|from| signatures.iter().find(|(_, sig)| signature_aligns(sig, &from)))
+            .or_else(|| signatures.first())
+            .cloned()
+            .ok_or(EmailError::MissingDkim)?;
+
+        let signature = DkimSignature::parse(&signature)?;
+        let algorithm = DkimAlgorithm::from_tag(&signature.algorithm)?;
+        let (header_canon, body_canon) = signature.canonicalization;
+
+        // Body hash
+        let mut canonical_body = body_canon.body(&body);
+        if let Some(limit) = signature.body_length {
+            canonical_body.truncate(limit);
+        }
+        let body_hash = base64::engine::general_purpose::STANDARD.encode(Sha256::digest(&canonical_body));
+        if body_hash != signature.body_hash {
+            return Err(EmailError::BodyHashMismatch);
+        }
+
+        // Signed headers, each taken bottom-up, then the signature itself with b= emptied
+        let mut used = vec![false; fields.len()];
+        let mut signing_input = String::new();
+        for name in &signature.headers {
+            let next = (0..fields.len())
+                .rev()
+                .find(|&i| !used[i] && &fields[i].0 == name);
+            if let Some(i) = next {
+                used[i] = true;
+                signing_input.push_str(&header_canon.header(&fields[i].1));
+                signing_input.push_str("\r\n");
+            }
+        }
+        signing_input.push_str(&header_canon.header(&strip_signature_value(signature_field)));
+
+        let signature_bytes = base64::engine::general_purpose::STANDARD
+            .decode(&signature.signature)
+            .map_err(|_| EmailError::DkimFieldsIncomplete("b"))?;
+
+        let key_name = format!("{}._domainkey.{}", signature.selector, signature.domain);
+        let record = resolver
+            .lookup_txt(&key_name)
+            .ok_or_else(|| EmailError::InvalidKey(format!("no key record at {}", key_name)))?;
+        let key = DkimPublicKey::parse(&record)?;
+        if key.algorithm != algorithm {
+            return Err(EmailError::InvalidKey(format!(
+                "key at {} is {} but the signature uses {}",
+                key_name,
+                key.algorithm.key_type(),
+                algorithm.key_type()
+            )));
+        }
+
+        key.verify(&Sha256::digest(signing_input.as_bytes()), &signature_bytes)?;
+        Ok(signature.domain)
+    }
+}
+
+/// A `DKIM-Signature` header value, parsed into its tags.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkimSignature {
+    /// Version (`v=` tag), `1` for RFC 6376 signatures
+    pub version: String,
+    /// Signing algorithm (`a=` tag) as written, e.g. `rsa-sha256`; see
+    /// `DkimAlgorithm::from_tag` for the supported ones
+    pub algorithm: String,
+    /// Signing domain (`d=` tag), lowercased
+    pub domain: String,
+    /// Key selector (`s=` tag)
+    pub selector: String,
+    /// Signed header names (`h=` tag), lowercased, in signing order
+    pub headers: Vec<String>,
+    /// Base64 body hash (`bh=` tag)
+    pub body_hash: String,
+    /// Base64 header signature (`b=` tag)
+    pub signature: String,
+    /// Header and body canonicalization (`c=` tag), each simple by default
+    pub canonicalization: (Canonicalization, Canonicalization),
+    /// Number of body bytes signed (`l=` tag), if limited
+    pub body_length: Option<usize>,
+}
+
+impl DkimSignature {
+    /// Parse a DKIM-Signature value, folded or not.
+    ///
+    /// Fails with `DkimFieldsIncomplete` naming the first required tag
+    /// that is missing or malformed, and with `FromNotSigned` if `h=`
+    /// does not list From. The algorithm is not checked here, so
+    /// signatures this crate cannot verify still parse.
+    pub fn parse(value: &str) -> Result<Self, EmailError> {
+        let required = |tag: &'static str| tag_value(value, tag).ok_or(EmailError::DkimFieldsIncomplete(tag));
+
+        let version = required("v")?;
+        let algorithm = required("a")?;
+        let domain = required("d")?.to_lowercase();
+        let selector = required("s")?;
+        let canonicalization = Canonicalization::from_tag(tag_value(value, "c"))?;
+        let body_length = tag_value(value, "l")
+            .map(|l| l.parse().map_err(|_| EmailError::DkimFieldsIncomplete("l")))
+            .transpose()?;
+        let body_hash = required("bh")?;
+        let headers: Vec<String> = required("h")?
+            .split(':')
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        if !headers.iter().any(|name| name == "from") {
+            return Err(EmailError::FromNotSigned);
+        }
+        let signature = required("b")?;
+
+        Ok(Self {
+            version,
+            algorithm,
+            domain,
+            selector,
+            headers,
+            body_hash,
+            signature,
+            canonicalization,
+            body_length,
+        })
+    }
+}
+
+/// DKIM signing algorithm from the `a=` tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DkimAlgorithm {
+    /// `rsa-sha256` (RFC 6376)
+    RsaSha256,
+    /// `ed25519-sha256` (RFC 8463)
+    Ed25519Sha256,
+}
+
+impl DkimAlgorithm {
+    /// Parse an `a=` tag value.
+    pub fn from_tag(tag: &str) -> Result<Self, EmailError> {
+        match tag.to_ascii_lowercase().as_str() {
+            "rsa-sha256" => Ok(Self::RsaSha256),
+            "ed25519-sha256" => Ok(Self::Ed25519Sha256),
+            other => Err(EmailError::UnsupportedAlgorithm(other.to_string())),
+        }
+    }
+
+    /// Key type (`k=` tag) this algorithm needs.
+    pub fn key_type(self) -> &'static str {
+        match self {
+            Self::RsaSha256 => "rsa",
+            Self::Ed25519Sha256 => "ed25519",
+        }
+    }
+}
+
+/// Source of DKIM key TXT records.
+pub trait DkimKeyResolver {
+    /// TXT record at `name` (e.g. `sel._domainkey.example.com`), with
+    /// multiple strings already concatenated.
+    fn lookup_txt(&self, name: &str) -> Option<String>;
+}
+
+/// Resolver backed by a fixed set of records, for tests and pinned keys.
+#[derive(Clone, Debug, Default)]
+pub struct StaticKeyResolver {
+    records: HashMap<String, String>,
+}
+
+impl StaticKeyResolver {
+    /// Create an empty resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish a TXT record under `name`.
+    pub fn insert(&mut self, name: &str, record: &str) {
+        self.records.insert(name.to_lowercase(), record.to_string());
+    }
+}
+
+impl DkimKeyResolver for StaticKeyResolver {
+    fn lookup_txt(&self, name: &str) -> Option<String> {
+        self.records.get(&name.to_lowercase()).cloned()
+    }
+}
+
+/// Public key parsed from a DKIM key record.
+enum DkimKey {
+    Rsa(rsa::RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+struct DkimPublicKey {
+    algorithm: DkimAlgorithm,
+    key: DkimKey,
+}
+
+impl DkimPublicKey {
+    /// Parse a `v=DKIM1; k=...; p=...` record. `k=` defaults to rsa.
+    fn parse(record: &str) -> Result<Self, EmailError> {
+        let key_type = tag_value(record, "k").unwrap_or_else(|| "rsa".to_string());
+        let encoded = tag_value(record, "p").ok_or_else(|| EmailError::InvalidKey("record has no p= tag".to_string()))?;
+        if encoded.is_empty() {
+            return Err(EmailError::InvalidKey("key has been revoked".to_string()));
+        }
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(&encoded)
+            .map_err(|e| EmailError::InvalidKey(format!("bad encoding: {}", e)))?;
+
+        match key_type.to_ascii_lowercase().as_str() {
+            "rsa" => {
+                use rsa::pkcs1::DecodeRsaPublicKey;
+                use rsa::pkcs8::DecodePublicKey;
+
+                let key = rsa::RsaPublicKey::from_public_key_der(&der)
+                    .or_else(|_| rsa::RsaPublicKey::from_pkcs1_der(&der))
+                    .map_err(|e| EmailError::InvalidKey(format!("bad RSA key: {}", e)))?;
+                Ok(Self {
+                    algorithm: DkimAlgorithm::RsaSha256,
+                    key: DkimKey::Rsa(key),
+                })
+            }
+            "ed25519" => {
+                let bytes: [u8; 32] = der
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| EmailError::InvalidKey(format!("Ed25519 key must be 32 bytes, got {}", der.len())))?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&bytes)
+                    .map_err(|e| EmailError::InvalidKey(format!("bad Ed25519 key: {}", e)))?;
+                Ok(Self {
+                    algorithm: DkimAlgorithm::Ed25519Sha256,
+                    key: DkimKey::Ed25519(key),
+                })
+            }
+            other => Err(EmailError::InvalidKey(format!("unsupported key type: {}", other))),
+        }
+    }
+
+    /// Verify `signature` over the SHA-256 digest of the signing input.
+    ///
+    /// Ed25519 signs the 32-byte digest itself, as RFC 8463 specifies.
+    fn verify(&self, digest: &[u8], signature: &[u8]) -> Result<(), EmailError> {
+        match &self.key {
+            DkimKey::Rsa(key) => key
+                .verify(rsa::Pkcs1v15Sign::new::<Sha256>(), digest, signature)
+                .map_err(|_| EmailError::SignatureInvalid("RSA")),
+            DkimKey::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_slice(signature)
+                    .map_err(|_| EmailError::SignatureInvalid("Ed25519"))?;
+                key.verify_strict(digest, &signature)
+                    .map_err(|_| EmailError::SignatureInvalid("Ed25519"))
+            }
+        }
+    }
+}
+
+/// DKIM canonicalization algorithm (RFC 6376 section 3.4).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Canonicalization {
+    /// Headers and body as sent, apart from trailing empty body lines
+    Simple,
+    /// Whitespace-normalized, with lowercase header names
+    Relaxed,
+}
+
+impl Canonicalization {
+    /// Parse a `c=header/body` tag; each half defaults to simple.
+    fn from_tag(tag: Option<String>) -> Result<(Self, Self), EmailError> {
+        let tag = tag.unwrap_or_default().to_ascii_lowercase();
+        let (header, body) = tag.split_once('/').unwrap_or((tag.as_str(), ""));
+        Ok((Self::parse(header)?, Self::parse(body)?))
+    }
+
+    fn parse(name: &str) -> Result<Self, EmailError> {
+        match name {
+            "" | "simple" => Ok(Self::Simple),
+            "relaxed" => Ok(Self::Relaxed),
+            other => Err(EmailError::ParseFailed(format!("unsupported DKIM canonicalization: {}", other))),
+        }
+    }
+
+    /// Canonicalize one raw header field (without its trailing CRLF).
+    fn header(self, raw: &str) -> String {
+        match self {
+            Self::Simple => raw.to_string(),
+            Self::Relaxed => {
+                let (name, value) = raw.split_once(':').unwrap_or((raw, ""));
+                format!(
+                    "{}:{}",
+                    name.trim_end().to_lowercase(),
+                    compress_whitespace(&unfold(value)).trim()
+                )
+            }
+        }
+    }
+
+    /// Canonicalize the body, given as lines without line endings.
+    fn body(self, lines: &[&str]) -> Vec<u8> {
+        let mut lines: Vec<String> = lines
+            .iter()
+            .map(|line| match self {
+                Self::Simple => line.to_string(),
+                Self::Relaxed => compress_whitespace(line).trim_end().to_string(),
+            })
+            .collect();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        if lines.is_empty() {
+            return match self {
+                Self::Simple => b"\r\n".to_vec(),
+                Self::Relaxed => Vec::new(),
+            };
+        }
+        let mut body = lines.join("\r\n");
+        body.push_str("\r\n");
+        body.into_bytes()
+    }
+}
+
+/// Pick the most trustworthy authenticated domain from a parsed email.
+///
+/// Preference order:
+/// 1. DKIM pass: the DKIM `d=` signing domain
+/// 2. ARC pass: the DKIM domain recorded by the first hop in the ARC chain
+///    (for forwarded mail whose original DKIM signature broke)
+/// 3. SPF pass: the `smtp.mailfrom` envelope domain
+pub fn best_authenticated_domain(parsed: &ParsedEmail) -> Option<String> {
+    if is_pass(&parsed.dkim_result) {
+        if let Some(domain) = &parsed.dkim_domain {
+            return Some(domain.clone());
+        }
+    }
+
+    if is_pass(&parsed.arc_result) {
+        let arc_domain = parsed
+            .auth_results
+            .as_deref()
+            .and_then(|r| property_after(r, "dkdomain="))
+            .or_else(|| {
+                parsed
+                    .arc_auth_results
+                    .as_deref()
+                    .and_then(|r| property_after(r, "header.d="))
+            });
+        if let Some(domain) = arc_domain {
+            return Some(domain);
+        }
+    }
+
+    if is_pass(&parsed.spf_result) {
+        if let Some(domain) = parsed
+            .auth_results
+            .as_deref()
+            .and_then(|r| property_after(r, "smtp.mailfrom="))
+        {
+            return Some(domain);
+        }
+    }
+
+    None
+}
+
+/// Decode RFC 2047 encoded words (`=?charset?B?...?=` / `=?charset?Q?...?=`).
+///
+/// Text is decoded as UTF-8 regardless of the declared charset; malformed
+/// words are left as-is. Whitespace between adjacent encoded words is
+/// dropped, as the RFC requires.
+pub fn decode_encoded_words(value: &str) -> String {
+    let mut output = String::new();
+    let mut rest = value;
+    let mut last_was_encoded = false;
+
+    while let Some(start) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(start);
+        let decoded = parse_encoded_word(candidate);
+
+        match decoded {
+            Some((text, consumed)) => {
+                if !(last_was_encoded && before.trim().is_empty()) {
+                    output.push_str(before);
+                }
+                output.push_str(&text);
+                rest = &candidate[consumed..];
+                last_was_encoded = true;
+            }
+            None => {
+                output.push_str(before);
+                output.push_str("=?");
+                rest = &candidate[2..];
+                last_was_encoded = false;
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Decode one encoded word at the start of `word`, returning the text and
+/// the number of bytes consumed.
+fn parse_encoded_word(word: &str) -> Option<(String, usize)> {
+    let inner = word.strip_prefix("=?")?;
+    let (_charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let text = &inner[..end];
+    let consumed = word.len() - inner.len() + end + 2;
+
+    let bytes = match encoding.to_ascii_uppercase().as_str() {
+        "B" => base64::engine::general_purpose::STANDARD.decode(text).ok()?,
+        "Q" => decode_q(text)?,
+        _ => return None,
+    };
+
+    Some((String::from_utf8_lossy(&bytes).into_owned(), consumed))
+}
+
+/// Decode RFC 2047 "Q" encoding.
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut chars = text.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hi = chars.next()?;
+                let lo = chars.next()?;
+                let hex = [hi, lo];
+                let decoded = u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?;
+                bytes.push(decoded);
+            }
+            _ => bytes.push(b),
+        }
+    }
+    Some(bytes)
+}
+
+/// Split an address list into individual mailboxes.
+///
+/// Commas inside quotes, angle brackets and comments are ignored, and
+/// group names (`Team: a@x.com, b@y.com;`) are dropped.
+fn split_address_list(value: &str) -> Vec<String> {
+    let mut mailboxes = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut angle_depth = 0usize;
+    let mut paren_depth = 0usize;
+    let mut escaped = false;
+
+    for c in value.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes || paren_depth > 0 => {
+                current.push(c);
+                escaped = true;
+            }
+            '"' if paren_depth == 0 => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '(' if !in_quotes => {
+                paren_depth += 1;
+                current.push(c);
+            }
+            ')' if !in_quotes && paren_depth > 0 => {
+                paren_depth -= 1;
+                current.push(c);
+            }
+            '<' if !in_quotes && paren_depth == 0 => {
+                angle_depth += 1;
+                current.push(c);
+            }
+            '>' if !in_quotes && paren_depth == 0 && angle_depth > 0 => {
+                angle_depth -= 1;
+                current.push(c);
+            }
+            ':' if !in_quotes && paren_depth == 0 && angle_depth == 0 => {
+                // Group name ends here; discard it
+                current.clear();
+            }
+            ',' | ';' if !in_quotes && paren_depth == 0 && angle_depth == 0 => {
+                if !current.trim().is_empty() {
+                    mailboxes.push(current.trim().to_string());
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.trim().is_empty() {
+        mailboxes.push(current.trim().to_string());
+    }
+    mailboxes
+}
+
+/// Bare address of a single mailbox (`Name <user@host>` or
+/// `user@host (comment)`), or `None` if it has no `@`.
+///
+/// Comments and angle brackets inside a quoted local-part or display
+/// name are left alone.
+fn addr_spec(mailbox: &str) -> Option<String> {
+    let mut cleaned = String::new();
+    let mut angle = None;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut paren_depth = 0usize;
+
+    for c in mailbox.chars() {
+        if escaped {
+            if paren_depth == 0 {
+                cleaned.push(c);
+            }
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if in_quotes || paren_depth > 0 => {
+                if paren_depth == 0 {
+                    cleaned.push(c);
+                }
+                escaped = true;
+            }
+            '"' if paren_depth == 0 => {
+                in_quotes = !in_quotes;
+                cleaned.push(c);
+            }
+            '(' if !in_quotes => paren_depth += 1,
+            ')' if !in_quotes && paren_depth > 0 => paren_depth -= 1,
+            _ if paren_depth > 0 => {}
+            '<' if !in_quotes => {
+                angle = Some((cleaned.len() + 1, None));
+                cleaned.push(c);
+            }
+            '>' if !in_quotes => {
+                if let Some((_, end @ None)) = angle.as_mut() {
+                    *end = Some(cleaned.len());
+                }
+                cleaned.push(c);
+            }
+            _ => cleaned.push(c),
+        }
+    }
+
+    let address = match angle {
+        Some((start, Some(end))) => &cleaned[start..end],
+        _ => cleaned.as_str(),
+    };
+    let address = address.trim();
+    address.contains('@').then(|| address.to_string())
+}
+
+/// Domain of a single mailbox (`Name <user@host>` or `user@host (comment)`).
+fn mailbox_domain(mailbox: &str) -> Option<String> {
+    let address = addr_spec(mailbox)?;
+    let domain = address.rsplit_once('@')?.1.trim().trim_end_matches('.');
+    if domain.is_empty() {
+        return None;
+    }
+    Some(domain.to_lowercase())
+}
+
+/// Whether a domain looks deliverable on the public internet.
+fn is_routable(domain: &str) -> bool {
+    domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.contains(char::is_whitespace)
+        && domain != "localhost"
+}
+
+/// Decoded display name of the first mailbox in a From value.
+fn display_name(from: &str) -> Option<String> {
+    let first = split_address_list(from).into_iter().next()?;
+    let name = first.split('<').next()?.trim();
+    if name.is_empty() || !first.contains('<') {
+        return None;
+    }
+    let decoded = decode_encoded_words(name);
+    Some(decoded.trim_matches('"').trim().to_string())
+}
+
+/// Whether a DKIM signature's `d=` equals or is a parent of `from_domain`.
+fn signature_aligns(signature: &str, from_domain: &str) -> bool {
+    EmailVerifier::extract_dkim_domain(signature).is_some_and(|d| EmailVerifier::domain_aligns(from_domain, &d))
+}
+
+fn is_pass(result: &Option<String>) -> bool {
+    result.as_deref() == Some("pass")
+}
+
+/// Split the header block into unfolded `(lowercase name, value)` pairs.
+fn parse_headers(raw_email: &str) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+
+    for line in raw_email.lines() {
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            // Folded continuation of the previous header
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    headers
+}
+
+/// Split a raw message into its header block and body lines.
+///
+/// Line endings are normalized to CRLF in the header block; body lines
+/// are returned without them.
+fn split_message(raw_email: &str) -> (String, Vec<&str>) {
+    let mut lines = raw_email.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line));
+    let mut header_block = Vec::new();
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        header_block.push(line);
+    }
+
+    let mut body: Vec<&str> = lines.collect();
+    // A trailing newline ends the last line rather than starting a new one
+    if body.last() == Some(&"") {
+        body.pop();
+    }
+    (header_block.join("\r\n"), body)
+}
+
+/// Raw header fields as `(lowercase name, field text)`, folding kept.
+fn raw_header_fields(header_block: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in header_block.split("\r\n") {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, raw)) = fields.last_mut() {
+                raw.push_str("\r\n");
+                raw.push_str(line);
+            }
+            continue;
+        }
+        if let Some((name, _)) = line.split_once(':') {
+            fields.push((name.trim().to_lowercase(), line.to_string()));
+        }
+    }
+    fields
+}
+
+/// Value part of a raw header field.
+fn field_value(raw: &str) -> &str {
+    raw.split_once(':').map_or("", |(_, value)| value)
+}
+
+/// Remove folding line breaks.
+fn unfold(value: &str) -> String {
+    value.replace("\r\n", "")
+}
+
+/// Collapse runs of spaces and tabs into one space.
+fn compress_whitespace(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut in_whitespace = false;
+    for c in value.chars() {
+        if c == ' ' || c == '\t' {
+            if !in_whitespace {
+                output.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            output.push(c);
+            in_whitespace = false;
+        }
+    }
+    output
+}
+
+/// Empty the `b=` tag of a raw DKIM-Signature field, keeping everything else.
+fn strip_signature_value(raw: &str) -> String {
+    let (name, value) = raw.split_once(':').unwrap_or((raw, ""));
+    let parts: Vec<String> = value
+        .split(';')
+        .map(|part| match part.split_once('=') {
+            Some((tag, _)) if tag.trim() == "b" => format!("{}=", tag),
+            _ => part.to_string(),
+        })
+        .collect();
+    format!("{}:{}", name, parts.join(";"))
+}
+
+/// RFC 2822 date to Unix seconds; `None` if unparseable or before 1970.
+fn parse_date(value: &str) -> Option<u64> {
+    mailparse::dateparse(value.trim()).ok().and_then(|secs| u64::try_from(secs).ok())
+}
+
+/// First value of a header, by lowercase name.
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Value of a `tag=value;` pair in a tag list such as a DKIM-Signature.
+fn tag_value(tag_list: &str, tag: &str) -> Option<String> {
+    tag_list.split(';').find_map(|part| {
+        let (name, value) = part.split_once('=')?;
+        if name.trim() == tag {
+            Some(value.split_whitespace().collect::<String>())
+        } else {
+            None
+        }
+    })
+}
+
+/// Verdict of an authentication method (`dkim=pass`, `spf=softfail`, ...).
+///
+/// Parenthesized comments are ignored, so nested verdicts such as
+/// `arc=pass (spf=pass dkim=pass)` don't shadow the top-level ones.
+fn method_result(results: &str, method: &str) -> Option<String> {
+    let needle = format!("{}=", method);
+    let stripped = strip_comments(&results.to_lowercase());
+
+    stripped
+        .split(|c: char| c == ';' || c.is_whitespace())
+        .find_map(|token| token.strip_prefix(&needle))
+        .map(|value| {
+            value
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|value| !value.is_empty())
+}
+
+/// Remove RFC 5322 parenthesized comments.
+fn strip_comments(value: &str) -> String {
+    let mut depth = 0usize;
+    value
+        .chars()
+        .filter(|c| match c {
+            '(' => {
+                depth += 1;
+                false
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+/// Domain following a property such as `smtp.mailfrom=` or `header.d=`.
+fn property_after(results: &str, property: &str) -> Option<String> {
+    let lower = results.to_lowercase();
+    let start = lower.find(property)? + property.len();
+    let value: String = lower[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != ';' && *c != ')')
+        .collect();
+    // smtp.mailfrom may hold a full address
+    let domain = value.rsplit('@').next()?.to_string();
+    if domain.is_empty() {
+        None
+    } else {
+        Some(domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const DIRECT_EML: &str = "\
+From: Alice <alice@google.com>
+To: bob@example.com
+Subject: Hello
+DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/relaxed; d=google.com; s=20230601;
+ h=from:to:subject; bh=abc=; b=def=
+Authentication-Results: mx.example.com;
+ dkim=pass header.i=@google.com header.s=20230601;
+ spf=pass (domain of alice@google.com designates 1.2.3.4) smtp.mailfrom=alice@google.com
+
+Hi Bob
+";
+
+    const FORWARDED_EML: &str = "\
+ARC-Authentication-Results: i=2; mx.google.com;
+ dkim=fail header.i=@corp.example;
+ arc=pass (i=1 spf=pass spfdomain=corp.example dkim=pass dkdomain=corp.example);
+ spf=softfail smtp.mailfrom=lists.example.org
+ARC-Authentication-Results: i=1; mx.lists.example.org;
+ dkim=pass header.d=corp.example header.s=sel;
+ spf=pass smtp.mailfrom=carol@corp.example
+Authentication-Results: mx.google.com;
+ dkim=fail header.i=@corp.example header.s=sel;
+ arc=pass (i=1 spf=pass spfdomain=corp.example dkim=pass dkdomain=corp.example);
+ spf=softfail (google.com: domain of transitioning lists.example.org) smtp.mailfrom=lists.example.org
+DKIM-Signature: v=1; a=rsa-sha256; d=corp.example; s=sel; h=from:to; bh=x=; b=y=
+From: Carol <carol@corp.example>
+To: dave@gmail.com
+Subject: Fwd: onboarding
+
+Forwarded body
+";
+
+    /// Signed sample from RFC 8463 Appendix A.
+    const ED25519_EML: &str = "\
+DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed;
+ d=football.example.com; i=@football.example.com;
+ q=dns/txt; s=brisbane; t=1528637909; h=from : to :
+ subject : date : message-id : from : subject : date;
+ bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;
+ b=/gCrinpcQOoIfuHNQIbq4pgh9kyIK3AQUdt9OdqQehSwhEIug4D11Bus
+ Fa3bT3FY5OsU7ZbnKELq+eXdp1Q1Dw==
+From: Joe SixPack <joe@football.example.com>
+To: Suzie Q <suzie@shopping.example.net>
+Subject: Is dinner ready?
+Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)
+Message-ID: <20030712040037.46341.5F8J@football.example.com>
+
+Hi.
+
+We lost the game.  Are you hungry yet?
+
+Joe.
+";
+
+    fn ed25519_resolver() -> StaticKeyResolver {
+        let mut resolver = StaticKeyResolver::new();
+        resolver.insert(
+            "brisbane._domainkey.football.example.com",
+            "v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=",
+        );
+        resolver
+    }
+
+    #[test]
+    fn test_verify_ed25519_dkim() {
+        let domain = EmailVerifier::verify_dkim(ED25519_EML, &ed25519_resolver()).unwrap();
+        assert_eq!(domain, "football.example.com");
+
+        // CRLF line endings canonicalize the same way
+        let crlf = ED25519_EML.replace('\n', "\r\n");
+        assert!(EmailVerifier::verify_dkim(&crlf, &ed25519_resolver()).is_ok());
+    }
+
+    #[test]
+    fn test_ed25519_dkim_rejects_tampering() {
+        let resolver = ed25519_resolver();
+
+        let body = ED25519_EML.replace("hungry", "thirsty");
+        assert_eq!(
+            EmailVerifier::verify_dkim(&body, &resolver).unwrap_err(),
+            EmailError::BodyHashMismatch
+        );
+
+        let header = ED25519_EML.replace("Is dinner ready?", "Is lunch ready?");
+        assert_eq!(
+            EmailVerifier::verify_dkim(&header, &resolver).unwrap_err(),
+            EmailError::SignatureInvalid("Ed25519")
+        );
+
+        // An RSA key record cannot verify an Ed25519 signature
+        let mut rsa_record = StaticKeyResolver::new();
+        rsa_record.insert(
+            "brisbane._domainkey.football.example.com",
+            "v=DKIM1; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=",
+        );
+        assert!(matches!(
+            EmailVerifier::verify_dkim(ED25519_EML, &rsa_record),
+            Err(EmailError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            EmailVerifier::verify_dkim(ED25519_EML, &StaticKeyResolver::new()),
+            Err(EmailError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_rsa_dkim() {
+        use rsa::pkcs8::EncodePublicKey;
+        use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(801);
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let public_der = private_key.to_public_key().to_public_key_der().unwrap();
+
+        let unsigned = "\
+DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/simple; d=example.com; s=sel;
+ h=from:subject; bh=zj5aWBeKzUsZXMcO6cgsbKQjcs7EFbBvlfw0S0UFFw4=; b=
+From: Alice <alice@example.com>
+Subject: Report
+
+Numbers attached.
+";
+        let (header_block, _) = split_message(unsigned);
+        let fields = raw_header_fields(&header_block);
+        let mut signing_input = String::new();
+        for name in ["from", "subject"] {
+            let (_, raw) = fields.iter().find(|(n, _)| n == name).unwrap();
+            signing_input.push_str(&Canonicalization::Relaxed.header(raw));
+            signing_input.push_str("\r\n");
+        }
+        signing_input.push_str(&Canonicalization::Relaxed.header(&fields[0].1));
+        let signature = private_key
+            .sign(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(signing_input.as_bytes()),
+            )
+            .unwrap();
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let signed = unsigned.replacen("b=\n", &format!("b={}\n", b64.encode(signature)), 1);
+
+        let mut resolver = StaticKeyResolver::new();
+        resolver.insert(
+            "sel._domainkey.example.com",
+            &format!("v=DKIM1; k=rsa; p={}", b64.encode(public_der.as_bytes())),
+        );
+        assert_eq!(
+            EmailVerifier::verify_dkim(&signed, &resolver).unwrap(),
+            "example.com"
+        );
+    }
+
+    #[test]
+    fn test_signature_must_cover_from() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let key = SigningKey::from_bytes(&[8u8; 32]);
+        let template = "\
+DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed; d=example.com; s=sel;
+ h=subject; bh=BH; b=
+From: Mallory <mallory@example.com>
+Subject: Report
+
+Numbers attached.
+";
+        let (_, body) = split_message(template);
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let unsigned = template.replace("BH", &b64.encode(Sha256::digest(Canonicalization::Relaxed.body(&body))));
+
+        // A valid signature over Subject alone
+        let (header_block, _) = split_message(&unsigned);
+        let fields = raw_header_fields(&header_block);
+        let (_, subject) = fields.iter().find(|(n, _)| n == "subject").unwrap();
+        let signing_input = format!(
+            "{}\r\n{}",
+            Canonicalization::Relaxed.header(subject),
+            Canonicalization::Relaxed.header(&fields[0].1)
+        );
+        let signature = key.sign(&Sha256::digest(signing_input.as_bytes()));
+        let signed = unsigned.replacen("b=\n", &format!("b={}\n", b64.encode(signature.to_bytes())), 1);
+
+        let mut resolver = StaticKeyResolver::new();
+        resolver.insert(
+            "sel._domainkey.example.com",
+            &format!("v=DKIM1; k=ed25519; p={}", b64.encode(key.verifying_key().as_bytes())),
+        );
+        assert_eq!(
+            EmailVerifier::verify_dkim(&signed, &resolver).unwrap_err(),
+            EmailError::FromNotSigned
+        );
+    }
+
+    #[test]
+    fn test_unsupported_dkim_algorithm() {
+        let eml = ED25519_EML.replace("a=ed25519-sha256", "a=rsa-sha1");
+        let err = EmailVerifier::verify_dkim(&eml, &ed25519_resolver()).unwrap_err();
+        assert_eq!(err, EmailError::UnsupportedAlgorithm("rsa-sha1".to_string()));
+        assert_eq!(err.to_string(), "Unsupported DKIM algorithm: rsa-sha1");
+    }
+
+    #[test]
+    fn test_parse_dkim_signature() {
+        let header = "v=1; a=rsa-sha256; c=relaxed/relaxed;
+        d=Gmail.com; s=20230601; t=1700000000; x=1700604800;
+        h=from:to:subject; bh=frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=;
+        b=Vd1kp0rVQ2DzUeqkHS5sZGQH3xtBKcbLUfX8mVPwjh0rWa1Wq2yZ3QoD0bm6Ja0M
+         Bgk0Jbnwq4fkMCDuWbnyZ6b4ZfFYS0w1k0XoLf9VbVh2QHqPzJxXbJ8Y1uKk3mSxL3
+         nWcTg==";
+        let sig = DkimSignature::parse(header).unwrap();
+        assert_eq!(sig.version, "1");
+        assert_eq!(sig.algorithm, "rsa-sha256");
+        assert_eq!(sig.domain, "gmail.com");
+        assert_eq!(sig.selector, "20230601");
+        assert_eq!(sig.headers, ["from", "to", "subject"]);
+        assert_eq!(sig.body_hash, "frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=");
+        assert!(sig.signature.starts_with("Vd1kp0rV") && sig.signature.ends_with("nWcTg=="));
+        assert!(!sig.signature.contains(char::is_whitespace));
+        assert_eq!(sig.canonicalization, (Canonicalization::Relaxed, Canonicalization::Relaxed));
+        assert_eq!(sig.body_length, None);
+        assert_eq!(EmailVerifier::extract_dkim_domain(header).as_deref(), Some("gmail.com"));
+
+        // Spaces around the h= separators and a missing c= tag
+        let sig = DkimSignature::parse("v=1; a=ed25519-sha256; d=x.example; s=sel; h=From : To; bh=x=; b=y=; l=12").unwrap();
+        assert_eq!(sig.headers, ["from", "to"]);
+        assert_eq!(sig.canonicalization, (Canonicalization::Simple, Canonicalization::Simple));
+        assert_eq!(sig.body_length, Some(12));
+
+        let unsigned = header.replace("s=20230601;", "");
+        assert_eq!(DkimSignature::parse(&unsigned).unwrap_err(), EmailError::DkimFieldsIncomplete("s"));
+        // The signing domain is still read from an incomplete or malformed signature
+        assert_eq!(EmailVerifier::extract_dkim_domain(&unsigned).as_deref(), Some("gmail.com"));
+        let malformed = header.replace("c=relaxed/relaxed", "c=nowsp");
+        assert_eq!(EmailVerifier::extract_dkim_domain(&malformed).as_deref(), Some("gmail.com"));
+        assert!(matches!(
+            DkimSignature::parse(&malformed),
+            Err(EmailError::ParseFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_direct_email() {
+        let parsed = EmailVerifier::parse_email(DIRECT_EML).unwrap();
+
+        assert_eq!(parsed.from_domain, "google.com");
+        assert_eq!(parsed.dkim_domain.as_deref(), Some("google.com"));
+        assert_eq!(parsed.dkim_result.as_deref(), Some("pass"));
+        assert_eq!(parsed.spf_result.as_deref(), Some("pass"));
+        assert_eq!(parsed.arc_result, None);
+        assert_eq!(best_authenticated_domain(&parsed).as_deref(), Some("google.com"));
+    }
+
+    #[test]
+    fn test_parse_email_timestamp() {
+        // Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)
+        let parsed = EmailVerifier::parse_email(ED25519_EML).unwrap();
+        assert_eq!(parsed.timestamp, Some(1_057_982_437));
+
+        // Without a Date header, the latest Received header's date is used
+        let received = format!(
+            "Received: from mx2.example.com by mx.example.com; Tue, 5 Mar 2024 10:15:00 +0100\n\
+             Received: from relay.google.com by mx2.example.com; Tue, 5 Mar 2024 09:14:58 +0000\n{}",
+            DIRECT_EML
+        );
+        assert_eq!(EmailVerifier::parse_email(&received).unwrap().timestamp, Some(1_709_630_100));
+
+        assert_eq!(EmailVerifier::parse_email(DIRECT_EML).unwrap().timestamp, None);
+    }
+
+    #[test]
+    fn test_forwarded_email_only_arc_passes() {
+        let parsed = EmailVerifier::parse_email(FORWARDED_EML).unwrap();
+
+        assert_eq!(parsed.dkim_result.as_deref(), Some("fail"));
+        assert_eq!(parsed.spf_result.as_deref(), Some("softfail"));
+        assert_eq!(parsed.arc_result.as_deref(), Some("pass"));
+        assert_eq!(best_authenticated_domain(&parsed).as_deref(), Some("corp.example"));
+    }
+
+    #[test]
+    fn test_spf_fallback() {
+        let parsed = ParsedEmail {
+            from_domain: "example.com".to_string(),
+            auth_results: Some("mx; dkim=none; spf=pass smtp.mailfrom=bounce@example.com".to_string()),
+            dkim_result: Some("none".to_string()),
+            spf_result: Some("pass".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(best_authenticated_domain(&parsed).as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn test_encoded_display_name() {
+        let from = "=?UTF-8?B?Sm9zw6kgR2FyY8OtYQ==?= <jose@google.com>";
+        assert_eq!(EmailVerifier::extract_domain(from).as_deref(), Some("google.com"));
+        assert_eq!(display_name(from).as_deref(), Some("José García"));
+
+        let q_from = "=?UTF-8?Q?Andr=C3=A9_M?= <andre@meta.com> (work)";
+        assert_eq!(EmailVerifier::extract_domain(q_from).as_deref(), Some("meta.com"));
+        assert_eq!(display_name(q_from).as_deref(), Some("André M"));
+    }
+
+    #[test]
+    fn test_decode_adjacent_encoded_words() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?Q?Hello?= =?UTF-8?Q?_World?="),
+            "Hello World"
+        );
+        assert_eq!(decode_encoded_words("plain text"), "plain text");
+    }
+
+    #[test]
+    fn test_multiple_from_addresses() {
+        let from = "\"Ops, Team\" <ops@localhost>, Alice <alice@google.com>, bob@meta.com";
+        assert_eq!(EmailVerifier::extract_domain(from).as_deref(), Some("google.com"));
+
+        let from = "bob@meta.com, alice@google.com";
+        assert_eq!(EmailVerifier::extract_domain(from).as_deref(), Some("meta.com"));
+    }
+
+    #[test]
+    fn test_extract_address() {
+        assert_eq!(
+            EmailVerifier::extract_address("Alice <alice@google.com>").as_deref(),
+            Some("alice@google.com")
+        );
+        assert_eq!(
+            EmailVerifier::extract_address("bob@meta.com (Bob)").as_deref(),
+            Some("bob@meta.com")
+        );
+        assert_eq!(EmailVerifier::extract_address("undisclosed"), None);
+    }
+
+    #[test]
+    fn test_from_with_comment_and_group() {
+        assert_eq!(
+            EmailVerifier::extract_domain("alice@google.com (Alice Smith)").as_deref(),
+            Some("google.com")
+        );
+        assert_eq!(
+            EmailVerifier::extract_domain("Team: alice@google.com, bob@google.com;").as_deref(),
+            Some("google.com")
+        );
+    }
+
+    #[test]
+    fn test_address_less_group() {
+        assert_eq!(EmailVerifier::extract_domain("undisclosed-recipients:;"), None);
+        assert_eq!(
+            EmailVerifier::parse_email("From: undisclosed-recipients:;\n\nbody").unwrap_err(),
+            EmailError::NoMailbox
+        );
+        assert!(matches!(
+            EmailVerifier::parse_email("From: root@localhost\n\nbody"),
+            Err(EmailError::ParseFailed(_))
+        ));
+        assert_eq!(
+            EmailVerifier::extract_domain("Empty:;, Registrar <office@uni.example.edu>").as_deref(),
+            Some("uni.example.edu")
+        );
+    }
+
+    #[test]
+    fn test_quoted_local_part() {
+        let from = "\"a@b\"@c.com";
+        assert_eq!(EmailVerifier::extract_domain(from).as_deref(), Some("c.com"));
+        assert_eq!(EmailVerifier::extract_address(from).as_deref(), Some(from));
+
+        let from = "\"Smith (Registrar) <x>\" <\"j.smith(ops)\"@uni.example.edu> (staff)";
+        assert_eq!(EmailVerifier::extract_domain(from).as_deref(), Some("uni.example.edu"));
+        assert_eq!(
+            EmailVerifier::extract_address(from).as_deref(),
+            Some("\"j.smith(ops)\"@uni.example.edu")
+        );
+    }
+
+    #[test]
+    fn test_multiple_dkim_signatures_pick_aligned() {
+        let eml = "\
+DKIM-Signature: v=1; a=rsa-sha256; d=lists.example.org; s=list; h=from; bh=x=; b=y=
+DKIM-Signature: v=1; a=rsa-sha256; d=corp.example; s=sel; h=from; bh=x=; b=y=
+From: Carol <carol@mail.corp.example>
+Subject: Minutes
+
+Body
+";
+        let parsed = EmailVerifier::parse_email(eml).unwrap();
+        assert_eq!(parsed.dkim_signatures.len(), 2);
+        assert_eq!(parsed.dkim_domain.as_deref(), Some("corp.example"));
+        assert!(parsed.aligned_dkim_signature().unwrap().contains("d=corp.example"));
+
+        let unaligned = EmailVerifier::parse_email(&eml.replace("mail.corp.example", "other.example")).unwrap();
+        assert_eq!(unaligned.dkim_domain.as_deref(), Some("lists.example.org"));
+        match unaligned.aligned_dkim_signature().unwrap_err() {
+            EmailError::DomainMismatch { dkim, from } => {
+                assert_eq!(dkim, "lists.example.org, corp.example");
+                assert_eq!(from, "other.example");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        // "xcorp.example" is not a subdomain of "corp.example"
+        let lookalike = EmailVerifier::parse_email(&eml.replace("mail.corp.example", "xcorp.example")).unwrap();
+        assert!(lookalike.aligned_dkim_signature().is_err());
+    }
+
+    #[test]
+    fn test_domain_alignment_is_label_aware() {
+        assert!(EmailVerifier::domain_aligns("google.com", "google.com"));
+        assert!(EmailVerifier::domain_aligns("mail.google.com", "google.com"));
+        assert!(EmailVerifier::domain_aligns("Mail.Google.com.", "google.COM"));
+        assert!(!EmailVerifier::domain_aligns("foogoogle.com", "google.com"));
+        assert!(!EmailVerifier::domain_aligns("google.com", "mail.google.com"));
+        assert!(!EmailVerifier::domain_aligns("google.com.evil", "google.com"));
+        assert!(!EmailVerifier::domain_aligns("google.com", ""));
+
+        let eml = "\
+DKIM-Signature: v=1; a=rsa-sha256; d=google.com; s=sel; h=from; bh=x=; b=y=
+From: Eve <eve@foogoogle.com>
+
+Body
+";
+        let parsed = EmailVerifier::parse_email(eml).unwrap();
+        assert!(matches!(
+            parsed.aligned_dkim_signature(),
+            Err(EmailError::DomainMismatch { .. })
+        ));
+        let parsed = EmailVerifier::parse_email(&eml.replace("foogoogle.com", "mail.google.com")).unwrap();
+        assert!(parsed.aligned_dkim_signature().unwrap().contains("d=google.com"));
+    }
+
+    #[test]
+    fn test_missing_from_header() {
+        assert_eq!(
+            EmailVerifier::parse_email("Subject: hi\n\nbody").unwrap_err(),
+            EmailError::MissingFromHeader
+        );
+    }
+
+    #[test]
+    fn test_email_error_variants() {
+        assert!(matches!(
+            EmailVerifier::parse_email("From: nobody@localhost\n\nbody"),
+            Err(EmailError::ParseFailed(_))
+        ));
+
+        let unsigned = EmailVerifier::parse_email("From: a@example.com\n\nbody").unwrap();
+        assert_eq!(unsigned.aligned_dkim_signature().unwrap_err(), EmailError::MissingDkim);
+        assert_eq!(
+            EmailVerifier::verify_dkim("From: a@example.com\n\nbody", &ed25519_resolver()).unwrap_err(),
+            EmailError::MissingDkim
+        );
+
+        let eml = "DKIM-Signature: v=1; a=rsa-sha256; d=other.example; s=sel; h=from; bh=x=; b=y=\n\
+From: a@example.com\n\nbody";
+        assert_eq!(
+            EmailVerifier::parse_email(eml).unwrap().aligned_dkim_signature().unwrap_err(),
+            EmailError::DomainMismatch {
+                dkim: "other.example".to_string(),
+                from: "example.com".to_string(),
+            }
+        );
+
+        let no_selector = ED25519_EML.replace("s=brisbane;", "");
+        assert_eq!(
+            EmailVerifier::verify_dkim(&no_selector, &ed25519_resolver()).unwrap_err(),
+            EmailError::DkimFieldsIncomplete("s")
+        );
+    }
+}
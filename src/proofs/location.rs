@@ -1,9 +1,126 @@
-//! Country bounding boxes for location verification.
+//! Country bounding boxes and region outlines for location verification.
 //!
-//! This module provides the country database used by the ZK proof system.
+//! This module provides the country and region databases used by the ZK
+//! proof system.
 //! The actual proof generation happens in wasm.rs using the circuit.
 
+use ark_bn254::Fr;
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{location_commitment, normalize_coords, CoordError};
+
+/// A point in decimal degrees
+///
+/// Deserializing checks the range as `normalize_coords` does, so
+/// `{"latitude": 200, ...}` is a serde error rather than a failure later
+/// at proving time. `new` does not check.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CoordinatesData")]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Self { latitude, longitude }
+    }
+}
+
+/// Unchecked wire form of `Coordinates`.
+#[derive(Deserialize)]
+struct CoordinatesData {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl TryFrom<CoordinatesData> for Coordinates {
+    type Error = CoordError;
+
+    fn try_from(data: CoordinatesData) -> Result<Self, CoordError> {
+        normalize_coords(data.latitude, data.longitude)?;
+        Ok(Self::new(data.latitude, data.longitude))
+    }
+}
+
+/// Parse one coordinate into signed decimal degrees.
+///
+/// Accepts decimal degrees (`-122.4194`), degrees and decimal minutes
+/// (`37 46.493`) and degrees, minutes and seconds (`37°46'29.6"`), with
+/// `°`, `'`, `"`, `′`, `″` or whitespace between the parts. A leading
+/// sign or a leading or trailing hemisphere letter (`N`, `S`, `E`, `W`)
+/// gives the direction; south and west are negative, and giving both a
+/// sign and a hemisphere is an error. The range is not checked here; see
+/// `parse_coordinates`.
+pub fn parse_coordinate(s: &str) -> Result<f64, CoordError> {
+    parse_with_hemisphere(s).map(|(degrees, _)| degrees)
+}
+
+/// Parse a latitude and longitude in any `parse_coordinate` format.
+///
+/// Fails if either does not parse, if the latitude has an `E`/`W` or the
+/// longitude an `N`/`S` hemisphere, or if they are out of range.
+pub fn parse_coordinates(lat: &str, lng: &str) -> Result<Coordinates, CoordError> {
+    let (latitude, lat_hemisphere) = parse_with_hemisphere(lat)?;
+    if matches!(lat_hemisphere, Some('E' | 'W')) {
+        return Err(CoordError::WrongHemisphere(lat.to_string()));
+    }
+    let (longitude, lng_hemisphere) = parse_with_hemisphere(lng)?;
+    if matches!(lng_hemisphere, Some('N' | 'S')) {
+        return Err(CoordError::WrongHemisphere(lng.to_string()));
+    }
+    normalize_coords(latitude, longitude)?;
+    Ok(Coordinates::new(latitude, longitude))
+}
+
+/// Parse a coordinate and return its hemisphere letter, if it had one.
+fn parse_with_hemisphere(s: &str) -> Result<(f64, Option<char>), CoordError> {
+    let invalid = || CoordError::Unparseable(s.to_string());
+    let is_hemisphere = |c: &char| "NSEW".contains(c.to_ascii_uppercase());
+
+    let mut body = s.trim();
+    let mut hemisphere = None;
+    if let Some(c) = body.chars().last().filter(is_hemisphere) {
+        hemisphere = Some(c.to_ascii_uppercase());
+        body = body[..body.len() - 1].trim_end();
+    } else if let Some(c) = body.chars().next().filter(is_hemisphere) {
+        hemisphere = Some(c.to_ascii_uppercase());
+        body = body[1..].trim_start();
+    }
+
+    let (negative, body) = match body.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, body.strip_prefix('+').unwrap_or(body)),
+    };
+    if negative && hemisphere.is_some() {
+        return Err(invalid());
+    }
+
+    let parts: Vec<f64> = body
+        .split(|c: char| c.is_whitespace() || "°º'\"′″".contains(c))
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(invalid());
+    }
+    for (i, part) in parts.iter().enumerate() {
+        // Only the last part may be fractional; minutes and seconds are below 60
+        let fractional_too_early = i + 1 < parts.len() && part.fract() != 0.0;
+        if !part.is_finite() || *part < 0.0 || (i > 0 && *part >= 60.0) || fractional_too_early {
+            return Err(invalid());
+        }
+    }
+
+    let mut degrees = parts.iter().zip([1.0, 60.0, 3600.0]).map(|(part, scale)| part / scale).sum::<f64>();
+    if negative || matches!(hemisphere, Some('S' | 'W')) {
+        degrees = -degrees;
+    }
+    Ok((degrees, hemisphere))
+}
+
 /// Country bounding box
+#[derive(Debug)]
 pub struct CountryBounds {
     pub code: &'static str,
     pub name: &'static str,
@@ -11,11 +128,15 @@ pub struct CountryBounds {
     pub max_lat: f64,
     pub min_lng: f64,
     pub max_lng: f64,
+    /// `(latitude, longitude)` of a point known to be on land inside the
+    /// country (its capital), for proofs made without real coordinates.
+    /// The box center can fall in the sea or a neighbouring country.
+    pub representative_point: (f64, f64),
 }
 
 /// Database of country bounding boxes (approximate)
 ///
-/// Format: (code, name, min_lat, max_lat, min_lng, max_lng)
+/// Format: (code, name, min_lat, max_lat, min_lng, max_lng, representative_point)
 /// These are used to verify coordinates fall within a country's bounds
 /// before generating a ZK proof.
 pub const COUNTRIES: &[CountryBounds] = &[
@@ -26,6 +147,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 49.384358,
         min_lng: -125.0,
         max_lng: -66.93457,
+        representative_point: (38.9072, -77.0369),
     },
     CountryBounds {
         code: "GB",
@@ -34,6 +156,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 61.061,
         min_lng: -14.015517,
         max_lng: 2.0919117,
+        representative_point: (51.5074, -0.1278),
     },
     CountryBounds {
         code: "CA",
@@ -42,6 +165,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 83.23324,
         min_lng: -141.0,
         max_lng: -52.6480987209,
+        representative_point: (45.4215, -75.6972),
     },
     CountryBounds {
         code: "AU",
@@ -50,6 +174,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: -10.6681857235,
         min_lng: 113.338953078,
         max_lng: 153.569469029,
+        representative_point: (-35.2809, 149.13),
     },
     CountryBounds {
         code: "DE",
@@ -58,6 +183,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 55.0815,
         min_lng: 5.8663425,
         max_lng: 15.0419319,
+        representative_point: (52.52, 13.405),
     },
     CountryBounds {
         code: "FR",
@@ -66,6 +192,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 51.124199,
         min_lng: -5.5591,
         max_lng: 9.6625,
+        representative_point: (48.8566, 2.3522),
     },
     CountryBounds {
         code: "JP",
@@ -74,6 +201,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 45.551483,
         min_lng: 122.93457,
         max_lng: 153.986672,
+        representative_point: (35.6762, 139.6503),
     },
     CountryBounds {
         code: "IN",
@@ -82,6 +210,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 35.6745457,
         min_lng: 68.1113787,
         max_lng: 97.395561,
+        representative_point: (28.6139, 77.209),
     },
     CountryBounds {
         code: "BR",
@@ -90,6 +219,7 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 5.24448639569,
         min_lng: -73.9872354804,
         max_lng: -34.7299934555,
+        representative_point: (-15.7939, -47.8828),
     },
     CountryBounds {
         code: "CN",
@@ -98,5 +228,306 @@ pub const COUNTRIES: &[CountryBounds] = &[
         max_lat: 53.56086,
         min_lng: 73.4994136,
         max_lng: 134.7754563,
+        representative_point: (39.9042, 116.4074),
+    },
+];
+
+impl CountryBounds {
+    /// Whether the point lies inside this box (edges included)
+    pub fn contains(&self, coords: &Coordinates) -> bool {
+        coords.latitude >= self.min_lat
+            && coords.latitude <= self.max_lat
+            && coords.longitude >= self.min_lng
+            && coords.longitude <= self.max_lng
+    }
+}
+
+/// An administrative region (state, province) inside a country
+///
+/// The polygon is a simple (non-self-intersecting) ring of
+/// `(latitude, longitude)` vertices; the closing edge back to the first
+/// vertex is implicit.
+#[derive(Debug)]
+pub struct Region {
+    /// ISO 3166-2 code, e.g. "US-CA"
+    pub code: &'static str,
+    pub name: &'static str,
+    /// ISO 3166-1 code of the containing country
+    pub parent_country: &'static str,
+    pub polygon: &'static [(f64, f64)],
+}
+
+/// Database of region outlines (simplified)
+///
+/// Borders are approximated with a handful of vertices; points within a
+/// few kilometres of a border may be misclassified.
+pub const REGIONS: &[Region] = &[
+    Region {
+        code: "US-CA",
+        name: "California",
+        parent_country: "US",
+        polygon: &[
+            (42.0, -124.21),
+            (42.0, -120.0),
+            (39.0, -120.0),
+            (35.0, -114.63),
+            (34.3, -114.13),
+            (32.72, -114.72),
+            (32.53, -117.12),
+            (34.0, -118.5),
+            (34.45, -120.47),
+            (36.3, -121.9),
+            (37.5, -122.5),
+            (38.3, -123.1),
+            (40.44, -124.41),
+        ],
+    },
+    Region {
+        code: "US-NV",
+        name: "Nevada",
+        parent_country: "US",
+        polygon: &[
+            (42.0, -120.0),
+            (42.0, -114.04),
+            (36.1, -114.04),
+            (36.1, -114.75),
+            (35.0, -114.63),
+            (39.0, -120.0),
+        ],
+    },
+];
+
+impl Region {
+    /// Whether the point lies inside the polygon (even-odd rule)
+    pub fn contains(&self, coords: &Coordinates) -> bool {
+        let (lat, lng) = (coords.latitude, coords.longitude);
+        let mut inside = false;
+
+        for (i, &(lat_i, lng_i)) in self.polygon.iter().enumerate() {
+            let (lat_j, lng_j) = self.polygon[(i + 1) % self.polygon.len()];
+            if (lat_i > lat) != (lat_j > lat)
+                && lng < lng_i + (lat - lat_i) * (lng_j - lng_i) / (lat_j - lat_i)
+            {
+                inside = !inside;
+            }
+        }
+
+        inside
+    }
+
+    /// Look up a region by code (case-insensitive)
+    pub fn by_code(code: &str) -> Option<&'static Region> {
+        REGIONS.iter().find(|r| r.code.eq_ignore_ascii_case(code))
+    }
+}
+
+/// A named group of countries, e.g. the EU
+///
+/// Only members present in `COUNTRIES` are listed, so a group is
+/// incomplete until the rest of its members are added there.
+#[derive(Debug)]
+pub struct CountryGroup {
+    /// Group code, e.g. "EU"
+    pub code: &'static str,
+    pub name: &'static str,
+    /// ISO 3166-1 codes of the member countries
+    pub members: &'static [&'static str],
+}
+
+/// Database of country groups
+pub const COUNTRY_GROUPS: &[CountryGroup] = &[
+    CountryGroup {
+        code: "EU",
+        name: "European Union",
+        members: &["DE", "FR"],
+    },
+    CountryGroup {
+        code: "NA",
+        name: "North America",
+        members: &["US", "CA"],
     },
 ];
+
+impl CountryGroup {
+    /// Member countries' bounds, in `members` order
+    pub fn countries(&self) -> impl Iterator<Item = &'static CountryBounds> + '_ {
+        self.members
+            .iter()
+            .filter_map(|code| COUNTRIES.iter().find(|c| c.code == *code))
+    }
+
+    /// Whether the point lies inside any member's box
+    pub fn contains(&self, coords: &Coordinates) -> bool {
+        self.countries().any(|c| c.contains(coords))
+    }
+
+    /// Look up a group by code (case-insensitive)
+    pub fn by_code(code: &str) -> Option<&'static CountryGroup> {
+        COUNTRY_GROUPS.iter().find(|g| g.code.eq_ignore_ascii_case(code))
+    }
+}
+
+/// Looks up coordinates in the `COUNTRIES` database
+pub struct LocationVerifier;
+
+impl LocationVerifier {
+    /// First country in `COUNTRIES` whose box contains the point.
+    ///
+    /// Boxes overlap near borders, so this may not be the only match;
+    /// use `find_countries` when the caller needs to disambiguate.
+    pub fn find_country(coords: &Coordinates) -> Option<&'static CountryBounds> {
+        COUNTRIES.iter().find(|c| c.contains(coords))
+    }
+
+    /// Every country whose box contains the point, in `COUNTRIES` order
+    pub fn find_countries(coords: &Coordinates) -> Vec<&'static CountryBounds> {
+        COUNTRIES.iter().filter(|c| c.contains(coords)).collect()
+    }
+
+    /// The commitment a country proof for these coordinates would publish.
+    ///
+    /// Uses the circuit's encoding (scaled coordinates, `country_code_to_field`,
+    /// Poseidon), so it equals `CountryProofCircuit::get_commitment` for the
+    /// same inputs. Does not check that the point lies in the country.
+    pub fn commitment(coords: &Coordinates, country_code: &str, issued_at: u64) -> Result<Fr, CoordError> {
+        location_commitment(coords.latitude, coords.longitude, country_code, issued_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_single_country() {
+        let paris = Coordinates::new(48.8566, 2.3522);
+        let codes: Vec<_> = LocationVerifier::find_countries(&paris).iter().map(|c| c.code).collect();
+
+        assert_eq!(codes, ["FR"]);
+        assert_eq!(LocationVerifier::find_country(&paris).unwrap().code, "FR");
+    }
+
+    #[test]
+    fn test_representative_points_inside_own_box() {
+        for country in COUNTRIES {
+            let (lat, lng) = country.representative_point;
+            assert!(country.contains(&Coordinates::new(lat, lng)), "{} representative point outside its box", country.code);
+        }
+    }
+
+    #[test]
+    fn test_find_overlapping_countries() {
+        // Ottawa sits inside both the US and Canada boxes
+        let ottawa = Coordinates::new(45.4215, -75.6972);
+        let codes: Vec<_> = LocationVerifier::find_countries(&ottawa).iter().map(|c| c.code).collect();
+
+        assert_eq!(codes, ["US", "CA"]);
+        assert_eq!(LocationVerifier::find_country(&ottawa).unwrap().code, "US");
+    }
+
+    #[test]
+    fn test_region_polygon() {
+        let california = Region::by_code("us-ca").unwrap();
+        let nevada = Region::by_code("US-NV").unwrap();
+
+        let san_francisco = Coordinates::new(37.7749, -122.4194);
+        let las_vegas = Coordinates::new(36.1699, -115.1398);
+
+        assert!(california.contains(&san_francisco));
+        assert!(!california.contains(&las_vegas));
+        assert!(nevada.contains(&las_vegas));
+        assert!(!nevada.contains(&san_francisco));
+    }
+
+    #[test]
+    fn test_commitment_matches_circuit() {
+        use crate::circuit::{CountryProofCircuit, ScaledBounds};
+
+        let sydney = Coordinates::new(-33.8688, 151.2093);
+        let au = COUNTRIES.iter().find(|c| c.code == "AU").unwrap();
+        let bounds = ScaledBounds::new(au.min_lat, au.max_lat, au.min_lng, au.max_lng);
+        let circuit = CountryProofCircuit::new_with_witness(sydney.latitude, sydney.longitude, &bounds, "au", 3, 1_700_000_000).unwrap();
+
+        assert_eq!(Some(LocationVerifier::commitment(&sydney, "AU", 1_700_000_000).unwrap()), circuit.get_commitment());
+        assert!(LocationVerifier::commitment(&Coordinates::new(95.0, 0.0), "AU", 1_700_000_000).is_err());
+    }
+
+    #[test]
+    fn test_country_group() {
+        let eu = CountryGroup::by_code("eu").unwrap();
+        assert_eq!(eu.countries().count(), eu.members.len());
+        assert!(eu.contains(&Coordinates::new(48.8566, 2.3522)));
+        assert!(!eu.contains(&Coordinates::new(40.7128, -74.006)));
+        assert!(CountryGroup::by_code("XX").is_none());
+    }
+
+    #[test]
+    fn test_parse_coordinate_formats() {
+        let close = |s: &str, expected: f64| {
+            let parsed = parse_coordinate(s).unwrap();
+            assert!((parsed - expected).abs() < 1e-9, "{} parsed as {}", s, parsed);
+        };
+
+        // Decimal degrees, signed or with a hemisphere
+        close("37.7749", 37.7749);
+        close("+37.7749", 37.7749);
+        close("-122.4194", -122.4194);
+        close("122.4194 W", -122.4194);
+        close("S 33.8688", -33.8688);
+
+        // Degrees and decimal minutes
+        close("37 46.494", 37.0 + 46.494 / 60.0);
+
+        // Degrees, minutes, seconds
+        let sf_lat = 37.0 + 46.0 / 60.0 + 29.6 / 3600.0;
+        close("37°46'29.6\"N", sf_lat);
+        close("37° 46′ 29.6″ N", sf_lat);
+        close("37 46 29.6", sf_lat);
+        close("33°52'7.7\"S", -(33.0 + 52.0 / 60.0 + 7.7 / 3600.0));
+        close("122°25'9.8\"W", -(122.0 + 25.0 / 60.0 + 9.8 / 3600.0));
+        close("w122°25'9.8\"", -(122.0 + 25.0 / 60.0 + 9.8 / 3600.0));
+
+        for bad in ["", "N", "abc", "-37 S", "37 60 0", "37 46 61", "37.5 30", "37 46 29 1", "inf", "37 -5"] {
+            assert_eq!(parse_coordinate(bad), Err(CoordError::Unparseable(bad.to_string())), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        let sydney = parse_coordinates("33°52'7.7\"S", "151°12'33.5\"E").unwrap();
+        assert_eq!(LocationVerifier::find_country(&sydney).unwrap().code, "AU");
+
+        assert_eq!(
+            parse_coordinates("151.2 E", "33.9 S"),
+            Err(CoordError::WrongHemisphere("151.2 E".to_string()))
+        );
+        assert_eq!(
+            parse_coordinates("33.9", "33.9 S"),
+            Err(CoordError::WrongHemisphere("33.9 S".to_string()))
+        );
+        assert_eq!(parse_coordinates("91 N", "0"), Err(CoordError::LatitudeOutOfRange(91.0)));
+    }
+
+    #[test]
+    fn test_find_no_country() {
+        let mid_pacific = Coordinates::new(0.0, -160.0);
+        assert!(LocationVerifier::find_countries(&mid_pacific).is_empty());
+        assert!(LocationVerifier::find_country(&mid_pacific).is_none());
+    }
+
+    #[test]
+    fn test_deserialize_validates_range() {
+        let paris: Coordinates = serde_json::from_str(r#"{"latitude": 48.8566, "longitude": 2.3522}"#).unwrap();
+        assert_eq!(paris, Coordinates::new(48.8566, 2.3522));
+        let round_trip: Coordinates = serde_json::from_str(&serde_json::to_string(&paris).unwrap()).unwrap();
+        assert_eq!(round_trip, paris);
+
+        let edge: Coordinates = serde_json::from_str(r#"{"latitude": -90, "longitude": 180}"#).unwrap();
+        assert_eq!(edge, Coordinates::new(-90.0, 180.0));
+
+        let err = serde_json::from_str::<Coordinates>(r#"{"latitude": 200, "longitude": 0}"#).unwrap_err();
+        assert!(err.to_string().contains("Latitude 200 is outside [-90, 90]"), "{}", err);
+        assert!(serde_json::from_str::<Coordinates>(r#"{"latitude": 0, "longitude": -180.5}"#).is_err());
+        assert!(serde_json::from_str::<Coordinates>(r#"{"latitude": 0}"#).is_err());
+    }
+}
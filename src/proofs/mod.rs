@@ -1,3 +1,5 @@
-//! Country database for location proofs.
+//! Credential parsing and country database for proofs.
 
+pub mod attestation;
+pub mod email;
 pub mod location;
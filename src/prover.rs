@@ -1,14 +1,39 @@
 //! Groth16 proof generation for Merkle membership proofs.
 
 use ark_bn254::{Bn254, Fr};
-use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
 use ark_snark::SNARK;
-use ark_std::rand::{rngs::StdRng, SeedableRng};
+use ark_std::rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::marker::PhantomData;
 use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
 
-use crate::circuit::MerkleProofCircuit;
+use crate::circuit::{index_commitment, MerkleProofCircuit, ProvableCircuit};
+use crate::merkle::hash::PoseidonHasher;
 use crate::merkle::tree::{MerkleTree, MerklePath};
+use crate::verifier::Verifier;
+
+/// Conventional seed for `Prover::setup_deterministic` fixtures. Public,
+/// so keys derived from it are not secret.
+pub const DEFAULT_SETUP_SEED: u64 = 0xDEADBEEF;
+
+/// Proving RNG seeded from getrandom (the Web Crypto API in browsers).
+///
+/// Use this instead of a timestamp seed, which an observer can guess.
+/// Every setup and proof in the crate that is not given an RNG draws
+/// from this one source. Fails if the platform has no entropy source.
+pub(crate) fn secure_rng() -> ProverResult<StdRng> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|e| ProverError::Entropy(e.to_string()))?;
+    Ok(StdRng::from_seed(seed))
+}
 
 /// Result type for prover operations.
 pub type ProverResult<T> = Result<T, ProverError>;
@@ -30,54 +55,338 @@ pub enum ProverError {
 
     #[error("Leaf not found in tree")]
     LeafNotFound,
+
+    #[error("Key format mismatch: {0}")]
+    FormatMismatch(String),
+
+    #[error("Truncated input: expected at least {expected} bytes, got {actual}")]
+    Truncated { expected: usize, actual: usize },
+
+    #[error("Could not get secure random bytes: {0}")]
+    Entropy(String),
+}
+
+/// Magic bytes at the start of every serialized key file.
+pub const KEY_MAGIC: &[u8; 4] = b"ZKVK";
+
+/// Current version of the key file format.
+///
+/// Version 2 added the tree depth to Merkle verifying keys. Version 3
+/// marks circuit shape changes that make older keys unusable: the Merkle
+/// circuit's non-zero leaf constraint, the email and country circuits'
+/// `identity_commitment` input and the composite circuit's public bounds
+/// hash.
+pub const KEY_FORMAT_VERSION: u16 = 3;
+
+/// Depth field value for a Merkle verifying key of unknown depth.
+const UNKNOWN_DEPTH: u32 = u32::MAX;
+
+/// Length of the key file header: magic, version, circuit tag and key kind.
+pub const KEY_HEADER_LEN: usize = 8;
+
+/// Circuit a serialized key was generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitTag {
+    /// Merkle membership circuit.
+    Merkle,
+    /// Country location circuit.
+    Country,
+    /// Email domain circuit.
+    Email,
+    /// Combined email + country circuit.
+    Composite,
+    /// Sparse Merkle key-value circuit.
+    SparseMerkle,
+    /// Region (state / province) polygon circuit.
+    Region,
+    /// Country exclusion (outside bounding box) circuit.
+    Exclusion,
+    /// Corporate (non-freemail) email circuit.
+    CorporateEmail,
+    /// Country group ("any of N countries") circuit.
+    CountrySet,
+    /// Merkle membership circuit over Pedersen-hashed trees.
+    PedersenMerkle,
+    /// Email domain circuit committing to the received time.
+    DatedEmail,
+    /// Convex polygon containment circuit.
+    ConvexPolygon,
+    /// Email domain circuit bound to an emailed challenge.
+    ChallengeEmail,
+    /// Merkle membership circuit proving a leaf's preimage.
+    MerklePreimage,
+}
+
+impl CircuitTag {
+    fn to_byte(self) -> u8 {
+        match self {
+            CircuitTag::Merkle => 1,
+            CircuitTag::Country => 2,
+            CircuitTag::Email => 3,
+            CircuitTag::Composite => 4,
+            CircuitTag::SparseMerkle => 5,
+            CircuitTag::Region => 6,
+            CircuitTag::Exclusion => 7,
+            CircuitTag::CorporateEmail => 8,
+            CircuitTag::CountrySet => 9,
+            CircuitTag::PedersenMerkle => 10,
+            CircuitTag::DatedEmail => 11,
+            CircuitTag::ConvexPolygon => 12,
+            CircuitTag::ChallengeEmail => 13,
+            CircuitTag::MerklePreimage => 14,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(CircuitTag::Merkle),
+            2 => Some(CircuitTag::Country),
+            3 => Some(CircuitTag::Email),
+            4 => Some(CircuitTag::Composite),
+            5 => Some(CircuitTag::SparseMerkle),
+            6 => Some(CircuitTag::Region),
+            7 => Some(CircuitTag::Exclusion),
+            8 => Some(CircuitTag::CorporateEmail),
+            9 => Some(CircuitTag::CountrySet),
+            10 => Some(CircuitTag::PedersenMerkle),
+            11 => Some(CircuitTag::DatedEmail),
+            12 => Some(CircuitTag::ConvexPolygon),
+            13 => Some(CircuitTag::ChallengeEmail),
+            14 => Some(CircuitTag::MerklePreimage),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a serialized key is a proving or a verifying key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Proving,
+    Verifying,
+}
+
+impl KeyKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            KeyKind::Proving => 1,
+            KeyKind::Verifying => 2,
+        }
+    }
+}
+
+/// Build the header that prefixes a serialized key.
+pub fn encode_key_header(circuit: CircuitTag, kind: KeyKind) -> Vec<u8> {
+    let mut header = Vec::with_capacity(KEY_HEADER_LEN);
+    header.extend_from_slice(KEY_MAGIC);
+    header.extend_from_slice(&KEY_FORMAT_VERSION.to_le_bytes());
+    header.push(circuit.to_byte());
+    header.push(kind.to_byte());
+    header
+}
+
+/// Validate a key header and return the bytes following it.
+pub fn decode_key_header(
+    bytes: &[u8],
+    circuit: CircuitTag,
+    kind: KeyKind,
+) -> ProverResult<&[u8]> {
+    if bytes.len() < KEY_HEADER_LEN {
+        return Err(ProverError::Truncated {
+            expected: KEY_HEADER_LEN,
+            actual: bytes.len(),
+        });
+    }
+    if !bytes.starts_with(KEY_MAGIC) {
+        return Err(ProverError::FormatMismatch(
+            "unversioned key: missing ZKVK header".to_string(),
+        ));
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != KEY_FORMAT_VERSION {
+        return Err(ProverError::FormatMismatch(format!(
+            "unsupported key format version {} (expected {})",
+            version, KEY_FORMAT_VERSION
+        )));
+    }
+
+    match CircuitTag::from_byte(bytes[6]) {
+        Some(tag) if tag == circuit => {}
+        Some(tag) => {
+            return Err(ProverError::FormatMismatch(format!(
+                "key is for the {:?} circuit, expected {:?}",
+                tag, circuit
+            )))
+        }
+        None => {
+            return Err(ProverError::FormatMismatch(format!(
+                "unknown circuit tag {}",
+                bytes[6]
+            )))
+        }
+    }
+
+    if bytes[7] != kind.to_byte() {
+        return Err(ProverError::FormatMismatch(format!(
+            "expected a {:?} key",
+            kind
+        )));
+    }
+
+    Ok(&bytes[KEY_HEADER_LEN..])
 }
 
 /// Groth16 proof for Merkle membership.
+///
+/// Two proofs are equal when their serialized bytes are, so proofs can be
+/// deduplicated in a `HashSet`. Groth16 proofs are randomized: proving
+/// the same leaf twice gives two different proofs.
 #[derive(Clone)]
 pub struct MembershipProof {
     /// The Groth16 proof.
     pub proof: ark_groth16::Proof<Bn254>,
     /// The public input (Merkle root).
     pub public_input: Fr,
+    /// Depth of the tree the proof was made for, if known.
+    pub depth: Option<usize>,
+    /// `index_commitment` of the leaf's position, for proofs from a
+    /// prover set up with `setup_with_index_commitment`.
+    pub index_commitment: Option<Fr>,
 }
 
+/// Length of the optional depth tag after the public input.
+const DEPTH_TAG_LEN: usize = 4;
+
 impl MembershipProof {
     /// Serialize proof to bytes.
     pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_with(Compress::Yes)
+    }
+
+    /// Serialize proof to bytes using the given point encoding.
+    ///
+    /// `Compress::No` is larger but skips point decompression when loading,
+    /// which is faster for latency-sensitive verifiers. An index commitment
+    /// follows the public input, then a known depth is appended as a
+    /// little-endian `u32` tag; proofs with neither keep the original
+    /// proof-then-input layout.
+    pub fn to_bytes_with(&self, mode: Compress) -> Vec<u8> {
         let mut bytes = Vec::new();
-        self.proof.serialize_compressed(&mut bytes).unwrap();
-        self.public_input.serialize_compressed(&mut bytes).unwrap();
+        self.proof.serialize_with_mode(&mut bytes, mode).unwrap();
+        self.public_input.serialize_with_mode(&mut bytes, mode).unwrap();
+        if let Some(commitment) = self.index_commitment {
+            commitment.serialize_with_mode(&mut bytes, mode).unwrap();
+        }
+        if let Some(depth) = self.depth {
+            bytes.extend_from_slice(&(depth as u32).to_le_bytes());
+        }
         bytes
     }
 
     /// Deserialize proof from bytes.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProverError> {
-        // Deserialize proof first (variable size due to compression)
-        let proof = ark_groth16::Proof::<Bn254>::deserialize_compressed(bytes)
+        Self::from_bytes_with(bytes, Compress::Yes)
+    }
+
+    /// Deserialize proof from bytes produced by `to_bytes_with(mode)`.
+    ///
+    /// Everything is decoded with `Validate::Yes`: proof points must be on
+    /// the curve and in the prime-order subgroup, and field elements must
+    /// be canonical (below the modulus), so each proof has exactly one
+    /// accepted encoding. Anything else is a `SerializationError`.
+    pub fn from_bytes_with(bytes: &[u8], mode: Compress) -> Result<Self, ProverError> {
+        // Deserialize proof first (size depends on the encoding mode)
+        let proof = ark_groth16::Proof::<Bn254>::deserialize_with_mode(bytes, mode, Validate::Yes)
             .map_err(|e| ProverError::SerializationError(e.to_string()))?;
-        
-        // Get the size of the serialized proof
-        let mut proof_bytes = Vec::new();
-        proof.serialize_compressed(&mut proof_bytes).unwrap();
-        let proof_size = proof_bytes.len();
-        
+
         // Deserialize public input from remaining bytes
-        let public_input = Fr::deserialize_compressed(&bytes[proof_size..])
+        let proof_size = proof.serialized_size(mode);
+        let public_input = Fr::deserialize_with_mode(&bytes[proof_size..], mode, Validate::Yes)
             .map_err(|e| ProverError::SerializationError(e.to_string()))?;
-        
-        Ok(Self { proof, public_input })
+
+        // Optional index commitment, then optional depth tag
+        let field_size = public_input.serialized_size(mode);
+        let mut rest = &bytes[proof_size + field_size..];
+        let index_commitment = if rest.len() >= field_size {
+            let commitment = Fr::deserialize_with_mode(&rest[..field_size], mode, Validate::Yes)
+                .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+            rest = &rest[field_size..];
+            Some(commitment)
+        } else {
+            None
+        };
+        let depth = match rest.len() {
+            0 => None,
+            DEPTH_TAG_LEN => Some(u32::from_le_bytes(rest.try_into().unwrap()) as usize),
+            n => {
+                return Err(ProverError::SerializationError(format!(
+                    "{} unexpected bytes after the public input",
+                    n
+                )))
+            }
+        };
+
+        Ok(Self { proof, public_input, depth, index_commitment })
+    }
+
+    /// Public inputs in circuit allocation order: the root, then the index
+    /// commitment if the proof has one.
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = MerkleProofCircuit::input_fields(&self.public_input);
+        inputs.extend(self.index_commitment);
+        inputs
+    }
+
+    /// Whether the proof commits to the leaf at `leaf_index`.
+    ///
+    /// Only meaningful once the proof has verified; false for proofs
+    /// without an index commitment.
+    pub fn matches_index(&self, leaf_index: usize) -> bool {
+        self.index_commitment == Some(index_commitment(leaf_index))
     }
 
     /// Get the actual size of this proof in bytes.
     pub fn size(&self) -> usize {
         self.to_bytes().len()
     }
+
+    /// A fresh proof of the same statement, unlinkable to this one.
+    ///
+    /// Applies Groth16 re-randomization to A, B and C, so presenting the
+    /// result instead of the original leaves no byte-level fingerprint to
+    /// correlate presentations. `vk` must be the key the proof verifies
+    /// under; the result verifies under it with the same public input.
+    pub fn rerandomize<R: RngCore + CryptoRng>(&self, vk: &VerifyingKey<Bn254>, rng: &mut R) -> Self {
+        Self {
+            proof: Groth16::<Bn254>::rerandomize_proof(vk, &self.proof, rng),
+            public_input: self.public_input,
+            depth: self.depth,
+            index_commitment: self.index_commitment,
+        }
+    }
+}
+
+impl PartialEq for MembershipProof {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl Eq for MembershipProof {}
+
+impl std::hash::Hash for MembershipProof {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state);
+    }
 }
 
 /// Prover for generating Merkle membership proofs.
+///
+/// Cloning is cheap: the proving key is shared behind an `Arc`.
+#[derive(Clone)]
 pub struct Prover {
     /// Groth16 proving key.
-    proving_key: ProvingKey<Bn254>,
+    proving_key: Arc<ProvingKey<Bn254>>,
     /// Tree depth this prover was set up for.
     depth: usize,
 }
@@ -85,23 +394,87 @@ pub struct Prover {
 impl Prover {
     /// Perform trusted setup for a given tree depth.
     ///
-    /// This generates the proving and verifying keys.
-    /// In production, this should use a secure multi-party computation.
+    /// The keys are generated from OS randomness, so each call gives new
+    /// keys and the setup trapdoor is discarded. A single party still
+    /// knows the trapdoor while this runs; use `setup_from_transcript`
+    /// for keys from a multi-party ceremony.
     pub fn setup(depth: usize) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
-        // Create a dummy circuit for setup
-        let circuit = MerkleProofCircuit::new_empty(depth);
+        Self::setup_with_rng(depth, &mut secure_rng()?)
+    }
 
-        // Use a deterministic RNG for reproducibility (NOT secure for production!)
-        let mut rng = StdRng::seed_from_u64(0xDEADBEEF);
+    /// Perform trusted setup from a fixed seed, for reproducible test
+    /// fixtures only.
+    ///
+    /// The same seed always gives the same keys, and anyone who knows the
+    /// seed can forge proofs.
+    #[doc(hidden)]
+    pub fn setup_deterministic(depth: usize, seed: u64) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
+        Self::setup_with_rng(depth, &mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Perform trusted setup with caller-supplied entropy.
+    pub fn setup_with_rng<R: RngCore + CryptoRng>(
+        depth: usize,
+        rng: &mut R,
+    ) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
+        Self::setup_circuit(MerkleProofCircuit::new_empty(depth), depth, rng)
+    }
+
+    /// Perform trusted setup for proofs that also commit to the leaf index.
+    ///
+    /// Proofs from this prover carry `index_commitment`, which the keys
+    /// bind as a second public input.
+    pub fn setup_with_index_commitment<R: RngCore + CryptoRng>(
+        depth: usize,
+        rng: &mut R,
+    ) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
+        Self::setup_circuit(MerkleProofCircuit::new_empty_with_index_commitment(depth), depth, rng)
+    }
 
-        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, &mut rng)
+    fn setup_circuit<R: RngCore + CryptoRng>(
+        circuit: MerkleProofCircuit,
+        depth: usize,
+        rng: &mut R,
+    ) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
+        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng)
             .map_err(|e| ProverError::SetupFailed(e.to_string()))?;
 
-        Ok((Self { proving_key: pk, depth }, vk))
+        Ok((Self { proving_key: Arc::new(pk), depth }, vk))
+    }
+
+    /// Import keys produced by a phase-2 setup ceremony.
+    ///
+    /// ark-groth16 does not run the MPC itself; the ceremony is driven by
+    /// external tooling and its final output is exported as a compressed
+    /// arkworks `ProvingKey`, which is read here. The key's shape is
+    /// checked against the circuit for `depth` so a ceremony run for a
+    /// different circuit is rejected.
+    pub fn setup_from_transcript<R: Read>(
+        depth: usize,
+        mut reader: R,
+    ) -> ProverResult<(Self, VerifyingKey<Bn254>)> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let pk = ProvingKey::<Bn254>::deserialize_compressed(&bytes[..])
+            .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+
+        check_key_shape(&pk, MerkleProofCircuit::new_empty(depth))?;
+
+        let vk = pk.vk.clone();
+        Ok((Self { proving_key: Arc::new(pk), depth }, vk))
     }
 
     /// Generate a proof that a password hash exists in the Merkle tree.
+    ///
+    /// Fails if `leaf` is the tree's padding value, so a `with_padding`
+    /// tree's padding slots cannot be proven through this entry point.
     pub fn prove(&self, tree: &MerkleTree, leaf: &Fr) -> ProverResult<MembershipProof> {
+        if *leaf == tree.padding() {
+            return Err(ProverError::ProofGenerationFailed(
+                "Padding leaves cannot be proven".to_string(),
+            ));
+        }
+
         // Find the leaf in the tree
         let leaf_index = tree
             .find_leaf(leaf)
@@ -115,10 +488,50 @@ impl Prover {
         self.prove_with_path(&path, tree.root())
     }
 
+    /// Like `prove`, for a prover from `setup_with_index_commitment`.
+    ///
+    /// The proof's `index_commitment` commits to the leaf's position; fails
+    /// if this prover's keys have no index commitment input.
+    pub fn prove_with_index_commitment(&self, tree: &MerkleTree, leaf: &Fr) -> ProverResult<MembershipProof> {
+        if !self.commits_to_index() {
+            return Err(ProverError::ProofGenerationFailed(
+                "prover was not set up with an index commitment".to_string(),
+            ));
+        }
+        self.prove(tree, leaf)
+    }
+
+    /// Whether this prover's keys bind an index commitment.
+    ///
+    /// Read from the key's public input count, so it survives
+    /// `proving_key_bytes`.
+    pub fn commits_to_index(&self) -> bool {
+        self.proving_key.vk.gamma_abc_g1.len() == 3
+    }
+
     /// Generate a proof given a pre-computed Merkle path.
+    ///
+    /// Fails if the path's depth differs from the prover's, the path
+    /// does not lead to `root`, or its leaf is zero (a padding slot).
+    /// Only zero is checked: a path into a `with_padding` tree's padding
+    /// slot is indistinguishable from a real leaf here. Proof randomness
+    /// comes from the OS, so two proofs of the same leaf are unlinkable.
     pub fn prove_with_path(&self, path: &MerklePath, root: Fr) -> ProverResult<MembershipProof> {
+        self.prove_path_with_rng(path, root, &mut secure_rng()?)
+    }
+
+    fn prove_path_with_rng<R: RngCore + CryptoRng>(
+        &self,
+        path: &MerklePath,
+        root: Fr,
+        rng: &mut R,
+    ) -> ProverResult<MembershipProof> {
         // Create the circuit with witness values
-        let circuit = MerkleProofCircuit::new_with_witness(path, root);
+        let mut circuit = MerkleProofCircuit::new_with_witness(path, root);
+        if self.commits_to_index() {
+            circuit = circuit.with_index_commitment();
+        }
+        let index_commitment = circuit.index_commitment;
 
         // Ensure path depth matches prover setup
         if circuit.depth() != self.depth {
@@ -128,15 +541,26 @@ impl Prover {
                 self.depth
             )));
         }
+        if !path.verify(&root, &PoseidonHasher::from_config(circuit.poseidon_config.clone())) {
+            return Err(ProverError::ProofGenerationFailed(
+                "Path does not lead to the given root".to_string(),
+            ));
+        }
+        if path.leaf == Fr::from(0u64) {
+            return Err(ProverError::ProofGenerationFailed(
+                "Zero leaves are padding and cannot be proven".to_string(),
+            ));
+        }
 
         // Generate the proof
-        let mut rng = StdRng::seed_from_u64(0xCAFEBABE);
-        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, &mut rng)
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, rng)
             .map_err(|e| ProverError::ProofGenerationFailed(e.to_string()))?;
 
         Ok(MembershipProof {
             proof,
             public_input: root,
+            depth: Some(self.depth),
+            index_commitment,
         })
     }
 
@@ -145,50 +569,340 @@ impl Prover {
         self.depth
     }
 
-    /// Save proving key to file.
-    pub fn save_proving_key(&self, path: &Path) -> ProverResult<()> {
-        let mut bytes = Vec::new();
+    /// Fingerprint of the matching verifying key; see `verifying_key_fingerprint`.
+    pub fn verifying_key_fingerprint(&self) -> String {
+        verifying_key_fingerprint(&self.proving_key.vk)
+    }
+
+    /// Serialize the proving key and depth.
+    ///
+    /// Layout: key header, depth as 4 little-endian bytes, compressed key.
+    pub fn proving_key_bytes(&self) -> ProverResult<Vec<u8>> {
+        let mut bytes = encode_key_header(CircuitTag::Merkle, KeyKind::Proving);
+        bytes.extend((self.depth as u32).to_le_bytes());
         self.proving_key
             .serialize_compressed(&mut bytes)
             .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Load a prover from `proving_key_bytes` output.
+    ///
+    /// This is the client side of proving without the tree: load the key
+    /// shipped by the server, then call `prove_with_path` with a path and
+    /// root from the server. The path's depth is checked against the depth
+    /// recorded with the key.
+    pub fn from_proving_key_bytes(bytes: &[u8]) -> ProverResult<Self> {
+        let body = decode_key_header(bytes, CircuitTag::Merkle, KeyKind::Proving)?;
+
+        if body.len() < 4 {
+            return Err(ProverError::Truncated {
+                expected: KEY_HEADER_LEN + 4,
+                actual: bytes.len(),
+            });
+        }
+        let depth = u32::from_le_bytes([body[0], body[1], body[2], body[3]]) as usize;
+        let pk = ProvingKey::deserialize_compressed(&body[4..])
+            .map_err(|e| ProverError::SerializationError(e.to_string()))?;
 
-        // Prepend depth as 4 bytes
-        let mut file_bytes = (self.depth as u32).to_le_bytes().to_vec();
-        file_bytes.extend(bytes);
+        Ok(Self {
+            proving_key: Arc::new(pk),
+            depth,
+        })
+    }
 
-        std::fs::write(path, file_bytes)?;
+    /// Save proving key to file, in the `proving_key_bytes` layout.
+    pub fn save_proving_key(&self, path: &Path) -> ProverResult<()> {
+        std::fs::write(path, self.proving_key_bytes()?)?;
         Ok(())
     }
 
     /// Load proving key from file.
     pub fn load_proving_key(path: &Path) -> ProverResult<Self> {
-        let bytes = std::fs::read(path)?;
+        Self::from_proving_key_bytes(&std::fs::read(path)?)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Prover {
+    /// Generate a membership proof without blocking the async runtime.
+    ///
+    /// The path lookup runs on the calling task; the CPU-heavy Groth16
+    /// proving runs on tokio's blocking thread pool via `spawn_blocking`.
+    pub async fn prove_async(&self, tree: &MerkleTree, leaf: &Fr) -> ProverResult<MembershipProof> {
+        let leaf_index = tree
+            .find_leaf(leaf)
+            .ok_or(ProverError::LeafNotFound)?;
+        let path = tree
+            .get_path(leaf_index)
+            .ok_or(ProverError::LeafNotFound)?;
+        let root = tree.root();
+
+        let prover = self.clone();
+        tokio::task::spawn_blocking(move || prover.prove_with_path(&path, root))
+            .await
+            .map_err(|e| ProverError::ProofGenerationFailed(e.to_string()))?
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl Prover {
+    /// Generate membership proofs for many leaves across all cores.
+    ///
+    /// Results are in the same order as `leaves`. The proving key is shared
+    /// between rayon workers; each proof draws fresh randomness from
+    /// `secure_rng`, so proofs of the same leaf are unlinkable.
+    pub fn prove_many_parallel(
+        &self,
+        tree: &MerkleTree,
+        leaves: &[Fr],
+    ) -> Vec<ProverResult<MembershipProof>> {
+        use rayon::prelude::*;
+
+        let root = tree.root();
+        leaves
+            .par_iter()
+            .map(|leaf| {
+                let path = tree
+                    .find_leaf(leaf)
+                    .and_then(|index| tree.get_path(index))
+                    .ok_or(ProverError::LeafNotFound)?;
+                self.prove_path_with_rng(&path, root, &mut secure_rng()?)
+            })
+            .collect()
+    }
+}
+
+/// Membership provers and verifiers set up lazily, one pair per depth.
+///
+/// Trees of different sizes often share a depth; the first request for a
+/// depth runs setup and later ones get clones sharing the same keys. Setup
+/// is `Prover::setup`, so each cache has its own keys.
+/// Setup runs under the cache lock, so concurrent first requests for
+/// different depths wait for each other.
+#[derive(Default)]
+pub struct ProverCache {
+    entries: Mutex<HashMap<usize, (Prover, Arc<Verifier>)>>,
+}
+
+impl ProverCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The prover and verifier for `depth`, running setup on first use.
+    pub fn get_or_setup(&self, depth: usize) -> ProverResult<(Prover, Arc<Verifier>)> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((prover, verifier)) = entries.get(&depth) {
+            return Ok((prover.clone(), Arc::clone(verifier)));
+        }
+        let (prover, vk) = Prover::setup(depth)?;
+        let verifier = Arc::new(Verifier::with_depth(vk, depth));
+        entries.insert(depth, (prover.clone(), Arc::clone(&verifier)));
+        Ok((prover, verifier))
+    }
+
+    /// The prover and verifier for `tree`'s depth.
+    pub fn get_for_tree(&self, tree: &MerkleTree) -> ProverResult<(Prover, Arc<Verifier>)> {
+        self.get_or_setup(tree.depth())
+    }
+
+    /// Number of depths set up so far.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    /// Whether no depth has been set up yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Key management and proving for any `ProvableCircuit`.
+///
+/// Cloning is cheap: the proving key is shared behind an `Arc`.
+pub struct GenericProver<C: ProvableCircuit> {
+    proving_key: Arc<ProvingKey<Bn254>>,
+    verifying_key: VerifyingKey<Bn254>,
+    prepared_vk: PreparedVerifyingKey<Bn254>,
+    _circuit: PhantomData<fn() -> C>,
+}
+
+impl<C: ProvableCircuit> Clone for GenericProver<C> {
+    fn clone(&self) -> Self {
+        Self {
+            proving_key: Arc::clone(&self.proving_key),
+            verifying_key: self.verifying_key.clone(),
+            prepared_vk: self.prepared_vk.clone(),
+            _circuit: PhantomData,
+        }
+    }
+}
+
+impl<C: ProvableCircuit> GenericProver<C> {
+    /// Run trusted setup for the circuit with the given parameters.
+    pub fn setup<R: RngCore + CryptoRng>(params: &C::SetupParams, rng: &mut R) -> ProverResult<Self> {
+        let (pk, vk) = C::setup(params, rng).map_err(|e| ProverError::SetupFailed(e.to_string()))?;
+        Ok(Self::from_keys(pk, vk))
+    }
+
+    /// Build a prover from existing keys.
+    pub fn from_keys(pk: ProvingKey<Bn254>, vk: VerifyingKey<Bn254>) -> Self {
+        let prepared_vk = prepare_verifying_key(&vk);
+        Self {
+            proving_key: Arc::new(pk),
+            verifying_key: vk,
+            prepared_vk,
+            _circuit: PhantomData,
+        }
+    }
+
+    /// Prove a circuit instance, returning the proof and its public inputs.
+    pub fn prove<R: RngCore + CryptoRng>(
+        &self,
+        circuit: C,
+        rng: &mut R,
+    ) -> ProverResult<(Proof<Bn254>, C::PublicInputs)> {
+        let public_inputs = circuit.public_inputs().ok_or_else(|| {
+            ProverError::ProofGenerationFailed("circuit has no public inputs assigned".to_string())
+        })?;
+        let proof = circuit
+            .prove(&self.proving_key, rng)
+            .map_err(|e| ProverError::ProofGenerationFailed(e.to_string()))?;
+        Ok((proof, public_inputs))
+    }
+
+    /// Verify a proof against typed public inputs.
+    pub fn verify(&self, proof: &Proof<Bn254>, public_inputs: &C::PublicInputs) -> ProverResult<bool> {
+        Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &C::input_fields(public_inputs), proof)
+            .map_err(|e| ProverError::ProofGenerationFailed(e.to_string()))
+    }
+
+    /// Get the verifying key.
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.verifying_key
+    }
+
+    /// Get the prepared verifying key.
+    pub fn prepared_verifying_key(&self) -> &PreparedVerifyingKey<Bn254> {
+        &self.prepared_vk
+    }
+
+    /// Fingerprint of the verifying key; see `verifying_key_fingerprint`.
+    pub fn verifying_key_fingerprint(&self) -> String {
+        verifying_key_fingerprint(&self.verifying_key)
+    }
+
+    /// Serialize the proving key with a `C::TAG` key header.
+    pub fn proving_key_bytes(&self) -> ProverResult<Vec<u8>> {
+        let mut bytes = encode_key_header(C::TAG, KeyKind::Proving);
+        self.proving_key
+            .serialize_compressed(&mut bytes)
+            .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+        Ok(bytes)
+    }
 
-        let depth = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
-        let pk = ProvingKey::deserialize_compressed(&bytes[4..])
+    /// Serialize the verifying key with a `C::TAG` key header.
+    pub fn verifying_key_bytes(&self) -> ProverResult<Vec<u8>> {
+        let mut bytes = encode_key_header(C::TAG, KeyKind::Verifying);
+        self.verifying_key
+            .serialize_compressed(&mut bytes)
             .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+        Ok(bytes)
+    }
 
-        Ok(Self {
-            proving_key: pk,
-            depth,
-        })
+    /// Rebuild a prover from `proving_key_bytes` and `verifying_key_bytes`.
+    pub fn from_key_bytes(pk_bytes: &[u8], vk_bytes: &[u8]) -> ProverResult<Self> {
+        let pk_body = decode_key_header(pk_bytes, C::TAG, KeyKind::Proving)?;
+        let vk_body = decode_key_header(vk_bytes, C::TAG, KeyKind::Verifying)?;
+        let pk = ProvingKey::deserialize_compressed(pk_body)
+            .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+        let vk = VerifyingKey::deserialize_compressed(vk_body)
+            .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+        Ok(Self::from_keys(pk, vk))
+    }
+}
+
+/// Check that an imported proving key was generated for `circuit`.
+fn check_key_shape<C: ConstraintSynthesizer<Fr>>(pk: &ProvingKey<Bn254>, circuit: C) -> ProverResult<()> {
+    // Mirror the synthesis settings used by the Groth16 generator
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit
+        .generate_constraints(cs.clone())
+        .map_err(|e| ProverError::SetupFailed(e.to_string()))?;
+    cs.finalize();
+
+    let num_instance = cs.num_instance_variables();
+    let num_variables = num_instance + cs.num_witness_variables();
+    if pk.vk.gamma_abc_g1.len() != num_instance || pk.a_query.len() != num_variables {
+        return Err(ProverError::SetupFailed(format!(
+            "transcript key does not match circuit: expected {} inputs and {} variables, got {} and {}",
+            num_instance,
+            num_variables,
+            pk.vk.gamma_abc_g1.len(),
+            pk.a_query.len()
+        )));
     }
+    Ok(())
 }
 
-/// Save verifying key to file.
+/// Save verifying key to file, with no recorded tree depth.
+///
+/// Use `Verifier::save` to keep the depth alongside the key.
 pub fn save_verifying_key(vk: &VerifyingKey<Bn254>, path: &Path) -> ProverResult<()> {
-    let mut bytes = Vec::new();
-    vk.serialize_compressed(&mut bytes)
-        .map_err(|e| ProverError::SerializationError(e.to_string()))?;
-    std::fs::write(path, bytes)?;
+    std::fs::write(path, encode_merkle_verifying_key(vk, None)?)?;
     Ok(())
 }
 
-/// Load verifying key from file.
+/// Load verifying key from file, ignoring any recorded tree depth.
 pub fn load_verifying_key(path: &Path) -> ProverResult<VerifyingKey<Bn254>> {
     let bytes = std::fs::read(path)?;
-    VerifyingKey::deserialize_compressed(&bytes[..])
-        .map_err(|e| ProverError::SerializationError(e.to_string()))
+    decode_merkle_verifying_key(&bytes).map(|(vk, _)| vk)
+}
+
+/// Serialize a Merkle verifying key.
+///
+/// Layout: key header, depth as 4 little-endian bytes (`u32::MAX` when
+/// unknown), compressed key.
+pub(crate) fn encode_merkle_verifying_key(
+    vk: &VerifyingKey<Bn254>,
+    depth: Option<usize>,
+) -> ProverResult<Vec<u8>> {
+    let mut bytes = encode_key_header(CircuitTag::Merkle, KeyKind::Verifying);
+    bytes.extend(depth.map_or(UNKNOWN_DEPTH, |d| d as u32).to_le_bytes());
+    vk.serialize_compressed(&mut bytes)
+        .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Parse a key written by `encode_merkle_verifying_key`.
+pub(crate) fn decode_merkle_verifying_key(
+    bytes: &[u8],
+) -> ProverResult<(VerifyingKey<Bn254>, Option<usize>)> {
+    let body = decode_key_header(bytes, CircuitTag::Merkle, KeyKind::Verifying)?;
+    if body.len() < 4 {
+        return Err(ProverError::Truncated {
+            expected: KEY_HEADER_LEN + 4,
+            actual: bytes.len(),
+        });
+    }
+    let depth = u32::from_le_bytes([body[0], body[1], body[2], body[3]]);
+    let vk = VerifyingKey::deserialize_compressed(&body[4..])
+        .map_err(|e| ProverError::SerializationError(e.to_string()))?;
+    Ok((vk, (depth != UNKNOWN_DEPTH).then_some(depth as usize)))
+}
+
+/// Hex SHA-256 of the compressed verifying key.
+///
+/// Stable across runs and platforms, so a client and server can compare
+/// fingerprints to confirm they hold the same key before verifying.
+pub fn verifying_key_fingerprint(vk: &VerifyingKey<Bn254>) -> String {
+    let mut bytes = Vec::new();
+    vk.serialize_compressed(&mut bytes).unwrap();
+    hex::encode(Sha256::digest(&bytes))
 }
 
 /// Prepare verifying key for faster verification.
@@ -205,7 +919,7 @@ mod tests {
     fn test_setup_and_prove() {
         // Create a small tree
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         // Setup prover for tree depth
         let (prover, vk) = Prover::setup(tree.depth()).unwrap();
@@ -221,12 +935,86 @@ mod tests {
             .unwrap();
 
         assert!(valid);
+
+        // Fresh randomness per proof: the same leaf gives different bytes
+        let again = prover.prove(&tree, &leaf).unwrap();
+        assert_ne!(again.proof, proof.proof);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &public_inputs, &again.proof).unwrap());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_prove_many_parallel_matches_serial() {
+        let leaves: Vec<Fr> = (1..=8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let pvk = prepare_verifying_key(&vk);
+
+        let mut requested = leaves.clone();
+        requested.push(Fr::from(4u64));
+        requested.push(Fr::from(100u64));
+        let results = prover.prove_many_parallel(&tree, &requested);
+        assert_eq!(results.len(), requested.len());
+
+        for (leaf, result) in leaves.iter().zip(&results) {
+            let parallel = result.as_ref().unwrap();
+            let serial = prover.prove(&tree, leaf).unwrap();
+            assert_eq!(parallel.public_input, serial.public_input);
+            assert!(Groth16::<Bn254>::verify_with_processed_vk(
+                &pvk,
+                &[parallel.public_input],
+                &parallel.proof
+            )
+            .unwrap());
+        }
+
+        // The same leaf proven twice gets independent randomness
+        assert_ne!(results[3].as_ref().unwrap().proof, results[8].as_ref().unwrap().proof);
+        assert!(matches!(results[9], Err(ProverError::LeafNotFound)));
+    }
+
+    #[test]
+    fn test_prove_with_transmitted_path() {
+        let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+
+        // Server computes the path; client proves from the bytes alone
+        let wire = tree.get_path(6).unwrap().to_bytes();
+        let path = MerklePath::from_bytes(&wire).unwrap();
+        let proof = prover.prove_with_path(&path, tree.root()).unwrap();
+
+        let pvk = prepare_verifying_key(&vk);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &[proof.public_input], &proof.proof).unwrap());
+    }
+
+    #[test]
+    fn test_client_proves_with_loaded_key() {
+        // Server: owns the tree and the keys
+        let tree = MerkleTree::new((0..8).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let (server, vk) = Prover::setup(tree.depth()).unwrap();
+        let pk_bytes = server.proving_key_bytes().unwrap();
+        let path_bytes = tree.get_path(3).unwrap().to_bytes();
+        let root = tree.root();
+
+        // Client: only the key bytes, the path and the root
+        let client = Prover::from_proving_key_bytes(&pk_bytes).unwrap();
+        assert_eq!(client.depth(), 3);
+        let path = MerklePath::from_bytes(&path_bytes).unwrap();
+        let proof = client.prove_with_path(&path, root).unwrap();
+        assert!(crate::verifier::Verifier::new(vk).verify(&proof).unwrap());
+
+        // Wrong root, or a path from a tree of another depth, is refused
+        assert!(client.prove_with_path(&path, Fr::from(1u64)).is_err());
+        let deeper = MerkleTree::new((0..16).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let deeper_path = deeper.get_path(3).unwrap();
+        assert!(client.prove_with_path(&deeper_path, deeper.root()).is_err());
     }
 
     #[test]
     fn test_proof_serialization() {
         let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         let (prover, _vk) = Prover::setup(tree.depth()).unwrap();
         let proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
@@ -236,6 +1024,371 @@ mod tests {
         let restored = MembershipProof::from_bytes(&bytes).unwrap();
 
         assert_eq!(proof.public_input, restored.public_input);
+        assert_eq!(restored.depth, Some(tree.depth()));
+
+        // Untagged proofs keep the original layout
+        let untagged = MembershipProof { depth: None, ..proof };
+        let bytes = untagged.to_bytes();
+        assert_eq!(bytes.len() + 4, restored.to_bytes().len());
+        assert_eq!(MembershipProof::from_bytes(&bytes).unwrap().depth, None);
+
+        let mut padded = bytes;
+        padded.push(0);
+        assert!(MembershipProof::from_bytes(&padded).is_err());
+    }
+
+    #[test]
+    fn test_proof_serialization_modes() {
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+
+        let (prover, _vk) = Prover::setup(tree.depth()).unwrap();
+        let proof = prover.prove(&tree, &Fr::from(1u64)).unwrap();
+
+        let compressed = proof.to_bytes_with(Compress::Yes);
+        let uncompressed = proof.to_bytes_with(Compress::No);
+        assert_eq!(compressed, proof.to_bytes());
+        assert!(uncompressed.len() > compressed.len());
+
+        for (bytes, mode) in [(compressed, Compress::Yes), (uncompressed, Compress::No)] {
+            let restored = MembershipProof::from_bytes_with(&bytes, mode).unwrap();
+            assert_eq!(restored.proof, proof.proof);
+            assert_eq!(restored.public_input, proof.public_input);
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_public_input_rejected() {
+        use ark_ff::{BigInteger, PrimeField};
+
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let (prover, _vk) = Prover::setup(tree.depth()).unwrap();
+        let proof = MembershipProof { depth: None, ..prover.prove(&tree, &Fr::from(3u64)).unwrap() };
+
+        // The modulus and modulus + 1 would reduce to 0 and 1
+        let modulus = Fr::MODULUS.to_bytes_le();
+        let mut modulus_plus_one = modulus.clone();
+        modulus_plus_one[0] += 1;
+
+        for mode in [Compress::Yes, Compress::No] {
+            let bytes = proof.to_bytes_with(mode);
+            let input_start = bytes.len() - 32;
+            for encoding in [&modulus, &modulus_plus_one] {
+                let mut forged = bytes.clone();
+                forged[input_start..].copy_from_slice(encoding);
+                assert!(matches!(
+                    MembershipProof::from_bytes_with(&forged, mode),
+                    Err(ProverError::SerializationError(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_prove_with_index_commitment() {
+        let tree = MerkleTree::new((0..8).map(|i| Fr::from(i as u64 * 10)).collect()).unwrap();
+        let mut rng = StdRng::seed_from_u64(858);
+        let (prover, vk) = Prover::setup_with_index_commitment(tree.depth(), &mut rng).unwrap();
+        assert!(prover.commits_to_index());
+
+        let proof = prover.prove_with_index_commitment(&tree, &Fr::from(50u64)).unwrap();
+        assert_eq!(proof.index_commitment, Some(index_commitment(5)));
+        assert!(proof.matches_index(5));
+        assert!(!proof.matches_index(4));
+
+        let pvk = prepare_verifying_key(&vk);
+        let verify = |proof: &MembershipProof| {
+            Groth16::<Bn254>::verify_with_processed_vk(&pvk, &proof.public_inputs(), &proof.proof).unwrap()
+        };
+        assert!(verify(&proof));
+        let restored = MembershipProof::from_bytes(&proof.to_bytes()).unwrap();
+        assert_eq!(restored.index_commitment, proof.index_commitment);
+        assert!(verify(&restored));
+
+        // Claiming another index fails
+        let relabelled = MembershipProof { index_commitment: Some(index_commitment(4)), ..proof };
+        assert!(!verify(&relabelled));
+
+        // A plain prover cannot make index proofs
+        let (plain, _) = Prover::setup(tree.depth()).unwrap();
+        assert!(!plain.commits_to_index());
+        assert!(plain.prove_with_index_commitment(&tree, &Fr::from(50u64)).is_err());
+    }
+
+    #[test]
+    fn test_prover_cache_reuses_keys_per_depth() {
+        let cache = ProverCache::new();
+        assert!(cache.is_empty());
+
+        // 5 and 8 leaves both need depth 3
+        let small = MerkleTree::new((0..5).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let large = MerkleTree::new((0..8).map(|i| Fr::from(i as u64 + 100)).collect()).unwrap();
+        let (first, _) = cache.get_for_tree(&small).unwrap();
+        let (second, verifier) = cache.get_for_tree(&large).unwrap();
+        assert!(Arc::ptr_eq(&first.proving_key, &second.proving_key));
+        assert_eq!(cache.len(), 1);
+
+        let proof = second.prove(&large, &Fr::from(107u64)).unwrap();
+        assert!(verifier.verify(&proof).unwrap());
+        let proof = first.prove(&small, &Fr::from(4u64)).unwrap();
+        assert!(verifier.verify(&proof).unwrap());
+
+        let (other, _) = cache.get_or_setup(2).unwrap();
+        assert!(!Arc::ptr_eq(&first.proving_key, &other.proving_key));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_rerandomize_proof() {
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(55);
+        let fresh = proof.rerandomize(&vk, &mut rng);
+        assert_ne!(fresh.proof, proof.proof);
+        assert_ne!(fresh.to_bytes(), proof.to_bytes());
+        assert_eq!(fresh.public_input, proof.public_input);
+        assert_ne!(fresh.rerandomize(&vk, &mut rng).to_bytes(), fresh.to_bytes());
+
+        let pvk = prepare_verifying_key(&vk);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &[fresh.public_input], &fresh.proof).unwrap());
+        assert!(!Groth16::<Bn254>::verify_with_processed_vk(&pvk, &[Fr::from(9u64)], &fresh.proof).unwrap());
+    }
+
+    #[test]
+    fn test_proofs_dedupe_in_hash_set() {
+        use std::collections::HashSet;
+
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let (prover, _) = Prover::setup(tree.depth()).unwrap();
+        let proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+        let copy = MembershipProof::from_bytes(&proof.to_bytes()).unwrap();
+
+        let mut proofs = HashSet::new();
+        proofs.insert(proof);
+        proofs.insert(copy);
+        assert_eq!(proofs.len(), 1);
+
+        let verified = crate::VerifiedProof {
+            proof_type: crate::ProofType::Country { country_code: "FR".to_string() },
+            proof_data: vec![1, 2, 3],
+            generated_at: 1_700_000_000,
+            expires_at: None,
+            circuit_version: crate::CIRCUIT_VERSION,
+        };
+        let mut verified_proofs = HashSet::new();
+        verified_proofs.insert(verified.clone());
+        verified_proofs.insert(verified.clone());
+        assert_eq!(verified_proofs.len(), 1);
+        verified_proofs.insert(crate::VerifiedProof { expires_at: Some(1), ..verified });
+        assert_eq!(verified_proofs.len(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_prove_async_concurrently() {
+        let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+
+        let (leaf_a, leaf_b) = (Fr::from(1u64), Fr::from(6u64));
+        let (first, second) = tokio::join!(
+            prover.prove_async(&tree, &leaf_a),
+            prover.prove_async(&tree, &leaf_b),
+        );
+
+        let pvk = prepare_verifying_key(&vk);
+        for proof in [first.unwrap(), second.unwrap()] {
+            assert_eq!(proof.public_input, tree.root());
+            assert!(Groth16::<Bn254>::verify_with_processed_vk(
+                &pvk,
+                &[proof.public_input],
+                &proof.proof
+            )
+            .unwrap());
+        }
+    }
+
+    #[test]
+    fn test_generic_prover_all_circuits() {
+        use crate::circuit::{
+            CompositeCircuit, CountryProofCircuit, EmailDomainCircuit, EmailProofInput, ScaledBounds,
+        };
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let email = EmailProofInput::new("alice@google.com", "sig", "dkim=pass");
+
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let merkle = GenericProver::<MerkleProofCircuit>::setup(&tree.depth(), &mut rng).unwrap();
+        let (proof, root) = merkle
+            .prove(MerkleProofCircuit::from_tree(&tree, 1).unwrap(), &mut rng)
+            .unwrap();
+        assert_eq!(root, tree.root());
+        assert!(merkle.verify(&proof, &root).unwrap());
+
+        let country = GenericProver::<CountryProofCircuit>::setup(&(), &mut rng).unwrap();
+        let circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_000).unwrap();
+        let (proof, inputs) = country.prove(circuit, &mut rng).unwrap();
+        assert!(country.verify(&proof, &inputs).unwrap());
+
+        let email_prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let (proof, inputs) = email_prover
+            .prove(EmailDomainCircuit::new_with_witness(&email).unwrap(), &mut rng)
+            .unwrap();
+        assert!(email_prover.verify(&proof, &inputs).unwrap());
+        assert!(!email_prover.verify(&proof, &(inputs.1, inputs.0, inputs.2)).unwrap());
+
+        let composite = GenericProver::<CompositeCircuit>::setup(&(), &mut rng).unwrap();
+        let circuit = CompositeCircuit::new_with_witness(&email, 37.7749, -122.4194, &bounds, "US").unwrap();
+        let (proof, inputs) = composite.prove(circuit, &mut rng).unwrap();
+        assert!(composite.verify(&proof, &inputs).unwrap());
+    }
+
+    #[test]
+    fn test_generic_prover_key_bytes() {
+        use crate::circuit::{CountryProofCircuit, EmailDomainCircuit};
+
+        let mut rng = StdRng::seed_from_u64(8);
+        let prover = GenericProver::<CountryProofCircuit>::setup(&(), &mut rng).unwrap();
+        let pk_bytes = prover.proving_key_bytes().unwrap();
+        let vk_bytes = prover.verifying_key_bytes().unwrap();
+
+        let restored = GenericProver::<CountryProofCircuit>::from_key_bytes(&pk_bytes, &vk_bytes).unwrap();
+        assert_eq!(restored.verifying_key(), prover.verifying_key());
+
+        // Keys carry their circuit tag
+        let wrong = GenericProver::<EmailDomainCircuit>::from_key_bytes(&pk_bytes, &vk_bytes);
+        assert!(matches!(wrong, Err(ProverError::FormatMismatch(_))));
+    }
+
+    #[test]
+    fn test_setup_seeds_give_distinct_keys() {
+        let (_, vk_a) = Prover::setup_deterministic(2, 1).unwrap();
+        let (_, vk_b) = Prover::setup_deterministic(2, 2).unwrap();
+        let (_, vk_a_again) = Prover::setup_deterministic(2, 1).unwrap();
+
+        assert_ne!(vk_a, vk_b);
+        assert_eq!(vk_a, vk_a_again);
+
+        // The default setup is randomized
+        let (_, vk_random) = Prover::setup(2).unwrap();
+        let (_, vk_random_again) = Prover::setup(2).unwrap();
+        assert_ne!(vk_random, vk_random_again);
+        assert_ne!(vk_random, Prover::setup_deterministic(2, DEFAULT_SETUP_SEED).unwrap().1);
+    }
+
+    #[test]
+    fn test_setup_from_transcript() {
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(99);
+        let (ceremony, _) = Prover::setup_with_rng(tree.depth(), &mut rng).unwrap();
+        let mut transcript = Vec::new();
+        ceremony.proving_key.serialize_compressed(&mut transcript).unwrap();
+
+        let (prover, vk) = Prover::setup_from_transcript(tree.depth(), &transcript[..]).unwrap();
+        let proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+        let pvk = prepare_verifying_key(&vk);
+        assert!(Groth16::<Bn254>::verify_with_processed_vk(&pvk, &[proof.public_input], &proof.proof).unwrap());
+
+        // A key for a different depth is a different circuit
+        let result = Prover::setup_from_transcript(tree.depth() + 1, &transcript[..]);
+        assert!(matches!(result, Err(ProverError::SetupFailed(_))));
+    }
+
+    fn temp_key_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("zkvault-{}-{}.key", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_proving_key_round_trip() {
+        let (prover, _vk) = Prover::setup(2).unwrap();
+        let path = temp_key_path("pk-roundtrip");
+
+        prover.save_proving_key(&path).unwrap();
+        let loaded = Prover::load_proving_key(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.depth(), 2);
+    }
+
+    #[test]
+    fn test_load_key_wrong_magic() {
+        let path = temp_key_path("pk-magic");
+        std::fs::write(&path, b"NOPE\x01\x00\x01\x01\x02\x00\x00\x00").unwrap();
+
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(ProverError::FormatMismatch(_))));
+    }
+
+    #[test]
+    fn test_load_key_wrong_version() {
+        let path = temp_key_path("pk-version");
+        let mut bytes = KEY_MAGIC.to_vec();
+        bytes.extend(99u16.to_le_bytes());
+        bytes.extend([1, 1, 2, 0, 0, 0]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(ProverError::FormatMismatch(_))));
+    }
+
+    #[test]
+    fn test_load_key_truncated() {
+        let path = temp_key_path("pk-truncated");
+        std::fs::write(&path, &KEY_MAGIC[..]).unwrap();
+
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(ProverError::Truncated { .. })));
+    }
+
+    #[test]
+    fn test_load_two_byte_key_file() {
+        let path = temp_key_path("pk-two-bytes");
+        std::fs::write(&path, [0x01, 0x02]).unwrap();
+
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(
+            result,
+            Err(ProverError::Truncated { expected: KEY_HEADER_LEN, actual: 2 })
+        ));
+    }
+
+    #[test]
+    fn test_load_key_missing_depth() {
+        let path = temp_key_path("pk-no-depth");
+        let mut bytes = encode_key_header(CircuitTag::Merkle, KeyKind::Proving);
+        bytes.extend([0x03, 0x00]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(ProverError::Truncated { .. })));
+    }
+
+    #[test]
+    fn test_verifying_key_rejected_as_proving_key() {
+        let (_prover, vk) = Prover::setup(2).unwrap();
+        let path = temp_key_path("vk-as-pk");
+
+        save_verifying_key(&vk, &path).unwrap();
+        let result = Prover::load_proving_key(&path);
+        std::fs::remove_file(&path).ok();
+
+        assert!(matches!(result, Err(ProverError::FormatMismatch(_))));
     }
 }
 
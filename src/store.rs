@@ -0,0 +1,215 @@
+//! Caching issued proofs so clients can reuse them until they expire.
+//!
+//! A store keeps at most one proof per `ProofType`: saving a proof
+//! replaces any earlier proof of the same type. Expired proofs are never
+//! returned by `load`, and are dropped the next time the store is written.
+
+use std::path::{Path, PathBuf};
+
+use crate::{ProofType, VerifiedProof};
+
+/// Errors writing to a credential store.
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("Storage unavailable: {0}")]
+    Unavailable(String),
+}
+
+/// Persistent cache of issued proofs, keyed by proof type.
+pub trait CredentialStore {
+    /// Store a proof, replacing any earlier proof of the same type.
+    fn save(&self, vp: &VerifiedProof) -> Result<(), StoreError>;
+
+    /// Load the cached proof of this type, if there is one that has not
+    /// expired.
+    fn load(&self, proof_type: &ProofType) -> Option<VerifiedProof>;
+}
+
+/// Credential store backed by a JSON file.
+///
+/// The whole file is rewritten on every save. A missing or unreadable
+/// file is treated as an empty store.
+pub struct FileCredentialStore {
+    path: PathBuf,
+}
+
+impl FileCredentialStore {
+    /// Use the JSON file at `path`, which is created on first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_entries(&self) -> Vec<VerifiedProof> {
+        std::fs::read(&self.path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl CredentialStore for FileCredentialStore {
+    fn save(&self, vp: &VerifiedProof) -> Result<(), StoreError> {
+        let entries = upsert(self.read_entries(), vp, now_secs());
+        std::fs::write(&self.path, serde_json::to_vec(&entries)?)?;
+        Ok(())
+    }
+
+    fn load(&self, proof_type: &ProofType) -> Option<VerifiedProof> {
+        find_unexpired(self.read_entries(), proof_type, now_secs())
+    }
+}
+
+/// Credential store backed by the browser's `localStorage`.
+///
+/// All proofs are kept as one JSON array under a single key.
+#[cfg(feature = "wasm")]
+pub struct LocalStorageCredentialStore {
+    key: String,
+}
+
+#[cfg(feature = "wasm")]
+impl LocalStorageCredentialStore {
+    /// Use the `localStorage` entry named `key`.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+
+    fn storage() -> Result<web_sys::Storage, StoreError> {
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .ok_or_else(|| StoreError::Unavailable("localStorage is not available".to_string()))
+    }
+
+    fn read_entries(&self) -> Vec<VerifiedProof> {
+        Self::storage()
+            .ok()
+            .and_then(|s| s.get_item(&self.key).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl CredentialStore for LocalStorageCredentialStore {
+    fn save(&self, vp: &VerifiedProof) -> Result<(), StoreError> {
+        let storage = Self::storage()?;
+        let entries = upsert(self.read_entries(), vp, now_secs());
+        storage
+            .set_item(&self.key, &serde_json::to_string(&entries)?)
+            .map_err(|e| StoreError::Unavailable(format!("{:?}", e)))
+    }
+
+    fn load(&self, proof_type: &ProofType) -> Option<VerifiedProof> {
+        find_unexpired(self.read_entries(), proof_type, now_secs())
+    }
+}
+
+/// Replace the entry of the same type and drop expired entries.
+fn upsert(mut entries: Vec<VerifiedProof>, vp: &VerifiedProof, now: u64) -> Vec<VerifiedProof> {
+    entries.retain(|e| e.proof_type != vp.proof_type && !e.is_expired(now));
+    entries.push(vp.clone());
+    entries
+}
+
+fn find_unexpired(entries: Vec<VerifiedProof>, proof_type: &ProofType, now: u64) -> Option<VerifiedProof> {
+    entries
+        .into_iter()
+        .find(|e| &e.proof_type == proof_type && !e.is_expired(now))
+}
+
+/// Current Unix time in seconds.
+#[cfg(target_arch = "wasm32")]
+fn now_secs() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+/// Current Unix time in seconds.
+#[cfg(not(target_arch = "wasm32"))]
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(proof_type: ProofType, expires_at: Option<u64>) -> VerifiedProof {
+        VerifiedProof {
+            proof_type,
+            proof_data: vec![1, 2, 3],
+            generated_at: 1_760_000_000,
+            expires_at,
+            circuit_version: crate::CIRCUIT_VERSION,
+        }
+    }
+
+    fn email(domain: &str) -> ProofType {
+        ProofType::EmailDomain {
+            domain: domain.to_string(),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("zk-vault-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let path = temp_path("store-round-trip");
+        let store = FileCredentialStore::new(&path);
+        assert!(store.load(&email("google.com")).is_none());
+
+        store.save(&proof(email("google.com"), None)).unwrap();
+        let country = ProofType::Country {
+            country_code: "US".to_string(),
+        };
+        store.save(&proof(country.clone(), Some(u64::MAX))).unwrap();
+
+        // Saving the same type again replaces the earlier proof
+        let mut newer = proof(email("google.com"), None);
+        newer.proof_data = vec![9];
+        store.save(&newer).unwrap();
+
+        assert_eq!(store.load(&email("google.com")).unwrap().proof_data, vec![9]);
+        assert_eq!(store.load(&country).unwrap().expires_at, Some(u64::MAX));
+        assert!(store.load(&email("meta.com")).is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_skips_expired() {
+        let path = temp_path("store-expiry");
+        let store = FileCredentialStore::new(&path);
+
+        store.save(&proof(email("google.com"), Some(1))).unwrap();
+        assert!(store.load(&email("google.com")).is_none());
+
+        // Expired entries are dropped when the store is next written
+        store.save(&proof(email("meta.com"), None)).unwrap();
+        let raw: Vec<VerifiedProof> = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(raw.len(), 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_is_expired() {
+        assert!(!proof(email("a.com"), None).is_expired(u64::MAX));
+        assert!(!proof(email("a.com"), Some(100)).is_expired(99));
+        assert!(proof(email("a.com"), Some(100)).is_expired(100));
+    }
+}
@@ -0,0 +1,348 @@
+//! One-stop API for proving and verifying email and location claims.
+//!
+//! `ZkVault` holds the keys for the email domain and country circuits and
+//! goes from a raw credential (an .eml file, a coordinate pair) to a
+//! `VerifiedProof` in one call, so callers need not wire up the parser,
+//! proof input, circuit and prover themselves.
+//!
+//! `proof_data` is the compressed Groth16 proof followed by the circuit's
+//! `input_fields` as canonical 32-byte field elements, as in `compact`.
+
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::circuit::{
+    normalize_domain, string_to_field, CoordError, CountryProofCircuit, DomainSource, EmailDomainCircuit, EmailInputError,
+    EmailProofInput, ProvableCircuit, ScaledBounds,
+};
+use crate::merkle::hash::{field_from_canonical_bytes, field_to_canonical_bytes};
+use crate::proofs::email::{DkimKeyResolver, EmailError, EmailVerifier};
+use crate::proofs::location::{Coordinates, LocationVerifier};
+use crate::prover::{secure_rng, GenericProver, ProverError};
+use crate::verifier::{check_circuit_version, check_country_id, VerifierResult};
+use crate::{ProofType, VerifiedProof, CIRCUIT_VERSION};
+
+/// Result type for vault operations.
+pub type VaultResult<T> = Result<T, VaultError>;
+
+/// Errors that can occur proving through the vault.
+#[derive(Debug, thiserror::Error)]
+pub enum VaultError {
+    #[error(transparent)]
+    Email(#[from] EmailError),
+
+    #[error(transparent)]
+    EmailInput(#[from] EmailInputError),
+
+    #[error(transparent)]
+    Coordinates(#[from] CoordError),
+
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+
+    #[error("Coordinates not within any supported country")]
+    UnsupportedLocation,
+}
+
+/// Keys for every circuit the vault proves with.
+pub struct ZkVault {
+    email: GenericProver<EmailDomainCircuit>,
+    country: GenericProver<CountryProofCircuit>,
+}
+
+impl ZkVault {
+    /// Run a fresh setup for every circuit with OS randomness.
+    pub fn new() -> VaultResult<Self> {
+        Self::setup(&mut secure_rng()?)
+    }
+
+    /// Run a fresh setup for every circuit.
+    pub fn setup<R: RngCore + CryptoRng>(rng: &mut R) -> VaultResult<Self> {
+        Ok(Self {
+            email: GenericProver::setup(&(), rng)?,
+            country: GenericProver::setup(&(), rng)?,
+        })
+    }
+
+    /// Use existing provers, e.g. from `GenericProver::from_key_bytes`.
+    pub fn from_provers(
+        email: GenericProver<EmailDomainCircuit>,
+        country: GenericProver<CountryProofCircuit>,
+    ) -> Self {
+        Self { email, country }
+    }
+
+    /// The email domain prover.
+    pub fn email_prover(&self) -> &GenericProver<EmailDomainCircuit> {
+        &self.email
+    }
+
+    /// The country prover.
+    pub fn country_prover(&self) -> &GenericProver<CountryProofCircuit> {
+        &self.country
+    }
+
+    /// Parse a raw .eml file and prove its DKIM signing domain.
+    ///
+    /// The signature must align with the From domain and is verified
+    /// against the key `resolver` returns, as in
+    /// `EmailVerifier::verify_dkim`. The message's own
+    /// `Authentication-Results` header is not trusted.
+    pub fn prove_email(&self, eml: &str, resolver: &impl DkimKeyResolver) -> VaultResult<VerifiedProof> {
+        self.prove_email_with(eml, resolver, &mut secure_rng()?)
+    }
+
+    /// `prove_email` with the given randomness.
+    pub fn prove_email_with<R: RngCore + CryptoRng>(
+        &self,
+        eml: &str,
+        resolver: &impl DkimKeyResolver,
+        rng: &mut R,
+    ) -> VaultResult<VerifiedProof> {
+        let parsed = EmailVerifier::parse_email(eml)?;
+        let dkim_signature = parsed.aligned_dkim_signature()?.to_string();
+        let domain = EmailVerifier::verify_dkim(eml, resolver)?;
+
+        let address = EmailVerifier::extract_address(&parsed.from)
+            .unwrap_or_else(|| format!("user@{}", parsed.from_domain));
+        let input = EmailProofInput::builder()
+            .email(&address)
+            .dkim_domain(&domain)
+            .dkim_signature(&dkim_signature)
+            .auth_results("dkim=pass")
+            .domain_source(DomainSource::Dkim)
+            .build()?;
+
+        let circuit = EmailDomainCircuit::new_with_witness(&input)?;
+        let (proof, inputs) = self.email.prove(circuit, rng)?;
+        Ok(VerifiedProof {
+            proof_type: ProofType::EmailDomain { domain: input.domain },
+            proof_data: encode_proof_data(&proof, &EmailDomainCircuit::input_fields(&inputs)),
+            generated_at: now_secs(),
+            expires_at: None,
+            circuit_version: CIRCUIT_VERSION,
+        })
+    }
+
+    /// Prove which country `coords` are in.
+    ///
+    /// The proof is scoped to epoch 0 and issued now. Coordinates that
+    /// fall in several country boxes are proven for the first match.
+    pub fn prove_location(&self, coords: Coordinates) -> VaultResult<VerifiedProof> {
+        self.prove_location_with(coords, &mut secure_rng()?)
+    }
+
+    /// `prove_location` with the given randomness.
+    pub fn prove_location_with<R: RngCore + CryptoRng>(
+        &self,
+        coords: Coordinates,
+        rng: &mut R,
+    ) -> VaultResult<VerifiedProof> {
+        let country = LocationVerifier::find_country(&coords).ok_or(VaultError::UnsupportedLocation)?;
+        let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
+        let issued_at = now_secs();
+        let circuit =
+            CountryProofCircuit::new_with_witness(coords.latitude, coords.longitude, &bounds, country.code, 0, issued_at)?;
+
+        let (proof, inputs) = self.country.prove(circuit, rng)?;
+        Ok(VerifiedProof {
+            proof_type: ProofType::Country {
+                country_code: country.code.to_string(),
+            },
+            proof_data: encode_proof_data(&proof, &CountryProofCircuit::input_fields(&inputs)),
+            generated_at: issued_at,
+            expires_at: None,
+            circuit_version: CIRCUIT_VERSION,
+        })
+    }
+
+    /// Verify a proof from `prove_email` or `prove_location`.
+    ///
+    /// Checks that the proof is for the domain or country its
+    /// `proof_type` names and has not expired. A country proof must also
+    /// have been issued at its `generated_at`. See `try_verify` to tell
+    /// an unsupported circuit version from a bad proof.
+    pub fn verify(&self, vp: &VerifiedProof) -> bool {
+        self.try_verify(vp).unwrap_or(false)
+    }
+
+    /// Like `verify`, but fails with `VerifierError::VersionMismatch` if
+    /// the proof's `circuit_version` is not one this build verifies.
+    pub fn try_verify(&self, vp: &VerifiedProof) -> VerifierResult<bool> {
+        check_circuit_version(vp.circuit_version)?;
+        if vp.is_expired(now_secs()) {
+            return Ok(false);
+        }
+        Ok(match &vp.proof_type {
+            ProofType::EmailDomain { domain } => match decode_proof_data(&vp.proof_data, 3) {
+                Some((proof, inputs)) => {
+                    inputs[0] == string_to_field(&normalize_domain(domain))
+                        && self.email.verify(&proof, &(inputs[0], inputs[1], inputs[2])).unwrap_or(false)
+                }
+                None => false,
+            },
+            ProofType::Country { country_code } => match decode_proof_data(&vp.proof_data, 6) {
+                Some((proof, inputs)) => {
+                    check_country_id(&inputs[0], Some(country_code)).is_ok()
+                        && inputs[4] == Fr::from(vp.generated_at)
+                        && self
+                            .country
+                            .verify(&proof, &(inputs[0], inputs[1], inputs[2], inputs[3], inputs[4], inputs[5]))
+                            .unwrap_or(false)
+                }
+                None => false,
+            },
+        })
+    }
+}
+
+/// Pack a proof and its input fields into `proof_data`.
+fn encode_proof_data(proof: &Proof<Bn254>, inputs: &[Fr]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    proof.serialize_compressed(&mut bytes).expect("serializing to a Vec does not fail");
+    for input in inputs {
+        bytes.extend_from_slice(&field_to_canonical_bytes(input));
+    }
+    bytes
+}
+
+/// Unpack `proof_data` holding exactly `num_inputs` input fields.
+fn decode_proof_data(bytes: &[u8], num_inputs: usize) -> Option<(Proof<Bn254>, Vec<Fr>)> {
+    let mut reader = bytes;
+    let proof = Proof::<Bn254>::deserialize_compressed(&mut reader).ok()?;
+    if reader.len() != num_inputs * 32 {
+        return None;
+    }
+    let inputs = reader
+        .chunks(32)
+        .map(|chunk| field_from_canonical_bytes(chunk).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some((proof, inputs))
+}
+
+/// Seconds since the Unix epoch.
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proofs::email::StaticKeyResolver;
+    use crate::verifier::VerifierError;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    /// Signed sample from RFC 8463 Appendix A.
+    const SIGNED_EML: &str = "\
+DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed;
+ d=football.example.com; i=@football.example.com;
+ q=dns/txt; s=brisbane; t=1528637909; h=from : to :
+ subject : date : message-id : from : subject : date;
+ bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;
+ b=/gCrinpcQOoIfuHNQIbq4pgh9kyIK3AQUdt9OdqQehSwhEIug4D11Bus
+ Fa3bT3FY5OsU7ZbnKELq+eXdp1Q1Dw==
+From: Joe SixPack <joe@football.example.com>
+To: Suzie Q <suzie@shopping.example.net>
+Subject: Is dinner ready?
+Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)
+Message-ID: <20030712040037.46341.5F8J@football.example.com>
+
+Hi.
+
+We lost the game.  Are you hungry yet?
+
+Joe.
+";
+
+    /// Claims `dkim=pass` for itself but carries no valid signature.
+    const FORGED_EML: &str = "\
+From: Alice <alice@google.com>
+To: bob@example.com
+Subject: Hello
+DKIM-Signature: v=1; a=rsa-sha256; c=relaxed/relaxed; d=google.com; s=20230601;
+ h=from:to:subject; bh=abc=; b=def=
+Authentication-Results: mx.example.com;
+ dkim=pass header.i=@google.com header.s=20230601
+
+Hi Bob
+";
+
+    #[test]
+    fn test_prove_and_verify_through_vault() {
+        let mut rng = StdRng::seed_from_u64(862);
+        let vault = ZkVault::setup(&mut rng).unwrap();
+
+        let mut resolver = StaticKeyResolver::new();
+        resolver.insert(
+            "brisbane._domainkey.football.example.com",
+            "v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=",
+        );
+
+        let email = vault.prove_email_with(SIGNED_EML, &resolver, &mut rng).unwrap();
+        assert_eq!(email.proof_type, ProofType::EmailDomain { domain: "football.example.com".to_string() });
+        assert!(vault.verify(&email));
+        // The label is compared in canonical form, as the prover hashed it
+        let fqdn = VerifiedProof {
+            proof_type: ProofType::EmailDomain { domain: "Football.Example.com.".to_string() },
+            ..email.clone()
+        };
+        assert!(vault.verify(&fqdn));
+
+        // Relabelled, truncated or expired proofs are rejected
+        let relabelled = VerifiedProof {
+            proof_type: ProofType::EmailDomain { domain: "apple.com".to_string() },
+            ..email.clone()
+        };
+        assert!(!vault.verify(&relabelled));
+        let truncated = VerifiedProof { proof_data: email.proof_data[..100].to_vec(), ..email.clone() };
+        assert!(!vault.verify(&truncated));
+        assert!(!vault.verify(&VerifiedProof { expires_at: Some(1), ..email.clone() }));
+
+        // The signature is checked, not the message's own verdict
+        assert!(matches!(
+            vault.prove_email_with(FORGED_EML, &resolver, &mut rng),
+            Err(VaultError::Email(EmailError::BodyHashMismatch))
+        ));
+        let tampered = SIGNED_EML.replace("hungry", "thirsty");
+        assert!(vault.prove_email_with(&tampered, &resolver, &mut rng).is_err());
+        assert!(matches!(
+            vault.prove_email_with(SIGNED_EML, &StaticKeyResolver::new(), &mut rng),
+            Err(VaultError::Email(EmailError::InvalidKey(_)))
+        ));
+
+        let location = vault.prove_location_with(Coordinates::new(37.7749, -122.4194), &mut rng).unwrap();
+        assert_eq!(location.proof_type, ProofType::Country { country_code: "US".to_string() });
+        assert!(vault.verify(&location));
+
+        let relabelled = VerifiedProof {
+            proof_type: ProofType::Country { country_code: "CA".to_string() },
+            ..location.clone()
+        };
+        assert!(!vault.verify(&relabelled));
+        assert!(!vault.verify(&VerifiedProof { generated_at: location.generated_at + 1, ..location.clone() }));
+
+        // Proofs from an unknown circuit version are rejected by name
+        let future = VerifiedProof { circuit_version: CIRCUIT_VERSION + 1, ..location.clone() };
+        assert!(!vault.verify(&future));
+        assert!(matches!(
+            vault.try_verify(&future),
+            Err(VerifierError::VersionMismatch { found, .. }) if found == CIRCUIT_VERSION + 1
+        ));
+        // As are version 1 proofs, made before identity commitments
+        let legacy = VerifiedProof { circuit_version: 1, ..location.clone() };
+        assert!(matches!(vault.try_verify(&legacy), Err(VerifierError::VersionMismatch { found: 1, .. })));
+        assert!(vault.try_verify(&location).unwrap());
+
+        // An email proof does not pass as a country proof
+        assert!(!vault.verify(&VerifiedProof { proof_type: location.proof_type.clone(), ..email }));
+
+        assert!(matches!(
+            vault.prove_location_with(Coordinates::new(0.5, -160.0), &mut rng),
+            Err(VaultError::UnsupportedLocation)
+        ));
+    }
+}
@@ -1,12 +1,26 @@
-//! Groth16 proof verification for Merkle membership proofs.
+//! Groth16 proof verification for Merkle membership proofs, plus
+//! native verification of email and country proofs.
 
 use ark_bn254::{Bn254, Fr};
-use ark_groth16::{Groth16, PreparedVerifyingKey, VerifyingKey};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_ff::PrimeField;
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
 use ark_snark::SNARK;
+use std::collections::HashMap;
+use std::io::BufRead;
 use std::path::Path;
 
-use crate::prover::MembershipProof;
+use crate::circuit::{
+    country_code_to_field, normalize_domain, string_to_field, CountryProofCircuit, DatedEmailCircuit, EmailDomainCircuit,
+    ProvableCircuit,
+};
+use crate::bundle::ProofBundle;
+use crate::{ProofType, CIRCUIT_VERSION, MIN_CIRCUIT_VERSION};
+use crate::proofs::location::COUNTRIES;
+use crate::prover::{
+    decode_merkle_verifying_key, encode_merkle_verifying_key, verifying_key_fingerprint, MembershipProof,
+    ProverError,
+};
 
 /// Result type for verifier operations.
 pub type VerifierResult<T> = Result<T, VerifierError>;
@@ -28,6 +42,30 @@ pub enum VerifierError {
 
     #[error("Root mismatch: proof is for a different tree")]
     RootMismatch,
+
+    #[error("Key format mismatch: {0}")]
+    FormatMismatch(String),
+
+    #[error("Country mismatch: {0}")]
+    CountryMismatch(String),
+
+    #[error("Domain mismatch: {0}")]
+    DomainMismatch(String),
+
+    #[error("Verifying key mismatch: client has {client}, server has {server}")]
+    KeyMismatch { client: String, server: String },
+
+    #[error("Stale proof: {0}")]
+    StaleProof(String),
+
+    #[error("Depth mismatch: proof is for depth {expected}, verifying key is for depth {actual}")]
+    DepthMismatch { expected: usize, actual: usize },
+
+    #[error("No verifying key registered for depth {0}")]
+    UnknownDepth(usize),
+
+    #[error("Circuit version mismatch: proof is version {found}, this build verifies {min} to {max}")]
+    VersionMismatch { found: u16, min: u16, max: u16 },
 }
 
 /// Verifier for Merkle membership proofs.
@@ -36,23 +74,44 @@ pub struct Verifier {
     prepared_vk: PreparedVerifyingKey<Bn254>,
     /// Original verifying key (for serialization).
     verifying_key: VerifyingKey<Bn254>,
+    /// Tree depth the key was set up for, if known.
+    depth: Option<usize>,
 }
 
 impl Verifier {
     /// Create a new verifier from a verifying key.
+    ///
+    /// The tree depth is unknown; use `with_depth` for Merkle keys so
+    /// depth mismatches can be reported.
     pub fn new(vk: VerifyingKey<Bn254>) -> Self {
         let prepared_vk = Groth16::<Bn254>::process_vk(&vk).unwrap();
         Self {
             prepared_vk,
             verifying_key: vk,
+            depth: None,
         }
     }
 
+    /// Create a verifier for a Merkle key set up for `depth`.
+    ///
+    /// Pass the depth of the `Prover` returned alongside `vk`.
+    pub fn with_depth(vk: VerifyingKey<Bn254>, depth: usize) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::new(vk)
+        }
+    }
+
+    /// Tree depth the verifying key was set up for, if known.
+    pub fn depth(&self) -> Option<usize> {
+        self.depth
+    }
+
     /// Verify a membership proof.
     ///
     /// Returns `true` if the proof is valid, `false` otherwise.
     pub fn verify(&self, proof: &MembershipProof) -> VerifierResult<bool> {
-        let public_inputs = vec![proof.public_input];
+        let public_inputs = proof.public_inputs();
 
         Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &public_inputs, &proof.proof)
             .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
@@ -70,6 +129,167 @@ impl Verifier {
         self.verify(proof)
     }
 
+    /// Verify a membership proof the caller expects to be for a tree of
+    /// `expected_depth`.
+    ///
+    /// A Groth16 proof does not record the depth it was made for, so a
+    /// proof from a different depth would otherwise just fail to verify.
+    /// This reports the mismatch instead. A verifier of unknown depth
+    /// (built with `new`) skips the check.
+    pub fn verify_at_depth(&self, proof: &MembershipProof, expected_depth: usize) -> VerifierResult<bool> {
+        match self.depth {
+            Some(actual) if actual != expected_depth => Err(VerifierError::DepthMismatch {
+                expected: expected_depth,
+                actual,
+            }),
+            _ => self.verify(proof),
+        }
+    }
+
+    /// Verify a proof against any root in a window of accepted roots.
+    ///
+    /// Pair with `MerkleTree::recent_roots` so proofs issued shortly
+    /// before a tree update remain verifiable.
+    pub fn verify_against_any(&self, proof: &MembershipProof, roots: &[Fr]) -> VerifierResult<bool> {
+        if !roots.contains(&proof.public_input) {
+            return Err(VerifierError::RootMismatch);
+        }
+
+        self.verify(proof)
+    }
+
+    /// Verify a batch of membership proofs with one pairing check.
+    ///
+    /// Fails if the batch was built for a different verifying key.
+    #[cfg(feature = "batch_verify")]
+    pub fn verify_batch(&self, batch: &crate::batch::ProofBatch) -> VerifierResult<bool> {
+        if !batch.is_for_key(&self.verifying_key) {
+            return Err(VerifierError::VerificationFailed(
+                "batch was built for a different verifying key".to_string(),
+            ));
+        }
+
+        batch
+            .verify(&self.prepared_vk)
+            .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
+    }
+
+    /// Verify an aggregate of membership proofs.
+    ///
+    /// `key` must come from the SRS the aggregate was built with. Fails if
+    /// the aggregate was built for a different verifying key.
+    #[cfg(feature = "aggregation")]
+    pub fn verify_aggregate(
+        &self,
+        aggregate: &crate::aggregation::AggregateProof,
+        key: &crate::aggregation::AggregationVerifierKey,
+    ) -> VerifierResult<bool> {
+        if !aggregate.is_for_key(&self.verifying_key) {
+            return Err(VerifierError::VerificationFailed(
+                "aggregate was built for a different verifying key".to_string(),
+            ));
+        }
+
+        Ok(aggregate.verify(&self.prepared_vk, key))
+    }
+
+    /// Verify an email domain proof for a claimed domain.
+    ///
+    /// The verifier must hold the email circuit's verifying key.
+    /// `proof_bytes` is the compressed Groth16 proof, as in the wasm
+    /// `EmailProofResult::proof_bytes`. The domain hash public input is
+    /// recomputed from `normalize_domain(domain)`, as the prover computes
+    /// it, so a proof for any other domain fails.
+    /// `identity_commitment` is the proof's last public input; see
+    /// `crate::circuit::identity_commitment`.
+    pub fn verify_email(
+        &self,
+        proof_bytes: &[u8],
+        domain: &str,
+        commitment: Fr,
+        identity_commitment: Fr,
+    ) -> VerifierResult<bool> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|e| VerifierError::InvalidProof(e.to_string()))?;
+
+        let domain_hash = string_to_field(&normalize_domain(domain));
+        let public_inputs = EmailDomainCircuit::input_fields(&(domain_hash, commitment, identity_commitment));
+        Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &public_inputs, &proof)
+            .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
+    }
+
+    /// Find which of several accepted domains an email proof is for.
+    ///
+    /// Tries each domain's hash as the public input, as `verify_email`
+    /// does, and returns the first domain (as given) that verifies, or
+    /// `None` if none does. A Groth16 proof only verifies for the domain
+    /// it was made for, so trying several cannot match the wrong one.
+    pub fn verify_email_any(
+        &self,
+        proof_bytes: &[u8],
+        commitment: Fr,
+        identity_commitment: Fr,
+        domains: &[&str],
+    ) -> VerifierResult<Option<String>> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|e| VerifierError::InvalidProof(e.to_string()))?;
+
+        for domain in domains {
+            let domain_hash = string_to_field(&normalize_domain(domain));
+            let public_inputs = EmailDomainCircuit::input_fields(&(domain_hash, commitment, identity_commitment));
+            if Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &public_inputs, &proof)
+                .map_err(|e| VerifierError::VerificationFailed(e.to_string()))?
+            {
+                return Ok(Some(domain.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Verify a dated email proof for `domain`.
+    ///
+    /// The verifier must hold the `DatedEmailCircuit` verifying key.
+    /// `email_timestamp` is the time the prover claims the email was
+    /// received; email age is not checked here, use `check_freshness` on
+    /// it to require a recent email.
+    pub fn verify_dated_email(
+        &self,
+        proof_bytes: &[u8],
+        domain: &str,
+        commitment: Fr,
+        identity_commitment: Fr,
+        email_timestamp: u64,
+    ) -> VerifierResult<bool> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|e| VerifierError::InvalidProof(e.to_string()))?;
+
+        let domain_hash = string_to_field(&normalize_domain(domain));
+        let public_inputs =
+            DatedEmailCircuit::input_fields(&(domain_hash, commitment, identity_commitment, email_timestamp));
+        Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &public_inputs, &proof)
+            .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
+    }
+
+    /// Verify a country proof and check which country it claims.
+    ///
+    /// The verifier must hold the country circuit's verifying key. Inputs
+    /// are `(country_id, commitment, epoch, nullifier, issued_at,
+    /// identity_commitment)`; see
+    /// `check_country_id` for how the id is checked. Proof age is not
+    /// checked here; use `check_freshness` on `issued_at` for that.
+    pub fn verify_country(
+        &self,
+        proof: &Proof<Bn254>,
+        inputs: &(Fr, Fr, Fr, Fr, Fr, Fr),
+        expected_country: Option<&str>,
+    ) -> VerifierResult<bool> {
+        check_country_id(&inputs.0, expected_country)?;
+
+        let public_inputs = CountryProofCircuit::input_fields(inputs);
+        Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &public_inputs, proof)
+            .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
+    }
+
     /// Verify a proof from raw bytes.
     pub fn verify_bytes(&self, proof_bytes: &[u8]) -> VerifierResult<bool> {
         let proof = MembershipProof::from_bytes(proof_bytes)
@@ -77,30 +297,276 @@ impl Verifier {
         self.verify(&proof)
     }
 
+    /// Verify a proof whose public inputs travel separately from it.
+    ///
+    /// `proof_bytes` is the compressed Groth16 proof and each entry of
+    /// `public_inputs` one compressed field element, in the circuit's
+    /// allocation order (for membership proofs, just the root). Works for
+    /// any circuit whose verifying key this verifier holds.
+    pub fn verify_parts(&self, proof_bytes: &[u8], public_inputs: &[&[u8]]) -> VerifierResult<bool> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|e| VerifierError::InvalidProof(e.to_string()))?;
+
+        // A key loaded from bytes may be degenerate, with no input terms at all
+        let expected = self.verifying_key.gamma_abc_g1.len().checked_sub(1).ok_or_else(|| {
+            VerifierError::FormatMismatch("verifying key has no public input terms".to_string())
+        })?;
+        if public_inputs.len() != expected {
+            return Err(VerifierError::InvalidProof(format!(
+                "expected {} public inputs, got {}",
+                expected,
+                public_inputs.len()
+            )));
+        }
+        let inputs = public_inputs
+            .iter()
+            .enumerate()
+            .map(|(i, bytes)| {
+                Fr::deserialize_compressed(*bytes)
+                    .map_err(|e| VerifierError::InvalidProof(format!("public input {}: {}", i, e)))
+            })
+            .collect::<VerifierResult<Vec<Fr>>>()?;
+
+        Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, &inputs, &proof)
+            .map_err(|e| VerifierError::VerificationFailed(e.to_string()))
+    }
+
+    /// Verify a `ProofBundle`.
+    ///
+    /// The bundle's key fingerprint is checked first, so a bundle made
+    /// for another key fails with `KeyMismatch` before the proof is even
+    /// decoded. The first public input must then be the domain hash or
+    /// country id named by `proof_type`, so a proof cannot be relabelled
+    /// as another domain's or country's. The rest is as `verify_parts`.
+    pub fn verify_bundle(&self, bundle: &ProofBundle) -> VerifierResult<bool> {
+        self.check_fingerprint(&bundle.vk_fingerprint)?;
+
+        let first = bundle
+            .public_inputs
+            .first()
+            .ok_or_else(|| VerifierError::InvalidProof("bundle has no public inputs".to_string()))?;
+        let first = Fr::deserialize_compressed(first.as_slice())
+            .map_err(|e| VerifierError::InvalidProof(format!("public input 0: {}", e)))?;
+        match &bundle.proof_type {
+            ProofType::EmailDomain { domain } if string_to_field(&normalize_domain(domain)) != first => {
+                return Err(VerifierError::DomainMismatch(format!("proof is not for domain {}", domain)));
+            }
+            ProofType::Country { country_code } if country_code_to_field(country_code) != first => {
+                return Err(VerifierError::CountryMismatch(format!(
+                    "proof is not for country {}",
+                    country_code.to_uppercase()
+                )));
+            }
+            _ => {}
+        }
+
+        let inputs: Vec<&[u8]> = bundle.public_inputs.iter().map(Vec::as_slice).collect();
+        self.verify_parts(&bundle.proof, &inputs)
+    }
+
+    /// Verify newline-delimited hex proofs, one result per line.
+    ///
+    /// Each line is the hex encoding of `MembershipProof::to_bytes`, i.e.
+    /// the proof followed by its public input. Yields the 1-based line
+    /// number with each result; a malformed line yields an error and the
+    /// stream carries on. Blank lines are skipped. A read error is yielded
+    /// once and ends the stream.
+    pub fn verify_stream<'a, R: BufRead + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = (usize, VerifierResult<bool>)> + 'a {
+        let mut read_failed = false;
+        reader
+            .lines()
+            .enumerate()
+            .map_while(move |(index, line)| {
+                if read_failed {
+                    return None;
+                }
+                let line_no = index + 1;
+                match line {
+                    Err(e) => {
+                        read_failed = true;
+                        Some(Some((line_no, Err(VerifierError::IoError(e)))))
+                    }
+                    Ok(line) if line.trim().is_empty() => Some(None),
+                    Ok(line) => Some(Some((line_no, self.verify_hex_line(line.trim())))),
+                }
+            })
+            .flatten()
+    }
+
+    fn verify_hex_line(&self, line: &str) -> VerifierResult<bool> {
+        let bytes = hex::decode(line).map_err(|e| VerifierError::InvalidProof(e.to_string()))?;
+        self.verify_bytes(&bytes)
+    }
+
     /// Get a reference to the verifying key.
     pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
         &self.verifying_key
     }
 
-    /// Save verifying key to file.
+    /// Hex SHA-256 of the compressed verifying key.
+    ///
+    /// Matches `Prover::verifying_key_fingerprint` for the paired prover.
+    pub fn fingerprint(&self) -> String {
+        verifying_key_fingerprint(&self.verifying_key)
+    }
+
+    /// Check a fingerprint sent by a client against this verifier's key.
+    ///
+    /// Lets a server reject a client holding a different key up front,
+    /// instead of every proof just failing to verify.
+    pub fn check_fingerprint(&self, client_fingerprint: &str) -> VerifierResult<()> {
+        let server = self.fingerprint();
+        if client_fingerprint.eq_ignore_ascii_case(&server) {
+            Ok(())
+        } else {
+            Err(VerifierError::KeyMismatch {
+                client: client_fingerprint.to_string(),
+                server,
+            })
+        }
+    }
+
+    /// Save verifying key and tree depth to file.
     pub fn save(&self, path: &Path) -> VerifierResult<()> {
-        let mut bytes = Vec::new();
-        self.verifying_key
-            .serialize_compressed(&mut bytes)
+        let bytes = encode_merkle_verifying_key(&self.verifying_key, self.depth)
             .map_err(|e| VerifierError::SerializationError(e.to_string()))?;
         std::fs::write(path, bytes)?;
         Ok(())
     }
 
-    /// Load verifier from file.
+    /// Load verifier (and its tree depth, if saved) from file.
     pub fn load(path: &Path) -> VerifierResult<Self> {
-        let bytes = std::fs::read(path)?;
-        let vk = VerifyingKey::deserialize_compressed(&bytes[..])
-            .map_err(|e| VerifierError::SerializationError(e.to_string()))?;
-        Ok(Self::new(vk))
+        Self::from_saved_bytes(&std::fs::read(path)?)
+    }
+
+    /// Build a verifier from a key file downloaded from `url`.
+    ///
+    /// The body must be a key as written by `save`, at most
+    /// `MAX_KEY_BYTES` long. With
+    /// `expected_fingerprint`, the key must also match it, so the host
+    /// serving the key cannot swap in one of its own.
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str, expected_fingerprint: Option<&str>) -> VerifierResult<Self> {
+        Self::from_url_with(url, expected_fingerprint, |url| {
+            use std::io::Read;
+
+            let response = ureq::get(url).call().map_err(std::io::Error::other)?;
+            let mut bytes = Vec::new();
+            response
+                .into_reader()
+                .take(MAX_KEY_BYTES as u64 + 1)
+                .read_to_end(&mut bytes)?;
+            if bytes.len() > MAX_KEY_BYTES {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("key is larger than {} bytes", MAX_KEY_BYTES),
+                ));
+            }
+            Ok(bytes)
+        })
+    }
+
+    /// Like `from_url`, with `fetch` downloading the key bytes.
+    ///
+    /// Lets callers bring their own HTTP client, or a canned response in
+    /// tests.
+    pub fn from_url_with<F>(url: &str, expected_fingerprint: Option<&str>, fetch: F) -> VerifierResult<Self>
+    where
+        F: FnOnce(&str) -> std::io::Result<Vec<u8>>,
+    {
+        let verifier = Self::from_saved_bytes(&fetch(url)?)?;
+        if let Some(fingerprint) = expected_fingerprint {
+            verifier.check_fingerprint(fingerprint)?;
+        }
+        Ok(verifier)
+    }
+
+    /// Parse a key written by `save`.
+    fn from_saved_bytes(bytes: &[u8]) -> VerifierResult<Self> {
+        let (vk, depth) = decode_merkle_verifying_key(bytes).map_err(|e| match e {
+            ProverError::FormatMismatch(msg) => VerifierError::FormatMismatch(msg),
+            other => VerifierError::SerializationError(other.to_string()),
+        })?;
+        Ok(Self {
+            depth,
+            ..Self::new(vk)
+        })
     }
 }
 
+/// Check a country proof's public country id.
+///
+/// With `expected_country`, the id must be that code's
+/// `country_code_to_field`. Without one, it must at least be the id of a
+/// supported country, so a proof for an arbitrary code is never accepted.
+pub(crate) fn check_country_id(country_id: &Fr, expected_country: Option<&str>) -> VerifierResult<()> {
+    match expected_country {
+        Some(code) if country_code_to_field(code) == *country_id => Ok(()),
+        Some(code) => Err(VerifierError::CountryMismatch(format!(
+            "proof is not for country {}",
+            code.to_uppercase()
+        ))),
+        None if COUNTRIES.iter().any(|c| country_code_to_field(c.code) == *country_id) => Ok(()),
+        None => Err(VerifierError::CountryMismatch(
+            "proof is for an unsupported country".to_string(),
+        )),
+    }
+}
+
+/// Check that a proof's circuit version is one this build can verify.
+///
+/// See the crate docs for the compatibility policy.
+pub fn check_circuit_version(version: u16) -> VerifierResult<()> {
+    if (MIN_CIRCUIT_VERSION..=CIRCUIT_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(VerifierError::VersionMismatch {
+            found: version,
+            min: MIN_CIRCUIT_VERSION,
+            max: CIRCUIT_VERSION,
+        })
+    }
+}
+
+/// Largest key body `Verifier::from_url` will download.
+#[cfg(feature = "http")]
+pub const MAX_KEY_BYTES: usize = 64 * 1024;
+
+/// Clock skew tolerated by `check_freshness` for timestamps in the future.
+pub const MAX_CLOCK_SKEW_SECS: u64 = 300;
+
+/// Check a proof's public `issued_at` timestamp against `now`.
+///
+/// Both are Unix seconds. Fails if the proof is more than
+/// `max_age_secs` old, or dated more than `MAX_CLOCK_SKEW_SECS` in the
+/// future (which would otherwise extend its lifetime).
+pub fn check_freshness(issued_at: &Fr, now: u64, max_age_secs: u64) -> VerifierResult<()> {
+    let bigint = issued_at.into_bigint();
+    let limbs = bigint.as_ref();
+    if limbs[1..].iter().any(|&limb| limb != 0) {
+        return Err(VerifierError::StaleProof(
+            "issue time is not a valid timestamp".to_string(),
+        ));
+    }
+    let issued_at = limbs[0];
+    if issued_at > now.saturating_add(MAX_CLOCK_SKEW_SECS) {
+        return Err(VerifierError::StaleProof(format!(
+            "issued at {}, which is in the future (now {})",
+            issued_at, now
+        )));
+    }
+    if now.saturating_sub(issued_at) > max_age_secs {
+        return Err(VerifierError::StaleProof(format!(
+            "issued at {}, more than {}s before {}",
+            issued_at, max_age_secs, now
+        )));
+    }
+    Ok(())
+}
+
 /// Verification result with additional metadata.
 #[derive(Debug, Clone)]
 pub struct VerificationReport {
@@ -125,6 +591,44 @@ impl Verifier {
     }
 }
 
+/// Verifier for Merkle proofs from trees of several depths.
+///
+/// Holds one verifying key per depth and routes each proof by the depth
+/// tag it carries (see `MembershipProof::depth`).
+#[derive(Default)]
+pub struct MultiDepthVerifier {
+    verifiers: HashMap<usize, Verifier>,
+}
+
+impl MultiDepthVerifier {
+    /// Create a verifier with no keys registered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the verifying key for trees of `depth`, replacing any
+    /// key already registered for it.
+    pub fn register(&mut self, depth: usize, vk: VerifyingKey<Bn254>) {
+        self.verifiers.insert(depth, Verifier::with_depth(vk, depth));
+    }
+
+    /// The verifier registered for `depth`, if any.
+    pub fn get(&self, depth: usize) -> Option<&Verifier> {
+        self.verifiers.get(&depth)
+    }
+
+    /// Verify a proof with the key registered for its depth.
+    ///
+    /// Fails if the proof carries no depth tag or no key is registered
+    /// for its depth.
+    pub fn verify(&self, proof: &MembershipProof) -> VerifierResult<bool> {
+        let depth = proof
+            .depth
+            .ok_or_else(|| VerifierError::InvalidProof("proof carries no depth tag".to_string()))?;
+        self.get(depth).ok_or(VerifierError::UnknownDepth(depth))?.verify(proof)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,7 +638,7 @@ mod tests {
     #[test]
     fn test_verify_valid_proof() {
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         let (prover, vk) = Prover::setup(tree.depth()).unwrap();
         let verifier = Verifier::new(vk);
@@ -146,7 +650,7 @@ mod tests {
     #[test]
     fn test_verify_with_wrong_root() {
         let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         let (prover, vk) = Prover::setup(tree.depth()).unwrap();
         let verifier = Verifier::new(vk);
@@ -160,10 +664,427 @@ mod tests {
         assert!(matches!(result, Err(VerifierError::RootMismatch)));
     }
 
+    #[test]
+    fn test_depth_mismatch() {
+        let leaves: Vec<Fr> = (0..8).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let proof = prover.prove(&tree, &Fr::from(5u64)).unwrap();
+        let (_, deeper_vk) = Prover::setup(tree.depth() + 2).unwrap();
+
+        let verifier = Verifier::with_depth(vk, prover.depth());
+        assert_eq!(verifier.depth(), Some(3));
+        assert!(verifier.verify_at_depth(&proof, tree.depth()).unwrap());
+
+        let deeper = Verifier::with_depth(deeper_vk, tree.depth() + 2);
+        assert!(matches!(
+            deeper.verify_at_depth(&proof, tree.depth()),
+            Err(VerifierError::DepthMismatch { expected: 3, actual: 5 })
+        ));
+
+        // The depth survives a save / load round trip
+        let path = std::env::temp_dir().join(format!("zk-vault-vk-depth-{}.bin", std::process::id()));
+        deeper.save(&path).unwrap();
+        let loaded = Verifier::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.depth(), Some(5));
+
+        let unknown = Verifier::new(loaded.verifying_key().clone());
+        assert_eq!(unknown.depth(), None);
+        unknown.save(&path).unwrap();
+        let loaded = Verifier::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.depth(), None);
+    }
+
+    #[test]
+    fn test_verify_against_root_window() {
+        let leaves: Vec<Fr> = (0..5).map(|i| Fr::from(i as u64)).collect();
+        let mut tree = MerkleTree::new(leaves).unwrap().with_root_history(2);
+
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let verifier = Verifier::new(vk);
+        let old_proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+
+        // One update: the old root is still inside the window
+        tree.extend_leaves(vec![Fr::from(100u64)]).unwrap();
+        assert!(verifier.verify_against_any(&old_proof, tree.recent_roots()).unwrap());
+
+        // Second update pushes the old root out
+        tree.extend_leaves(vec![Fr::from(101u64)]).unwrap();
+        let result = verifier.verify_against_any(&old_proof, tree.recent_roots());
+        assert!(matches!(result, Err(VerifierError::RootMismatch)));
+    }
+
+    #[test]
+    fn test_verify_email_for_domain() {
+        use crate::circuit::EmailProofInput;
+        use crate::prover::GenericProver;
+        use ark_serialize::CanonicalSerialize;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(13);
+        let prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let verifier = Verifier::new(prover.verifying_key().clone());
+
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let (proof, (_, commitment, identity)) = prover
+            .prove(EmailDomainCircuit::new_with_witness(&input).unwrap(), &mut rng)
+            .unwrap();
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+        assert!(verifier.verify_email(&proof_bytes, "google.com", commitment, identity).unwrap());
+        assert!(verifier.verify_email(&proof_bytes, "Google.COM", commitment, identity).unwrap());
+        assert!(verifier.verify_email(&proof_bytes, "google.com.", commitment, identity).unwrap());
+        assert!(!verifier.verify_email(&proof_bytes, "meta.com", commitment, identity).unwrap());
+        assert!(!verifier.verify_email(&proof_bytes, "google.com", Fr::from(1u64), identity).unwrap());
+        assert!(!verifier.verify_email(&proof_bytes, "google.com", commitment, Fr::from(1u64)).unwrap());
+        assert!(matches!(
+            verifier.verify_email(&proof_bytes[..10], "google.com", commitment, identity),
+            Err(VerifierError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_email_any() {
+        use crate::circuit::EmailProofInput;
+        use crate::prover::GenericProver;
+        use ark_serialize::CanonicalSerialize;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(14);
+        let prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let verifier = Verifier::new(prover.verifying_key().clone());
+
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let (proof, (_, commitment, identity)) = prover
+            .prove(EmailDomainCircuit::new_with_witness(&input).unwrap(), &mut rng)
+            .unwrap();
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+        let partners = ["meta.com", "apple.com", "google.com.", "amazon.com", "microsoft.com"];
+        assert_eq!(
+            verifier.verify_email_any(&proof_bytes, commitment, identity, &partners).unwrap(),
+            Some("google.com.".to_string())
+        );
+        assert_eq!(
+            verifier.verify_email_any(&proof_bytes, commitment, identity, &partners[..2]).unwrap(),
+            None
+        );
+        assert_eq!(
+            verifier.verify_email_any(&proof_bytes, Fr::from(1u64), identity, &partners).unwrap(),
+            None
+        );
+        assert!(matches!(
+            verifier.verify_email_any(&proof_bytes[..10], commitment, identity, &partners),
+            Err(VerifierError::InvalidProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_dated_email_with_recency() {
+        use crate::circuit::EmailProofInput;
+        use crate::proofs::email::EmailVerifier;
+        use crate::prover::GenericProver;
+        use ark_serialize::CanonicalSerialize;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let eml = "From: Alice <alice@google.com>\r\nDate: Tue, 5 Mar 2024 10:15:00 +0100\r\n\r\nWelcome aboard\r\n";
+        let received_at = EmailVerifier::parse_email(eml).unwrap().timestamp.unwrap();
+        assert_eq!(received_at, 1_709_630_100);
+
+        let mut rng = StdRng::seed_from_u64(861);
+        let prover = GenericProver::<DatedEmailCircuit>::setup(&(), &mut rng).unwrap();
+        let verifier = Verifier::new(prover.verifying_key().clone());
+
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let circuit = DatedEmailCircuit::new_with_witness(&input, received_at).unwrap();
+        let (proof, (_, commitment, identity, timestamp)) = prover.prove(circuit, &mut rng).unwrap();
+        assert_eq!(timestamp, received_at);
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+        assert!(verifier.verify_dated_email(&proof_bytes, "google.com", commitment, identity, received_at).unwrap());
+        assert!(verifier.verify_dated_email(&proof_bytes, "Google.com.", commitment, identity, received_at).unwrap());
+        // Claiming a later receipt time fails
+        assert!(!verifier
+            .verify_dated_email(&proof_bytes, "google.com", commitment, identity, received_at + 86_400)
+            .unwrap());
+
+        // Recency is checked on the proven timestamp: 30 days
+        let max_age = 30 * 86_400;
+        assert!(check_freshness(&Fr::from(received_at), received_at + 10 * 86_400, max_age).is_ok());
+        assert!(matches!(
+            check_freshness(&Fr::from(received_at), received_at + 31 * 86_400, max_age),
+            Err(VerifierError::StaleProof(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_country_checks_claimed_country() {
+        use crate::circuit::ScaledBounds;
+        use crate::prover::GenericProver;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let prover = GenericProver::<CountryProofCircuit>::setup(&(), &mut rng).unwrap();
+        let verifier = Verifier::new(prover.verifying_key().clone());
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+
+        let circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 0, 1_700_000_000).unwrap();
+        let (proof, inputs) = prover.prove(circuit, &mut rng).unwrap();
+        assert!(verifier.verify_country(&proof, &inputs, Some("us")).unwrap());
+        assert!(verifier.verify_country(&proof, &inputs, None).unwrap());
+        assert!(matches!(
+            verifier.verify_country(&proof, &inputs, Some("CA")),
+            Err(VerifierError::CountryMismatch(_))
+        ));
+
+        // A valid proof for a code that is not a supported country
+        let circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "ZZ", 0, 1_700_000_000).unwrap();
+        let (proof, inputs) = prover.prove(circuit, &mut rng).unwrap();
+        assert!(matches!(
+            verifier.verify_country(&proof, &inputs, None),
+            Err(VerifierError::CountryMismatch(_))
+        ));
+        assert!(matches!(
+            verifier.verify_country(&proof, &inputs, Some("US")),
+            Err(VerifierError::CountryMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_freshness() {
+        let issued_at = Fr::from(1_700_000_000u64);
+
+        assert!(check_freshness(&issued_at, 1_700_000_000, 60).is_ok());
+        assert!(check_freshness(&issued_at, 1_700_000_060, 60).is_ok());
+        assert!(matches!(
+            check_freshness(&issued_at, 1_700_000_061, 60),
+            Err(VerifierError::StaleProof(_))
+        ));
+
+        // Small clock skew is tolerated, a far-future date is not
+        assert!(check_freshness(&issued_at, 1_700_000_000 - MAX_CLOCK_SKEW_SECS, 60).is_ok());
+        assert!(check_freshness(&issued_at, 1_600_000_000, 60).is_err());
+        assert!(check_freshness(&-Fr::from(1u64), 1_700_000_000, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_verify_bundle() {
+        use crate::circuit::EmailProofInput;
+        use crate::prover::GenericProver;
+        use crate::ProofType;
+        use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(17);
+        let prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let verifier = Verifier::new(prover.verifying_key().clone());
+
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let (proof, inputs) = prover
+            .prove(EmailDomainCircuit::new_with_witness(&input).unwrap(), &mut rng)
+            .unwrap();
+        let bundle = ProofBundle::new(
+            ProofType::EmailDomain { domain: "google.com".to_string() },
+            &proof,
+            &EmailDomainCircuit::input_fields(&inputs),
+            verifier.fingerprint(),
+            1_760_000_000,
+        );
+
+        let parsed = ProofBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, bundle);
+        assert!(verifier.verify_bundle(&parsed).unwrap());
+
+        let mut tampered = bundle.clone();
+        tampered.public_inputs.swap(1, 2);
+        assert!(!verifier.verify_bundle(&tampered).unwrap());
+
+        // A google.com proof relabelled as another domain is rejected
+        let mut relabelled = bundle.clone();
+        relabelled.proof_type = ProofType::EmailDomain { domain: "meta.com".to_string() };
+        assert!(matches!(
+            verifier.verify_bundle(&relabelled),
+            Err(VerifierError::DomainMismatch(_))
+        ));
+        let mut relabelled = bundle.clone();
+        relabelled.proof_type = ProofType::Country { country_code: "US".to_string() };
+        assert!(matches!(
+            verifier.verify_bundle(&relabelled),
+            Err(VerifierError::CountryMismatch(_))
+        ));
+        let mut case_only = bundle.clone();
+        case_only.proof_type = ProofType::EmailDomain { domain: "Google.COM.".to_string() };
+        assert!(verifier.verify_bundle(&case_only).unwrap());
+
+        // The fingerprint is checked before the proof is decoded or paired
+        let mut other_key = bundle;
+        other_key.vk_fingerprint = "00".repeat(32);
+        other_key.proof = vec![0xff; 3];
+        assert!(matches!(
+            verifier.verify_bundle(&other_key),
+            Err(VerifierError::KeyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let (prover, vk) = Prover::setup(2).unwrap();
+        let (_, other_vk) = Prover::setup_deterministic(2, 7).unwrap();
+
+        let verifier = Verifier::new(vk.clone());
+        let same = Verifier::with_depth(vk, 2);
+        let other = Verifier::new(other_vk);
+
+        assert_eq!(verifier.fingerprint(), same.fingerprint());
+        assert_eq!(verifier.fingerprint(), prover.verifying_key_fingerprint());
+        assert_eq!(verifier.fingerprint().len(), 64);
+        assert_ne!(verifier.fingerprint(), other.fingerprint());
+
+        assert!(verifier.check_fingerprint(&prover.verifying_key_fingerprint()).is_ok());
+        assert!(matches!(
+            verifier.check_fingerprint(&other.fingerprint()),
+            Err(VerifierError::KeyMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_url_with_mock_fetcher() {
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let served = encode_merkle_verifying_key(&vk, Some(tree.depth())).unwrap();
+        let fetch = |url: &str| {
+            assert_eq!(url, "https://keys.example.com/merkle.vk");
+            Ok(served.clone())
+        };
+
+        let fingerprint = prover.verifying_key_fingerprint();
+        let verifier =
+            Verifier::from_url_with("https://keys.example.com/merkle.vk", Some(&fingerprint), fetch).unwrap();
+        assert_eq!(verifier.depth(), Some(tree.depth()));
+        let proof = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+        assert!(verifier.verify(&proof).unwrap());
+
+        let (_, other_vk) = Prover::setup_deterministic(2, 7).unwrap();
+        let other = Verifier::new(other_vk).fingerprint();
+        assert!(matches!(
+            Verifier::from_url_with("https://keys.example.com/merkle.vk", Some(&other), fetch),
+            Err(VerifierError::KeyMismatch { .. })
+        ));
+        assert!(matches!(
+            Verifier::from_url_with("https://keys.example.com/merkle.vk", None, |_| Ok(served[..40].to_vec())),
+            Err(VerifierError::SerializationError(_))
+        ));
+        assert!(matches!(
+            Verifier::from_url_with("https://keys.example.com/merkle.vk", None, |_| {
+                Err(std::io::Error::other("connection refused"))
+            }),
+            Err(VerifierError::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_stream() {
+        let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
+        let tree = MerkleTree::new(leaves).unwrap();
+
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let verifier = Verifier::new(vk);
+
+        let valid = prover.prove(&tree, &Fr::from(2u64)).unwrap();
+        let wrong_root = MembershipProof {
+            proof: valid.proof.clone(),
+            public_input: Fr::from(999u64),
+            ..valid.clone()
+        };
+        let file = format!(
+            "{}\nnot-a-proof\n\n{}\n",
+            hex::encode(valid.to_bytes()),
+            hex::encode(wrong_root.to_bytes())
+        );
+
+        let results: Vec<_> = verifier.verify_stream(file.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[0], (1, Ok(true))));
+        assert!(matches!(results[1], (2, Err(VerifierError::InvalidProof(_)))));
+        assert!(matches!(results[2], (4, Ok(false))));
+    }
+
+    #[test]
+    fn test_verify_parts() {
+        use ark_serialize::CanonicalSerialize;
+
+        let tree = MerkleTree::new((0..4).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let (prover, vk) = Prover::setup(tree.depth()).unwrap();
+        let verifier = Verifier::new(vk);
+        let proof = prover.prove(&tree, &Fr::from(1u64)).unwrap();
+
+        let mut proof_bytes = Vec::new();
+        proof.proof.serialize_compressed(&mut proof_bytes).unwrap();
+        let mut root_bytes = Vec::new();
+        tree.root().serialize_compressed(&mut root_bytes).unwrap();
+        assert!(verifier.verify_parts(&proof_bytes, &[&root_bytes]).unwrap());
+
+        let mut other_root = Vec::new();
+        Fr::from(999u64).serialize_compressed(&mut other_root).unwrap();
+        assert!(!verifier.verify_parts(&proof_bytes, &[&other_root]).unwrap());
+
+        assert!(matches!(
+            verifier.verify_parts(&proof_bytes, &[]),
+            Err(VerifierError::InvalidProof(_))
+        ));
+        assert!(matches!(
+            verifier.verify_parts(&proof_bytes, &[&root_bytes[..4]]),
+            Err(VerifierError::InvalidProof(_))
+        ));
+
+        let degenerate = Verifier::new(VerifyingKey::default());
+        assert!(matches!(
+            degenerate.verify_parts(&proof_bytes, &[]),
+            Err(VerifierError::FormatMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_multi_depth_verifier() {
+        let small = MerkleTree::new((0..8).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        let large = MerkleTree::new((0..16).map(|i| Fr::from(i as u64)).collect()).unwrap();
+        assert_eq!((small.depth(), large.depth()), (3, 4));
+
+        let (small_prover, small_vk) = Prover::setup(3).unwrap();
+        let (large_prover, large_vk) = Prover::setup(4).unwrap();
+
+        let mut verifier = MultiDepthVerifier::new();
+        verifier.register(3, small_vk);
+        verifier.register(4, large_vk);
+
+        let small_proof = small_prover.prove(&small, &Fr::from(5u64)).unwrap();
+        let large_proof = large_prover.prove(&large, &Fr::from(12u64)).unwrap();
+        for proof in [small_proof, large_proof.clone()] {
+            let restored = MembershipProof::from_bytes(&proof.to_bytes()).unwrap();
+            assert!(verifier.verify(&restored).unwrap());
+        }
+
+        // Routed by the tag, so a mislabelled proof hits the wrong key
+        let mislabelled = MembershipProof { depth: Some(3), ..large_proof.clone() };
+        assert!(!verifier.verify(&mislabelled).unwrap());
+
+        let unknown = MembershipProof { depth: Some(5), ..large_proof.clone() };
+        assert!(matches!(verifier.verify(&unknown), Err(VerifierError::UnknownDepth(5))));
+
+        let untagged = MembershipProof { depth: None, ..large_proof };
+        assert!(matches!(verifier.verify(&untagged), Err(VerifierError::InvalidProof(_))));
+    }
+
     #[test]
     fn test_verification_report() {
         let leaves: Vec<Fr> = (0..4).map(|i| Fr::from(i as u64)).collect();
-        let tree = MerkleTree::new(leaves);
+        let tree = MerkleTree::new(leaves).unwrap();
 
         let (prover, vk) = Prover::setup(tree.depth()).unwrap();
         let verifier = Verifier::new(vk);
@@ -5,45 +5,172 @@
 
 use wasm_bindgen::prelude::*;
 use ark_bn254::{Bn254, Fr};
-use ark_groth16::{Groth16, PreparedVerifyingKey, ProvingKey, VerifyingKey};
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use ark_snark::SNARK;
+use ark_groth16::PreparedVerifyingKey;
+use ark_serialize::CanonicalSerialize;
 use ark_std::rand::{rngs::StdRng, SeedableRng};
-use ark_ff::PrimeField;
-use sha2::{Digest, Sha256};
+use ark_ff::{BigInteger, PrimeField};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
 use std::sync::Mutex;
 
-use crate::circuit::{CountryProofCircuit, ScaledBounds, country_code_to_field};
-use crate::circuit::{EmailDomainCircuit, EmailProofInput};
-use crate::proofs::location::COUNTRIES;
+use crate::circuit::{CountryProofCircuit, ScaledBounds, country_code_to_field, normalize_coords};
+use crate::circuit::{normalize_domain, string_to_field, DomainSource, EmailDomainCircuit, EmailProofInput};
+use crate::circuit::{CompositeCircuit, ProvableCircuit};
+use crate::circuit::{region_code_to_field, region_polygon_hash, RegionProofCircuit};
+use crate::circuit::{exclusion_bounds_hash, ExclusionProofCircuit};
+use crate::circuit::{is_freemail_domain, CorporateEmailCircuit};
+use crate::circuit::{challenge_commitment, ChallengeEmailCircuit};
+use crate::circuit::{country_group_bounds_hash, country_group_to_field, CountrySetCircuit};
+use crate::proofs::attestation::AttestationProof;
+use crate::proofs::email::{DkimKeyResolver, EmailVerifier};
+use crate::proofs::location::{
+    parse_coordinates, Coordinates, CountryBounds, CountryGroup, LocationVerifier, Region, COUNTRIES, REGIONS,
+};
+use crate::prover::{secure_rng, GenericProver};
+use crate::wasm_verify::{
+    decode_field_or_placeholder, decode_proof_or_placeholder, field_from_hex, field_to_hex, lock_state, now_secs,
+    verify_country_proof_with,
+};
 
-// Initialize panic hook for better error messages in browser console
-#[wasm_bindgen(start)]
-pub fn init() {
-    #[cfg(feature = "console_error_panic_hook")]
-    console_error_panic_hook::set_once();
+// ============== PROVER STATE ==============
+
+static COUNTRY_PROVER: Mutex<Option<GenericProver<CountryProofCircuit>>> = Mutex::new(None);
+static EMAIL_PROVER: Mutex<Option<GenericProver<EmailDomainCircuit>>> = Mutex::new(None);
+static COMPOSITE_PROVER: Mutex<Option<GenericProver<CompositeCircuit>>> = Mutex::new(None);
+static REGION_PROVER: Mutex<Option<GenericProver<RegionProofCircuit>>> = Mutex::new(None);
+static EXCLUSION_PROVER: Mutex<Option<GenericProver<ExclusionProofCircuit>>> = Mutex::new(None);
+static CORPORATE_EMAIL_PROVER: Mutex<Option<GenericProver<CorporateEmailCircuit>>> = Mutex::new(None);
+static CHALLENGE_EMAIL_PROVER: Mutex<Option<GenericProver<ChallengeEmailCircuit>>> = Mutex::new(None);
+static COUNTRY_GROUP_PROVER: Mutex<Option<GenericProver<CountrySetCircuit>>> = Mutex::new(None);
+
+
+/// Ed25519 keys of the notaries whose attestations may be proven.
+static TRUSTED_NOTARIES: Mutex<Vec<[u8; 32]>> = Mutex::new(Vec::new());
+
+/// Country prover's verifying key, for `verify_country_proof` when no key
+/// was imported.
+pub(crate) fn country_prover_key() -> Option<PreparedVerifyingKey<Bn254>> {
+    lock_state(&COUNTRY_PROVER).as_ref().map(|p| p.prepared_verifying_key().clone())
 }
 
-// ============== PROVER STATE ==============
+/// Email prover's verifying key; see `country_prover_key`.
+pub(crate) fn email_prover_key() -> Option<PreparedVerifyingKey<Bn254>> {
+    lock_state(&EMAIL_PROVER).as_ref().map(|p| p.prepared_verifying_key().clone())
+}
+
+// ============== PROGRESS REPORTING ==============
+
+/// Receives `(stage, elapsed_ms, circuit)` for each progress event.
+type ProgressHook = Rc<dyn Fn(&str, f64, &str)>;
+
+thread_local! {
+    static PROGRESS_HOOK: RefCell<Option<ProgressHook>> = RefCell::new(None);
+
+    /// Domains email proofs may be issued for; `None` allows any domain.
+    ///
+    /// Thread-local like `PROGRESS_HOOK`: a wasm instance runs on one
+    /// thread, and native tests each get their own list.
+    static EMAIL_ALLOWLIST: RefCell<Option<HashSet<String>>> = const { RefCell::new(None) };
+}
+
+/// Register a callback for prover setup and proving progress.
+///
+/// Called as `cb(stage, elapsed_ms, circuit)`, where `stage` is one of
+/// `setup_start`, `setup_done`, `setup_failed`, `prove_start`,
+/// `prove_done` or `prove_failed`, `elapsed_ms` is the time since the
+/// matching `*_start`, and `circuit` is `"country"`, `"email"`,
+/// `"composite"`, `"region"`, `"exclusion"`, `"country_group"`,
+/// `"corporate_email"` or `"challenge_email"`. Replaces console logging
+/// until cleared.
+#[wasm_bindgen]
+pub fn set_progress_callback(cb: js_sys::Function) {
+    set_progress_hook(Some(Rc::new(move |stage: &str, elapsed_ms: f64, circuit: &str| {
+        // A throwing callback must not abort setup or proving
+        let _ = cb.call3(&JsValue::NULL, &stage.into(), &elapsed_ms.into(), &circuit.into());
+    })));
+}
+
+/// Remove the progress callback and fall back to console logging.
+#[wasm_bindgen]
+pub fn clear_progress_callback() {
+    set_progress_hook(None);
+}
+
+fn set_progress_hook(hook: Option<ProgressHook>) {
+    PROGRESS_HOOK.with(|h| *h.borrow_mut() = hook);
+}
+
+/// Reports the stages of one setup or proving step.
+struct Progress {
+    circuit: &'static str,
+    phase: &'static str,
+    started_ms: f64,
+}
+
+impl Progress {
+    fn start(circuit: &'static str, phase: &'static str) -> Self {
+        let progress = Self {
+            circuit,
+            phase,
+            started_ms: now_ms(),
+        };
+        progress.report("start", None);
+        progress
+    }
+
+    fn done(self) {
+        self.report("done", None);
+    }
+
+    fn failed(self, error: &str) {
+        self.report("failed", Some(error));
+    }
+
+    fn report(&self, outcome: &str, error: Option<&str>) {
+        let stage = format!("{}_{}", self.phase, outcome);
+        let elapsed_ms = now_ms() - self.started_ms;
+
+        // Clone out of the cell so the callback may re-register itself
+        let hook = PROGRESS_HOOK.with(|h| h.borrow().clone());
+        match (hook, error) {
+            (Some(hook), _) => hook(&stage, elapsed_ms, self.circuit),
+            (None, Some(error)) => console_log(&format!("{} {}: {}", self.circuit, stage, error), true),
+            (None, None) => console_log(
+                &format!("{} {} ({:.0} ms)", self.circuit, stage, elapsed_ms),
+                false,
+            ),
+        }
+    }
+}
+
+/// Log to the browser console, or to stderr when running natively.
+#[cfg(target_arch = "wasm32")]
+fn console_log(message: &str, is_error: bool) {
+    if is_error {
+        web_sys::console::error_1(&message.into());
+    } else {
+        web_sys::console::log_1(&message.into());
+    }
+}
 
-/// Global prover state for country proofs
-#[allow(dead_code)]
-struct CountryProverState {
-    proving_key: ProvingKey<Bn254>,
-    verifying_key: VerifyingKey<Bn254>,
-    prepared_vk: PreparedVerifyingKey<Bn254>,
+#[cfg(not(target_arch = "wasm32"))]
+fn console_log(message: &str, _is_error: bool) {
+    eprintln!("{}", message);
 }
 
-/// Global prover state for email proofs
-#[allow(dead_code)]
-struct EmailProverState {
-    proving_key: ProvingKey<Bn254>,
-    verifying_key: VerifyingKey<Bn254>,
-    prepared_vk: PreparedVerifyingKey<Bn254>,
+/// Milliseconds since the Unix epoch.
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    js_sys::Date::now()
 }
 
-static COUNTRY_PROVER: Mutex<Option<CountryProverState>> = Mutex::new(None);
-static EMAIL_PROVER: Mutex<Option<EmailProverState>> = Mutex::new(None);
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
 
 // ============== RESULT TYPES ==============
 
@@ -55,9 +182,32 @@ pub struct CountryProofResult {
     country_name: String,
     proof_bytes: Vec<u8>,
     public_input: String,
+    commitment: String,
+    epoch: u64,
+    nullifier: String,
+    issued_at: u64,
+    identity_commitment: String,
     error: Option<String>,
 }
 
+impl CountryProofResult {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            country_code: String::new(),
+            country_name: String::new(),
+            proof_bytes: Vec::new(),
+            public_input: String::new(),
+            commitment: String::new(),
+            epoch: 0,
+            nullifier: String::new(),
+            issued_at: 0,
+            identity_commitment: String::new(),
+            error: Some(error),
+        }
+    }
+}
+
 #[wasm_bindgen]
 impl CountryProofResult {
     #[wasm_bindgen(getter)]
@@ -90,6 +240,41 @@ impl CountryProofResult {
         self.public_input.clone()
     }
 
+    /// The country id public input as 32 big-endian bytes, as the EVM
+    /// expects a `uint256`. Note `public_input` is little-endian hex.
+    #[wasm_bindgen(getter)]
+    pub fn public_input_bytes(&self) -> Vec<u8> {
+        hex_field_to_be_bytes(&self.public_input)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nullifier(&self) -> String {
+        self.nullifier.clone()
+    }
+
+    /// Unix time (seconds) the proof was issued at, a public input
+    #[wasm_bindgen(getter)]
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    /// Poseidon hash of the identity secret, a public input; equal across
+    /// proofs made with the same secret
+    #[wasm_bindgen(getter)]
+    pub fn identity_commitment(&self) -> String {
+        self.identity_commitment.clone()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn error(&self) -> Option<String> {
         self.error.clone()
@@ -104,10 +289,26 @@ pub struct EmailProofResult {
     proof_bytes: Vec<u8>,
     domain_hash: String,
     commitment: String,
+    identity_commitment: String,
     dkim_verified: bool,
     error: Option<String>,
 }
 
+impl EmailProofResult {
+    fn failure(domain: &str, dkim_verified: bool, error: String) -> Self {
+        Self {
+            success: false,
+            domain: domain.to_string(),
+            proof_bytes: Vec::new(),
+            domain_hash: String::new(),
+            commitment: String::new(),
+            identity_commitment: String::new(),
+            dkim_verified,
+            error: Some(error),
+        }
+    }
+}
+
 #[wasm_bindgen]
 impl EmailProofResult {
     #[wasm_bindgen(getter)]
@@ -134,12 +335,33 @@ impl EmailProofResult {
     pub fn domain_hash(&self) -> String {
         self.domain_hash.clone()
     }
+
+    /// The domain hash as 32 big-endian bytes (EVM `uint256` order).
+    /// Note `domain_hash` is little-endian hex.
+    #[wasm_bindgen(getter)]
+    pub fn domain_hash_bytes(&self) -> Vec<u8> {
+        hex_field_to_be_bytes(&self.domain_hash)
+    }
     
     #[wasm_bindgen(getter)]
     pub fn commitment(&self) -> String {
         self.commitment.clone()
     }
 
+    /// The commitment as 32 big-endian bytes (EVM `uint256` order).
+    /// Note `commitment` is little-endian hex.
+    #[wasm_bindgen(getter)]
+    pub fn commitment_bytes(&self) -> Vec<u8> {
+        hex_field_to_be_bytes(&self.commitment)
+    }
+
+    /// Poseidon hash of the identity secret, a public input; equal across
+    /// proofs made with the same secret
+    #[wasm_bindgen(getter)]
+    pub fn identity_commitment(&self) -> String {
+        self.identity_commitment.clone()
+    }
+
     #[wasm_bindgen(getter)]
     pub fn dkim_verified(&self) -> bool {
         self.dkim_verified
@@ -151,433 +373,1778 @@ impl EmailProofResult {
     }
 }
 
-// ============== INITIALIZATION ==============
-
-/// Initialize the ZK prover for country proofs.
-/// This performs trusted setup - call once at startup.
-/// Returns true if successful.
+/// Combined email + country proof result for JavaScript
 #[wasm_bindgen]
-pub fn init_country_prover() -> bool {
-    let mut state = COUNTRY_PROVER.lock().unwrap();
-    
-    // Already initialized?
-    if state.is_some() {
-        return true;
-    }
-    
-    // Create dummy circuit for trusted setup
-    let circuit = CountryProofCircuit::new_empty();
-    
-    // Deterministic RNG for reproducible setup
-    // NOTE: In production, use a proper trusted setup ceremony!
-    let mut rng = StdRng::seed_from_u64(0x5A4B5F5641554C54); // "ZK_VAULT" in hex
-    
-    match Groth16::<Bn254>::circuit_specific_setup(circuit, &mut rng) {
-        Ok((pk, vk)) => {
-            let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
-            *state = Some(CountryProverState {
-                proving_key: pk,
-                verifying_key: vk,
-                prepared_vk: pvk,
-            });
-            web_sys::console::log_1(&"✓ Country ZK prover initialized".into());
-            true
-        }
-        Err(e) => {
-            web_sys::console::error_1(&format!("Failed to init prover: {:?}", e).into());
-            false
+pub struct CompositeProofResult {
+    success: bool,
+    domain: String,
+    country_code: String,
+    country_name: String,
+    proof_bytes: Vec<u8>,
+    domain_hash: String,
+    country_id: String,
+    commitment: String,
+    error: Option<String>,
+}
+
+impl CompositeProofResult {
+    fn failure(domain: &str, error: String) -> Self {
+        Self {
+            success: false,
+            domain: domain.to_string(),
+            country_code: String::new(),
+            country_name: String::new(),
+            proof_bytes: Vec::new(),
+            domain_hash: String::new(),
+            country_id: String::new(),
+            commitment: String::new(),
+            error: Some(error),
         }
     }
 }
 
-/// Initialize the ZK prover for email domain proofs.
-/// This performs trusted setup - call once at startup.
-/// Returns true if successful.
 #[wasm_bindgen]
-pub fn init_email_prover() -> bool {
-    let mut state = EMAIL_PROVER.lock().unwrap();
-    
-    // Already initialized?
-    if state.is_some() {
-        return true;
+impl CompositeProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
     }
-    
-    // Create dummy circuit for trusted setup
-    let circuit = EmailDomainCircuit::new_empty();
-    
-    // Deterministic RNG for reproducible setup
-    let mut rng = StdRng::seed_from_u64(0x454D41494C5F5A4B); // "EMAIL_ZK" in hex
-    
-    match Groth16::<Bn254>::circuit_specific_setup(circuit, &mut rng) {
-        Ok((pk, vk)) => {
-            let pvk = Groth16::<Bn254>::process_vk(&vk).unwrap();
-            *state = Some(EmailProverState {
-                proving_key: pk,
-                verifying_key: vk,
-                prepared_vk: pvk,
-            });
-            web_sys::console::log_1(&"✓ Email ZK prover initialized".into());
-            true
-        }
-        Err(e) => {
-            web_sys::console::error_1(&format!("Failed to init email prover: {:?}", e).into());
-            false
-        }
+
+    #[wasm_bindgen(getter)]
+    pub fn domain(&self) -> String {
+        self.domain.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_code(&self) -> String {
+        self.country_code.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_name(&self) -> String {
+        self.country_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn domain_hash(&self) -> String {
+        self.domain_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_id(&self) -> String {
+        self.country_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
     }
 }
 
-/// Check if country prover is initialized
+/// Region proof result for JavaScript
 #[wasm_bindgen]
-pub fn is_prover_ready() -> bool {
-    COUNTRY_PROVER.lock().unwrap().is_some()
+pub struct RegionProofResult {
+    success: bool,
+    region_code: String,
+    region_name: String,
+    parent_country: String,
+    proof_bytes: Vec<u8>,
+    region_id: String,
+    commitment: String,
+    error: Option<String>,
 }
 
-// ============== COUNTRY VERIFICATION ==============
-
-/// Generate a REAL ZK proof of country from coordinates.
-/// 
-/// This creates a Groth16 proof that proves you're in a specific country
-/// without revealing your exact coordinates.
-#[wasm_bindgen]
-pub fn prove_country_from_coords(lat: f64, lng: f64) -> CountryProofResult {
-    // Find which country contains these coordinates
-    let country = COUNTRIES.iter().find(|c| {
-        lat >= c.min_lat && lat <= c.max_lat && lng >= c.min_lng && lng <= c.max_lng
-    });
-    
-    let country = match country {
-        Some(c) => c,
-        None => {
-            return CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some("Coordinates not within any supported country".to_string()),
-            }
-        }
-    };
-    
-    // Get prover state
-    let state = COUNTRY_PROVER.lock().unwrap();
-    let prover = match state.as_ref() {
-        Some(p) => p,
-        None => {
-            return CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some("Prover not initialized. Call init_country_prover() first.".to_string()),
-            }
-        }
-    };
-    
-    // Create circuit with actual coordinates
-    let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
-    let circuit = CountryProofCircuit::new_with_witness(lat, lng, &bounds, country.code);
-    
-    // Generate Groth16 proof
-    let mut rng = StdRng::seed_from_u64(js_sys::Date::now() as u64);
-    
-    match Groth16::<Bn254>::prove(&prover.proving_key, circuit, &mut rng) {
-        Ok(proof) => {
-            // Serialize proof
-            let mut proof_bytes = Vec::new();
-            proof.serialize_compressed(&mut proof_bytes).unwrap();
-            
-            // Get public input (country identifier)
-            let country_id = country_code_to_field(country.code);
-            let mut public_input_bytes = Vec::new();
-            country_id.serialize_compressed(&mut public_input_bytes).unwrap();
-            
-            CountryProofResult {
-                success: true,
-                country_code: country.code.to_string(),
-                country_name: country.name.to_string(),
-                proof_bytes,
-                public_input: hex::encode(public_input_bytes),
-                error: None,
-            }
-        }
-        Err(e) => {
-            CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some(format!("Proof generation failed: {:?}", e)),
-            }
+impl RegionProofResult {
+    fn failure(region_code: &str, error: String) -> Self {
+        Self {
+            success: false,
+            region_code: region_code.to_string(),
+            region_name: String::new(),
+            parent_country: String::new(),
+            proof_bytes: Vec::new(),
+            region_id: String::new(),
+            commitment: String::new(),
+            error: Some(error),
         }
     }
 }
 
-/// Simpler version: prove country from country code (for IP geolocation).
-/// This still generates a real ZK proof but uses predefined bounds.
 #[wasm_bindgen]
-pub fn prove_country(country_code: &str) -> CountryProofResult {
-    let code_upper = country_code.to_uppercase();
-    
-    // Find the country
-    let country = COUNTRIES.iter().find(|c| c.code == code_upper);
-    
-    let country = match country {
-        Some(c) => c,
-        None => {
-            return CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some(format!("Unknown country code: {}", country_code)),
-            }
-        }
-    };
-    
-    // Get prover state
-    let state = COUNTRY_PROVER.lock().unwrap();
-    let prover = match state.as_ref() {
-        Some(p) => p,
-        None => {
-            return CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some("Prover not initialized. Call init_country_prover() first.".to_string()),
-            }
-        }
-    };
-    
-    // Use center of country as coordinates (this is for demo - in production, use actual coords)
-    let lat = (country.min_lat + country.max_lat) / 2.0;
-    let lng = (country.min_lng + country.max_lng) / 2.0;
-    
-    // Create circuit
-    let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
-    let circuit = CountryProofCircuit::new_with_witness(lat, lng, &bounds, country.code);
-    
-    // Generate proof with cryptographically secure randomness
-    // Use getrandom (Web Crypto API) instead of predictable timestamp
-    let mut seed = [0u8; 32];
-    getrandom::getrandom(&mut seed).expect("Failed to get secure random bytes");
-    let mut rng = StdRng::from_seed(seed);
-    
-    match Groth16::<Bn254>::prove(&prover.proving_key, circuit, &mut rng) {
-        Ok(proof) => {
-            let mut proof_bytes = Vec::new();
-            proof.serialize_compressed(&mut proof_bytes).unwrap();
-            
-            let country_id = country_code_to_field(country.code);
-            let mut public_input_bytes = Vec::new();
-            country_id.serialize_compressed(&mut public_input_bytes).unwrap();
-            
-            CountryProofResult {
-                success: true,
-                country_code: country.code.to_string(),
+impl RegionProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn region_code(&self) -> String {
+        self.region_code.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn region_name(&self) -> String {
+        self.region_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn parent_country(&self) -> String {
+        self.parent_country.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn region_id(&self) -> String {
+        self.region_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Country exclusion proof result for JavaScript
+#[wasm_bindgen]
+pub struct ExclusionProofResult {
+    success: bool,
+    country_code: String,
+    country_name: String,
+    proof_bytes: Vec<u8>,
+    country_id: String,
+    commitment: String,
+    error: Option<String>,
+}
+
+impl ExclusionProofResult {
+    fn failure(country_code: &str, error: String) -> Self {
+        Self {
+            success: false,
+            country_code: country_code.to_string(),
+            country_name: String::new(),
+            proof_bytes: Vec::new(),
+            country_id: String::new(),
+            commitment: String::new(),
+            error: Some(error),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ExclusionProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_code(&self) -> String {
+        self.country_code.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_name(&self) -> String {
+        self.country_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn country_id(&self) -> String {
+        self.country_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Country group proof result for JavaScript
+#[wasm_bindgen]
+pub struct CountryGroupProofResult {
+    success: bool,
+    group_code: String,
+    group_name: String,
+    proof_bytes: Vec<u8>,
+    group_id: String,
+    commitment: String,
+    error: Option<String>,
+}
+
+impl CountryGroupProofResult {
+    fn failure(group_code: &str, error: String) -> Self {
+        Self {
+            success: false,
+            group_code: group_code.to_string(),
+            group_name: String::new(),
+            proof_bytes: Vec::new(),
+            group_id: String::new(),
+            commitment: String::new(),
+            error: Some(error),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CountryGroupProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn group_code(&self) -> String {
+        self.group_code.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn group_name(&self) -> String {
+        self.group_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn group_id(&self) -> String {
+        self.group_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Corporate (non-freemail) email proof result for JavaScript
+#[wasm_bindgen]
+pub struct CorporateEmailProofResult {
+    success: bool,
+    proof_bytes: Vec<u8>,
+    commitment: String,
+    dkim_verified: bool,
+    error: Option<String>,
+}
+
+impl CorporateEmailProofResult {
+    fn failure(dkim_verified: bool, error: String) -> Self {
+        Self {
+            success: false,
+            proof_bytes: Vec::new(),
+            commitment: String::new(),
+            dkim_verified,
+            error: Some(error),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl CorporateEmailProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dkim_verified(&self) -> bool {
+        self.dkim_verified
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Result of challenge email proof generation
+#[wasm_bindgen]
+pub struct ChallengeEmailProofResult {
+    success: bool,
+    proof_bytes: Vec<u8>,
+    domain_hash: String,
+    commitment: String,
+    error: Option<String>,
+}
+
+impl ChallengeEmailProofResult {
+    fn failure(error: String) -> Self {
+        Self {
+            success: false,
+            proof_bytes: Vec::new(),
+            domain_hash: String::new(),
+            commitment: String::new(),
+            error: Some(error),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl ChallengeEmailProofResult {
+    #[wasm_bindgen(getter)]
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_hex(&self) -> String {
+        hex::encode(&self.proof_bytes)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn proof_bytes(&self) -> Vec<u8> {
+        self.proof_bytes.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn domain_hash(&self) -> String {
+        self.domain_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+// ============== INITIALIZATION ==============
+
+/// Run setup for `C` into `slot`, unless it already holds a prover.
+///
+/// With a `seed` the keys are reproducible, so a verifier elsewhere can
+/// rebuild them; without one the setup randomness comes from
+/// `secure_rng()`. Neither replaces a trusted setup ceremony.
+fn init_prover<C: ProvableCircuit>(
+    slot: &Mutex<Option<GenericProver<C>>>,
+    params: &C::SetupParams,
+    circuit: &'static str,
+    seed: Option<u64>,
+) -> bool {
+    let mut state = lock_state(slot);
+
+    // Already initialized?
+    if state.is_some() {
+        return true;
+    }
+
+    let rng = match seed {
+        Some(seed) => Ok(StdRng::seed_from_u64(seed)),
+        None => secure_rng(),
+    };
+
+    let progress = Progress::start(circuit, "setup");
+    match rng.and_then(|mut rng| GenericProver::<C>::setup(params, &mut rng)) {
+        Ok(prover) => {
+            *state = Some(prover);
+            progress.done();
+            true
+        }
+        Err(e) => {
+            progress.failed(&format!("Failed to init {} prover: {:?}", circuit.replace('_', " "), e));
+            false
+        }
+    }
+}
+
+/// Initialize the ZK prover for country proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_country_prover(seed: Option<u64>) -> bool {
+    init_prover(&COUNTRY_PROVER, &(), "country", seed)
+}
+
+/// Initialize the ZK prover for email domain proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_email_prover(seed: Option<u64>) -> bool {
+    init_prover(&EMAIL_PROVER, &(), "email", seed)
+}
+
+/// Initialize the email prover and restrict proofs to `domains`.
+///
+/// Domains are normalized like `extract_domain` (lowercase, no trailing
+/// dot). Email proofs for any other domain fail before proving. Calling
+/// this again replaces the list. `seed` is as for `init_email_prover`.
+#[wasm_bindgen]
+pub fn init_email_prover_with_allowlist(domains: Vec<String>, seed: Option<u64>) -> bool {
+    let allowlist = domains.iter().map(|d| normalize_domain(d)).collect();
+    EMAIL_ALLOWLIST.with(|list| *list.borrow_mut() = Some(allowlist));
+    init_email_prover(seed)
+}
+
+/// Check a domain against the allowlist set by
+/// `init_email_prover_with_allowlist`, if any.
+///
+/// Every entry point that proves an email domain calls this before
+/// proving, whichever circuit it uses.
+fn check_email_allowlist(domain: &str) -> Result<(), String> {
+    EMAIL_ALLOWLIST.with(|list| match list.borrow().as_ref() {
+        Some(allowlist) if !allowlist.contains(&normalize_domain(domain)) => {
+            Err(format!("Domain {} is not on the allowlist", domain))
+        }
+        _ => Ok(()),
+    })
+}
+
+/// Initialize the ZK prover for combined email + country proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_composite_prover(seed: Option<u64>) -> bool {
+    init_prover(&COMPOSITE_PROVER, &(), "composite", seed)
+}
+
+/// Initialize the ZK prover for region (state / province) proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_region_prover(seed: Option<u64>) -> bool {
+    init_prover(&REGION_PROVER, &(), "region", seed)
+}
+
+/// Initialize the ZK prover for country exclusion proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_exclusion_prover(seed: Option<u64>) -> bool {
+    init_prover(&EXCLUSION_PROVER, &(), "exclusion", seed)
+}
+
+/// Initialize the ZK prover for corporate (non-freemail) email proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_corporate_email_prover(seed: Option<u64>) -> bool {
+    init_prover(&CORPORATE_EMAIL_PROVER, &(), "corporate_email", seed)
+}
+
+/// Initialize the ZK prover for challenge email proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_challenge_email_prover(seed: Option<u64>) -> bool {
+    init_prover(&CHALLENGE_EMAIL_PROVER, &(), "challenge_email", seed)
+}
+
+/// Initialize the ZK prover for country group proofs.
+/// Pass a `seed` for reproducible keys; otherwise setup draws from
+/// `secure_rng()`. Returns true if successful.
+#[wasm_bindgen]
+pub fn init_country_group_prover(seed: Option<u64>) -> bool {
+    init_prover(&COUNTRY_GROUP_PROVER, &(), "country_group", seed)
+}
+
+/// Check if country prover is initialized
+#[wasm_bindgen]
+pub fn is_prover_ready() -> bool {
+    lock_state(&COUNTRY_PROVER).is_some()
+}
+
+// ============== PROVER HANDLES ==============
+
+/// A country prover owned by JavaScript rather than held in global state.
+///
+/// Each Web Worker can build its own handle, or rebuild one from the key
+/// bytes of a handle set up elsewhere, instead of sharing the global
+/// `init_country_prover()` state.
+#[wasm_bindgen]
+pub struct CountryProverHandle {
+    prover: GenericProver<CountryProofCircuit>,
+}
+
+#[wasm_bindgen]
+impl CountryProverHandle {
+    /// Run setup with randomness from `secure_rng()`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<CountryProverHandle, JsError> {
+        let mut rng = secure_rng().map_err(|e| JsError::new(&e.to_string()))?;
+        Self::setup(&mut rng)
+    }
+
+    /// Run setup with a caller-chosen seed; the keys match those of
+    /// `init_country_prover` with the same seed.
+    pub fn with_seed(seed: u64) -> Result<CountryProverHandle, JsError> {
+        Self::setup(&mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Rebuild a handle from `proving_key_bytes` and `verifying_key_bytes`,
+    /// e.g. in a worker that received them from the main thread.
+    pub fn from_key_bytes(pk_bytes: &[u8], vk_bytes: &[u8]) -> Result<CountryProverHandle, JsError> {
+        GenericProver::from_key_bytes(pk_bytes, vk_bytes)
+            .map(|prover| Self { prover })
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Proving key with key header.
+    pub fn proving_key_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.prover.proving_key_bytes().map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Verifying key with key header, as `export_country_verifying_key`.
+    pub fn verifying_key_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.prover.verifying_key_bytes().map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// `prove_country_from_coords` with this handle's keys.
+    pub fn prove_from_coords(
+        &self,
+        lat: f64,
+        lng: f64,
+        epoch: u64,
+        preferred_code: Option<String>,
+        identity_secret: Option<String>,
+    ) -> CountryProofResult {
+        let mut rng = match secure_rng() {
+            Ok(rng) => rng,
+            Err(e) => return CountryProofResult::failure(e.to_string()),
+        };
+        prove_country_from_coords_with(
+            &self.prover,
+            lat,
+            lng,
+            epoch,
+            preferred_code.as_deref(),
+            identity_secret.as_deref(),
+            &mut rng,
+        )
+    }
+
+    /// `prove_country` with this handle's keys.
+    pub fn prove(&self, country_code: &str, identity_secret: Option<String>) -> CountryProofResult {
+        let mut rng = match secure_rng() {
+            Ok(rng) => rng,
+            Err(e) => return CountryProofResult::failure(e.to_string()),
+        };
+        prove_country_with(&self.prover, country_code, identity_secret.as_deref(), &mut rng)
+    }
+
+    /// `verify_country_proof` with this handle's keys.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify(
+        &self,
+        proof_hex: &str,
+        country_id_hex: &str,
+        commitment_hex: &str,
+        epoch: u64,
+        nullifier_hex: &str,
+        issued_at: u64,
+        identity_commitment_hex: &str,
+        expected_country_code: Option<String>,
+        max_age_secs: Option<u64>,
+    ) -> bool {
+        verify_country_proof_with(
+            self.prover.prepared_verifying_key(),
+            proof_hex,
+            country_id_hex,
+            commitment_hex,
+            epoch,
+            nullifier_hex,
+            issued_at,
+            identity_commitment_hex,
+            expected_country_code.as_deref(),
+            max_age_secs,
+        )
+    }
+}
+
+impl CountryProverHandle {
+    /// Run setup for a handle.
+    fn setup(rng: &mut StdRng) -> Result<CountryProverHandle, JsError> {
+        let progress = Progress::start("country", "setup");
+        match GenericProver::<CountryProofCircuit>::setup(&(), rng) {
+            Ok(prover) => {
+                progress.done();
+                Ok(Self { prover })
+            }
+            Err(e) => {
+                let error = format!("Failed to init prover: {:?}", e);
+                progress.failed(&error);
+                Err(JsError::new(&error))
+            }
+        }
+    }
+}
+
+// ============== COUNTRY VERIFICATION ==============
+
+/// Generate a REAL ZK proof of country from coordinates.
+/// 
+/// This creates a Groth16 proof that proves you're in a specific country
+/// without revealing your exact coordinates. The proof also carries a
+/// nullifier for `epoch`, so the same coordinates can be claimed at most
+/// once per epoch, and the current time as its public `issued_at`.
+///
+/// Near borders the coordinates may fall inside several country boxes;
+/// pass `preferred_code` to choose one, otherwise an ambiguous location
+/// is an error.
+///
+/// NaN, infinite and out-of-range coordinates are rejected up front, as
+/// is exactly `(0, 0)`, which is what a failed geolocation usually
+/// reports rather than a real position.
+///
+/// Pass the same `identity_secret` to this and `prove_email_domain` to
+/// give both proofs the same `identity_commitment`, so a verifier can
+/// tell they come from one person. Without it the proof is unlinkable.
+#[wasm_bindgen]
+pub fn prove_country_from_coords(
+    lat: f64,
+    lng: f64,
+    epoch: u64,
+    preferred_code: Option<String>,
+    identity_secret: Option<String>,
+) -> CountryProofResult {
+    let state = lock_state(&COUNTRY_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return CountryProofResult::failure(
+                "Prover not initialized. Call init_country_prover() first.".to_string(),
+            )
+        }
+    };
+    
+    let mut rng = match secure_rng() {
+        Ok(rng) => rng,
+        Err(e) => return CountryProofResult::failure(e.to_string()),
+    };
+    prove_country_from_coords_with(
+        prover,
+        lat,
+        lng,
+        epoch,
+        preferred_code.as_deref(),
+        identity_secret.as_deref(),
+        &mut rng,
+    )
+}
+
+/// `prove_country_from_coords` with coordinates given as strings.
+///
+/// Accepts decimal degrees, degrees and decimal minutes, and
+/// degrees-minutes-seconds such as `37°46'29.6"N`, with a sign or an
+/// `N`/`S`/`E`/`W` hemisphere; see `proofs::location::parse_coordinate`.
+#[wasm_bindgen]
+pub fn prove_country_from_dms(
+    lat: &str,
+    lng: &str,
+    epoch: u64,
+    preferred_code: Option<String>,
+    identity_secret: Option<String>,
+) -> CountryProofResult {
+    match parse_coordinates(lat, lng) {
+        Ok(coords) => {
+            prove_country_from_coords(coords.latitude, coords.longitude, epoch, preferred_code, identity_secret)
+        }
+        Err(e) => CountryProofResult::failure(e.to_string()),
+    }
+}
+
+/// `prove_country_from_coords` against a given prover.
+fn prove_country_from_coords_with(
+    prover: &GenericProver<CountryProofCircuit>,
+    lat: f64,
+    lng: f64,
+    epoch: u64,
+    preferred_code: Option<&str>,
+    identity_secret: Option<&str>,
+    rng: &mut StdRng,
+) -> CountryProofResult {
+    if let Err(e) = normalize_coords(lat, lng) {
+        return CountryProofResult::failure(e.to_string());
+    }
+    if lat == 0.0 && lng == 0.0 {
+        return CountryProofResult::failure(
+            "Coordinates (0, 0) look like a missing location".to_string(),
+        );
+    }
+    
+    // Find which country contains these coordinates
+    let country = match select_country(lat, lng, preferred_code) {
+        Ok(c) => c,
+        Err(e) => return CountryProofResult::failure(e),
+    };
+    
+    // Create circuit with actual coordinates
+    let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
+    let issued_at = now_secs();
+    let circuit = match CountryProofCircuit::new_with_witness(lat, lng, &bounds, country.code, epoch, issued_at) {
+        Ok(c) => with_identity(c, identity_secret, CountryProofCircuit::with_identity_secret),
+        Err(e) => return CountryProofResult::failure(e.to_string()),
+    };
+    
+    country_proof_result(prover, circuit, country.code, country.name, epoch, issued_at, rng)
+}
+
+/// Simpler version: prove country from country code (for IP geolocation).
+/// This still generates a real ZK proof but uses predefined bounds.
+///
+/// The proof is scoped to epoch 0; since every caller proves the same
+/// representative point, its nullifier is shared by the whole country.
+/// Prefer `prove_country_from_coords` when real coordinates are available.
+/// `identity_secret` is as for `prove_country_from_coords`.
+#[wasm_bindgen]
+pub fn prove_country(country_code: &str, identity_secret: Option<String>) -> CountryProofResult {
+    let state = lock_state(&COUNTRY_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return CountryProofResult::failure(
+                "Prover not initialized. Call init_country_prover() first.".to_string(),
+            )
+        }
+    };
+    
+    let mut rng = match secure_rng() {
+        Ok(rng) => rng,
+        Err(e) => return CountryProofResult::failure(e.to_string()),
+    };
+    prove_country_with(prover, country_code, identity_secret.as_deref(), &mut rng)
+}
+
+/// `prove_country` against a given prover.
+fn prove_country_with(
+    prover: &GenericProver<CountryProofCircuit>,
+    country_code: &str,
+    identity_secret: Option<&str>,
+    rng: &mut StdRng,
+) -> CountryProofResult {
+    let code_upper = country_code.to_uppercase();
+    
+    // Find the country
+    let country = match COUNTRIES.iter().find(|c| c.code == code_upper) {
+        Some(c) => c,
+        None => return CountryProofResult::failure(format!("Unknown country code: {}", country_code)),
+    };
+    
+    // No real coordinates: use the country's interior representative point
+    // (the box center can lie in the sea, e.g. for Japan)
+    let (lat, lng) = country.representative_point;
+    
+    // Create circuit
+    let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
+    let issued_at = now_secs();
+    let circuit = match CountryProofCircuit::new_with_witness(lat, lng, &bounds, country.code, 0, issued_at) {
+        Ok(c) => with_identity(c, identity_secret, CountryProofCircuit::with_identity_secret),
+        Err(e) => return CountryProofResult::failure(e.to_string()),
+    };
+    
+    country_proof_result(prover, circuit, country.code, country.name, 0, issued_at, rng)
+}
+
+/// Apply a caller's identity secret, hashed to a field element, if given.
+fn with_identity<C>(circuit: C, identity_secret: Option<&str>, apply: fn(C, Fr) -> C) -> C {
+    match identity_secret {
+        Some(secret) => apply(circuit, string_to_field(secret)),
+        None => circuit,
+    }
+}
+
+/// Pick the country box containing the coordinates.
+///
+/// Fails if no box matches, if `preferred_code` is given but its box does
+/// not contain the point, or if several boxes match and none is preferred.
+fn select_country(
+    lat: f64,
+    lng: f64,
+    preferred_code: Option<&str>,
+) -> Result<&'static CountryBounds, String> {
+    let matches = LocationVerifier::find_countries(&Coordinates::new(lat, lng));
+    
+    if let Some(code) = preferred_code {
+        return matches
+            .into_iter()
+            .find(|c| c.code.eq_ignore_ascii_case(code))
+            .ok_or_else(|| format!("Coordinates not within preferred country {}", code));
+    }
+    
+    match matches.as_slice() {
+        [] => Err("Coordinates not within any supported country".to_string()),
+        [country] => Ok(country),
+        _ => {
+            let codes: Vec<&str> = matches.iter().map(|c| c.code).collect();
+            Err(format!(
+                "Coordinates match several countries ({}); pass a preferred country code",
+                codes.join(", ")
+            ))
+        }
+    }
+}
+
+/// Prove a country circuit and package the result for JavaScript.
+fn country_proof_result(
+    prover: &GenericProver<CountryProofCircuit>,
+    circuit: CountryProofCircuit,
+    country_code: &str,
+    country_name: &str,
+    epoch: u64,
+    issued_at: u64,
+    rng: &mut StdRng,
+) -> CountryProofResult {
+    let progress = Progress::start("country", "prove");
+    match prover.prove(circuit, rng) {
+        Ok((proof, (country_id, commitment, _, nullifier, _, identity_commitment))) => {
+            progress.done();
+
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+            
+            CountryProofResult {
+                success: true,
+                country_code: country_code.to_string(),
+                country_name: country_name.to_string(),
+                proof_bytes,
+                public_input: field_to_hex(&country_id),
+                commitment: field_to_hex(&commitment),
+                epoch,
+                nullifier: field_to_hex(&nullifier),
+                issued_at,
+                identity_commitment: field_to_hex(&identity_commitment),
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            CountryProofResult::failure(error)
+        }
+    }
+}
+
+/// Export the country verifying key (with key header) from an initialized prover.
+///
+/// Ship these bytes to verify-only clients, which can then call
+/// `verify_country_proof_with_vk` or `import_country_verifying_key`
+/// without running setup.
+#[wasm_bindgen]
+pub fn export_country_verifying_key() -> Option<Vec<u8>> {
+    let state = lock_state(&COUNTRY_PROVER);
+    state.as_ref()?.verifying_key_bytes().ok()
+}
+
+/// Re-encode a `field_to_hex` string as 32 canonical big-endian bytes.
+///
+/// arkworks serializes field elements little-endian; Solidity verifiers
+/// take public inputs as big-endian `uint256`. Empty if `s` is not a
+/// field element (e.g. on a failed result).
+fn hex_field_to_be_bytes(s: &str) -> Vec<u8> {
+    field_from_hex(s)
+        .map(|f| f.into_bigint().to_bytes_be())
+        .unwrap_or_default()
+}
+
+// ============== EMAIL DOMAIN VERIFICATION ==============
+
+/// Generate a REAL ZK proof of email domain ownership.
+/// 
+/// This creates a Groth16 proof that you own an email at the specified domain
+/// without revealing the actual email address.
+///
+/// `identity_secret` links the proof to country proofs made with the same
+/// secret; see `prove_country_from_coords`.
+#[wasm_bindgen]
+pub fn prove_email_domain(
+    domain: &str,
+    dkim_signature: &str,
+    auth_results: &str,
+    identity_secret: Option<String>,
+) -> EmailProofResult {
+    // Verify DKIM passed (auth_results is the reliable indicator)
+    // Gmail and most providers set auth_results even if raw DKIM header isn't exposed
+    let dkim_verified = auth_results.to_lowercase().contains("dkim=pass");
+
+    if !dkim_verified {
+        return EmailProofResult::failure(
+            domain,
+            false,
+            "DKIM verification failed - email may not be authentic".to_string(),
+        );
+    }
+    
+    // Use DKIM signature if available, otherwise use auth_results as proof data
+    let dkim_data = if !dkim_signature.is_empty() {
+        dkim_signature.to_string()
+    } else {
+        auth_results.to_string()
+    };
+
+    // Create proof input with actual DKIM data
+    let input = EmailProofInput::from_domain_with_dkim(domain, dkim_verified, &dkim_data);
+    
+    email_proof_result(&input, identity_secret.as_deref())
+}
+
+/// Parse a raw .eml file and prove its authenticated domain in one call.
+///
+/// The From and DKIM-Signature headers are extracted, and the domain
+/// proven is the DKIM signing (`d=`) domain. If `dkim_key_record` (the
+/// `<selector>._domainkey.<domain>` TXT record, e.g. fetched over
+/// DNS-over-HTTPS) is given, the signature is verified cryptographically;
+/// otherwise the receiving server's `Authentication-Results` DKIM verdict
+/// is trusted, as in `prove_email_domain`. `identity_secret` is as for
+/// `prove_email_domain`.
+#[wasm_bindgen]
+pub fn prove_email_from_eml(
+    eml_content: &str,
+    dkim_key_record: Option<String>,
+    identity_secret: Option<String>,
+) -> EmailProofResult {
+    let parsed = match EmailVerifier::parse_email(eml_content) {
+        Ok(p) => p,
+        Err(e) => return EmailProofResult::failure("", false, format!("Could not parse email: {}", e)),
+    };
+    let dkim_signature = match parsed.aligned_dkim_signature() {
+        Ok(sig) => sig.to_string(),
+        Err(e) => return EmailProofResult::failure(&parsed.from_domain, false, e.to_string()),
+    };
+
+    let domain = match dkim_key_record {
+        Some(record) => match EmailVerifier::verify_dkim(eml_content, &FixedKeyResolver(record)) {
+            Ok(domain) => domain,
+            Err(e) => {
+                return EmailProofResult::failure(
+                    &parsed.from_domain,
+                    false,
+                    format!("DKIM verification failed: {}", e),
+                )
+            }
+        },
+        None => match (&parsed.dkim_result, &parsed.dkim_domain) {
+            (Some(result), Some(domain)) if result == "pass" => domain.clone(),
+            _ => {
+                return EmailProofResult::failure(
+                    &parsed.from_domain,
+                    false,
+                    "DKIM verification failed - email may not be authentic".to_string(),
+                )
+            }
+        },
+    };
+
+    let address = EmailVerifier::extract_address(&parsed.from)
+        .unwrap_or_else(|| format!("user@{}", parsed.from_domain));
+    let input = EmailProofInput::builder()
+        .email(&address)
+        .dkim_domain(&domain)
+        .dkim_signature(&dkim_signature)
+        .auth_results("dkim=pass")
+        .domain_source(DomainSource::Dkim)
+        .build();
+
+    match input {
+        Ok(input) => email_proof_result(&input, identity_secret.as_deref()),
+        Err(e) => EmailProofResult::failure(&domain, true, e.to_string()),
+    }
+}
+
+/// Pin the notary keys (hex Ed25519 public keys) whose attestations
+/// `prove_email_from_attestation` accepts, replacing any set before.
+///
+/// Returns false, leaving the pinned keys unchanged, if any key is not
+/// 32 hex-encoded bytes.
+#[wasm_bindgen]
+pub fn set_trusted_notaries(keys_hex: Vec<String>) -> bool {
+    let keys: Option<Vec<[u8; 32]>> = keys_hex
+        .iter()
+        .map(|k| hex::decode(k).ok().and_then(|b| <[u8; 32]>::try_from(b).ok()))
+        .collect();
+    match keys {
+        Some(keys) => {
+            *lock_state(&TRUSTED_NOTARIES) = keys;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Prove a webmail provider domain from a notarized TLS session.
+///
+/// An alternative to DKIM for users who cannot export a signed email:
+/// `signature_hex` is a notary's Ed25519 signature over the provider
+/// domain and `transcript_commitment_hex` (32 bytes). The notary must be
+/// one pinned with `set_trusted_notaries`; a self-signed attestation is
+/// rejected. The proof has the same public inputs as
+/// `prove_email_domain`, so `verify_email_proof` checks it.
+#[wasm_bindgen]
+pub fn prove_email_from_attestation(
+    provider_domain: &str,
+    transcript_commitment_hex: &str,
+    signature_hex: &str,
+    identity_secret: Option<String>,
+) -> EmailProofResult {
+    let decode = |s: &str| hex::decode(s).ok();
+    let (transcript_commitment, signature) = match (
+        decode(transcript_commitment_hex).and_then(|b| <[u8; 32]>::try_from(b).ok()),
+        decode(signature_hex).and_then(|b| <[u8; 64]>::try_from(b).ok()),
+    ) {
+        (Some(t), Some(s)) => (t, s),
+        _ => return EmailProofResult::failure(provider_domain, false, "Invalid attestation encoding".to_string()),
+    };
+
+    let attestation = AttestationProof {
+        provider_domain: provider_domain.to_string(),
+        transcript_commitment,
+        signature,
+    };
+    let trusted = lock_state(&TRUSTED_NOTARIES).clone();
+    match attestation.to_email_input(&trusted) {
+        Ok(input) => email_proof_result(&input, identity_secret.as_deref()),
+        Err(e) => EmailProofResult::failure(provider_domain, false, e.to_string()),
+    }
+}
+
+/// Serves one key record for whatever selector the signature names.
+struct FixedKeyResolver(String);
+
+impl DkimKeyResolver for FixedKeyResolver {
+    fn lookup_txt(&self, _name: &str) -> Option<String> {
+        Some(self.0.clone())
+    }
+}
+
+/// Prove an email input and package the result for JavaScript.
+fn email_proof_result(input: &EmailProofInput, identity_secret: Option<&str>) -> EmailProofResult {
+    if let Err(e) = check_email_allowlist(&input.domain) {
+        return EmailProofResult::failure(&input.domain, input.dkim_verified, e);
+    }
+
+    // Get email prover state
+    let state = lock_state(&EMAIL_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return EmailProofResult::failure(
+                &input.domain,
+                input.dkim_verified,
+                "Email prover not initialized. Call init_email_prover() first.".to_string(),
+            )
+        }
+    };
+
+    // Create circuit with real witness
+    let circuit = match EmailDomainCircuit::new_with_witness(input) {
+        Ok(c) => with_identity(c, identity_secret, EmailDomainCircuit::with_identity_secret),
+        Err(e) => return EmailProofResult::failure(&input.domain, input.dkim_verified, e.to_string()),
+    };
+    
+    // Generate proof with cryptographically secure randomness
+    let progress = Progress::start("email", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (domain_hash, commitment, identity_commitment))) => {
+            progress.done();
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+            
+            EmailProofResult {
+                success: true,
+                domain: input.domain.clone(),
+                proof_bytes,
+                domain_hash: field_to_hex(&domain_hash),
+                commitment: field_to_hex(&commitment),
+                identity_commitment: field_to_hex(&identity_commitment),
+                dkim_verified: input.dkim_verified,
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            EmailProofResult::failure(&input.domain, input.dkim_verified, error)
+        }
+    }
+}
+
+/// Export the email verifying key (with key header) from an initialized prover.
+///
+/// For verify-only builds; see `export_country_verifying_key`.
+#[wasm_bindgen]
+pub fn export_email_verifying_key() -> Option<Vec<u8>> {
+    let state = lock_state(&EMAIL_PROVER);
+    state.as_ref()?.verifying_key_bytes().ok()
+}
+
+/// Check if email prover is initialized
+#[wasm_bindgen]
+pub fn is_email_prover_ready() -> bool {
+    lock_state(&EMAIL_PROVER).is_some()
+}
+
+// ============== COMBINED EMAIL + COUNTRY ==============
+
+/// Generate a single ZK proof of email domain ownership AND country location.
+///
+/// Both claims share one commitment and nonce, so they cannot be combined
+/// from two different people's credentials.
+///
+/// As in `prove_country_from_coords`, `preferred_code` picks the country
+/// where the coordinates fall inside several boxes.
+#[wasm_bindgen]
+pub fn prove_email_and_country(
+    domain: &str,
+    dkim_signature: &str,
+    auth_results: &str,
+    lat: f64,
+    lng: f64,
+    preferred_code: Option<String>,
+) -> CompositeProofResult {
+    if !auth_results.to_lowercase().contains("dkim=pass") {
+        return CompositeProofResult::failure(
+            domain,
+            "DKIM verification failed - email may not be authentic".to_string(),
+        );
+    }
+    if let Err(e) = check_email_allowlist(domain) {
+        return CompositeProofResult::failure(domain, e);
+    }
+
+    // Find which country contains these coordinates
+    let country = match select_country(lat, lng, preferred_code.as_deref()) {
+        Ok(c) => c,
+        Err(e) => return CompositeProofResult::failure(domain, e),
+    };
+
+    let state = lock_state(&COMPOSITE_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return CompositeProofResult::failure(
+                domain,
+                "Composite prover not initialized. Call init_composite_prover() first.".to_string(),
+            )
+        }
+    };
+
+    let dkim_data = if !dkim_signature.is_empty() {
+        dkim_signature
+    } else {
+        auth_results
+    };
+    let input = EmailProofInput::from_domain_with_dkim(domain, true, dkim_data);
+    let bounds = ScaledBounds::new(country.min_lat, country.max_lat, country.min_lng, country.max_lng);
+    let circuit = match CompositeCircuit::new_with_witness(&input, lat, lng, &bounds, country.code) {
+        Ok(c) => c,
+        Err(e) => return CompositeProofResult::failure(domain, e.to_string()),
+    };
+
+    let progress = Progress::start("composite", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (domain_hash, country_id, _, commitment))) => {
+            progress.done();
+
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+            CompositeProofResult {
+                success: true,
+                domain: domain.to_string(),
+                country_code: country.code.to_string(),
+                country_name: country.name.to_string(),
+                proof_bytes,
+                domain_hash: field_to_hex(&domain_hash),
+                country_id: field_to_hex(&country_id),
+                commitment: field_to_hex(&commitment),
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            CompositeProofResult::failure(domain, error)
+        }
+    }
+}
+
+/// Verify a combined email + country proof
+///
+/// The bounds hash is derived from the supported country whose id is
+/// `country_id_hex`, so a proof against any other box does not verify.
+/// Malformed input or an unknown country still runs a full verification
+/// against placeholders; see `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+pub fn verify_email_and_country_proof(
+    proof_hex: &str,
+    domain_hash_hex: &str,
+    country_id_hex: &str,
+    commitment_hex: &str,
+) -> bool {
+    let state = lock_state(&COMPOSITE_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (domain_hash, domain_hash_ok) = decode_field_or_placeholder(domain_hash_hex);
+    let (country_id, country_id_ok) = decode_field_or_placeholder(country_id_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let (bounds_hash, country_ok) = match COUNTRIES.iter().find(|c| country_code_to_field(c.code) == country_id) {
+        Some(c) => (exclusion_bounds_hash(c), true),
+        None => (Fr::from(0u64), false),
+    };
+
+    let verified = prover
+        .verify(&proof, &(domain_hash, country_id, bounds_hash, commitment))
+        .unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & domain_hash_ok & country_id_ok & country_ok & commitment_ok & verified
+}
+
+// ============== REGION VERIFICATION ==============
+
+/// Generate a ZK proof that coordinates lie within a region, e.g. "US-CA".
+///
+/// The point is checked against the region's polygon in-circuit; the
+/// proof reveals only the region.
+#[wasm_bindgen]
+pub fn prove_region(lat: f64, lng: f64, region_code: &str) -> RegionProofResult {
+    let region = match Region::by_code(region_code) {
+        Some(r) => r,
+        None => return RegionProofResult::failure(region_code, format!("Unknown region code: {}", region_code)),
+    };
+
+    if let Err(e) = normalize_coords(lat, lng) {
+        return RegionProofResult::failure(region_code, e.to_string());
+    }
+    if !region.contains(&Coordinates::new(lat, lng)) {
+        return RegionProofResult::failure(region_code, format!("Coordinates not within {}", region.name));
+    }
+
+    let state = lock_state(&REGION_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return RegionProofResult::failure(
+                region_code,
+                "Region prover not initialized. Call init_region_prover() first.".to_string(),
+            )
+        }
+    };
+
+    let circuit = match RegionProofCircuit::new_with_witness(lat, lng, region) {
+        Ok(c) => c,
+        Err(e) => return RegionProofResult::failure(region_code, e.to_string()),
+    };
+
+    let progress = Progress::start("region", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (region_id, _, commitment))) => {
+            progress.done();
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+            RegionProofResult {
+                success: true,
+                region_code: region.code.to_string(),
+                region_name: region.name.to_string(),
+                parent_country: region.parent_country.to_string(),
+                proof_bytes,
+                region_id: field_to_hex(&region_id),
+                commitment: field_to_hex(&commitment),
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            RegionProofResult::failure(region_code, error)
+        }
+    }
+}
+
+/// Verify a region proof
+///
+/// The region identifier and polygon hash are derived from `region_code`
+/// and the built-in region outlines, so the proof must have been made
+/// against the same polygon. Malformed input or an unknown region still
+/// runs a full verification against placeholders; see
+/// `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+pub fn verify_region_proof(proof_hex: &str, region_code: &str, commitment_hex: &str) -> bool {
+    let state = lock_state(&REGION_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let polygon_hash = Region::by_code(region_code).and_then(|r| region_polygon_hash(r.polygon));
+    let (polygon_hash, region_ok) = match polygon_hash {
+        Some(hash) => (hash, true),
+        None => (Fr::from(0u64), false),
+    };
+    let region_id = region_code_to_field(region_code);
+
+    let verified = prover
+        .verify(&proof, &(region_id, polygon_hash, commitment))
+        .unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & commitment_ok & region_ok & verified
+}
+
+/// Check if region prover is initialized
+#[wasm_bindgen]
+pub fn is_region_prover_ready() -> bool {
+    lock_state(&REGION_PROVER).is_some()
+}
+
+// ============== COUNTRY EXCLUSION ==============
+
+/// Generate a ZK proof that coordinates lie OUTSIDE a country's box.
+///
+/// The point is checked against the country's bounding box in-circuit;
+/// the proof reveals only which country it is not in. Boxes are
+/// approximate, so a point inside the box but outside the country
+/// cannot be proven excluded.
+#[wasm_bindgen]
+pub fn prove_not_in_country(lat: f64, lng: f64, country_code: &str) -> ExclusionProofResult {
+    let country = match COUNTRIES.iter().find(|c| c.code.eq_ignore_ascii_case(country_code)) {
+        Some(c) => c,
+        None => {
+            return ExclusionProofResult::failure(country_code, format!("Unknown country code: {}", country_code))
+        }
+    };
+
+    if let Err(e) = normalize_coords(lat, lng) {
+        return ExclusionProofResult::failure(country.code, e.to_string());
+    }
+    if country.contains(&Coordinates::new(lat, lng)) {
+        return ExclusionProofResult::failure(country.code, format!("Coordinates are within {}", country.name));
+    }
+
+    let state = lock_state(&EXCLUSION_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return ExclusionProofResult::failure(
+                country.code,
+                "Exclusion prover not initialized. Call init_exclusion_prover() first.".to_string(),
+            )
+        }
+    };
+
+    let circuit = match ExclusionProofCircuit::new_with_witness(lat, lng, country) {
+        Ok(c) => c,
+        Err(e) => return ExclusionProofResult::failure(country.code, e.to_string()),
+    };
+
+    let progress = Progress::start("exclusion", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (country_id, _, commitment))) => {
+            progress.done();
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+            ExclusionProofResult {
+                success: true,
+                country_code: country.code.to_string(),
                 country_name: country.name.to_string(),
                 proof_bytes,
-                public_input: hex::encode(public_input_bytes),
+                country_id: field_to_hex(&country_id),
+                commitment: field_to_hex(&commitment),
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            ExclusionProofResult::failure(country.code, error)
+        }
+    }
+}
+
+/// Verify a country exclusion proof
+///
+/// The country identifier and bounds hash are derived from
+/// `country_code` and the built-in country boxes, so the proof must have
+/// been made against the same box. Malformed input or an unknown country
+/// still runs a full verification against placeholders; see
+/// `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+pub fn verify_exclusion_proof(proof_hex: &str, country_code: &str, commitment_hex: &str) -> bool {
+    let state = lock_state(&EXCLUSION_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => return false,
+    };
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let country = COUNTRIES.iter().find(|c| c.code.eq_ignore_ascii_case(country_code));
+    let (bounds_hash, country_ok) = match country {
+        Some(c) => (exclusion_bounds_hash(c), true),
+        None => (Fr::from(0u64), false),
+    };
+    let country_id = country_code_to_field(country_code);
+
+    let verified = prover
+        .verify(&proof, &(country_id, bounds_hash, commitment))
+        .unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & commitment_ok & country_ok & verified
+}
+
+/// Check if exclusion prover is initialized
+#[wasm_bindgen]
+pub fn is_exclusion_prover_ready() -> bool {
+    lock_state(&EXCLUSION_PROVER).is_some()
+}
+
+// ============== COUNTRY GROUPS ==============
+
+/// Generate a ZK proof that coordinates lie in one of a group's
+/// countries (e.g. "EU"), without revealing which.
+///
+/// The point is checked against every member's bounding box in-circuit;
+/// the proof reveals only the group.
+#[wasm_bindgen]
+pub fn prove_country_group(lat: f64, lng: f64, group_code: &str) -> CountryGroupProofResult {
+    let group = match CountryGroup::by_code(group_code) {
+        Some(g) => g,
+        None => {
+            return CountryGroupProofResult::failure(group_code, format!("Unknown country group: {}", group_code))
+        }
+    };
+
+    if let Err(e) = normalize_coords(lat, lng) {
+        return CountryGroupProofResult::failure(group.code, e.to_string());
+    }
+    if !group.contains(&Coordinates::new(lat, lng)) {
+        return CountryGroupProofResult::failure(group.code, format!("Coordinates are not within {}", group.name));
+    }
+
+    let state = lock_state(&COUNTRY_GROUP_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return CountryGroupProofResult::failure(
+                group.code,
+                "Country group prover not initialized. Call init_country_group_prover() first.".to_string(),
+            )
+        }
+    };
+
+    let circuit = match CountrySetCircuit::new_with_witness(lat, lng, group) {
+        Ok(c) => c,
+        Err(e) => return CountryGroupProofResult::failure(group.code, e.to_string()),
+    };
+
+    let progress = Progress::start("country_group", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (group_id, _, commitment))) => {
+            progress.done();
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+            CountryGroupProofResult {
+                success: true,
+                group_code: group.code.to_string(),
+                group_name: group.name.to_string(),
+                proof_bytes,
+                group_id: field_to_hex(&group_id),
+                commitment: field_to_hex(&commitment),
                 error: None,
             }
         }
         Err(e) => {
-            CountryProofResult {
-                success: false,
-                country_code: String::new(),
-                country_name: String::new(),
-                proof_bytes: Vec::new(),
-                public_input: String::new(),
-                error: Some(format!("Proof generation failed: {:?}", e)),
-            }
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            CountryGroupProofResult::failure(group.code, error)
         }
     }
 }
 
-/// Verify a country proof
+/// Verify a country group proof
+///
+/// The group identifier and bounds hash are derived from `group_code`
+/// and the built-in groups. Malformed input or an unknown group still
+/// runs a full verification against placeholders; see
+/// `decode_proof_or_placeholder`.
 #[wasm_bindgen]
-pub fn verify_country_proof(proof_hex: &str, public_input_hex: &str) -> bool {
-    let proof_bytes = match hex::decode(proof_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
+pub fn verify_country_group_proof(proof_hex: &str, group_code: &str, commitment_hex: &str) -> bool {
+    let state = lock_state(&COUNTRY_GROUP_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => return false,
     };
-    
-    let public_input_bytes = match hex::decode(public_input_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let (bounds_hash, group_ok) = match CountryGroup::by_code(group_code).and_then(country_group_bounds_hash) {
+        Some(hash) => (hash, true),
+        None => (Fr::from(0u64), false),
     };
-    
-    let proof = match ark_groth16::Proof::<Bn254>::deserialize_compressed(&proof_bytes[..]) {
-        Ok(p) => p,
-        Err(_) => return false,
+    let group_id = country_group_to_field(group_code);
+
+    let verified = prover
+        .verify(&proof, &(group_id, bounds_hash, commitment))
+        .unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & commitment_ok & group_ok & verified
+}
+
+/// Check if country group prover is initialized
+#[wasm_bindgen]
+pub fn is_country_group_prover_ready() -> bool {
+    lock_state(&COUNTRY_GROUP_PROVER).is_some()
+}
+
+// ============== CORPORATE EMAIL ==============
+
+/// Generate a ZK proof that an email is at a corporate (non-freemail)
+/// domain, without revealing the domain.
+///
+/// The domain is checked against `FREEMAIL_DOMAINS` in-circuit. The
+/// commitment matches the one `prove_email_domain` would produce for
+/// the same email, DKIM data and nonce.
+#[wasm_bindgen]
+pub fn prove_corporate_email(email: &str, dkim_signature: &str, auth_results: &str) -> CorporateEmailProofResult {
+    let input = EmailProofInput::new(email, dkim_signature, auth_results);
+
+    if !input.dkim_verified {
+        return CorporateEmailProofResult::failure(
+            false,
+            "DKIM verification failed - email may not be authentic".to_string(),
+        );
+    }
+    if input.domain.is_empty() {
+        return CorporateEmailProofResult::failure(true, format!("Could not extract a domain from {}", email));
+    }
+    if is_freemail_domain(&input.domain) {
+        return CorporateEmailProofResult::failure(true, format!("{} is a freemail domain", input.domain));
+    }
+    if let Err(e) = check_email_allowlist(&input.domain) {
+        return CorporateEmailProofResult::failure(true, e);
+    }
+
+    let state = lock_state(&CORPORATE_EMAIL_PROVER);
+    let prover = match state.as_ref() {
+        Some(p) => p,
+        None => {
+            return CorporateEmailProofResult::failure(
+                true,
+                "Corporate email prover not initialized. Call init_corporate_email_prover() first.".to_string(),
+            )
+        }
     };
-    
-    let public_input = match Fr::deserialize_compressed(&public_input_bytes[..]) {
-        Ok(f) => f,
-        Err(_) => return false,
+
+    let circuit = match CorporateEmailCircuit::new_with_witness(&input) {
+        Ok(c) => c,
+        Err(e) => return CorporateEmailProofResult::failure(true, e.to_string()),
     };
-    
-    let state = COUNTRY_PROVER.lock().unwrap();
+
+    let progress = Progress::start("corporate_email", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (_, commitment))) => {
+            progress.done();
+            let mut proof_bytes = Vec::new();
+            proof.serialize_compressed(&mut proof_bytes).unwrap();
+
+            CorporateEmailProofResult {
+                success: true,
+                proof_bytes,
+                commitment: field_to_hex(&commitment),
+                dkim_verified: true,
+                error: None,
+            }
+        }
+        Err(e) => {
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            CorporateEmailProofResult::failure(true, error)
+        }
+    }
+}
+
+/// Verify a corporate email proof
+///
+/// The `is_corporate` public input is fixed to true, so a proof that a
+/// domain is freemail does not verify. Malformed input still runs a full
+/// verification against placeholders; see `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+pub fn verify_corporate_email_proof(proof_hex: &str, commitment_hex: &str) -> bool {
+    let state = lock_state(&CORPORATE_EMAIL_PROVER);
     let prover = match state.as_ref() {
         Some(p) => p,
         None => return false,
     };
-    
-    Groth16::<Bn254>::verify_with_processed_vk(&prover.prepared_vk, &[public_input], &proof)
-        .unwrap_or(false)
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+
+    let verified = prover.verify(&proof, &(Fr::from(1u64), commitment)).unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & commitment_ok & verified
 }
 
-// ============== EMAIL DOMAIN VERIFICATION ==============
+/// Check if corporate email prover is initialized
+#[wasm_bindgen]
+pub fn is_corporate_email_prover_ready() -> bool {
+    lock_state(&CORPORATE_EMAIL_PROVER).is_some()
+}
 
-/// Generate a REAL ZK proof of email domain ownership.
-/// 
-/// This creates a Groth16 proof that you own an email at the specified domain
-/// without revealing the actual email address.
+// ============== CHALLENGE EMAIL ==============
+
+/// Generate a ZK proof of a domain from a challenge emailed to it.
+///
+/// For mail that arrives without a usable DKIM signature: the server
+/// sends `challenge` to the claimed address and the user pastes it back
+/// here. The proof binds the challenge to `domain` without revealing it.
 #[wasm_bindgen]
-pub fn prove_email_domain(domain: &str, dkim_signature: &str, auth_results: &str) -> EmailProofResult {
-    // Verify DKIM passed (auth_results is the reliable indicator)
-    // Gmail and most providers set auth_results even if raw DKIM header isn't exposed
-    let dkim_verified = auth_results.to_lowercase().contains("dkim=pass");
+pub fn prove_email_with_challenge(domain: &str, challenge: &str) -> ChallengeEmailProofResult {
+    if challenge.is_empty() {
+        return ChallengeEmailProofResult::failure("Challenge must not be empty".to_string());
+    }
 
-    if !dkim_verified {
-        return EmailProofResult {
-            success: false,
-            domain: domain.to_string(),
-            proof_bytes: Vec::new(),
-            domain_hash: String::new(),
-            commitment: String::new(),
-            dkim_verified: false,
-            error: Some("DKIM verification failed - email may not be authentic".to_string()),
-        };
+    if let Err(e) = check_email_allowlist(domain) {
+        return ChallengeEmailProofResult::failure(e);
     }
-    
-    // Use DKIM signature if available, otherwise use auth_results as proof data
-    let dkim_data = if !dkim_signature.is_empty() {
-        dkim_signature.to_string()
-    } else {
-        auth_results.to_string()
-    };
 
-    // Get email prover state
-    let state = EMAIL_PROVER.lock().unwrap();
+    let state = lock_state(&CHALLENGE_EMAIL_PROVER);
     let prover = match state.as_ref() {
         Some(p) => p,
         None => {
-            return EmailProofResult {
-                success: false,
-                domain: domain.to_string(),
-                proof_bytes: Vec::new(),
-                domain_hash: String::new(),
-                commitment: String::new(),
-                dkim_verified,
-                error: Some("Email prover not initialized. Call init_email_prover() first.".to_string()),
-            }
+            return ChallengeEmailProofResult::failure(
+                "Challenge email prover not initialized. Call init_challenge_email_prover() first.".to_string(),
+            )
         }
     };
 
-    // Create proof input with actual DKIM data
-    let input = EmailProofInput::from_domain_with_dkim(domain, dkim_verified, &dkim_data);
-    
-    // Create circuit with real witness
-    let circuit = EmailDomainCircuit::new_with_witness(&input);
-    
-    // Get public inputs before circuit is consumed
-    let domain_hash = circuit.get_domain_hash().unwrap();
-    let commitment = circuit.get_commitment().unwrap();
-    
-    // Generate Groth16 proof
-    let mut rng = StdRng::seed_from_u64(js_sys::Date::now() as u64);
-    
-    match Groth16::<Bn254>::prove(&prover.proving_key, circuit, &mut rng) {
-        Ok(proof) => {
-            // Serialize proof
+    let circuit = match ChallengeEmailCircuit::new_with_witness(domain, challenge) {
+        Ok(c) => c,
+        Err(e) => return ChallengeEmailProofResult::failure(e.to_string()),
+    };
+
+    let progress = Progress::start("challenge_email", "prove");
+    match secure_rng().and_then(|mut rng| prover.prove(circuit, &mut rng)) {
+        Ok((proof, (domain_hash, commitment))) => {
+            progress.done();
             let mut proof_bytes = Vec::new();
             proof.serialize_compressed(&mut proof_bytes).unwrap();
-            
-            // Serialize public inputs
-            let mut domain_hash_bytes = Vec::new();
-            domain_hash.serialize_compressed(&mut domain_hash_bytes).unwrap();
-            
-            let mut commitment_bytes = Vec::new();
-            commitment.serialize_compressed(&mut commitment_bytes).unwrap();
-            
-            EmailProofResult {
+
+            ChallengeEmailProofResult {
                 success: true,
-                domain: domain.to_string(),
                 proof_bytes,
-                domain_hash: hex::encode(domain_hash_bytes),
-                commitment: hex::encode(commitment_bytes),
-                dkim_verified,
+                domain_hash: field_to_hex(&domain_hash),
+                commitment: field_to_hex(&commitment),
                 error: None,
             }
         }
         Err(e) => {
-            EmailProofResult {
-                success: false,
-                domain: domain.to_string(),
-                proof_bytes: Vec::new(),
-                domain_hash: String::new(),
-                commitment: String::new(),
-                dkim_verified,
-                error: Some(format!("Proof generation failed: {:?}", e)),
-            }
+            let error = format!("Proof generation failed: {:?}", e);
+            progress.failed(&error);
+            ChallengeEmailProofResult::failure(error)
         }
     }
 }
 
-/// Verify an email domain proof
+/// Verify a challenge email proof
+///
+/// The server passes the challenge it sent, and the expected commitment
+/// is recomputed from it and `domain`, so a proof made with any other
+/// challenge does not verify. Malformed input still runs a full
+/// verification against placeholders; see `decode_proof_or_placeholder`.
 #[wasm_bindgen]
-pub fn verify_email_proof(proof_hex: &str, domain_hash_hex: &str, commitment_hex: &str) -> bool {
-    let proof_bytes = match hex::decode(proof_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
-    };
-    
-    let domain_hash_bytes = match hex::decode(domain_hash_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
-    };
-    
-    let commitment_bytes = match hex::decode(commitment_hex) {
-        Ok(b) => b,
-        Err(_) => return false,
-    };
-    
-    let proof = match ark_groth16::Proof::<Bn254>::deserialize_compressed(&proof_bytes[..]) {
-        Ok(p) => p,
-        Err(_) => return false,
-    };
-    
-    let domain_hash = match Fr::deserialize_compressed(&domain_hash_bytes[..]) {
-        Ok(f) => f,
-        Err(_) => return false,
-    };
-    
-    let commitment = match Fr::deserialize_compressed(&commitment_bytes[..]) {
-        Ok(f) => f,
-        Err(_) => return false,
-    };
-    
-    let state = EMAIL_PROVER.lock().unwrap();
+pub fn verify_challenge_email_proof(proof_hex: &str, domain: &str, challenge: &str) -> bool {
+    let state = lock_state(&CHALLENGE_EMAIL_PROVER);
     let prover = match state.as_ref() {
         Some(p) => p,
         None => return false,
     };
-    
-    // Public inputs: [domain_hash, commitment]
-    Groth16::<Bn254>::verify_with_processed_vk(&prover.prepared_vk, &[domain_hash, commitment], &proof)
-        .unwrap_or(false)
+
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let domain_hash = string_to_field(&normalize_domain(domain));
+    let commitment = challenge_commitment(challenge, domain);
+
+    let verified = prover.verify(&proof, &(domain_hash, commitment)).unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & verified
 }
 
-/// Check if email prover is initialized
+/// Check if challenge email prover is initialized
 #[wasm_bindgen]
-pub fn is_email_prover_ready() -> bool {
-    EMAIL_PROVER.lock().unwrap().is_some()
+pub fn is_challenge_email_prover_ready() -> bool {
+    lock_state(&CHALLENGE_EMAIL_PROVER).is_some()
 }
 
 // ============== UTILITIES ==============
@@ -598,6 +2165,23 @@ pub fn get_supported_countries() -> String {
     serde_json::to_string(&countries).unwrap_or_else(|_| "[]".to_string())
 }
 
+/// Get list of supported regions as JSON
+#[wasm_bindgen]
+pub fn get_supported_regions() -> String {
+    let regions: Vec<serde_json::Value> = REGIONS
+        .iter()
+        .map(|r| {
+            serde_json::json!({
+                "code": r.code,
+                "name": r.name,
+                "country": r.parent_country
+            })
+        })
+        .collect();
+
+    serde_json::to_string(&regions).unwrap_or_else(|_| "[]".to_string())
+}
+
 /// Get version info
 #[wasm_bindgen]
 pub fn get_version() -> String {
@@ -605,14 +2189,596 @@ pub fn get_version() -> String {
 }
 
 /// Hash a string to a field element (for ZK circuits)
+///
+/// Same as the circuits' `string_to_field`, hex-encoded like the proof
+/// results, so `hash_to_field(domain)` is the `domain_hash` an email
+/// proof for `domain` publishes. Pass the domain normalized (lowercase,
+/// no trailing dot), as the prover hashes it that way.
 #[wasm_bindgen]
 pub fn hash_to_field(input: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(input.as_bytes());
-    let hash = hasher.finalize();
-    let fr = Fr::from_be_bytes_mod_order(&hash);
-    
-    let mut bytes = Vec::new();
-    fr.serialize_compressed(&mut bytes).unwrap();
-    hex::encode(bytes)
+    field_to_hex(&string_to_field(input))
+}
+
+/// The `domain_hash` an email proof for `domain` will publish.
+///
+/// Unlike `hash_to_field`, the domain is normalized first (trimmed,
+/// lowercased, trailing dot dropped) exactly as the prover does, so a
+/// UI can show the value before proving and it will match the proof.
+#[wasm_bindgen]
+pub fn compute_domain_hash(domain: &str) -> String {
+    field_to_hex(&string_to_field(&normalize_domain(domain)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm_verify::{verify_country_proof_with_vk, verify_email_proof};
+
+    #[test]
+    fn test_verify_email_proof_failures_all_false() {
+        let mut rng = StdRng::seed_from_u64(0x454D41494C5F5A4B);
+        let prover = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let (proof, (domain_hash, commitment, identity)) = prover
+            .prove(EmailDomainCircuit::new_with_witness(&input).unwrap(), &mut rng)
+            .unwrap();
+        *EMAIL_PROVER.lock().unwrap() = Some(prover);
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        let proof_hex = hex::encode(proof_bytes);
+        let domain_hash_hex = field_to_hex(&domain_hash);
+        let commitment_hex = field_to_hex(&commitment);
+        let identity_hex = field_to_hex(&identity);
+
+        assert!(verify_email_proof(&proof_hex, &domain_hash_hex, &commitment_hex, &identity_hex));
+
+        // Malformed: bad hex, truncated proof, bad field encoding
+        assert!(!verify_email_proof("zz", &domain_hash_hex, &commitment_hex, &identity_hex));
+        assert!(!verify_email_proof(&proof_hex[..20], &domain_hash_hex, &commitment_hex, &identity_hex));
+        assert!(!verify_email_proof(&proof_hex, "00", &commitment_hex, &identity_hex));
+
+        // Well-formed but wrong
+        let other_domain = field_to_hex(&crate::circuit::string_to_field("example.com"));
+        assert!(!verify_email_proof(&proof_hex, &other_domain, &commitment_hex, &identity_hex));
+        assert!(!verify_email_proof(&proof_hex, &commitment_hex, &domain_hash_hex, &identity_hex));
+        assert!(!verify_email_proof(&proof_hex, &domain_hash_hex, &commitment_hex, &commitment_hex));
+    }
+
+    #[test]
+    fn test_progress_callback_receives_stages() {
+        let events: Rc<RefCell<Vec<(String, f64, String)>>> = Rc::default();
+        let sink = events.clone();
+        set_progress_hook(Some(Rc::new(move |stage: &str, elapsed_ms: f64, circuit: &str| {
+            sink.borrow_mut().push((stage.to_string(), elapsed_ms, circuit.to_string()));
+        })));
+
+        assert!(init_composite_prover(None));
+        let result = prove_email_and_country("google.com", "sig", "dkim=pass", 48.8566, 2.3522, None);
+        assert!(result.success(), "{:?}", result.error());
+        set_progress_hook(None);
+
+        let events = events.borrow();
+        let stages: Vec<&str> = events.iter().map(|(stage, _, _)| stage.as_str()).collect();
+        assert_eq!(stages, ["setup_start", "setup_done", "prove_start", "prove_done"]);
+        assert!(events.iter().all(|(_, elapsed, circuit)| *elapsed >= 0.0 && circuit == "composite"));
+        assert!(events[1].1 > 0.0);
+
+        // Checked here rather than in a test of its own: a second test
+        // initializing the composite prover would race the setup events
+        assert!(verify_email_and_country_proof(
+            &result.proof_hex(),
+            &result.domain_hash(),
+            &result.country_id(),
+            &result.commitment(),
+        ));
+
+        // Relabelling the proof as another country picks that country's box
+        assert!(!verify_email_and_country_proof(
+            &result.proof_hex(),
+            &result.domain_hash(),
+            &field_to_hex(&country_code_to_field("US")),
+            &result.commitment(),
+        ));
+
+        // Ottawa is inside both the US and Canada boxes
+        let ambiguous = prove_email_and_country("google.com", "sig", "dkim=pass", 45.4215, -75.6972, None);
+        assert!(!ambiguous.success());
+        let canada =
+            prove_email_and_country("google.com", "sig", "dkim=pass", 45.4215, -75.6972, Some("CA".to_string()));
+        assert!(canada.success(), "{:?}", canada.error());
+        assert_eq!(canada.country_id(), field_to_hex(&country_code_to_field("CA")));
+    }
+
+    #[test]
+    fn test_prove_and_verify_region() {
+        assert!(init_region_prover(None));
+
+        let result = prove_region(37.7749, -122.4194, "us-ca");
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.region_code(), "US-CA");
+        assert_eq!(result.parent_country(), "US");
+        assert_eq!(result.region_id(), field_to_hex(&region_code_to_field("US-CA")));
+
+        assert!(verify_region_proof(&result.proof_hex(), "US-CA", &result.commitment()));
+        assert!(!verify_region_proof(&result.proof_hex(), "US-NV", &result.commitment()));
+        assert!(!verify_region_proof(&result.proof_hex(), "XX-YY", &result.commitment()));
+
+        // Las Vegas is not in California
+        let outside = prove_region(36.1699, -115.1398, "US-CA");
+        assert!(!outside.success());
+        assert!(prove_region(36.1699, -115.1398, "US-NV").success());
+    }
+
+    #[test]
+    fn test_prove_and_verify_not_in_country() {
+        assert!(init_exclusion_prover(None));
+
+        // Paris is not in the US
+        let result = prove_not_in_country(48.8566, 2.3522, "us");
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.country_code(), "US");
+        assert_eq!(result.country_id(), field_to_hex(&country_code_to_field("US")));
+
+        assert!(verify_exclusion_proof(&result.proof_hex(), "US", &result.commitment()));
+        assert!(!verify_exclusion_proof(&result.proof_hex(), "CA", &result.commitment()));
+        assert!(!verify_exclusion_proof(&result.proof_hex(), "ZZ", &result.commitment()));
+
+        // San Francisco is in the US
+        let inside = prove_not_in_country(37.7749, -122.4194, "US");
+        assert!(!inside.success());
+        assert!(inside.error().unwrap().contains("within United States"));
+
+        let invalid = prove_not_in_country(95.0, 2.3522, "US");
+        assert!(invalid.error().unwrap().contains("Latitude 95 is outside"));
+    }
+
+    #[test]
+    fn test_public_input_bytes_are_big_endian() {
+        let handle = CountryProverHandle::new().unwrap();
+        let country = handle.prove("FR", None);
+        assert!(country.success(), "{:?}", country.error());
+        let bytes = country.public_input_bytes();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(Fr::from_be_bytes_mod_order(&bytes), country_code_to_field("FR"));
+
+        assert!(init_email_prover(None));
+        let email = prove_email_domain("football.example.com", "sig", "dkim=pass", None);
+        assert!(email.success(), "{:?}", email.error());
+        assert_eq!(email.domain_hash_bytes().len(), 32);
+        assert_eq!(email.commitment_bytes().len(), 32);
+        assert_eq!(
+            Fr::from_be_bytes_mod_order(&email.domain_hash_bytes()),
+            field_from_hex(&email.domain_hash()).unwrap()
+        );
+
+        assert!(CountryProofResult::failure("no".to_string()).public_input_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_shared_identity_secret_links_proofs() {
+        let handle = CountryProverHandle::new().unwrap();
+        assert!(init_email_prover(None));
+
+        let country = handle.prove("FR", Some("alice".to_string()));
+        let email = prove_email_domain("football.example.com", "sig", "dkim=pass", Some("alice".to_string()));
+        assert!(country.success() && email.success());
+        assert_eq!(country.identity_commitment(), email.identity_commitment());
+        assert!(verify_email_proof(
+            &email.proof_hex(),
+            &email.domain_hash(),
+            &email.commitment(),
+            &country.identity_commitment(),
+        ));
+
+        assert_ne!(handle.prove("FR", Some("bob".to_string())).identity_commitment(), country.identity_commitment());
+        assert_ne!(handle.prove("FR", None).identity_commitment(), country.identity_commitment());
+    }
+
+    #[test]
+    fn test_prove_and_verify_country_group() {
+        assert!(init_country_group_prover(None));
+
+        // Paris is in the EU
+        let result = prove_country_group(48.8566, 2.3522, "eu");
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.group_code(), "EU");
+        assert_eq!(result.group_id(), field_to_hex(&country_group_to_field("EU")));
+
+        assert!(verify_country_group_proof(&result.proof_hex(), "EU", &result.commitment()));
+        assert!(!verify_country_group_proof(&result.proof_hex(), "NA", &result.commitment()));
+        assert!(!verify_country_group_proof(&result.proof_hex(), "ZZ", &result.commitment()));
+
+        // New York is not
+        let outside = prove_country_group(40.7128, -74.006, "EU");
+        assert!(!outside.success());
+        assert!(outside.error().unwrap().contains("not within European Union"));
+    }
+
+    #[test]
+    fn test_hash_to_field_matches_email_domain_hash() {
+        assert!(init_email_prover(None));
+
+        let result = prove_email_domain("football.example.com", "sig", "dkim=pass", None);
+        assert!(result.success(), "{:?}", result.error());
+
+        let domain_hash = hash_to_field("football.example.com");
+        assert_eq!(domain_hash, result.domain_hash());
+        assert!(verify_email_proof(&result.proof_hex(), &domain_hash, &result.commitment(), &result.identity_commitment()));
+        assert!(!verify_email_proof(
+            &result.proof_hex(),
+            &hash_to_field("google.com"),
+            &result.commitment(),
+            &result.identity_commitment(),
+        ));
+    }
+
+    #[test]
+    fn test_compute_domain_hash_matches_proof() {
+        assert!(init_email_prover(None));
+
+        let result = prove_email_domain("Football.Example.com.", "sig", "dkim=pass", None);
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(compute_domain_hash("Football.Example.com."), result.domain_hash());
+        assert_eq!(compute_domain_hash(" football.example.com"), result.domain_hash());
+        assert_ne!(hash_to_field("Football.Example.com."), result.domain_hash());
+    }
+
+    #[test]
+    fn test_prove_corporate_email() {
+        assert!(init_corporate_email_prover(None));
+
+        let corporate = prove_corporate_email("alice@google.com", "sig", "dkim=pass");
+        assert!(corporate.success(), "{:?}", corporate.error());
+        assert!(verify_corporate_email_proof(&corporate.proof_hex(), &corporate.commitment()));
+        assert!(!verify_corporate_email_proof(&corporate.proof_hex(), &field_to_hex(&Fr::from(7u64))));
+
+        let freemail = prove_corporate_email("bob@gmail.com", "sig", "dkim=pass");
+        assert!(!freemail.success());
+        assert_eq!(freemail.error().unwrap(), "gmail.com is a freemail domain");
+
+        assert!(!prove_corporate_email("alice@google.com", "sig", "dkim=fail").success());
+    }
+
+    #[test]
+    fn test_prove_email_with_challenge() {
+        assert!(init_challenge_email_prover(Some(0x4348414C4C454E47)));
+
+        let result = prove_email_with_challenge("Corp.Example.", "K7-QX2P-91");
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.domain_hash(), compute_domain_hash("corp.example"));
+        assert_eq!(result.commitment(), field_to_hex(&challenge_commitment("K7-QX2P-91", "corp.example")));
+        assert!(verify_challenge_email_proof(&result.proof_hex(), "corp.example", "K7-QX2P-91"));
+
+        // Wrong challenge or domain on the server side
+        assert!(!verify_challenge_email_proof(&result.proof_hex(), "corp.example", "K7-QX2P-92"));
+        assert!(!verify_challenge_email_proof(&result.proof_hex(), "other.example", "K7-QX2P-91"));
+
+        // A proof made from a wrong challenge
+        let wrong = prove_email_with_challenge("corp.example", "K7-QX2P-92");
+        assert!(wrong.success());
+        assert!(!verify_challenge_email_proof(&wrong.proof_hex(), "corp.example", "K7-QX2P-91"));
+
+        assert!(!prove_email_with_challenge("corp.example", "").success());
+    }
+
+    /// Signed sample from RFC 8463 Appendix A.
+    const SIGNED_EML: &str = "\
+DKIM-Signature: v=1; a=ed25519-sha256; c=relaxed/relaxed;
+ d=football.example.com; i=@football.example.com;
+ q=dns/txt; s=brisbane; t=1528637909; h=from : to :
+ subject : date : message-id : from : subject : date;
+ bh=2jUSOH9NhtVGCQWNr9BrIAPreKQjO6Sn7XIkfJVOzv8=;
+ b=/gCrinpcQOoIfuHNQIbq4pgh9kyIK3AQUdt9OdqQehSwhEIug4D11Bus
+ Fa3bT3FY5OsU7ZbnKELq+eXdp1Q1Dw==
+From: Joe SixPack <joe@football.example.com>
+To: Suzie Q <suzie@shopping.example.net>
+Subject: Is dinner ready?
+Date: Fri, 11 Jul 2003 21:00:37 -0700 (PDT)
+Message-ID: <20030712040037.46341.5F8J@football.example.com>
+
+Hi.
+
+We lost the game.  Are you hungry yet?
+
+Joe.
+";
+
+    #[test]
+    fn test_prove_email_from_eml() {
+        assert!(init_email_prover(None));
+        let key = "v=DKIM1; k=ed25519; p=11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=";
+
+        let result = prove_email_from_eml(SIGNED_EML, Some(key.to_string()), None);
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.domain(), "football.example.com");
+        assert!(result.dkim_verified());
+        assert_eq!(result.domain_hash(), hash_to_field("football.example.com"));
+
+        // Tampered body fails signature verification
+        let tampered = SIGNED_EML.replace("hungry", "thirsty");
+        let result = prove_email_from_eml(&tampered, Some(key.to_string()), None);
+        assert!(result.error().unwrap().contains("DKIM verification failed"));
+
+        // No key and no Authentication-Results verdict to fall back on
+        assert!(!prove_email_from_eml(SIGNED_EML, None, None).success());
+
+        let no_from = prove_email_from_eml("Subject: hi\n\nbody\n", None, None);
+        assert!(no_from.error().unwrap().contains("Missing From header"));
+
+        let no_dkim = prove_email_from_eml("From: a@example.com\n\nbody\n", None, None);
+        assert!(no_dkim.error().unwrap().contains("no DKIM-Signature"));
+    }
+
+    #[test]
+    fn test_prove_email_from_attestation() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        assert!(init_email_prover(None));
+        let notary = SigningKey::from_bytes(&[3u8; 32]);
+        let notary_key = hex::encode(notary.verifying_key().to_bytes());
+        let transcript = [5u8; 32];
+        let message = AttestationProof::signed_message("football.example.com", &transcript);
+        let signature = hex::encode(notary.sign(&message).to_bytes());
+
+        assert!(!set_trusted_notaries(vec!["00".to_string()]));
+        assert!(set_trusted_notaries(vec![notary_key]));
+
+        let result = prove_email_from_attestation("football.example.com", &hex::encode(transcript), &signature, None);
+        assert!(result.success(), "{:?}", result.error());
+        assert_eq!(result.domain_hash(), hash_to_field("football.example.com"));
+        assert!(verify_email_proof(
+            &result.proof_hex(),
+            &result.domain_hash(),
+            &result.commitment(),
+            &result.identity_commitment(),
+        ));
+
+        let forged = prove_email_from_attestation("google.com", &hex::encode(transcript), &signature, None);
+        assert_eq!(forged.error().unwrap(), "Attestation is not signed by a trusted notary");
+        let malformed = prove_email_from_attestation("football.example.com", "00", &signature, None);
+        assert_eq!(malformed.error().unwrap(), "Invalid attestation encoding");
+
+        // A self-signed attestation from an unpinned notary is rejected
+        let rogue = SigningKey::from_bytes(&[4u8; 32]);
+        let message = AttestationProof::signed_message("google.com", &transcript);
+        let rogue_signature = hex::encode(rogue.sign(&message).to_bytes());
+        let self_signed = prove_email_from_attestation("google.com", &hex::encode(transcript), &rogue_signature, None);
+        assert_eq!(self_signed.error().unwrap(), "Attestation is not signed by a trusted notary");
+    }
+
+    #[test]
+    fn test_select_country_overlap() {
+        // Ottawa is inside both the US and Canada boxes
+        let ambiguous = select_country(45.4215, -75.6972, None);
+        assert!(ambiguous.unwrap_err().contains("US, CA"));
+
+        assert_eq!(select_country(45.4215, -75.6972, Some("ca")).unwrap().code, "CA");
+        assert!(select_country(45.4215, -75.6972, Some("FR")).is_err());
+        assert_eq!(select_country(48.8566, 2.3522, None).unwrap().code, "FR");
+    }
+
+    #[test]
+    fn test_verify_country_proof_with_exported_vk() {
+        let mut rng = StdRng::seed_from_u64(0x5A4B5F5641554C54);
+        *COUNTRY_PROVER.lock().unwrap() =
+            Some(GenericProver::<CountryProofCircuit>::setup(&(), &mut rng).unwrap());
+
+        let vk_bytes = export_country_verifying_key().unwrap();
+        let result = {
+            let state = COUNTRY_PROVER.lock().unwrap();
+            let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+            let circuit =
+                CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 3, now_secs()).unwrap();
+            country_proof_result(state.as_ref().unwrap(), circuit, "US", "United States", 3, now_secs(), &mut rng)
+        };
+        assert!(result.success);
+
+        // Fresh state: no prover in this session
+        *COUNTRY_PROVER.lock().unwrap() = None;
+
+        let outcome = verify_country_proof_with_vk(
+            &result.proof_hex(),
+            &result.public_input,
+            &result.commitment,
+            3,
+            &result.nullifier,
+            result.issued_at,
+            &result.identity_commitment,
+            Some("US".to_string()),
+            Some(3600),
+            &vk_bytes,
+        );
+        assert!(outcome.valid(), "{:?}", outcome.error());
+
+        let wrong_epoch = verify_country_proof_with_vk(
+            &result.proof_hex(),
+            &result.public_input,
+            &result.commitment,
+            4,
+            &result.nullifier,
+            result.issued_at,
+            &result.identity_commitment,
+            None,
+            Some(3600),
+            &vk_bytes,
+        );
+        assert!(!wrong_epoch.valid());
+        assert!(wrong_epoch.error().is_none());
+
+        let wrong_country = verify_country_proof_with_vk(
+            &result.proof_hex(),
+            &result.public_input,
+            &result.commitment,
+            3,
+            &result.nullifier,
+            result.issued_at,
+            &result.identity_commitment,
+            Some("CA".to_string()),
+            Some(3600),
+            &vk_bytes,
+        );
+        assert!(!wrong_country.valid());
+        assert!(wrong_country.error().is_none());
+
+        let bad_vk = verify_country_proof_with_vk(
+            &result.proof_hex(),
+            &result.public_input,
+            &result.commitment,
+            3,
+            &result.nullifier,
+            result.issued_at,
+            &result.identity_commitment,
+            None,
+            Some(3600),
+            &vk_bytes[..vk_bytes.len() / 2],
+        );
+        assert!(!bad_vk.valid());
+        assert!(bad_vk.error().is_some());
+    }
+
+    #[test]
+    fn test_independent_country_prover_handles() {
+        let a = CountryProverHandle::new().unwrap();
+        let b = CountryProverHandle::with_seed(1).unwrap();
+
+        let result = a.prove_from_coords(48.8566, 2.3522, 5, None, None);
+        assert!(result.success(), "{:?}", result.error());
+        let verify = |h: &CountryProverHandle| {
+            h.verify(
+                &result.proof_hex(),
+                &result.public_input(),
+                &result.commitment(),
+                5,
+                &result.nullifier(),
+                result.issued_at(),
+                &result.identity_commitment(),
+                Some("FR".to_string()),
+                Some(3600),
+            )
+        };
+        assert!(verify(&a));
+        assert!(!verify(&b));
+
+        // A worker rebuilt from a's key bytes verifies a's proofs
+        let copy = CountryProverHandle::from_key_bytes(&a.proving_key_bytes().unwrap(), &a.verifying_key_bytes().unwrap()).unwrap();
+        assert!(verify(&copy));
+        assert!(b.prove("JP", None).success());
+    }
+
+    #[test]
+    fn test_poisoned_prover_lock_recovers() {
+        let _ = std::thread::spawn(|| {
+            let _guard = lock_state(&REGION_PROVER);
+            panic!("poison the lock");
+        })
+        .join();
+
+        assert!(REGION_PROVER.is_poisoned());
+        assert!(init_region_prover(None));
+        assert!(is_region_prover_ready());
+    }
+
+    #[test]
+    fn test_country_proof_freshness() {
+        let handle = CountryProverHandle::new().unwrap();
+        let bounds = ScaledBounds::new(41.3658, 51.124199, -5.5591, 9.6625);
+        let prove_at = |issued_at| {
+            let circuit = CountryProofCircuit::new_with_witness(48.8566, 2.3522, &bounds, "FR", 0, issued_at).unwrap();
+            country_proof_result(&handle.prover, circuit, "FR", "France", 0, issued_at, &mut secure_rng().unwrap())
+        };
+        let verify = |result: &CountryProofResult, max_age_secs| {
+            handle.verify(
+                &result.proof_hex(),
+                &result.public_input(),
+                &result.commitment(),
+                0,
+                &result.nullifier(),
+                result.issued_at(),
+                &result.identity_commitment(),
+                None,
+                max_age_secs,
+            )
+        };
+
+        let fresh = prove_at(now_secs() - 60);
+        assert!(verify(&fresh, Some(3600)));
+
+        let stale = prove_at(now_secs() - 7200);
+        assert!(verify(&stale, None));
+        assert!(!verify(&stale, Some(3600)));
+
+        // Claiming a newer issue time breaks the commitment
+        assert!(!handle.verify(
+            &stale.proof_hex(),
+            &stale.public_input(),
+            &stale.commitment(),
+            0,
+            &stale.nullifier(),
+            now_secs(),
+            &stale.identity_commitment(),
+            None,
+            Some(3600),
+        ));
+    }
+
+    #[test]
+    fn test_email_domain_allowlist() {
+        // The allowlist is thread-local, so other tests are unaffected
+        let domains = ["Google.com.", "football.example.com"].map(String::from).to_vec();
+        assert!(init_email_prover_with_allowlist(domains, None));
+
+        let allowed = prove_email_domain("google.com", "sig", "dkim=pass", None);
+        assert!(allowed.success(), "{:?}", allowed.error());
+
+        let denied = prove_email_domain("meta.com", "sig", "dkim=pass", None);
+        assert!(!denied.success());
+        assert!(denied.proof_bytes().is_empty());
+        assert_eq!(denied.error().unwrap(), "Domain meta.com is not on the allowlist");
+
+        // Every email entry point applies it, before needing its prover
+        let denied = prove_email_and_country("meta.com", "sig", "dkim=pass", 48.8566, 2.3522, None);
+        assert_eq!(denied.error().unwrap(), "Domain meta.com is not on the allowlist");
+        let denied = prove_corporate_email("alice@meta.com", "sig", "dkim=pass");
+        assert_eq!(denied.error().unwrap(), "Domain meta.com is not on the allowlist");
+        let denied = prove_email_with_challenge("Meta.com.", "K7-QX2P-91");
+        assert_eq!(denied.error().unwrap(), "Domain Meta.com. is not on the allowlist");
+
+        EMAIL_ALLOWLIST.with(|list| *list.borrow_mut() = None);
+        assert!(prove_email_domain("meta.com", "sig", "dkim=pass", None).success());
+    }
+
+    #[test]
+    fn test_prove_country_from_coords_uses_fresh_randomness() {
+        assert!(init_country_prover(None));
+
+        let first = prove_country_from_coords(48.8566, 2.3522, 1, None, None);
+        let second = prove_country_from_dms("48°51'24\"N", "2°21'8\"E", 1, None, None);
+        assert!(first.success(), "{:?}", first.error());
+        assert!(second.success(), "{:?}", second.error());
+        assert_eq!(first.country_code(), "FR");
+        assert_ne!(first.proof_hex(), second.proof_hex());
+    }
+
+    #[test]
+    fn test_prove_country_from_dms_rejects_bad_input() {
+        // Parsing happens before the prover is needed
+        let error = |lat, lng| prove_country_from_dms(lat, lng, 0, None, None).error().unwrap();
+        assert_eq!(error("48°51'N", "abc"), "Could not parse coordinate \"abc\"");
+        assert_eq!(error("2°21'E", "48°51'N"), "Coordinate \"2°21'E\" has a hemisphere for the other axis");
+        assert!(error("91 N", "2.35").contains("Latitude 91 is outside"));
+    }
+
+    #[test]
+    fn test_prove_country_rejects_invalid_coords() {
+        let handle = CountryProverHandle::new().unwrap();
+        let error = |lat, lng| handle.prove_from_coords(lat, lng, 0, None, None).error().unwrap();
+
+        assert!(error(f64::NAN, 2.3522).contains("must be finite"));
+        assert!(error(48.8566, f64::INFINITY).contains("must be finite"));
+        assert!(error(f64::NEG_INFINITY, f64::NAN).contains("must be finite"));
+        assert!(error(91.0, 2.3522).contains("Latitude 91 is outside"));
+        assert!(error(0.0, 0.0).contains("missing location"));
+        assert!(error(-0.0, 0.0).contains("missing location"));
+    }
 }
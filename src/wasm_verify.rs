@@ -0,0 +1,437 @@
+//! Verification WASM bindings for ZK Vault, and the decoding helpers the
+//! full `wasm` bindings share.
+//!
+//! Built with the `verify_only` feature, which `wasm` includes. Pages
+//! that only check proofs build with `verify_only` alone, import
+//! verifying keys exported by a full build (`export_country_verifying_key`,
+//! `export_email_verifying_key`) and verify against them; no setup,
+//! proving keys or prover state are compiled in, which keeps the bundle
+//! small.
+//!
+//! `verify_country_proof` and `verify_email_proof` check against the
+//! imported key, or in a full build without one, against the initialized
+//! prover's key.
+
+use wasm_bindgen::prelude::*;
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use ark_snark::SNARK;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::circuit::{CountryProofCircuit, EmailDomainCircuit, ProvableCircuit};
+use crate::merkle::hash::field_from_canonical_bytes;
+use crate::prover::{decode_key_header, CircuitTag, KeyKind};
+use crate::verifier::{check_country_id, check_freshness};
+
+// Initialize panic hook for better error messages in browser console
+#[wasm_bindgen(start)]
+pub fn init() {
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+}
+
+// ============== VERIFIER STATE ==============
+
+static COUNTRY_VK: Mutex<Option<PreparedVerifyingKey<Bn254>>> = Mutex::new(None);
+static EMAIL_VK: Mutex<Option<PreparedVerifyingKey<Bn254>>> = Mutex::new(None);
+
+/// Lock a key or prover slot, recovering the state if a panic poisoned it.
+///
+/// A slot only ever holds a fully built key or prover or `None`, so the
+/// value behind a poisoned lock is still consistent.
+pub(crate) fn lock_state<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Decode a verifying key with its key header and prepare it.
+fn decode_key(vk_bytes: &[u8], circuit: CircuitTag) -> Result<PreparedVerifyingKey<Bn254>, String> {
+    let body = decode_key_header(vk_bytes, circuit, KeyKind::Verifying).map_err(|e| e.to_string())?;
+    let vk = VerifyingKey::<Bn254>::deserialize_compressed(body).map_err(|e| e.to_string())?;
+    Groth16::<Bn254>::process_vk(&vk).map_err(|e| e.to_string())
+}
+
+/// Decode a verifying key into `slot`, keeping the old key on failure.
+fn import_key(
+    vk_bytes: &[u8],
+    circuit: CircuitTag,
+    slot: &Mutex<Option<PreparedVerifyingKey<Bn254>>>,
+) -> bool {
+    match decode_key(vk_bytes, circuit) {
+        Ok(pvk) => {
+            *lock_state(slot) = Some(pvk);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Import the country verifying key from `export_country_verifying_key`.
+///
+/// Returns false, keeping any earlier key, if the bytes are not a country
+/// verifying key.
+#[wasm_bindgen]
+pub fn import_country_verifying_key(vk_bytes: &[u8]) -> bool {
+    import_key(vk_bytes, CircuitTag::Country, &COUNTRY_VK)
+}
+
+/// Import the email verifying key from `export_email_verifying_key`.
+#[wasm_bindgen]
+pub fn import_email_verifying_key(vk_bytes: &[u8]) -> bool {
+    import_key(vk_bytes, CircuitTag::Email, &EMAIL_VK)
+}
+
+/// Key `verify_country_proof` checks against: the imported one, else the
+/// full build's country prover's.
+fn country_key() -> Option<PreparedVerifyingKey<Bn254>> {
+    let key = lock_state(&COUNTRY_VK).clone();
+    #[cfg(feature = "wasm")]
+    let key = key.or_else(crate::wasm::country_prover_key);
+    key
+}
+
+/// Key `verify_email_proof` checks against; see `country_key`.
+fn email_key() -> Option<PreparedVerifyingKey<Bn254>> {
+    let key = lock_state(&EMAIL_VK).clone();
+    #[cfg(feature = "wasm")]
+    let key = key.or_else(crate::wasm::email_prover_key);
+    key
+}
+
+/// Check if a country verifying key has been imported
+#[wasm_bindgen]
+pub fn is_country_verifier_ready() -> bool {
+    lock_state(&COUNTRY_VK).is_some()
+}
+
+/// Check if an email verifying key has been imported
+#[wasm_bindgen]
+pub fn is_email_verifier_ready() -> bool {
+    lock_state(&EMAIL_VK).is_some()
+}
+
+// ============== VERIFICATION ==============
+
+/// Verify a country proof
+///
+/// Public inputs: the proof's country id (`public_input` of the result),
+/// its commitment, the epoch it was scoped to, its nullifier, the time
+/// it was issued at and its identity commitment.
+///
+/// If `expected_country_code` is given the country id must match it;
+/// otherwise it must be the id of a supported country. If `max_age_secs`
+/// is given, proofs issued longer ago than that are rejected. Returns
+/// false if no key is available.
+///
+/// Malformed input still runs a full verification against placeholders;
+/// see `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn verify_country_proof(
+    proof_hex: &str,
+    country_id_hex: &str,
+    commitment_hex: &str,
+    epoch: u64,
+    nullifier_hex: &str,
+    issued_at: u64,
+    identity_commitment_hex: &str,
+    expected_country_code: Option<String>,
+    max_age_secs: Option<u64>,
+) -> bool {
+    match country_key() {
+        Some(pvk) => verify_country_proof_with(
+            &pvk,
+            proof_hex,
+            country_id_hex,
+            commitment_hex,
+            epoch,
+            nullifier_hex,
+            issued_at,
+            identity_commitment_hex,
+            expected_country_code.as_deref(),
+            max_age_secs,
+        ),
+        None => false,
+    }
+}
+
+/// `verify_country_proof` against a given key.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_country_proof_with(
+    pvk: &PreparedVerifyingKey<Bn254>,
+    proof_hex: &str,
+    country_id_hex: &str,
+    commitment_hex: &str,
+    epoch: u64,
+    nullifier_hex: &str,
+    issued_at: u64,
+    identity_commitment_hex: &str,
+    expected_country_code: Option<&str>,
+    max_age_secs: Option<u64>,
+) -> bool {
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (country_id, country_ok) = decode_field_or_placeholder(country_id_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let (nullifier, nullifier_ok) = decode_field_or_placeholder(nullifier_hex);
+    let (identity, identity_ok) = decode_field_or_placeholder(identity_commitment_hex);
+    let claim_ok = check_country_id(&country_id, expected_country_code).is_ok();
+    let issued_at = Fr::from(issued_at);
+    let fresh_ok = max_age_secs.is_none_or(|max_age| check_freshness(&issued_at, now_secs(), max_age).is_ok());
+
+    let inputs = CountryProofCircuit::input_fields(&(
+        country_id,
+        commitment,
+        Fr::from(epoch),
+        nullifier,
+        issued_at,
+        identity,
+    ));
+    let verified = Groth16::<Bn254>::verify_with_processed_vk(pvk, &inputs, &proof).unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & country_ok & commitment_ok & nullifier_ok & identity_ok & claim_ok & fresh_ok & verified
+}
+
+/// Outcome of a verification against a caller-supplied key
+#[wasm_bindgen]
+pub struct VerifyOutcome {
+    valid: bool,
+    error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl VerifyOutcome {
+    /// Whether the proof verified against the supplied key and inputs
+    #[wasm_bindgen(getter)]
+    pub fn valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Why the verifying key could not be used, if it was malformed.
+    ///
+    /// Only the key is reported on: a malformed or wrong proof or input
+    /// just gives `valid == false`, as for `verify_country_proof`.
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+}
+
+/// Verify a country proof against a caller-supplied verifying key.
+///
+/// Uses no global verifier or prover state, so neither
+/// `import_country_verifying_key` nor `init_country_prover` is needed.
+/// `vk_bytes` is the output of `export_country_verifying_key`. Other
+/// arguments, and the handling of malformed input, are as for
+/// `verify_country_proof`.
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn verify_country_proof_with_vk(
+    proof_hex: &str,
+    country_id_hex: &str,
+    commitment_hex: &str,
+    epoch: u64,
+    nullifier_hex: &str,
+    issued_at: u64,
+    identity_commitment_hex: &str,
+    expected_country_code: Option<String>,
+    max_age_secs: Option<u64>,
+    vk_bytes: &[u8],
+) -> VerifyOutcome {
+    match decode_key(vk_bytes, CircuitTag::Country) {
+        Ok(pvk) => VerifyOutcome {
+            valid: verify_country_proof_with(
+                &pvk,
+                proof_hex,
+                country_id_hex,
+                commitment_hex,
+                epoch,
+                nullifier_hex,
+                issued_at,
+                identity_commitment_hex,
+                expected_country_code.as_deref(),
+                max_age_secs,
+            ),
+            error: None,
+        },
+        Err(e) => VerifyOutcome {
+            valid: false,
+            error: Some(format!("Invalid verifying key: {}", e)),
+        },
+    }
+}
+
+/// Verify an email domain proof
+///
+/// Returns false if no key is available. Malformed input still runs a
+/// full verification against placeholders; see
+/// `decode_proof_or_placeholder`.
+#[wasm_bindgen]
+pub fn verify_email_proof(
+    proof_hex: &str,
+    domain_hash_hex: &str,
+    commitment_hex: &str,
+    identity_commitment_hex: &str,
+) -> bool {
+    match email_key() {
+        Some(pvk) => verify_email_proof_with(&pvk, proof_hex, domain_hash_hex, commitment_hex, identity_commitment_hex),
+        None => false,
+    }
+}
+
+/// `verify_email_proof` against a given key.
+fn verify_email_proof_with(
+    pvk: &PreparedVerifyingKey<Bn254>,
+    proof_hex: &str,
+    domain_hash_hex: &str,
+    commitment_hex: &str,
+    identity_commitment_hex: &str,
+) -> bool {
+    let (proof, proof_ok) = decode_proof_or_placeholder(proof_hex);
+    let (domain_hash, domain_hash_ok) = decode_field_or_placeholder(domain_hash_hex);
+    let (commitment, commitment_ok) = decode_field_or_placeholder(commitment_hex);
+    let (identity, identity_ok) = decode_field_or_placeholder(identity_commitment_hex);
+
+    let inputs = EmailDomainCircuit::input_fields(&(domain_hash, commitment, identity));
+    let verified = Groth16::<Bn254>::verify_with_processed_vk(pvk, &inputs, &proof).unwrap_or(false);
+
+    // Non-short-circuiting: every failure mode reaches this point
+    proof_ok & domain_hash_ok & commitment_ok & identity_ok & verified
+}
+
+/// Decode a hex proof, or return a placeholder and `false` if malformed.
+///
+/// The verify functions use this so malformed input goes through the same
+/// pairing check as a well-formed proof and the outcome is combined at the
+/// end, instead of returning early. This removes the obvious timing gap
+/// between "could not decode" and "did not verify"; it is best-effort and
+/// not a constant-time guarantee, since hex and point decoding and the
+/// pairing arithmetic still take input-dependent time.
+pub(crate) fn decode_proof_or_placeholder(s: &str) -> (Proof<Bn254>, bool) {
+    match hex::decode(s)
+        .ok()
+        .and_then(|b| Proof::<Bn254>::deserialize_compressed(&b[..]).ok())
+    {
+        Some(proof) => (proof, true),
+        None => (Proof::default(), false),
+    }
+}
+
+/// Decode a hex field element, or return zero and `false` if malformed.
+pub(crate) fn decode_field_or_placeholder(s: &str) -> (Fr, bool) {
+    match field_from_hex(s) {
+        Some(f) => (f, true),
+        None => (Fr::from(0u64), false),
+    }
+}
+
+/// Hex-encode a field element's canonical (little-endian) bytes.
+#[cfg(any(feature = "wasm", test))]
+pub(crate) fn field_to_hex(f: &Fr) -> String {
+    hex::encode(crate::merkle::hash::field_to_canonical_bytes(f))
+}
+
+/// Decode a `field_to_hex` string.
+pub(crate) fn field_from_hex(s: &str) -> Option<Fr> {
+    let bytes = hex::decode(s).ok()?;
+    field_from_canonical_bytes(&bytes).ok()
+}
+
+/// Seconds since the Unix epoch.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_secs() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{EmailProofInput, ScaledBounds};
+    use crate::prover::GenericProver;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+
+    fn proof_hex(proof: &Proof<Bn254>) -> String {
+        let mut bytes = Vec::new();
+        proof.serialize_compressed(&mut bytes).unwrap();
+        hex::encode(bytes)
+    }
+
+    #[test]
+    fn test_verify_with_imported_keys() {
+        let mut rng = StdRng::seed_from_u64(0x5645524946595F4F);
+
+        // Local slots: a full build's tests share the global ones
+        let country_slot = Mutex::new(None);
+        let email_slot = Mutex::new(None);
+        let country = GenericProver::<CountryProofCircuit>::setup(&(), &mut rng).unwrap();
+        let email = GenericProver::<EmailDomainCircuit>::setup(&(), &mut rng).unwrap();
+        assert!(!import_key(&email.verifying_key_bytes().unwrap(), CircuitTag::Country, &country_slot));
+        assert!(lock_state(&country_slot).is_none());
+        assert!(import_key(&country.verifying_key_bytes().unwrap(), CircuitTag::Country, &country_slot));
+        assert!(import_key(&email.verifying_key_bytes().unwrap(), CircuitTag::Email, &email_slot));
+        let country_pvk = lock_state(&country_slot).clone().unwrap();
+        let email_pvk = lock_state(&email_slot).clone().unwrap();
+
+        let bounds = ScaledBounds::new(24.396308, 49.384358, -125.0, -66.93457);
+        let issued_at = now_secs();
+        let circuit = CountryProofCircuit::new_with_witness(37.7749, -122.4194, &bounds, "US", 2, issued_at).unwrap();
+        let (proof, (id, commitment, _, nullifier, _, identity)) = country.prove(circuit, &mut rng).unwrap();
+        let verify = |epoch, expected: &str| {
+            verify_country_proof_with(
+                &country_pvk,
+                &proof_hex(&proof),
+                &field_to_hex(&id),
+                &field_to_hex(&commitment),
+                epoch,
+                &field_to_hex(&nullifier),
+                issued_at,
+                &field_to_hex(&identity),
+                Some(expected),
+                Some(3600),
+            )
+        };
+        assert!(verify(2, "US"));
+        assert!(!verify(3, "US"));
+        assert!(!verify(2, "CA"));
+
+        // The same check against a key passed in, with no imported state
+        let country_vk = country.verifying_key_bytes().unwrap();
+        let verify_with_vk = |vk_bytes: &[u8]| {
+            verify_country_proof_with_vk(
+                &proof_hex(&proof),
+                &field_to_hex(&id),
+                &field_to_hex(&commitment),
+                2,
+                &field_to_hex(&nullifier),
+                issued_at,
+                &field_to_hex(&identity),
+                Some("US".to_string()),
+                Some(3600),
+                vk_bytes,
+            )
+        };
+        let outcome = verify_with_vk(&country_vk);
+        assert!(outcome.valid() && outcome.error().is_none());
+        let outcome = verify_with_vk(&email.verifying_key_bytes().unwrap());
+        assert!(!outcome.valid() && outcome.error().is_some());
+
+        let input = EmailProofInput::from_domain_with_dkim("google.com", true, "sig");
+        let circuit = EmailDomainCircuit::new_with_witness(&input).unwrap();
+        let (proof, (domain_hash, commitment, identity)) = email.prove(circuit, &mut rng).unwrap();
+        let proof = proof_hex(&proof);
+        let (domain_hash, commitment, identity) =
+            (field_to_hex(&domain_hash), field_to_hex(&commitment), field_to_hex(&identity));
+        assert!(verify_email_proof_with(&email_pvk, &proof, &domain_hash, &commitment, &identity));
+        assert!(!verify_email_proof_with(&email_pvk, &proof, &commitment, &domain_hash, &identity));
+        assert!(!verify_email_proof_with(&email_pvk, "zz", &domain_hash, &commitment, &identity));
+        assert!(!verify_email_proof_with(&country_pvk, &proof, &domain_hash, &commitment, &identity));
+    }
+}